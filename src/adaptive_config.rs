@@ -1,20 +1,31 @@
 //! Adaptive Configuration System
 //!
 //! This module provides runtime adaptation and learning capabilities
-//! to improve game compatibility automatically.  The module is exposed as a
-//! library API but is not yet wired into the CLI or GUI front-ends; the
-//! `dead_code` allowance below silences the warnings the unused surface
-//! otherwise produces.
-
-#![allow(dead_code)]
+//! to improve game compatibility automatically.
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use serde::{Deserialize, Serialize};
 use log::{info, warn, debug};
 use crate::errors::{HydraError, Result};
-use crate::game_detection::GameProfile;
+use crate::game_detection::{GameConfiguration, GameProfile, InstanceSeparation, WorkingDirStrategy};
+use crate::window_manager::WindowMatchStrategy;
+
+/// File name of the adaptive store inside the data directory.
+pub const ADAPTIVE_CONFIG_FILE: &str = "adaptive.toml";
+
+/// Stored entries (adaptations plus patterns) above which loading prunes
+const PRUNE_ON_LOAD_THRESHOLD: usize = 500;
+
+/// Age after which an adaptation without a new success is dropped on load
+const STALE_ADAPTATION_AGE: Duration = Duration::from_secs(86400 * 180);
+
+/// Success patterns at or below this rate are considered useless
+const MIN_PATTERN_SUCCESS_RATE: f64 = 0.05;
+
+/// Shortest time between two writes of the store while recording launches
+pub const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Error type for adaptive configuration operations.
 #[derive(Debug)]
@@ -93,6 +104,9 @@ pub struct OptimalConfig {
     pub layout: String,
     /// Required launch delay between instances
     pub launch_delay_ms: u64,
+    /// How the game's windows were last told apart, tried first next launch
+    #[serde(default)]
+    pub window_match_strategy: Option<WindowMatchStrategy>,
 }
 
 /// Pattern that led to successful multi-instance launch
@@ -125,6 +139,11 @@ pub struct FailedConfig {
 pub struct AdaptiveConfigManager {
     config: AdaptiveConfig,
     config_path: PathBuf,
+    /// Recorded changes not written to `config_path` yet
+    dirty: bool,
+    /// When the store was last written, for spacing out saves
+    last_saved: Option<Instant>,
+    save_interval: Duration,
 }
 
 impl AdaptiveConfigManager {
@@ -136,10 +155,39 @@ impl AdaptiveConfigManager {
             AdaptiveConfig::default()
         };
 
-        Ok(Self {
+        let mut manager = Self {
             config,
             config_path,
-        })
+            dirty: false,
+            last_saved: None,
+            save_interval: DEFAULT_SAVE_INTERVAL,
+        };
+
+        let stored = manager.config.game_adaptations.len() + manager.config.success_patterns.len();
+        if stored > PRUNE_ON_LOAD_THRESHOLD && manager.prune(STALE_ADAPTATION_AGE) > 0 {
+            manager.save_config()?;
+        }
+
+        Ok(manager)
+    }
+
+    /// Drop adaptations whose last success is older than `older_than` and
+    /// success patterns with a near-zero success rate.
+    /// Returns the number of entries removed; the caller decides when to save.
+    pub fn prune(&mut self, older_than: Duration) -> usize {
+        let before = self.config.game_adaptations.len() + self.config.success_patterns.len();
+
+        self.config.game_adaptations.retain(|_, adaptation| {
+            adaptation.last_success.elapsed().map(|age| age <= older_than).unwrap_or(true)
+        });
+        self.config.success_patterns
+            .retain(|pattern| pattern.success_rate > MIN_PATTERN_SUCCESS_RATE);
+
+        let removed = before - self.config.game_adaptations.len() - self.config.success_patterns.len();
+        if removed > 0 {
+            info!("Pruned {} stale adaptive entries", removed);
+        }
+        removed
     }
 
     /// Load adaptive configuration from file
@@ -151,18 +199,89 @@ impl AdaptiveConfigManager {
             .map_err(|e| HydraError::application(format!("Failed to parse adaptive config: {}", e)))
     }
 
-    /// Save adaptive configuration to file
-    pub fn save_config(&self) -> Result<()> {
+    /// Save adaptive configuration to file. The file is replaced atomically,
+    /// so a crash mid-write leaves the previous version intact.
+    pub fn save_config(&mut self) -> Result<()> {
         let content = toml::to_string_pretty(&self.config)
             .map_err(|e| HydraError::application(format!("Failed to serialize adaptive config: {}", e)))?;
 
-        std::fs::write(&self.config_path, content)
+        crate::utils::write_atomic(&self.config_path, content.as_bytes())
             .map_err(HydraError::Io)?;
 
+        self.dirty = false;
+        self.last_saved = Some(Instant::now());
         debug!("Saved adaptive configuration to {}", self.config_path.display());
         Ok(())
     }
 
+    /// Sets the shortest time between two writes while recording launches.
+    /// Changes made sooner are kept in memory until a later change, `flush`,
+    /// or the manager being dropped writes them.
+    pub fn set_save_interval(&mut self, interval: Duration) {
+        self.save_interval = interval;
+    }
+
+    /// Writes recorded changes that are still only in memory.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.dirty {
+            self.save_config()?;
+        }
+        Ok(())
+    }
+
+    /// Marks the store changed and saves it unless it was saved less than
+    /// `save_interval` ago.
+    fn save_soon(&mut self) -> Result<()> {
+        self.dirty = true;
+        match self.last_saved {
+            Some(last_saved) if last_saved.elapsed() < self.save_interval => {
+                debug!("Deferring adaptive configuration save");
+                Ok(())
+            }
+            _ => self.save_config(),
+        }
+    }
+
+    /// Returns the adaptive key for a game executable, see
+    /// [`crate::game_detection::compute_game_id`].
+    ///
+    /// Older versions keyed adaptations by the bare file name; if such an entry
+    /// exists and nothing is stored under the new ID yet, it is moved over.
+    pub fn game_id_for(&mut self, executable_path: &Path) -> Result<String> {
+        let game_id = crate::game_detection::compute_game_id(executable_path)?;
+
+        let legacy_id = executable_path.file_name().and_then(|n| n.to_str());
+        if let Some(legacy_id) = legacy_id {
+            if !self.config.game_adaptations.contains_key(&game_id) {
+                if let Some(mut adaptation) = self.config.game_adaptations.remove(legacy_id) {
+                    info!("Migrating adaptive entry '{}' to '{}'", legacy_id, game_id);
+                    adaptation.game_id = game_id.clone();
+                    self.config.game_adaptations.insert(game_id.clone(), adaptation);
+                    for failed in &mut self.config.failed_configs {
+                        if failed.game_id == legacy_id {
+                            failed.game_id = game_id.clone();
+                        }
+                    }
+                    self.save_soon()?;
+                }
+            }
+        }
+
+        Ok(game_id)
+    }
+
+    /// All per-game adaptations, sorted by game ID
+    pub fn adaptations(&self) -> Vec<&GameAdaptation> {
+        let mut adaptations: Vec<_> = self.config.game_adaptations.values().collect();
+        adaptations.sort_by(|a, b| a.game_id.cmp(&b.game_id));
+        adaptations
+    }
+
+    /// Confidence a recommendation built from `adaptation` would carry
+    pub fn confidence(&self, adaptation: &GameAdaptation) -> f64 {
+        self.calculate_confidence(adaptation)
+    }
+
     /// Get adaptive configuration for a specific game
     pub fn get_game_adaptation(&self, game_id: &str) -> Option<&GameAdaptation> {
         self.config.game_adaptations.get(game_id)
@@ -173,7 +292,7 @@ impl AdaptiveConfigManager {
         &mut self,
         game_id: String,
         profile: &GameProfile,
-        config: &crate::game_detection::GameConfiguration,
+        config: &GameConfiguration,
         launch_time: Duration,
     ) -> Result<()> {
         info!("Recording successful launch for game: {}", game_id);
@@ -191,6 +310,7 @@ impl AdaptiveConfigManager {
                     ports: config.ports.clone(),
                     layout: config.layout.clone(),
                     launch_delay_ms: 0,
+                    window_match_strategy: None,
                 },
                 working_args: config.launch_args.clone(),
                 working_env_vars: config.environment_vars.clone(),
@@ -233,15 +353,34 @@ impl AdaptiveConfigManager {
             });
         }
 
-        self.save_config()?;
+        self.save_soon()?;
         Ok(())
     }
 
+    /// Record how `game_id`'s windows were found, so the next launch looks
+    /// for them that way first. Games without a recorded success are skipped.
+    pub fn record_window_match(&mut self, game_id: &str, strategy: WindowMatchStrategy) -> Result<()> {
+        let Some(adaptation) = self.config.game_adaptations.get_mut(game_id) else {
+            return Ok(());
+        };
+        if adaptation.optimal_config.window_match_strategy == Some(strategy) {
+            return Ok(());
+        }
+        debug!("Game {} windows are found by {:?}", game_id, strategy);
+        adaptation.optimal_config.window_match_strategy = Some(strategy);
+        self.save_soon()
+    }
+
+    /// How `game_id`'s windows were found last time, see `record_window_match`
+    pub fn window_match_strategy(&self, game_id: &str) -> Option<WindowMatchStrategy> {
+        self.get_game_adaptation(game_id)?.optimal_config.window_match_strategy
+    }
+
     /// Record a failed game launch configuration
     pub fn record_failure(
         &mut self,
         game_id: String,
-        config: &crate::game_detection::GameConfiguration,
+        config: &GameConfiguration,
         error: &str,
     ) -> std::result::Result<(), AdaptiveConfigError> {
         warn!("Recording failed launch for game: {} - {}", game_id, error);
@@ -263,7 +402,7 @@ impl AdaptiveConfigManager {
             self.config.failed_configs.drain(0..100); // Remove oldest 100 failures
         }
 
-        self.save_config()?;
+        self.save_soon()?;
         Ok(())
     }
 
@@ -377,6 +516,14 @@ impl AdaptiveConfigManager {
     }
 }
 
+impl Drop for AdaptiveConfigManager {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("Could not save adaptive configuration: {}", e);
+        }
+    }
+}
+
 /// Recommended configuration based on learned patterns
 #[derive(Debug, Clone)]
 pub struct RecommendedConfig {
@@ -390,6 +537,62 @@ pub struct RecommendedConfig {
     pub notes: Vec<String>,
 }
 
+impl RecommendedConfig {
+    /// Overlay the learned settings onto a freshly generated configuration.
+    /// Settings that no longer parse are left as generated, and ports are
+    /// only taken over when there are enough of them for every instance.
+    pub fn apply_to(&self, config: &mut GameConfiguration) {
+        if let Some(strategy) = self.learned_working_dir_strategy() {
+            config.working_dir_strategy = strategy;
+        }
+
+        match self.separation_level.as_str() {
+            "None" => config.instance_separation = InstanceSeparation::None,
+            "Environment" => config.instance_separation = InstanceSeparation::Environment,
+            "Full" => config.instance_separation = InstanceSeparation::Full,
+            other => debug!("Ignoring unknown learned separation level '{}'", other),
+        }
+
+        if self.ports.len() >= config.ports.len() {
+            config.ports = self.ports[..config.ports.len()].to_vec();
+        }
+        config.layout = self.layout.clone();
+        if !self.launch_args.is_empty() {
+            config.launch_args = self.launch_args.clone();
+        }
+        config.environment_vars.extend(self.env_vars.clone());
+    }
+
+    /// Overlay the learned settings onto a detected profile, so an exported
+    /// profile carries what launches of the game have taught us.
+    pub fn apply_to_profile(&self, profile: &mut GameProfile) {
+        if let Some(strategy) = self.learned_working_dir_strategy() {
+            profile.working_dir_strategy = strategy;
+        }
+        if !self.ports.is_empty() {
+            profile.default_ports = self.ports.clone();
+        }
+        profile.default_layout = self.layout.clone();
+        if !self.launch_args.is_empty() {
+            profile.launch_args = self.launch_args.clone();
+        }
+        profile.environment_vars.extend(self.env_vars.clone());
+    }
+
+    fn learned_working_dir_strategy(&self) -> Option<WorkingDirStrategy> {
+        match self.working_dir_strategy.as_str() {
+            "GameDirectory" => Some(WorkingDirStrategy::GameDirectory),
+            "SeparateDirectories" => Some(WorkingDirStrategy::SeparateDirectories),
+            "Temporary" => Some(WorkingDirStrategy::Temporary),
+            "Current" => Some(WorkingDirStrategy::Current),
+            other => {
+                debug!("Ignoring unknown learned working dir strategy '{}'", other);
+                None
+            }
+        }
+    }
+}
+
 /// Statistics about the adaptive configuration system
 #[derive(Debug, Clone)]
 pub struct AdaptiveStats {
@@ -413,6 +616,7 @@ impl Default for AdaptiveConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::game_detection::{MultiInstanceSupport, NetworkMode};
     use tempfile::tempdir;
 
     #[test]
@@ -440,6 +644,8 @@ mod tests {
             launch_args: vec![],
             environment_vars: HashMap::new(),
             working_dir_strategy: crate::game_detection::WorkingDirStrategy::SeparateDirectories,
+            network_mode: crate::game_detection::NetworkMode::RawUdp,
+            instance_blockers: Vec::new(),
         };
 
         let config = crate::game_detection::GameConfiguration {
@@ -449,6 +655,7 @@ mod tests {
             environment_vars: HashMap::new(),
             working_dir_strategy: crate::game_detection::WorkingDirStrategy::SeparateDirectories,
             instance_separation: crate::game_detection::InstanceSeparation::Environment,
+            instance_args: Vec::new(),
         };
 
         manager.record_success(
@@ -461,4 +668,159 @@ mod tests {
         assert_eq!(manager.config.game_adaptations.len(), 1);
         assert!(manager.get_game_adaptation("test_game").is_some());
     }
+
+    #[test]
+    fn test_window_match_strategy_is_remembered() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("adaptive.toml");
+        let mut manager = AdaptiveConfigManager::new(config_path.clone()).unwrap();
+        manager.set_save_interval(Duration::ZERO);
+
+        // Only games with a recorded launch get one
+        manager.record_window_match("unknown", WindowMatchStrategy::Title).unwrap();
+        assert_eq!(manager.window_match_strategy("unknown"), None);
+
+        manager.config.game_adaptations.insert("game".to_string(), test_adaptation("game", SystemTime::now()));
+        assert_eq!(manager.window_match_strategy("game"), None);
+        manager.record_window_match("game", WindowMatchStrategy::Title).unwrap();
+        assert_eq!(manager.window_match_strategy("game"), Some(WindowMatchStrategy::Title));
+
+        let reloaded = AdaptiveConfigManager::new(config_path).unwrap();
+        assert_eq!(reloaded.window_match_strategy("game"), Some(WindowMatchStrategy::Title));
+    }
+
+    #[test]
+    fn test_saves_are_debounced_and_flushed_on_drop() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("adaptive.toml");
+        let mut manager = AdaptiveConfigManager::new(config_path.clone()).unwrap();
+        manager.set_save_interval(Duration::from_secs(3600));
+
+        let config = GameConfiguration {
+            ports: vec![7777],
+            layout: "horizontal".to_string(),
+            launch_args: vec![],
+            environment_vars: HashMap::new(),
+            working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+            instance_separation: InstanceSeparation::Environment,
+            instance_args: Vec::new(),
+        };
+        manager.record_failure("first".to_string(), &config, "crashed").unwrap();
+        manager.record_failure("second".to_string(), &config, "crashed").unwrap();
+
+        // The first change is written right away, the second waits
+        let stored_failures = |path: &Path| AdaptiveConfigManager::load_config(path).unwrap().failed_configs.len();
+        assert_eq!(stored_failures(&config_path), 1);
+        assert!(!temp_dir.path().join("adaptive.toml.tmp").exists());
+
+        drop(manager);
+        assert_eq!(stored_failures(&config_path), 2);
+    }
+
+    #[test]
+    fn test_recommendation_applies_to_config() {
+        let mut config = GameConfiguration {
+            ports: vec![7777, 7778],
+            layout: "horizontal".to_string(),
+            launch_args: vec!["-windowed".to_string()],
+            environment_vars: HashMap::new(),
+            working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+            instance_separation: InstanceSeparation::Environment,
+            instance_args: Vec::new(),
+        };
+        let recommendation = RecommendedConfig {
+            confidence: 0.8,
+            working_dir_strategy: "GameDirectory".to_string(),
+            separation_level: "NoLongerAVariant".to_string(),
+            ports: vec![9000, 9001, 9002],
+            layout: "vertical".to_string(),
+            launch_args: vec![],
+            env_vars: HashMap::from([("LEARNED".to_string(), "1".to_string())]),
+            notes: vec![],
+        };
+
+        recommendation.apply_to(&mut config);
+
+        assert!(matches!(config.working_dir_strategy, WorkingDirStrategy::GameDirectory));
+        assert!(matches!(config.instance_separation, InstanceSeparation::Environment));
+        assert_eq!(config.ports, vec![9000, 9001]);
+        assert_eq!(config.layout, "vertical");
+        assert_eq!(config.launch_args, vec!["-windowed".to_string()]);
+        assert_eq!(config.environment_vars.get("LEARNED").map(String::as_str), Some("1"));
+
+        let mut profile = GameProfile {
+            executable_pattern: "game.exe".to_string(),
+            engine: None,
+            default_ports: vec![7777],
+            default_layout: "horizontal".to_string(),
+            multi_instance_support: MultiInstanceSupport::Configurable,
+            launch_args: vec!["-windowed".to_string()],
+            environment_vars: HashMap::new(),
+            working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+            network_mode: NetworkMode::RawUdp,
+            instance_blockers: Vec::new(),
+        };
+        recommendation.apply_to_profile(&mut profile);
+        assert!(matches!(profile.working_dir_strategy, WorkingDirStrategy::GameDirectory));
+        assert_eq!(profile.default_ports, vec![9000, 9001, 9002]);
+        assert_eq!(profile.default_layout, "vertical");
+        assert_eq!(profile.environment_vars.get("LEARNED").map(String::as_str), Some("1"));
+    }
+
+    fn test_adaptation(game_id: &str, last_success: SystemTime) -> GameAdaptation {
+        GameAdaptation {
+            game_id: game_id.to_string(),
+            success_count: 3,
+            last_success,
+            optimal_config: OptimalConfig {
+                working_dir_strategy: "SeparateDirectories".to_string(),
+                separation_level: "Environment".to_string(),
+                ports: vec![7777],
+                layout: "horizontal".to_string(),
+                launch_delay_ms: 0,
+                window_match_strategy: None,
+            },
+            working_args: vec![],
+            working_env_vars: HashMap::new(),
+            notes: vec![],
+        }
+    }
+
+    #[test]
+    fn test_prune_drops_stale_entries() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = AdaptiveConfigManager::new(temp_dir.path().join("adaptive.toml")).unwrap();
+
+        let long_ago = SystemTime::now() - Duration::from_secs(86400 * 400);
+        manager.config.game_adaptations.insert("old".to_string(), test_adaptation("old", long_ago));
+        manager.config.game_adaptations.insert("recent".to_string(), test_adaptation("recent", SystemTime::now()));
+        for success_rate in [0.0, 0.9] {
+            manager.config.success_patterns.push(SuccessPattern {
+                engine: None,
+                config: HashMap::new(),
+                success_rate,
+                success_count: 1,
+            });
+        }
+
+        assert_eq!(manager.prune(Duration::from_secs(86400 * 30)), 2);
+        assert!(manager.get_game_adaptation("old").is_none());
+        assert!(manager.get_game_adaptation("recent").is_some());
+        assert_eq!(manager.config.success_patterns.len(), 1);
+    }
+
+    #[test]
+    fn test_game_id_migrates_filename_key() {
+        let temp_dir = tempdir().unwrap();
+        let exe_path = temp_dir.path().join("game.exe");
+        std::fs::write(&exe_path, b"game binary").unwrap();
+
+        let mut manager = AdaptiveConfigManager::new(temp_dir.path().join("adaptive.toml")).unwrap();
+        manager.config.game_adaptations.insert("game.exe".to_string(), test_adaptation("game.exe", SystemTime::now()));
+
+        let game_id = manager.game_id_for(&exe_path).unwrap();
+        assert_ne!(game_id, "game.exe");
+        assert!(manager.get_game_adaptation("game.exe").is_none());
+        assert_eq!(manager.get_game_adaptation(&game_id).unwrap().success_count, 3);
+    }
 }
\ No newline at end of file
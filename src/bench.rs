@@ -0,0 +1,245 @@
+//! Benchmarks
+//!
+//! `hydra-coop-launcher bench` measures the two hot paths of a session: the
+//! network relay, flooded with timestamped packets at a target rate, and the
+//! input routing each capture thread runs on every read. The numbers make
+//! changes to either comparable between builds and machines.
+
+use std::collections::HashSet;
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+use evdev::{AbsoluteAxisType, EventType, InputEvent, Key, Synchronization};
+use crate::input_mux::{frames_to_forward, SyncMode};
+use crate::net_emulator::{NetEmulator, NetEmulatorError, RelayStats};
+
+/// How long receivers keep listening for late packets after the last send
+const DRAIN_TIME: Duration = Duration::from_millis(500);
+
+/// Bytes at the start of every packet: send time in nanoseconds since the
+/// run started
+const TIMESTAMP_LEN: usize = 8;
+
+/// Events per simulated read in the input benchmark, about what a gamepad
+/// reports when a stick and a button change together
+const EVENTS_PER_READ: usize = 8;
+
+/// Settings for [`bench_net`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetBenchOptions {
+    /// Game instances, each with its own relay socket; instance `i` sends to
+    /// instance `i + 1`, the last one to the first
+    pub instances: u8,
+    /// Packets per second over all instances
+    pub rate: u32,
+    pub duration: Duration,
+    /// Size of each packet, at least the 8-byte timestamp
+    pub payload: usize,
+}
+
+impl Default for NetBenchOptions {
+    fn default() -> Self {
+        NetBenchOptions { instances: 2, rate: 10_000, duration: Duration::from_secs(5), payload: 256 }
+    }
+}
+
+/// Latency percentiles of delivered packets or routed events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Percentiles {
+    /// Percentiles of `samples`, which get sorted; all zero when empty.
+    pub fn of(samples: &mut [Duration]) -> Self {
+        samples.sort_unstable();
+        let at = |fraction: f64| {
+            let index = ((samples.len() as f64 * fraction).ceil() as usize).saturating_sub(1);
+            samples.get(index).copied().unwrap_or_default()
+        };
+        Percentiles { p50: at(0.50), p90: at(0.90), p99: at(0.99), max: samples.last().copied().unwrap_or_default() }
+    }
+}
+
+impl std::fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "p50 {:?}, p90 {:?}, p99 {:?}, max {:?}", self.p50, self.p90, self.p99, self.max)
+    }
+}
+
+/// What [`bench_net`] measured
+#[derive(Debug, Clone)]
+pub struct NetBenchReport {
+    pub sent: u64,
+    pub received: u64,
+    /// How long sending took; below `duration` means the target rate was met
+    pub elapsed: Duration,
+    /// The relay's own counts
+    pub relay: RelayStats,
+    pub latency: Percentiles,
+}
+
+impl NetBenchReport {
+    /// Packets per second that arrived
+    pub fn throughput(&self) -> f64 {
+        self.received as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Share of sent packets that never arrived, from 0 to 1
+    pub fn loss(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        self.sent.saturating_sub(self.received) as f64 / self.sent as f64
+    }
+}
+
+/// Runs the relay with `options.instances` instances and floods it with
+/// timestamped packets at `options.rate` for `options.duration`.
+pub fn bench_net(options: NetBenchOptions) -> Result<NetBenchReport, NetEmulatorError> {
+    let instances = options.instances.max(1);
+    let payload_len = options.payload.max(TIMESTAMP_LEN);
+    let mut emulator = NetEmulator::new();
+    let mut senders = Vec::new();
+    let mut receivers = Vec::new();
+    for instance_id in 0..instances {
+        let relay_port = emulator.add_instance(instance_id)?;
+        senders.push((UdpSocket::bind("127.0.0.1:0")?, SocketAddr::from(([127, 0, 0, 1], relay_port))));
+        let receiver = UdpSocket::bind("127.0.0.1:0")?;
+        receiver.set_read_timeout(Some(Duration::from_millis(50)))?;
+        receivers.push(receiver);
+    }
+    for (i, (sender, _)) in senders.iter().enumerate() {
+        let next = &receivers[(i + 1) % receivers.len()];
+        emulator.add_mapping(sender.local_addr()?, next.local_addr()?);
+    }
+    emulator.start_relay()?;
+
+    let start = Instant::now();
+    let total = (options.rate as f64 * options.duration.as_secs_f64()).round() as u64;
+    let deadline = options.duration + DRAIN_TIME;
+    let listeners: Vec<_> = receivers
+        .into_iter()
+        .map(|receiver| thread::spawn(move || receive_timestamps(receiver, start, deadline)))
+        .collect();
+
+    let interval = Duration::from_secs_f64(1.0 / f64::from(options.rate.max(1)));
+    let mut packet = vec![0u8; payload_len];
+    let mut sent = 0;
+    for n in 0..total {
+        let due = interval.mul_f64(n as f64);
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+        let (sender, relay) = &senders[n as usize % senders.len()];
+        packet[..TIMESTAMP_LEN].copy_from_slice(&(start.elapsed().as_nanos() as u64).to_le_bytes());
+        if sender.send_to(&packet, relay).is_ok() {
+            sent += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let mut latencies = Vec::new();
+    for listener in listeners {
+        latencies.extend(listener.join().unwrap_or_default());
+    }
+    emulator.stop_relay()?;
+    Ok(NetBenchReport {
+        sent,
+        received: latencies.len() as u64,
+        elapsed,
+        relay: emulator.stats(),
+        latency: Percentiles::of(&mut latencies),
+    })
+}
+
+/// Latency of every packet `receiver` gets until `deadline` after `start`.
+fn receive_timestamps(receiver: UdpSocket, start: Instant, deadline: Duration) -> Vec<Duration> {
+    let mut buf = [0u8; 65507];
+    let mut latencies = Vec::new();
+    while start.elapsed() < deadline {
+        let Ok(len) = receiver.recv(&mut buf) else { continue };
+        let Some(&[b0, b1, b2, b3, b4, b5, b6, b7]) = buf.get(..TIMESTAMP_LEN.min(len)) else { continue };
+        let sent_at = Duration::from_nanos(u64::from_le_bytes([b0, b1, b2, b3, b4, b5, b6, b7]));
+        latencies.push(start.elapsed().saturating_sub(sent_at));
+    }
+    latencies
+}
+
+/// What [`bench_input`] measured
+#[derive(Debug, Clone)]
+pub struct InputBenchReport {
+    pub events: usize,
+    pub frames: usize,
+    pub elapsed: Duration,
+    /// Routing time per event, from the time of each simulated read
+    pub per_event: Percentiles,
+}
+
+/// Feeds `events` synthetic gamepad events through the routing a capture
+/// thread runs on every read (reserved-key filter and frame splitting per
+/// `sync_mode`). Writing to the virtual devices isn't included; that needs
+/// `/dev/uinput` and measures the kernel more than Hydra.
+pub fn bench_input(events: usize, sync_mode: SyncMode) -> InputBenchReport {
+    let reserved: HashSet<Key> = [Key::KEY_SCROLLLOCK].into_iter().collect();
+    let pattern = [
+        InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, 1200),
+        InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_Y.0, -800),
+        InputEvent::new(EventType::SYNCHRONIZATION, Synchronization::SYN_REPORT.0, 0),
+        InputEvent::new(EventType::KEY, Key::BTN_SOUTH.code(), 1),
+        InputEvent::new(EventType::SYNCHRONIZATION, Synchronization::SYN_REPORT.0, 0),
+        InputEvent::new(EventType::KEY, Key::KEY_SCROLLLOCK.code(), 1),
+        InputEvent::new(EventType::KEY, Key::BTN_SOUTH.code(), 0),
+        InputEvent::new(EventType::SYNCHRONIZATION, Synchronization::SYN_REPORT.0, 0),
+    ];
+    let input: Vec<InputEvent> = pattern.iter().copied().cycle().take(events).collect();
+
+    let mut partial = Vec::new();
+    let mut frames = 0;
+    let mut per_event = Vec::with_capacity(events.div_ceil(EVENTS_PER_READ));
+    let start = Instant::now();
+    for read in input.chunks(EVENTS_PER_READ) {
+        let read_start = Instant::now();
+        frames += std::hint::black_box(frames_to_forward(&mut partial, read.iter().copied(), sync_mode, &reserved)).len();
+        per_event.push(read_start.elapsed() / read.len() as u32);
+    }
+    InputBenchReport { events, frames, elapsed: start.elapsed(), per_event: Percentiles::of(&mut per_event) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let mut samples: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let percentiles = Percentiles::of(&mut samples);
+        assert_eq!(percentiles.p50, Duration::from_millis(50));
+        assert_eq!(percentiles.p90, Duration::from_millis(90));
+        assert_eq!(percentiles.p99, Duration::from_millis(99));
+        assert_eq!(percentiles.max, Duration::from_millis(100));
+        assert_eq!(Percentiles::of(&mut []), Percentiles::default());
+    }
+
+    #[test]
+    fn test_bench_net_relays_between_instances() {
+        let options = NetBenchOptions { instances: 3, rate: 2_000, duration: Duration::from_millis(200), payload: 64 };
+        let report = bench_net(options).unwrap();
+        assert_eq!(report.sent, 400);
+        assert!(report.received > 0, "{:?}", report);
+        assert!(report.received <= report.sent);
+        assert!(report.received <= report.relay.forwarded);
+        assert!(report.latency.max >= report.latency.p50);
+    }
+
+    #[test]
+    fn test_bench_input_counts_frames() {
+        let report = bench_input(800, SyncMode::Source);
+        assert_eq!(report.events, 800);
+        // Each 8-event pattern holds 3 SYN_REPORTs
+        assert_eq!(report.frames, 300);
+    }
+}
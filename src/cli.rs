@@ -1,4 +1,5 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgGroup, Command};
+use std::net::SocketAddr;
 
 /// Builds the Clap Command structure for the application.
 pub fn build_cli() -> Command {
@@ -13,8 +14,9 @@ pub fn build_cli() -> Command {
             Arg::new("game_executable")
                 .short('g')
                 .long("game-executable")
+                .visible_alias("game")
                 .value_name("PATH")
-                .help("Specifies the path to the game executable") // Use .help() instead of .about() for arguments
+                .help("Specifies the path to the game executable; it becomes the first of the recent games in the config") // Use .help() instead of .about() for arguments
                 .required(false), // Made optional since GUI mode doesn't require it
         )
         .arg(
@@ -36,6 +38,27 @@ pub fn build_cli() -> Command {
                 .required(false) // Made optional since GUI mode doesn't require it
                 .action(clap::ArgAction::Append), // Use Append to collect multiple values into a Vec
         )
+        .arg(
+            Arg::new("assign_by_button")
+                .long("assign-by-button")
+                .help("Assign devices by having each player in turn press a button on theirs")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("input_devices"),
+        )
+        .arg(
+            Arg::new("assign")
+                .long("assign")
+                .value_name("SPEC")
+                .help("Assigns devices to instances explicitly, e.g. \"0=id:045e:028e;1=auto;2=none\" (instances count from 0; DEVICE is auto, none, id:VENDOR:PRODUCT, phys:PATH or a name). Takes precedence over --input-devices")
+                .conflicts_with("assign_by_button"),
+        )
+        .arg(
+            Arg::new("mirror_input")
+                .long("mirror-input")
+                .value_name("DEVICE")
+                .help("Send this device's input to every instance at once, in addition to each instance's own device")
+                .required(false),
+        )
         .arg(
             Arg::new("layout")
                 .short('l')
@@ -43,7 +66,7 @@ pub fn build_cli() -> Command {
                 .value_name("LAYOUT")
                 .help("Chooses the desired split-screen layout")
                 .required(false) // Made optional since GUI mode doesn't require it
-                .value_parser(["horizontal", "vertical", "custom"]), // Simpler way to define possible values
+                .value_parser(["horizontal", "vertical", "grid2x2", "grid3x1", "tabbed"]), // Grid and custom layouts need the config file
         )
         .arg(
             Arg::new("proton")
@@ -52,12 +75,102 @@ pub fn build_cli() -> Command {
                 .help("Use Proton to launch Windows games")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("wine")
+                .long("wine")
+                .help("Use the system's Wine to launch Windows games")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("proton"),
+        )
+        .arg(
+            Arg::new("flatpak")
+                .long("flatpak")
+                .value_name("APP_ID")
+                .help("Launch the installed Flatpak app with this id (e.g. org.example.Game) instead of a game executable")
+                .conflicts_with_all(["game_executable", "proton", "wine"]),
+        )
         .arg(
             Arg::new("gui")
                 .long("gui")
                 .help("Launch the graphical user interface")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .help("Set up and run a session from a text interface in the terminal; --game-executable and --instances fill in its first values")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["gui", "headless", "from_session", "export_profile"]),
+        )
+        .arg(
+            Arg::new("from_session")
+                .long("from-session")
+                .value_name("PATH")
+                .help("Re-launch the session recorded in a session.json manifest")
+                .conflicts_with_all(["game_executable", "flatpak", "instances", "input_devices", "assign_by_button", "assign", "mirror_input", "layout", "proton", "wine"]),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Launch a saved profile; other options override its settings")
+                .conflicts_with("from_session"),
+        )
+        .arg(
+            Arg::new("save_profile")
+                .long("save-profile")
+                .value_name("NAME")
+                .help("Save this launch's settings as a named profile before launching")
+                .conflicts_with("from_session"),
+        )
+        .arg(
+            Arg::new("export_profile")
+                .long("export-profile")
+                .value_name("PATH")
+                .help("Write the game's detected profile, with what earlier launches taught, to a TOML file for sharing, then exit without launching")
+                .conflicts_with_all(["from_session", "gui"]),
+        )
+        .arg(
+            Arg::new("capture_packets")
+                .long("capture-packets")
+                .value_name("PATH")
+                .help("Write every packet the network relay forwards or drops to a pcap file for Wireshark"),
+        )
+        .arg(
+            Arg::new("no_network")
+                .long("no-network")
+                .help("Skip the network emulator, for games played only on this screen")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("capture_packets"),
+        )
+        .arg(
+            Arg::new("print_config")
+                .long("print-config")
+                .help("Print the configuration this launch would use, after merging the config file, profile and options, then exit without launching")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["gui", "assign_by_button", "save_profile"]),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .help("Launch without a display: set up networking and input but skip window layout")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("gui"),
+        )
+        .arg(
+            Arg::new("run_as")
+                .long("run-as")
+                .value_name("USER")
+                .help("Run the next instance as this user via sudo; provide once per instance. Needs passwordless sudo to each user")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("enumerate_json")
+                .long("enumerate-json")
+                .value_name("PATH")
+                .help("Write every input device's identifier, keys, axes and force feedback effects to a JSON file, to attach to bug reports")
+                .conflicts_with_all(["game_executable", "profile", "from_session", "gui"]),
+        )
         .arg(
             Arg::new("config")
                 .short('c')
@@ -80,6 +193,179 @@ pub fn build_cli() -> Command {
                 .help("Enable verbose output")
                 .action(clap::ArgAction::Count),
         )
+        .subcommand(
+            Command::new("adaptive-stats")
+                .about("Print what adaptive learning has recorded for each game"),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Print the version and what Hydra found on this system (useful for bug reports)"),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Check the config for problems launching would run into, without launching anything"),
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Measure the network relay's throughput and latency and the input routing's speed, for comparing builds")
+                .arg(
+                    Arg::new("instances")
+                        .long("instances")
+                        .value_name("N")
+                        .help("Relay sockets to set up, one per simulated game instance")
+                        .value_parser(clap::value_parser!(u8).range(1..=8))
+                        .default_value("2"),
+                )
+                .arg(
+                    Arg::new("rate")
+                        .long("rate")
+                        .value_name("PACKETS_PER_SECOND")
+                        .help("Packets to send per second over all instances")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .default_value("10000"),
+                )
+                .arg(
+                    Arg::new("seconds")
+                        .long("seconds")
+                        .value_name("SECONDS")
+                        .help("How long to send packets for")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("5"),
+                )
+                .arg(
+                    Arg::new("payload")
+                        .long("payload")
+                        .value_name("BYTES")
+                        .help("Size of each packet")
+                        .value_parser(clap::value_parser!(u16).range(8..))
+                        .default_value("256"),
+                )
+                .arg(
+                    Arg::new("input_events")
+                        .long("input-events")
+                        .value_name("N")
+                        .help("Synthetic input events to route")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("1000000"),
+                ),
+        )
+        .subcommand(
+            Command::new("ctl")
+                .about("Send a command to the running session through its control socket")
+                .arg(
+                    Arg::new("command")
+                        .value_name("COMMAND")
+                        .help("Command to send")
+                        .required(true)
+                        .value_parser(crate::control::ControlCommand::ALL.map(|command| command.as_str())),
+                ),
+        )
+        .subcommand(
+            Command::new("prefixes")
+                .about("Manage the WINEPREFIXes Hydra created for Proton and Wine games")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("Show each prefix with its game, size and when it was last used"))
+                .subcommand(
+                    Command::new("clean")
+                        .about("Delete the prefixes no game has used for a while")
+                        .arg(
+                            Arg::new("days")
+                                .long("days")
+                                .value_name("DAYS")
+                                .help("Delete prefixes unused for this many days")
+                                .value_parser(clap::value_parser!(u64))
+                                .default_value("30"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Delete every prefix of a game")
+                        .arg(
+                            Arg::new("game")
+                                .value_name("GAME")
+                                .help("The game's executable file name, as shown by `prefixes list`")
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("layout")
+                .about("Arrange the windows of games that are already running, without launching anything")
+                .arg(
+                    Arg::new("pids")
+                        .long("pids")
+                        .value_name("PID,PID,...")
+                        .help("Process IDs of the games' windows, in player order")
+                        .value_parser(clap::value_parser!(u32))
+                        .value_delimiter(',')
+                        .num_args(1..)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("layout")
+                        .short('l')
+                        .long("layout")
+                        .value_name("LAYOUT")
+                        .help("Layout to arrange the windows in; defaults to window_layout from the config file")
+                        .value_parser(["horizontal", "vertical", "grid2x2", "grid3x1", "tabbed"]),
+                ),
+        )
+        .subcommand(
+            Command::new("netemu")
+                .about("Run the network relay on its own, for testing a game's netcode without launching it through Hydra")
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Game port of one instance, which gets its own relay socket (repeat once per instance)")
+                        .value_parser(clap::value_parser!(u16))
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("route")
+                        .long("route")
+                        .value_name("SRC=DST")
+                        .help("Forward packets from address SRC to address DST, e.g. 127.0.0.1:7777=127.0.0.1:7778 (repeatable)")
+                        .value_parser(parse_route)
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("routes_file")
+                        .long("routes-file")
+                        .value_name("PATH")
+                        .help("Also forward along the routes listed in this TOML or JSON file"),
+                )
+                .group(ArgGroup::new("relay").args(["port", "route", "routes_file"]).multiple(true).required(true))
+                .arg(
+                    Arg::new("loopback_per_instance")
+                        .long("loopback-per-instance")
+                        .help("Bind instance N's relay socket to 127.0.0.(N+1) instead of 127.0.0.1")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("capture")
+                        .long("capture")
+                        .value_name("PATH")
+                        .help("Write every packet the relay forwards or drops to a pcap file for Wireshark"),
+                )
+                .arg(
+                    Arg::new("stats_interval")
+                        .long("stats-interval")
+                        .value_name("SECONDS")
+                        .help("How often to print packet counts")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("5"),
+                ),
+        )
+}
+
+/// Parses a `--route` value: two socket addresses joined by `=`.
+fn parse_route(value: &str) -> Result<(SocketAddr, SocketAddr), String> {
+    let (src, dst) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected SRC=DST, e.g. 127.0.0.1:7777=127.0.0.1:7778, not '{}'", value))?;
+    let parse = |addr: &str| addr.trim().parse::<SocketAddr>().map_err(|e| format!("'{}' is not an address and port: {}", addr, e));
+    Ok((parse(src)?, parse(dst)?))
 }
 
 // Test code moved into a test module
@@ -99,6 +385,209 @@ mod tests {
         build_cli().debug_assert(); // clap's built-in debug assertion
     }
 
+    #[test]
+    fn test_adaptive_stats_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "adaptive-stats"])
+            .expect("adaptive-stats should parse");
+        assert_eq!(matches.subcommand_name(), Some("adaptive-stats"));
+    }
+
+    #[test]
+    fn test_lint_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "lint"])
+            .expect("lint should parse");
+        assert_eq!(matches.subcommand_name(), Some("lint"));
+    }
+
+    #[test]
+    fn test_bench_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "bench", "--instances", "4", "--rate", "50000"])
+            .expect("bench should parse");
+        let (name, bench) = matches.subcommand().unwrap();
+        assert_eq!(name, "bench");
+        assert_eq!(bench.get_one::<u8>("instances"), Some(&4));
+        assert_eq!(bench.get_one::<u32>("rate"), Some(&50000));
+        assert_eq!(bench.get_one::<u64>("seconds"), Some(&5));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "bench", "--payload", "4"]);
+        assert!(result.is_err(), "packets must hold the timestamp");
+    }
+
+    #[test]
+    fn test_info_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "info"])
+            .expect("info should parse");
+        assert_eq!(matches.subcommand_name(), Some("info"));
+    }
+
+    #[test]
+    fn test_enumerate_json() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--enumerate-json", "devices.json"])
+            .expect("--enumerate-json should parse");
+        assert_eq!(matches.get_one::<String>("enumerate_json").map(|s| s.as_str()), Some("devices.json"));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--enumerate-json", "devices.json", "--game", "/games/game"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_export_profile() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--game", "/games/game", "--export-profile", "game.toml"])
+            .expect("--export-profile should parse");
+        assert_eq!(matches.get_one::<String>("export_profile").map(|s| s.as_str()), Some("game.toml"));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--export-profile", "game.toml", "--gui"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_prefixes_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "prefixes", "clean", "--days", "7"])
+            .expect("prefixes clean should parse");
+        let (_, prefixes) = matches.subcommand().unwrap();
+        let (action, clean) = prefixes.subcommand().unwrap();
+        assert_eq!(action, "clean");
+        assert_eq!(clean.get_one::<u64>("days"), Some(&7));
+
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "prefixes", "remove", "Borderlands.exe"])
+            .expect("prefixes remove should parse");
+        let (_, prefixes) = matches.subcommand().unwrap();
+        assert_eq!(prefixes.subcommand().unwrap().1.get_one::<String>("game").map(|s| s.as_str()), Some("Borderlands.exe"));
+
+        assert!(build_cli().try_get_matches_from(vec![command_name(), "prefixes"]).is_err());
+    }
+
+    #[test]
+    fn test_layout_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "layout", "--pids", "1234,1235", "--layout", "vertical"])
+            .expect("layout should parse");
+        let (name, layout) = matches.subcommand().unwrap();
+        assert_eq!(name, "layout");
+        let pids: Vec<u32> = layout.get_many::<u32>("pids").unwrap().copied().collect();
+        assert_eq!(pids, [1234, 1235]);
+        assert_eq!(layout.get_one::<String>("layout").map(String::as_str), Some("vertical"));
+
+        assert!(build_cli().try_get_matches_from(vec![command_name(), "layout"]).is_err());
+        assert!(build_cli().try_get_matches_from(vec![command_name(), "layout", "--pids", "12,abc"]).is_err());
+    }
+
+    #[test]
+    fn test_netemu_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![
+                command_name(), "netemu", "--port", "7777", "--port", "7778",
+                "--route", "127.0.0.1:9000=127.0.0.1:9001",
+            ])
+            .expect("netemu should parse");
+        let (name, netemu) = matches.subcommand().unwrap();
+        assert_eq!(name, "netemu");
+        let ports: Vec<u16> = netemu.get_many::<u16>("port").unwrap().copied().collect();
+        assert_eq!(ports, [7777, 7778]);
+        let routes: Vec<_> = netemu.get_many::<(SocketAddr, SocketAddr)>("route").unwrap().collect();
+        assert_eq!(routes, [&("127.0.0.1:9000".parse().unwrap(), "127.0.0.1:9001".parse().unwrap())]);
+        assert_eq!(netemu.get_one::<u64>("stats_interval"), Some(&5));
+
+        // Something has to be relayed
+        assert!(build_cli().try_get_matches_from(vec![command_name(), "netemu"]).is_err());
+        assert!(build_cli().try_get_matches_from(vec![command_name(), "netemu", "--route", "127.0.0.1:9000"]).is_err());
+    }
+
+    #[test]
+    fn test_assign_by_button_conflicts_with_input_devices() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--game", "/games/game", "--instances", "2", "--assign-by-button"])
+            .expect("--assign-by-button should parse");
+        assert!(matches.get_flag("assign_by_button"));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--assign-by-button", "--input-devices", "Pad"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_assign_flag() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--game", "/games/game", "--instances", "3", "-d", "Pad", "--assign", "0=id:045e:028e;2=none"])
+            .expect("--assign should parse alongside --input-devices");
+        assert_eq!(matches.get_one::<String>("assign").map(String::as_str), Some("0=id:045e:028e;2=none"));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--assign-by-button", "--assign", "0=auto"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_flatpak_flag() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--flatpak", "org.example.Game", "--instances", "2"])
+            .expect("--flatpak should parse");
+        assert_eq!(matches.get_one::<String>("flatpak").map(String::as_str), Some("org.example.Game"));
+
+        for other in [vec!["--game", "/games/game"], vec!["--proton"]] {
+            let mut args = vec![command_name(), "--flatpak", "org.example.Game"];
+            args.extend(other);
+            let result = build_cli().try_get_matches_from(args);
+            assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+        }
+    }
+
+    #[test]
+    fn test_print_config_flag() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--game", "/games/game", "--instances", "2", "--print-config"])
+            .expect("--print-config should parse");
+        assert!(matches.get_flag("print_config"));
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--gui", "--print-config"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+        // Printing never prompts for buttons or writes a profile
+        for flag in [vec!["--assign-by-button"], vec!["--save-profile", "coop"]] {
+            let mut args = vec![command_name(), "--game", "/games/game", "--instances", "2", "--print-config"];
+            args.extend(flag);
+            let result = build_cli().try_get_matches_from(args);
+            assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+        }
+    }
+
+    #[test]
+    fn test_ctl_subcommand() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "ctl", "pause-input"])
+            .expect("ctl should parse");
+        let (name, sub) = matches.subcommand().unwrap();
+        assert_eq!(name, "ctl");
+        assert_eq!(sub.get_one::<String>("command").map(|s| s.as_str()), Some("pause-input"));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "ctl", "reboot"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::InvalidValue);
+    }
+
+    #[test]
+    fn test_profile_with_overrides() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--profile", "Borderlands 4P", "-i", "2"])
+            .expect("--profile should combine with overrides");
+        assert_eq!(matches.get_one::<String>("profile").map(|s| s.as_str()), Some("Borderlands 4P"));
+        assert_eq!(matches.get_one::<u32>("instances"), Some(&2));
+    }
+
+    #[test]
+    fn test_wine_conflicts_with_proton() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--game", "/games/Game.exe", "--wine"])
+            .expect("--wine should parse");
+        assert!(matches.get_flag("wine"));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--game", "/games/Game.exe", "--wine", "--proton"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
     #[test]
     fn test_no_arguments_is_ok() {
         // All CLI args are optional (GUI mode launches with none), so parsing with
@@ -165,5 +654,53 @@ mod tests {
      }
 
 
+     #[test]
+     fn test_from_session_conflicts_with_game_executable() {
+         let cmd = build_cli();
+         let result = cmd.try_get_matches_from(vec![
+             command_name(),
+             "--from-session", "/tmp/session.json",
+             "-g", "/path/to/game",
+         ]);
+         assert!(result.is_err());
+         assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+     }
+
+    #[test]
+    fn test_headless_conflicts_with_gui() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "-g", "/path/to/game", "-i", "2", "--headless"])
+            .expect("--headless should parse");
+        assert!(matches.get_flag("headless"));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--headless", "--gui"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_no_network_flag() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "-g", "/path/to/game", "-i", "2", "--no-network"])
+            .expect("--no-network should parse");
+        assert!(matches.get_flag("no_network"));
+
+        let result = build_cli().try_get_matches_from(vec![
+            command_name(), "-g", "/path/to/game", "--no-network", "--capture-packets", "/tmp/hydra.pcap",
+        ]);
+        assert!(result.is_err(), "--no-network with --capture-packets should be rejected");
+    }
+
+    #[test]
+    fn test_tui_flag() {
+        let matches = build_cli()
+            .try_get_matches_from(vec![command_name(), "--tui", "-g", "/path/to/game", "-i", "3"])
+            .expect("--tui should accept a game and instance count");
+        assert!(matches.get_flag("tui"));
+        assert_eq!(matches.get_one::<u32>("instances"), Some(&3));
+
+        let result = build_cli().try_get_matches_from(vec![command_name(), "--tui", "--gui"]);
+        assert_eq!(result.unwrap_err().kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
     // Add more tests for various argument combinations and edge cases
 }
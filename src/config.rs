@@ -1,17 +1,70 @@
+use serde::de::{self, Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
+use std::collections::BTreeMap;
+use std::io;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use log::{info, warn, error, debug};
 use std::error::Error;
+use crate::input_mux::{InputBackendKind, SyncMode};
+use crate::ports::PortStrategy;
+use crate::universal_launcher::{
+    AlreadyRunningPolicy, CopyPattern, LaunchPolicy, WindowsRuntime, DEFAULT_INSTANCE_COPY_EXCLUDE,
+    DEFAULT_INSTANCE_COPY_INCLUDE,
+};
+use crate::window_manager::{Hotkey, Layout, LayoutFailurePolicy, WindowRect};
+
+/// What [`Config::describe_effective`] prints. Plain values come before
+/// the tables, as TOML requires.
+#[derive(Serialize)]
+struct EffectiveConfig<'a> {
+    game: Option<PathBuf>,
+    instances: usize,
+    runtime: WindowsRuntime,
+    layout: String,
+    remove_window_decorations: bool,
+    network_emulation: bool,
+    /// Which of the ways of keeping the instances apart are on
+    separation: Vec<String>,
+    #[serde(rename = "player")]
+    players: Vec<EffectivePlayer>,
+    environment: BTreeMap<String, String>,
+    settings: &'a Config,
+}
+
+/// One player's part of [`EffectiveConfig`]
+#[derive(Serialize)]
+struct EffectivePlayer {
+    player: usize,
+    input: String,
+    port: Option<u16>,
+    run_as: Option<String>,
+    args: Vec<String>,
+}
 
 /// Configuration validation errors
 #[derive(Debug)]
 pub enum ValidationError {
     InvalidInstanceCount(usize),
+    /// `input_mappings` has more entries than there are instances
+    InstanceCountMismatch { instances: usize, mappings: usize },
     InvalidNetworkPort(u16),
     MissingGamePath,
     InvalidGamePath(PathBuf),
+    MissingGoldbergPath,
+    InvalidGoldbergPath(PathBuf),
+    InvalidConfidenceThreshold(f64),
+    InvalidAspectRatio(String),
+    InvalidOpacity(f64),
+    NotEnoughRunAsUsers { users: usize, instances: usize },
+    TooManyInstanceArgs { entries: usize, instances: usize },
+    UnknownLayout(String),
+    InvalidLayout(String),
+    InvalidHotkey(String),
+    InvalidReservedKey(String),
+    InvalidCopyPattern(String),
+    InvalidWindowSearchBackoff(u64),
 }
 
 impl std::fmt::Display for ValidationError {
@@ -20,6 +73,9 @@ impl std::fmt::Display for ValidationError {
             ValidationError::InvalidInstanceCount(count) => {
                 write!(f, "Invalid instance count: {}. Must be between 1 and {}", count, crate::defaults::MAX_INSTANCES)
             }
+            ValidationError::InstanceCountMismatch { instances, mappings } => {
+                write!(f, "input_mappings has {} entries but instances is {}; list one input per player", mappings, instances)
+            }
             ValidationError::InvalidNetworkPort(port) => {
                 write!(f, "Invalid network port: {}. Must be between 1024 and 65535", port)
             }
@@ -29,6 +85,45 @@ impl std::fmt::Display for ValidationError {
             ValidationError::InvalidGamePath(path) => {
                 write!(f, "Invalid game executable path: {}", path.display())
             }
+            ValidationError::MissingGoldbergPath => {
+                write!(f, "use_goldberg is enabled but no goldberg_path is set")
+            }
+            ValidationError::InvalidGoldbergPath(path) => {
+                write!(f, "Invalid Goldberg emulator directory: {}", path.display())
+            }
+            ValidationError::InvalidConfidenceThreshold(value) => {
+                write!(f, "Invalid adaptive confidence threshold: {}. Must be between 0.0 and 1.0", value)
+            }
+            ValidationError::InvalidAspectRatio(value) => {
+                write!(f, "Invalid aspect ratio: '{}'. Use a form like \"16:9\" or \"1.78\"", value)
+            }
+            ValidationError::InvalidOpacity(value) => {
+                write!(f, "Invalid window opacity: {}. Must be above 0.0 and at most 1.0", value)
+            }
+            ValidationError::NotEnoughRunAsUsers { users, instances } => {
+                write!(f, "run_as_users lists {} user(s) but {} instances are configured; each instance needs its own user", users, instances)
+            }
+            ValidationError::TooManyInstanceArgs { entries, instances } => {
+                write!(f, "per_instance_args has {} entries but only {} instances are configured", entries, instances)
+            }
+            ValidationError::UnknownLayout(name) => {
+                write!(f, "Unknown layout '{}' in remove_window_decorations_by_layout", name)
+            }
+            ValidationError::InvalidLayout(reason) => {
+                write!(f, "Invalid window_layout: {}", reason)
+            }
+            ValidationError::InvalidHotkey(hotkey) => {
+                write!(f, "Invalid tab_cycle_hotkey '{}': use a key such as F12 or Scroll_Lock, optionally after Ctrl+, Shift+, Alt+ or Super+", hotkey)
+            }
+            ValidationError::InvalidReservedKey(key) => {
+                write!(f, "Invalid key '{}' in host_reserved_keys: use a Linux key name such as KEY_LEFTMETA or KEY_VOLUMEUP", key)
+            }
+            ValidationError::InvalidWindowSearchBackoff(ms) => {
+                write!(f, "Invalid window search backoff: {} ms. Must be at least 1 ms", ms)
+            }
+            ValidationError::InvalidCopyPattern(pattern) => {
+                write!(f, "Invalid pattern '{}' in instance_copy_include or instance_copy_exclude: use globs such as \"*.dll\" or \"scripts/\"", pattern)
+            }
         }
     }
 }
@@ -42,6 +137,8 @@ pub enum ConfigError {
     TomlDeError(toml::de::Error),
     TomlSeError(toml::ser::Error),
     Validation(ValidationError),
+    ProfileNotFound(String),
+    InvalidProfileName(String),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -51,6 +148,8 @@ impl std::fmt::Display for ConfigError {
             ConfigError::TomlDeError(e) => write!(f, "Configuration deserialization error: {}", e),
             ConfigError::TomlSeError(e) => write!(f, "Configuration serialization error: {}", e),
             ConfigError::Validation(e) => write!(f, "Configuration validation error: {}", e),
+            ConfigError::ProfileNotFound(name) => write!(f, "No profile named '{}'", name),
+            ConfigError::InvalidProfileName(name) => write!(f, "Invalid profile name: '{}'", name),
         }
     }
 }
@@ -62,6 +161,7 @@ impl Error for ConfigError {
             ConfigError::TomlDeError(e) => Some(e),
             ConfigError::TomlSeError(e) => Some(e),
             ConfigError::Validation(e) => Some(e),
+            ConfigError::ProfileNotFound(_) | ConfigError::InvalidProfileName(_) => None,
         }
     }
 }
@@ -91,17 +191,462 @@ impl From<ValidationError> for ConfigError {
     }
 }
 
+/// How game windows are arranged, as written in the config file: the name of
+/// a preset (`window_layout = "vertical"`) or a table for grid and custom
+/// layouts (`window_layout = { grid = { cols = 3, rows = 2 } }`).
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutConfig {
+    #[default]
+    Horizontal,
+    Vertical,
+    Grid2x2,
+    Grid3x1,
+    /// `cols` by `rows` cells per monitor
+    Grid { cols: u8, rows: u8 },
+    /// One rectangle per instance, in desktop pixels
+    Custom { rects: Vec<WindowRect> },
+    /// Every window fullscreen on the first monitor, switched with `tab_cycle_hotkey`
+    Tabbed,
+}
+
+impl LayoutConfig {
+    /// The layout the window manager applies
+    pub fn layout(&self) -> Layout {
+        match self {
+            LayoutConfig::Horizontal => Layout::Horizontal,
+            LayoutConfig::Vertical => Layout::Vertical,
+            LayoutConfig::Grid2x2 => Layout::Grid2x2,
+            LayoutConfig::Grid3x1 => Layout::Grid3x1,
+            LayoutConfig::Grid { cols, rows } => Layout::Grid { cols: *cols, rows: *rows },
+            LayoutConfig::Custom { .. } => Layout::Custom,
+            LayoutConfig::Tabbed => Layout::Tabbed,
+        }
+    }
+
+    /// Window positions of a custom layout; empty for every other layout
+    pub fn custom_rects(&self) -> &[WindowRect] {
+        match self {
+            LayoutConfig::Custom { rects } => rects,
+            _ => &[],
+        }
+    }
+
+    fn validate(&self, instance_count: usize) -> Result<(), ValidationError> {
+        match self {
+            LayoutConfig::Grid { cols, rows } if *cols == 0 || *rows == 0 => Err(ValidationError::InvalidLayout(
+                "a grid needs at least one column and one row".to_string(),
+            )),
+            LayoutConfig::Custom { rects } if rects.len() < instance_count => Err(ValidationError::InvalidLayout(format!(
+                "the custom layout has {} rectangle(s) but {} instances are configured",
+                rects.len(),
+                instance_count
+            ))),
+            LayoutConfig::Custom { rects } if rects.iter().any(|rect| rect.width == 0 || rect.height == 0) => Err(
+                ValidationError::InvalidLayout("custom layout rectangles need a width and height".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<Layout> for LayoutConfig {
+    /// Custom layouts convert without rectangles.
+    fn from(layout: Layout) -> Self {
+        match layout {
+            Layout::Horizontal => LayoutConfig::Horizontal,
+            Layout::Vertical => LayoutConfig::Vertical,
+            Layout::Grid2x2 => LayoutConfig::Grid2x2,
+            Layout::Grid3x1 => LayoutConfig::Grid3x1,
+            Layout::Grid { cols, rows } => LayoutConfig::Grid { cols, rows },
+            Layout::Custom => LayoutConfig::Custom { rects: Vec::new() },
+            Layout::Tabbed => LayoutConfig::Tabbed,
+        }
+    }
+}
+
+impl FromStr for LayoutConfig {
+    type Err = ValidationError;
+
+    /// Parses a preset name, ignoring case.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "horizontal" => Ok(LayoutConfig::Horizontal),
+            "vertical" => Ok(LayoutConfig::Vertical),
+            "grid2x2" => Ok(LayoutConfig::Grid2x2),
+            "grid3x1" => Ok(LayoutConfig::Grid3x1),
+            "tabbed" => Ok(LayoutConfig::Tabbed),
+            "grid" | "custom" => Err(ValidationError::InvalidLayout(format!(
+                "'{}' needs settings; write it as a table, e.g. {{ grid = {{ cols = 3, rows = 2 }} }}",
+                name
+            ))),
+            _ => Err(ValidationError::InvalidLayout(format!(
+                "unknown layout '{}'; expected horizontal, vertical, grid2x2, grid3x1, tabbed, a grid or a custom layout",
+                name
+            ))),
+        }
+    }
+}
+
+/// The table forms of `LayoutConfig`
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+enum LayoutTable {
+    Grid { cols: u8, rows: u8 },
+    Custom { rects: Vec<WindowRect> },
+}
+
+impl<'de> Deserialize<'de> for LayoutConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LayoutVisitor;
+
+        impl<'de> Visitor<'de> for LayoutVisitor {
+            type Value = LayoutConfig;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a layout name or a grid or custom layout table")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<LayoutConfig, E> {
+                // Older versions accepted "custom" without any positions and
+                // arranged the windows horizontally.
+                if name.eq_ignore_ascii_case("custom") {
+                    warn!("window_layout = \"custom\" lists no window positions; using the horizontal layout.");
+                    return Ok(LayoutConfig::Horizontal);
+                }
+                name.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<LayoutConfig, A::Error> {
+                Ok(match LayoutTable::deserialize(de::value::MapAccessDeserializer::new(map))? {
+                    LayoutTable::Grid { cols, rows } => LayoutConfig::Grid { cols, rows },
+                    LayoutTable::Custom { rects } => LayoutConfig::Custom { rects },
+                })
+            }
+        }
+
+        deserializer.deserialize_any(LayoutVisitor)
+    }
+}
+
 /// Represents the application's configuration.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)] // Added Default derive
 pub struct Config {
-    pub game_paths: Vec<PathBuf>, // Use PathBuf for paths
+    /// Recently used games, most recent first; the first one is launched
+    pub game_paths: Vec<PathBuf>,
+    /// How many instances (players) to launch. Older files without it get
+    /// one per entry in `input_mappings`, see `Config::load`
+    #[serde(default)]
+    pub instances: usize,
+    /// Input for each player, `instances` entries long
     pub input_mappings: Vec<String>, // Store input mappings (names or serialized IDs)
-    pub window_layout: String, // Store layout as a string (e.g., "horizontal", "vertical")
+    pub window_layout: LayoutConfig,
     pub network_ports: Vec<u16>, // Ports the game instances use for network communication
+    /// Where the instances' ports come from; unset means `network_ports`,
+    /// see `Config::port_strategy`
+    #[serde(default)]
+    pub port_strategy: Option<PortStrategy>,
     pub use_proton: bool, // Added use_proton field
+    /// What runs Windows games: `none`, `proton` or `wine`. `use_proton =
+    /// true` is the same as `proton`, see `Config::runtime`
+    #[serde(default)]
+    pub windows_runtime: WindowsRuntime,
+    /// How long to keep looking for Proton while Steam may still be
+    /// installing it, in seconds; 0 gives up right away
+    #[serde(default)]
+    pub proton_wait_secs: u64,
+    /// Have Proton write its debug log (`PROTON_LOG`) for each instance
+    #[serde(default)]
+    pub proton_log: bool,
+    /// Where Proton logs go when `proton_log` is set; defaults to
+    /// `proton-logs` in the data directory
+    #[serde(default)]
+    pub proton_log_dir: Option<PathBuf>,
+    /// Directory holding the Goldberg Steam emulator libraries
+    #[serde(default)]
+    pub goldberg_path: Option<PathBuf>,
+    /// Stage Goldberg into each instance when a Steamworks game is detected
+    #[serde(default)]
+    pub use_goldberg: bool,
+    /// Relaunch instances that crash shortly after starting
+    #[serde(default)]
+    pub restart_on_early_crash: bool,
+    /// Learn from launches and apply adaptive recommendations
+    #[serde(default = "default_adaptive_enabled")]
+    pub adaptive_enabled: bool,
+    /// Minimum confidence (0.0-1.0) an adaptive recommendation needs to be applied
+    #[serde(default = "default_adaptive_confidence_threshold")]
+    pub adaptive_confidence_threshold: f64,
+    /// Shortest time in seconds between writes of the adaptive store; changes
+    /// in between are written once the launch is over
+    #[serde(default = "default_adaptive_save_interval_secs")]
+    pub adaptive_save_interval_secs: u64,
+    /// Name template for virtual input devices; `{instance}` is the player
+    /// number and `{session}` a short per-session id
+    #[serde(default = "default_virtual_device_name_template")]
+    pub virtual_device_name_template: String,
+    /// Give each virtual device the name and IDs of its physical device
+    #[serde(default)]
+    pub mimic_controller_names: bool,
+    /// Tell SDL games about only their own player's virtual controller, so
+    /// it is the first controller in every instance
+    #[serde(default = "default_per_instance_controllers")]
+    pub per_instance_controllers: bool,
+    /// Hide every input device but its own player's virtual device from each
+    /// instance, see `InstanceSeparation::InputIsolation`
+    #[serde(default)]
+    pub isolate_input: bool,
+    /// Launch even when no input device can be opened, leaving the games
+    /// with only the desktop's keyboard and mouse
+    #[serde(default)]
+    pub allow_no_input: bool,
+    /// How keyboards and mice reach their instance: through virtual uinput
+    /// devices (`uinput`) or a separate X pointer and keyboard per instance
+    /// (`xinput2`, X11 only)
+    #[serde(default)]
+    pub input_backend: InputBackendKind,
+    /// Where the events forwarded to virtual devices are grouped into
+    /// reports: where the physical device grouped them (`source`), or after
+    /// every key or button event as older versions did (`after_keys`)
+    #[serde(default)]
+    pub input_sync: SyncMode,
+    /// Keys that only the host session sees and no game gets, by Linux key
+    /// name (`KEY_LEFTMETA`, `KEY_VOLUMEUP`, ...)
+    #[serde(default)]
+    pub host_reserved_keys: Vec<String>,
+    /// Text only instance `{instance}`'s window title contains, used to find
+    /// windows when PIDs can't tell instances apart (e.g. under Proton)
+    #[serde(default)]
+    pub window_title_marker: Option<String>,
+    /// Run the network emulator for games that network over raw UDP; off
+    /// for games played purely on one screen
+    #[serde(default = "default_enable_network_emulation")]
+    pub enable_network_emulation: bool,
+    /// Write every packet the network relay handles to this pcap file
+    #[serde(default)]
+    pub net_capture_path: Option<PathBuf>,
+    /// File of extra relay routes for the network emulator, see
+    /// `NetEmulator::load_routes`
+    #[serde(default)]
+    pub net_routes_file: Option<PathBuf>,
+    /// Give each instance its own loopback address (127.0.0.2, 127.0.0.3,
+    /// ...) so the relay can tell apart games that all send from 127.0.0.1
+    #[serde(default)]
+    pub loopback_per_instance: bool,
+    /// How long input capture threads wait for events before checking whether
+    /// to stop, in milliseconds (default 100)
+    #[serde(default)]
+    pub input_poll_timeout_ms: Option<u64>,
+    /// Letterbox each window to its aspect ratio instead of filling its layout cell
+    #[serde(default)]
+    pub preserve_aspect_ratio: bool,
+    /// Aspect ratio per instance ("16:9", "4:3", "1.6", ...); instances
+    /// without an entry use 16:9
+    #[serde(default)]
+    pub aspect_ratios: Vec<String>,
+    /// Don't touch the display at all: launch instances and set up
+    /// networking and input, but skip window management (servers, CI)
+    #[serde(default)]
+    pub headless: bool,
+    /// Keep game windows above other windows for the whole session
+    #[serde(default)]
+    pub keep_above: bool,
+    /// Add "[Player N]" to each game window's title, and put it back when
+    /// the game changes its title
+    #[serde(default)]
+    pub label_windows: bool,
+    /// Stack the game windows in player order, Player 1 on top, so
+    /// keyboard window switching goes through the players in turn
+    #[serde(default = "default_restack_windows")]
+    pub restack_windows: bool,
+    /// Opacity of each player's window, from 0.0 to 1.0, to tell players
+    /// apart; players without an entry stay opaque. Needs a compositor
+    #[serde(default)]
+    pub window_opacity: Vec<f64>,
+    /// In the tabbed layout, dim the windows behind the front one to this
+    /// opacity
+    #[serde(default)]
+    pub dim_unfocused_opacity: Option<f64>,
+    /// Key that brings the next game window to the front in the tabbed layout
+    #[serde(default = "default_tab_cycle_hotkey")]
+    pub tab_cycle_hotkey: String,
+    /// What to do when some game windows don't appear in time
+    #[serde(default)]
+    pub on_layout_failure: LayoutFailurePolicy,
+    /// Strip titlebars and borders from game windows so they tile edge to edge
+    #[serde(default = "default_remove_window_decorations")]
+    pub remove_window_decorations: bool,
+    /// Overrides `remove_window_decorations` for individual layouts, keyed by
+    /// layout name ("horizontal", "vertical", "grid2x2", "grid3x1")
+    #[serde(default)]
+    pub remove_window_decorations_by_layout: BTreeMap<String, bool>,
+    /// Start each game at the size of its layout cell (through engine
+    /// options and `${WIDTH}`/`${HEIGHT}`) instead of resizing it afterwards
+    #[serde(default = "default_match_layout_resolution")]
+    pub match_layout_resolution: bool,
+    /// How long to wait for game windows to appear (default 30 seconds)
+    #[serde(default)]
+    pub window_search_timeout_secs: Option<u64>,
+    /// First delay between window lookups (default 50 ms), doubled on each miss
+    #[serde(default)]
+    pub window_search_initial_backoff_ms: Option<u64>,
+    /// Longest delay between window lookups (default 500 ms)
+    #[serde(default)]
+    pub window_search_max_backoff_ms: Option<u64>,
+    /// Run instance N as the Nth user in this list via `sudo -u`; empty runs
+    /// every instance as the current user
+    #[serde(default)]
+    pub run_as_users: Vec<String>,
+    /// Extra launch arguments for each instance, indexed by instance.
+    /// `${INSTANCE}` is replaced with the player number and `${PORT}` with the
+    /// instance's network port, see `template::expand_template`
+    #[serde(default)]
+    pub per_instance_args: Vec<Vec<String>>,
+    /// Program to run before the instances start; a non-zero exit aborts
+    /// the launch
+    #[serde(default)]
+    pub pre_launch_hook: Option<PathBuf>,
+    /// Program to run once the instances have started, e.g. to start
+    /// recording; told the PIDs and ports through `HYDRA_*` variables
+    #[serde(default)]
+    pub post_launch_hook: Option<PathBuf>,
+    /// What to do when one instance fails to start: stop the others
+    /// (`all_or_nothing`) or play on without it (`best_effort`)
+    #[serde(default)]
+    pub launch_policy: LaunchPolicy,
+    /// How many instances may start at the same time; games that may not
+    /// allow two copies starting together always start one at a time
+    #[serde(default = "default_launch_concurrency")]
+    pub launch_concurrency: usize,
+    /// Minimum time between two instances starting, in milliseconds
+    #[serde(default)]
+    pub launch_delay_ms: u64,
+    /// Seconds the games get to close on their own when the session stops
+    /// or a launch is cancelled, before they are killed; 0 kills them at once
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// What to do when the game is already running outside Hydra: `warn`
+    /// and launch anyway, or `refuse` to launch
+    #[serde(default)]
+    pub already_running: AlreadyRunningPolicy,
+    /// Game files copied into each instance's own directory when instances
+    /// run from separate directories, as globs relative to the game
+    /// directory; a trailing `/` matches directories only
+    #[serde(default = "default_instance_copy_include")]
+    pub instance_copy_include: Vec<String>,
+    /// Game files never copied into instance directories, even when an
+    /// include matches them
+    #[serde(default = "default_instance_copy_exclude")]
+    pub instance_copy_exclude: Vec<String>,
+    /// Run each instance inside a Firejail sandbox
+    #[serde(default)]
+    pub firejail: Option<FirejailConfig>,
     // Add other configuration fields as needed (e.g., Proton path, advanced settings)
 }
 
+/// How game instances are sandboxed with Firejail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirejailConfig {
+    /// Firejail profile name or path; firejail picks its own when unset
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Use each instance's working directory as its home directory
+    /// (`--private=<dir>`), hiding the real one
+    #[serde(default = "default_firejail_private_home")]
+    pub private_home: bool,
+    /// Further firejail options, passed before the game command
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+impl Default for FirejailConfig {
+    fn default() -> Self {
+        Self {
+            profile: None,
+            private_home: default_firejail_private_home(),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+fn default_firejail_private_home() -> bool {
+    true
+}
+
+/// How many recently used games `game_paths` keeps
+pub const MAX_RECENT_GAMES: usize = 10;
+
+/// Move `path` to the front of a recently used games list, dropping the
+/// oldest entries beyond `MAX_RECENT_GAMES`.
+pub fn remember_recent_game(paths: &mut Vec<PathBuf>, path: PathBuf) {
+    paths.retain(|listed| *listed != path);
+    paths.insert(0, path);
+    paths.truncate(MAX_RECENT_GAMES);
+}
+
+fn expand(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(raw) => crate::utils::expand_path(raw),
+        None => path.to_path_buf(),
+    }
+}
+
+fn default_adaptive_enabled() -> bool {
+    true
+}
+
+fn default_adaptive_confidence_threshold() -> f64 {
+    0.6
+}
+
+fn default_per_instance_controllers() -> bool {
+    true
+}
+
+fn default_adaptive_save_interval_secs() -> u64 {
+    crate::adaptive_config::DEFAULT_SAVE_INTERVAL.as_secs()
+}
+
+fn default_remove_window_decorations() -> bool {
+    true
+}
+
+fn default_match_layout_resolution() -> bool {
+    true
+}
+
+fn default_restack_windows() -> bool {
+    true
+}
+
+fn default_enable_network_emulation() -> bool {
+    true
+}
+
+fn default_instance_copy_include() -> Vec<String> {
+    DEFAULT_INSTANCE_COPY_INCLUDE.iter().map(|pattern| pattern.to_string()).collect()
+}
+
+fn default_instance_copy_exclude() -> Vec<String> {
+    DEFAULT_INSTANCE_COPY_EXCLUDE.iter().map(|pattern| pattern.to_string()).collect()
+}
+
+fn default_virtual_device_name_template() -> String {
+    crate::input_mux::DEFAULT_DEVICE_NAME_TEMPLATE.to_string()
+}
+
+fn default_tab_cycle_hotkey() -> String {
+    "Scroll_Lock".to_string()
+}
+
+fn default_launch_concurrency() -> usize {
+    1
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    crate::defaults::SHUTDOWN_GRACE.as_secs()
+}
+
 impl Config {
     /// Loads the configuration from a TOML file.
     /// If the file does not exist, returns the default configuration.
@@ -111,7 +656,9 @@ impl Config {
             Ok(contents) => {
                 debug!("Read config file contents:\n{}", contents);
                 // Use the ? operator after mapping the error
-                let config: Config = toml::from_str(&contents)?;
+                let mut config: Config = toml::from_str(&contents)?;
+                config.expand_paths();
+                config.reconcile_instances();
                 Ok(config)
             }
             Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
@@ -142,10 +689,9 @@ impl Config {
         let toml_string = toml::to_string_pretty(self)?;
         debug!("Saving config contents:\n{}", toml_string);
 
-        // Use ? operator for file creation and map the error
-        let mut file = fs::File::create(path)?;
-        // Use ? operator for writing and map the error
-        file.write_all(toml_string.as_bytes())?;
+        // Write a temporary file and rename it over the old one, so an
+        // interrupted save leaves the previous config intact
+        crate::utils::write_atomic(path, toml_string.as_bytes())?;
 
         info!("Configuration saved successfully to {}", path.display());
         Ok(())
@@ -156,13 +702,180 @@ impl Config {
         info!("Generating default configuration.");
         Config {
             game_paths: Vec::new(),
+            instances: 2,
             input_mappings: vec!["Auto-detect".to_string(), "Auto-detect".to_string()], // Default for 2 players
-            window_layout: "horizontal".to_string(), // Default layout
+            window_layout: LayoutConfig::Horizontal,
             network_ports: vec![7777, 7778], // Example default ports for 2 instances
+            port_strategy: None,
             use_proton: false, // Default to not using Proton
+            windows_runtime: WindowsRuntime::None,
+            proton_wait_secs: 0,
+            proton_log: false,
+            proton_log_dir: None,
+            goldberg_path: None,
+            use_goldberg: false,
+            restart_on_early_crash: false,
+            adaptive_enabled: default_adaptive_enabled(),
+            adaptive_confidence_threshold: default_adaptive_confidence_threshold(),
+            adaptive_save_interval_secs: default_adaptive_save_interval_secs(),
+            per_instance_controllers: default_per_instance_controllers(),
+            isolate_input: false,
+            virtual_device_name_template: default_virtual_device_name_template(),
+            mimic_controller_names: false,
+            allow_no_input: false,
+            input_backend: InputBackendKind::Uinput,
+            input_sync: SyncMode::Source,
+            host_reserved_keys: Vec::new(),
+            window_title_marker: None,
+            enable_network_emulation: true,
+            net_capture_path: None,
+            net_routes_file: None,
+            loopback_per_instance: false,
+            input_poll_timeout_ms: None,
+            preserve_aspect_ratio: false,
+            aspect_ratios: Vec::new(),
+            headless: false,
+            keep_above: false,
+            label_windows: false,
+            restack_windows: default_restack_windows(),
+            window_opacity: Vec::new(),
+            dim_unfocused_opacity: None,
+            tab_cycle_hotkey: default_tab_cycle_hotkey(),
+            on_layout_failure: LayoutFailurePolicy::LayoutFound,
+            remove_window_decorations: default_remove_window_decorations(),
+            remove_window_decorations_by_layout: BTreeMap::new(),
+            match_layout_resolution: default_match_layout_resolution(),
+            window_search_timeout_secs: None,
+            window_search_initial_backoff_ms: None,
+            window_search_max_backoff_ms: None,
+            run_as_users: Vec::new(),
+            per_instance_args: Vec::new(),
+            pre_launch_hook: None,
+            post_launch_hook: None,
+            launch_policy: LaunchPolicy::AllOrNothing,
+            launch_concurrency: 1,
+            launch_delay_ms: 0,
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            already_running: AlreadyRunningPolicy::Warn,
+            instance_copy_include: default_instance_copy_include(),
+            instance_copy_exclude: default_instance_copy_exclude(),
+            firejail: None,
         }
     }
     
+    /// How the instances' ports are chosen: `port_strategy`, or the
+    /// `network_ports` list when it isn't set.
+    pub fn port_strategy(&self) -> PortStrategy {
+        self.port_strategy.clone().unwrap_or_else(|| PortStrategy::Fixed(self.network_ports.clone()))
+    }
+
+    /// The Windows runtime to launch with: `windows_runtime`, or Proton when
+    /// only `use_proton` is set.
+    pub fn runtime(&self) -> WindowsRuntime {
+        self.windows_runtime.or_proton(self.use_proton)
+    }
+
+    /// Sets the Windows runtime, keeping `use_proton` in step for older
+    /// versions reading the file.
+    pub fn set_runtime(&mut self, runtime: WindowsRuntime) {
+        self.windows_runtime = runtime;
+        self.use_proton = runtime == WindowsRuntime::Proton;
+    }
+
+    /// What a launch with this config would do, as TOML: the resolved game,
+    /// runtime, layout, separation and each player's input, port, user and
+    /// arguments, the environment variables that change where Hydra looks
+    /// for things, and every setting in full under `[settings]`.
+    pub fn describe_effective(&self) -> String {
+        let layout = match self.window_layout.layout() {
+            Layout::Grid { cols, rows } => format!("grid ({}x{})", cols, rows),
+            layout => layout.name().to_string(),
+        };
+        let ports = match self.port_strategy().allocate(self.instances) {
+            Ok(ports) => ports,
+            Err(e) => {
+                warn!("Could not work out the instances' ports: {}", e);
+                Vec::new()
+            }
+        };
+        let mut separation = Vec::new();
+        if !self.run_as_users.is_empty() {
+            separation.push("run_as_users".to_string());
+        }
+        if self.isolate_input {
+            separation.push("isolate_input".to_string());
+        }
+        if self.firejail.is_some() {
+            separation.push("firejail".to_string());
+        }
+        let players = (0..self.instances)
+            .map(|i| EffectivePlayer {
+                player: i + 1,
+                input: self.input_mappings.get(i).cloned().unwrap_or_else(|| "Auto-detect".to_string()),
+                port: ports.get(i).copied(),
+                run_as: self.run_as_users.get(i).cloned(),
+                args: self.per_instance_args.get(i).cloned().unwrap_or_default(),
+            })
+            .collect();
+        let environment = ["PROTON_PATH", "WINE_PATH", "CONFIG_PATH", "INPUT_PATH", "LOG_PATH", "RUST_LOG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name.to_string(), value)))
+            .collect();
+        let effective = EffectiveConfig {
+            game: self.primary_game_path().cloned(),
+            instances: self.instances,
+            runtime: self.runtime(),
+            layout,
+            remove_window_decorations: self.removes_window_decorations(self.window_layout.layout()),
+            network_emulation: self.enable_network_emulation,
+            separation,
+            players,
+            environment,
+            settings: self,
+        };
+        toml::to_string_pretty(&effective).unwrap_or_else(|e| format!("# Could not describe the configuration: {}\n", e))
+    }
+
+    /// Makes a freshly loaded config's instance count and input mappings
+    /// agree: a missing `instances` is taken from `input_mappings`, and
+    /// players without an input get "Auto-detect". Extra mappings are left
+    /// for `validate` to report.
+    fn reconcile_instances(&mut self) {
+        if self.instances == 0 {
+            self.instances = self.input_mappings.len().max(1);
+        }
+        if self.input_mappings.len() < self.instances {
+            self.input_mappings.resize(self.instances, "Auto-detect".to_string());
+        }
+    }
+
+    /// Sets the number of instances, adding "Auto-detect" inputs for new
+    /// players and dropping those of removed ones.
+    pub fn set_instances(&mut self, instances: usize) {
+        self.instances = instances;
+        self.input_mappings.resize(instances, "Auto-detect".to_string());
+    }
+
+    /// Expand `~`, environment variables and relative paths in every path
+    /// field, see `utils::expand_path`.
+    pub fn expand_paths(&mut self) {
+        for path in &mut self.game_paths {
+            *path = expand(path);
+        }
+        if let Some(path) = &mut self.goldberg_path {
+            *path = expand(path);
+        }
+        if let Some(path) = &mut self.net_capture_path {
+            *path = expand(path);
+        }
+        for path in [&mut self.proton_log_dir, &mut self.pre_launch_hook, &mut self.post_launch_hook, &mut self.net_routes_file]
+            .into_iter()
+            .flatten()
+        {
+            *path = expand(path);
+        }
+    }
+
     /// Validate the configuration
     pub fn validate(&self) -> Result<(), ConfigError> {
         // Validate game paths
@@ -170,24 +883,103 @@ impl Config {
             return Err(ValidationError::MissingGamePath.into());
         }
         
-        for path in &self.game_paths {
-            if !path.exists() {
-                return Err(ValidationError::InvalidGamePath(path.clone()).into());
-            }
+        // The rest of the list is only history, and may name games that have
+        // since been uninstalled
+        if let Some(path) = self.primary_game_path().filter(|path| !expand(path).exists()) {
+            return Err(ValidationError::InvalidGamePath(path.clone()).into());
         }
         
-        // Validate instance count based on input mappings
-        let instance_count = self.input_mappings.len();
+        // Validate the instance count and that every player has one input
+        let instance_count = self.instances;
         if instance_count == 0 || instance_count > crate::defaults::MAX_INSTANCES {
             return Err(ValidationError::InvalidInstanceCount(instance_count).into());
         }
+        if self.input_mappings.len() != instance_count {
+            return Err(ValidationError::InstanceCountMismatch {
+                instances: instance_count,
+                mappings: self.input_mappings.len(),
+            }
+            .into());
+        }
         
         // Validate network ports
-        for &port in &self.network_ports {
+        let strategy_ports = match &self.port_strategy {
+            Some(PortStrategy::Fixed(ports)) => ports.clone(),
+            Some(PortStrategy::SequentialFrom(start)) => vec![*start],
+            Some(PortStrategy::Ephemeral) | None => Vec::new(),
+        };
+        for &port in self.network_ports.iter().chain(&strategy_ports) {
             if port < 1024 || port == 0 {
                 return Err(ValidationError::InvalidNetworkPort(port).into());
             }
         }
+
+        // Validate the Goldberg directory when the integration is enabled
+        if self.use_goldberg {
+            match &self.goldberg_path {
+                Some(path) if expand(path).is_dir() => {}
+                Some(path) => return Err(ValidationError::InvalidGoldbergPath(path.clone()).into()),
+                None => return Err(ValidationError::MissingGoldbergPath.into()),
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.adaptive_confidence_threshold) {
+            return Err(ValidationError::InvalidConfidenceThreshold(self.adaptive_confidence_threshold).into());
+        }
+
+        if let Some(ratio) = self.aspect_ratios.iter().find(|r| parse_aspect_ratio(r).is_none()) {
+            return Err(ValidationError::InvalidAspectRatio(ratio.clone()).into());
+        }
+
+        let mut opacities = self.window_opacity.iter().chain(&self.dim_unfocused_opacity);
+        if let Some(&opacity) = opacities.find(|&&opacity| !(opacity > 0.0 && opacity <= 1.0)) {
+            return Err(ValidationError::InvalidOpacity(opacity).into());
+        }
+
+        self.window_layout.validate(instance_count)?;
+
+        // Without a delay the window search would spin between lookups
+        let mut backoffs = self.window_search_initial_backoff_ms.iter().chain(&self.window_search_max_backoff_ms);
+        if let Some(&backoff) = backoffs.find(|&&backoff| backoff == 0) {
+            return Err(ValidationError::InvalidWindowSearchBackoff(backoff).into());
+        }
+
+        if self.tab_cycle_hotkey.parse::<Hotkey>().is_err() {
+            return Err(ValidationError::InvalidHotkey(self.tab_cycle_hotkey.clone()).into());
+        }
+
+        if let Some(key) = self.host_reserved_keys.iter().find(|key| key.parse::<evdev::Key>().is_err()) {
+            return Err(ValidationError::InvalidReservedKey(key.clone()).into());
+        }
+
+        if let Some(pattern) = self
+            .instance_copy_include
+            .iter()
+            .chain(&self.instance_copy_exclude)
+            .find(|pattern| CopyPattern::parse(pattern).is_err())
+        {
+            return Err(ValidationError::InvalidCopyPattern(pattern.clone()).into());
+        }
+
+        if let Some(name) = self.remove_window_decorations_by_layout.keys().find(|name| !Layout::NAMES.contains(&name.as_str())) {
+            return Err(ValidationError::UnknownLayout(name.clone()).into());
+        }
+
+        if self.per_instance_args.len() > instance_count {
+            return Err(ValidationError::TooManyInstanceArgs {
+                entries: self.per_instance_args.len(),
+                instances: instance_count,
+            }
+            .into());
+        }
+
+        if !self.run_as_users.is_empty() && self.run_as_users.len() < instance_count {
+            return Err(ValidationError::NotEnoughRunAsUsers {
+                users: self.run_as_users.len(),
+                instances: instance_count,
+            }
+            .into());
+        }
         
         Ok(())
     }
@@ -196,12 +988,104 @@ impl Config {
     pub fn primary_game_path(&self) -> Option<&PathBuf> {
         self.game_paths.first()
     }
+
+    /// Make `path` the primary game, keeping the others as recently used
+    /// games behind it.
+    pub fn select_game_path(&mut self, path: PathBuf) {
+        remember_recent_game(&mut self.game_paths, path);
+    }
     
-    /// Get the number of instances based on input mappings
+    /// Number of instances to launch, at least 1
     pub fn instance_count(&self) -> usize {
-        self.input_mappings.len().max(1)
+        self.instances.max(1)
+    }
+
+    /// Width/height ratio to letterbox each instance's window to, or `None`
+    /// when windows should fill their layout cell. Unparseable entries fall
+    /// back to the default like missing ones.
+    pub fn instance_aspect_ratios(&self) -> Option<Vec<f64>> {
+        if !self.preserve_aspect_ratio {
+            return None;
+        }
+        Some(
+            self.aspect_ratios
+                .iter()
+                .map(|ratio| parse_aspect_ratio(ratio).unwrap_or(crate::defaults::ASPECT_RATIO))
+                .collect(),
+        )
+    }
+
+    /// Whether window decorations are removed when arranging `layout`.
+    pub fn removes_window_decorations(&self, layout: Layout) -> bool {
+        self.remove_window_decorations_by_layout
+            .get(layout.name())
+            .copied()
+            .unwrap_or(self.remove_window_decorations)
+    }
+}
+
+/// Parses "W:H" or a plain decimal ratio such as "1.78".
+fn parse_aspect_ratio(value: &str) -> Option<f64> {
+    let ratio = match value.split_once(':') {
+        Some((width, height)) => width.trim().parse::<f64>().ok()? / height.trim().parse::<f64>().ok()?,
+        None => value.trim().parse().ok()?,
+    };
+    (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+}
+
+/// Named configurations, each stored as `<name>.toml` in one directory.
+/// A profile is a complete `Config`, so any config file can serve as one.
+pub struct ProfileStore {
+    dir: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn new(dir: PathBuf) -> Self {
+        ProfileStore { dir }
+    }
+
+    /// Maps a profile name to its file, rejecting names that would escape the directory.
+    fn profile_path(&self, name: &str) -> Result<PathBuf, ConfigError> {
+        let name = name.trim();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(ConfigError::InvalidProfileName(name.to_string()));
+        }
+        Ok(self.dir.join(format!("{}.toml", name)))
+    }
+
+    /// Loads the named profile. Unlike `Config::load`, a missing file is an error.
+    pub fn load_profile(&self, name: &str) -> Result<Config, ConfigError> {
+        let path = self.profile_path(name)?;
+        if !path.is_file() {
+            return Err(ConfigError::ProfileNotFound(name.to_string()));
+        }
+        Config::load(&path)
+    }
+
+    /// Saves `config` under `name`, replacing any existing profile of that name.
+    pub fn save_profile(&self, name: &str, config: &Config) -> Result<(), ConfigError> {
+        config.save(&self.profile_path(name)?)
+    }
+
+    /// Names of all stored profiles, sorted.
+    pub fn list_profiles(&self) -> Result<Vec<String>, ConfigError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "toml") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
     }
-    
 }
 
 // Test code (add necessary dependencies like tempfile)
@@ -223,9 +1107,463 @@ mod tests {
         let config = Config::default_config();
         assert_eq!(config.game_paths.len(), 0);
         assert_eq!(config.input_mappings, vec!["Auto-detect".to_string(), "Auto-detect".to_string()]);
-        assert_eq!(config.window_layout, "horizontal".to_string());
+        assert_eq!(config.window_layout, LayoutConfig::Horizontal);
         assert_eq!(config.network_ports, vec![7777, 7778]);
         assert_eq!(config.use_proton, false);
+        assert_eq!(config.goldberg_path, None);
+        assert!(!config.use_goldberg);
+    }
+
+    #[test]
+    fn test_instances_and_input_mappings_agree() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let game = temp_dir.path().join("game.sh");
+        fs::write(&game, "#!/bin/sh\n").expect("Failed to write game");
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(&config_path, format!(r#"
+        game_paths = ["{}"]
+        instances = 3
+        input_mappings = ["Keyboard"]
+        window_layout = "horizontal"
+        network_ports = [7777]
+        use_proton = false
+        "#, game.display())).expect("Failed to write config");
+
+        let mut config = Config::load(&config_path).expect("Failed to load config");
+        assert_eq!(config.instance_count(), 3);
+        assert_eq!(config.input_mappings, vec!["Keyboard", "Auto-detect", "Auto-detect"]);
+        assert!(config.validate().is_ok());
+
+        config.set_instances(2);
+        assert_eq!(config.input_mappings, vec!["Keyboard", "Auto-detect"]);
+
+        config.input_mappings.push("Gamepad".to_string());
+        match config.validate() {
+            Err(ConfigError::Validation(ValidationError::InstanceCountMismatch { instances: 2, mappings: 3 })) => {}
+            other => panic!("Expected InstanceCountMismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_load_config_without_goldberg_fields() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let config_path = temp_dir.path().join("old_config.toml");
+        fs::write(&config_path, r#"
+        game_paths = ["/path/to/game"]
+        input_mappings = ["Auto-detect"]
+        window_layout = "horizontal"
+        network_ports = [7777]
+        use_proton = false
+        "#).expect("Failed to write config");
+
+        let config = Config::load(&config_path).expect("Older configs should still load");
+        assert_eq!(config.goldberg_path, None);
+        assert!(!config.use_goldberg);
+        assert!(config.adaptive_enabled);
+        assert_eq!(config.adaptive_confidence_threshold, 0.6);
+        assert_eq!(config.adaptive_save_interval_secs, 5);
+        assert!(config.per_instance_controllers);
+        assert!(!config.label_windows);
+        assert!(config.restack_windows);
+        assert!(config.window_opacity.is_empty());
+        assert_eq!(config.dim_unfocused_opacity, None);
+        assert!(config.instance_copy_include.iter().any(|pattern| pattern == "*.dll"));
+        assert_eq!(config.instance_copy_exclude, vec!["instance_*"]);
+        assert!(!config.isolate_input);
+        assert_eq!(config.virtual_device_name_template, "HydraCoop P{instance} {session}");
+        assert!(!config.mimic_controller_names);
+        assert!(!config.allow_no_input);
+        assert_eq!(config.windows_runtime, WindowsRuntime::None);
+        assert_eq!(config.input_backend, InputBackendKind::Uinput);
+        assert_eq!(config.input_sync, SyncMode::Source);
+        assert!(config.host_reserved_keys.is_empty());
+        assert!(config.enable_network_emulation);
+        assert_eq!(config.net_routes_file, None);
+        assert!(!config.loopback_per_instance);
+        assert!(!config.headless);
+        assert_eq!(config.tab_cycle_hotkey, "Scroll_Lock");
+        assert_eq!(config.on_layout_failure, LayoutFailurePolicy::LayoutFound);
+        assert!(config.match_layout_resolution);
+        assert_eq!(config.firejail, None);
+        assert_eq!(config.launch_policy, LaunchPolicy::AllOrNothing);
+        assert_eq!(config.launch_concurrency, 1);
+        assert_eq!(config.launch_delay_ms, 0);
+        assert_eq!(config.shutdown_grace_secs, 3);
+        assert_eq!(config.already_running, AlreadyRunningPolicy::Warn);
+        assert_eq!(config.proton_wait_secs, 0);
+        assert_eq!(config.port_strategy, None);
+        assert_eq!(config.instances, 1);
+        assert_eq!(config.port_strategy(), PortStrategy::Fixed(vec![7777]));
+        assert!(!config.proton_log);
+        assert_eq!(config.proton_log_dir, None);
+        assert_eq!(config.pre_launch_hook, None);
+        assert_eq!(config.post_launch_hook, None);
+    }
+
+    #[test]
+    fn test_window_layout_forms() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            window_layout: LayoutConfig,
+        }
+        let parse = |toml_str: &str| toml::from_str::<Wrapper>(toml_str).map(|w| w.window_layout);
+
+        assert_eq!(parse(r#"window_layout = "Vertical""#).unwrap(), LayoutConfig::Vertical);
+        assert_eq!(parse(r#"window_layout = "grid2x2""#).unwrap(), LayoutConfig::Grid2x2);
+        assert_eq!(parse(r#"window_layout = "tabbed""#).unwrap().layout(), Layout::Tabbed);
+        // Older configs could say "custom" without giving any positions
+        assert_eq!(parse(r#"window_layout = "custom""#).unwrap(), LayoutConfig::Horizontal);
+        assert_eq!(
+            parse("window_layout = { grid = { cols = 3, rows = 2 } }").unwrap(),
+            LayoutConfig::Grid { cols: 3, rows: 2 }
+        );
+        let custom = parse(
+            "[window_layout.custom]\nrects = [{ x = 0, y = 0, width = 1280, height = 720 }, { x = 1280, y = 0, width = 640, height = 720 }]",
+        )
+        .unwrap();
+        assert_eq!(custom.layout(), Layout::Custom);
+        assert_eq!(custom.custom_rects()[1], WindowRect { x: 1280, y: 0, width: 640, height: 720 });
+
+        assert!(parse(r#"window_layout = "diagonal""#).unwrap_err().to_string().contains("unknown layout 'diagonal'"));
+        assert!(parse("window_layout = { spiral = { turns = 2 } }").is_err());
+        assert!(parse("window_layout = { grid = { cols = 3, rows = 2, gap = 4 } }").is_err());
+    }
+
+    #[test]
+    fn test_firejail_table() {
+        let config: Config = toml::from_str(
+            r#"
+            game_paths = []
+            input_mappings = ["Auto-detect"]
+            window_layout = "horizontal"
+            network_ports = [7777]
+            use_proton = false
+
+            [firejail]
+            profile = "steam"
+            "#,
+        )
+        .unwrap();
+        let firejail = config.firejail.expect("the [firejail] table enables the sandbox");
+        assert_eq!(firejail.profile.as_deref(), Some("steam"));
+        assert!(firejail.private_home);
+        assert!(firejail.extra_args.is_empty());
+    }
+
+    #[test]
+    fn test_recent_game_paths() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let game = temp_dir.path().join("game.sh");
+        fs::write(&game, "#!/bin/sh\n").expect("Failed to write game");
+
+        let mut config = Config::default_config();
+        config.set_instances(1);
+        config.game_paths = vec![PathBuf::from("/uninstalled/game"), PathBuf::from("/games/other")];
+        assert!(config.validate().is_err());
+
+        // Games further down the list may be gone without failing validation
+        config.select_game_path(game.clone());
+        assert!(config.validate().is_ok());
+        config.select_game_path(PathBuf::from("/games/other"));
+        assert_eq!(
+            config.game_paths,
+            vec![PathBuf::from("/games/other"), game.clone(), PathBuf::from("/uninstalled/game")]
+        );
+
+        for i in 0..MAX_RECENT_GAMES + 2 {
+            config.select_game_path(PathBuf::from(format!("/games/{}", i)));
+        }
+        assert_eq!(config.game_paths.len(), MAX_RECENT_GAMES);
+        assert_eq!(config.primary_game_path(), Some(&PathBuf::from(format!("/games/{}", MAX_RECENT_GAMES + 1))));
+    }
+
+    #[test]
+    fn test_validate_window_layout() {
+        let rect = WindowRect { x: 0, y: 0, width: 960, height: 1080 };
+        assert!(LayoutConfig::Grid { cols: 0, rows: 2 }.validate(2).is_err());
+        assert!(LayoutConfig::Grid { cols: 2, rows: 1 }.validate(2).is_ok());
+        assert!(LayoutConfig::Custom { rects: vec![rect] }.validate(2).is_err());
+        assert!(LayoutConfig::Custom { rects: vec![rect, rect] }.validate(2).is_ok());
+        assert!(LayoutConfig::Custom { rects: vec![rect, WindowRect { width: 0, ..rect }] }.validate(2).is_err());
+    }
+
+    #[test]
+    fn test_load_config_expands_paths() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let game_path = temp_dir.path().join("game.sh");
+        fs::write(&game_path, "#!/bin/sh\n").expect("Failed to write game");
+        std::env::set_var("HYDRA_TEST_GAMES_DIR", temp_dir.path());
+
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(&config_path, r#"
+        game_paths = ["${HYDRA_TEST_GAMES_DIR}/game.sh", "relative/game", "~"]
+        input_mappings = ["Auto-detect"]
+        window_layout = "horizontal"
+        network_ports = [7777]
+        use_proton = false
+        "#).expect("Failed to write config");
+
+        let config = Config::load(&config_path).expect("Failed to load config");
+        assert_eq!(config.game_paths[0], game_path);
+        assert_eq!(config.game_paths[1], std::env::current_dir().unwrap().join("relative/game"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(config.game_paths[2], home);
+        }
+        assert_eq!(crate::utils::expand_path("$HYDRA_TEST_UNSET_VAR/x"), std::env::current_dir().unwrap().join("$HYDRA_TEST_UNSET_VAR/x"));
+    }
+
+    #[test]
+    fn test_validate_confidence_threshold() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.adaptive_confidence_threshold = 1.5;
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidConfidenceThreshold(_)))
+        ));
+
+        config.adaptive_confidence_threshold = 0.9;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_tab_cycle_hotkey() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.tab_cycle_hotkey = "Ctrl+Hyper+Q".to_string();
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidHotkey(_)))
+        ));
+
+        config.tab_cycle_hotkey = "Ctrl+Alt+F9".to_string();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_window_search_backoff() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.window_search_max_backoff_ms = Some(0);
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidWindowSearchBackoff(0)))
+        ));
+
+        config.window_search_max_backoff_ms = Some(200);
+        config.window_search_initial_backoff_ms = Some(0);
+        assert!(config.validate().is_err());
+
+        config.window_search_initial_backoff_ms = Some(1);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_host_reserved_keys() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.host_reserved_keys = vec!["KEY_LEFTMETA".to_string(), "Super".to_string()];
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidReservedKey(key))) if key == "Super"
+        ));
+
+        config.host_reserved_keys.pop();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_instance_copy_patterns() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.instance_copy_include.push("scripts/".to_string());
+        assert!(config.validate().is_ok());
+
+        config.instance_copy_exclude.push("saves/[".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidCopyPattern(pattern))) if pattern == "saves/["
+        ));
+    }
+
+    #[test]
+    fn test_describe_effective() {
+        let mut config = Config::default_config();
+        config.select_game_path(PathBuf::from("/games/game.exe"));
+        config.set_instances(2);
+        config.input_mappings = vec!["Pad A".to_string(), "none".to_string()];
+        config.port_strategy = Some(PortStrategy::Fixed(vec![7777, 7778]));
+        config.set_runtime(WindowsRuntime::Proton);
+        config.window_layout = LayoutConfig::Grid { cols: 2, rows: 1 };
+        config.run_as_users = vec!["p1".to_string(), "p2".to_string()];
+        config.per_instance_args = vec![vec![], vec!["-server".to_string()]];
+
+        let description: toml::Value = toml::from_str(&config.describe_effective()).unwrap();
+        assert_eq!(description["game"].as_str(), Some("/games/game.exe"));
+        assert_eq!(description["runtime"].as_str(), Some("proton"));
+        assert_eq!(description["layout"].as_str(), Some("grid (2x1)"));
+        assert_eq!(description["separation"].as_array().unwrap().len(), 1);
+        let players = description["player"].as_array().unwrap();
+        assert_eq!(players.len(), 2);
+        assert_eq!(players[0]["input"].as_str(), Some("Pad A"));
+        assert_eq!(players[1]["port"].as_integer(), Some(7778));
+        assert_eq!(players[1]["run_as"].as_str(), Some("p2"));
+        assert_eq!(players[1]["args"].as_array().unwrap()[0].as_str(), Some("-server"));
+        assert_eq!(description["settings"]["instances"].as_integer(), Some(2));
+    }
+
+    #[test]
+    fn test_aspect_ratios() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.aspect_ratios = vec!["4:3".to_string(), "1.6".to_string()];
+        assert_eq!(config.instance_aspect_ratios(), None);
+
+        config.preserve_aspect_ratio = true;
+        assert_eq!(config.instance_aspect_ratios(), Some(vec![4.0 / 3.0, 1.6]));
+        assert!(config.validate().is_ok());
+
+        config.aspect_ratios.push("16:0".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidAspectRatio(_)))
+        ));
+    }
+
+    #[test]
+    fn test_validate_window_opacity() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.window_opacity = vec![1.0, 0.8];
+        config.dim_unfocused_opacity = Some(0.5);
+        assert!(config.validate().is_ok());
+
+        config.dim_unfocused_opacity = Some(0.0);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidOpacity(value))) if value == 0.0
+        ));
+        config.dim_unfocused_opacity = None;
+        config.window_opacity.push(1.5);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::InvalidOpacity(value))) if value == 1.5
+        ));
+    }
+
+    #[test]
+    fn test_validate_goldberg_requires_path() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.use_goldberg = true;
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::MissingGoldbergPath))
+        ));
+
+        config.goldberg_path = Some(temp_dir.path().to_path_buf());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_per_instance_args_count() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.per_instance_args = vec![vec!["-player".to_string(), "${INSTANCE}".to_string()]; 2];
+        assert!(config.validate().is_ok());
+
+        config.per_instance_args.push(Vec::new());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::TooManyInstanceArgs { entries: 3, instances: 2 }))
+        ));
+    }
+
+    #[test]
+    fn test_decoration_removal_per_layout() {
+        use crate::window_manager::Layout;
+
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        assert!(config.removes_window_decorations(Layout::Vertical));
+
+        config.remove_window_decorations = false;
+        config.remove_window_decorations_by_layout.insert("grid2x2".to_string(), true);
+        assert!(!config.removes_window_decorations(Layout::Vertical));
+        assert!(config.removes_window_decorations(Layout::Grid2x2));
+        assert!(config.validate().is_ok());
+
+        config.remove_window_decorations_by_layout.insert("diagonal".to_string(), false);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::UnknownLayout(_)))
+        ));
+    }
+
+    #[test]
+    fn test_validate_run_as_users_covers_instances() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let mut config = Config::default_config();
+        config.game_paths.push(temp_dir.path().to_path_buf());
+        config.run_as_users = vec!["player1".to_string()];
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Validation(ValidationError::NotEnoughRunAsUsers { users: 1, instances: 2 }))
+        ));
+
+        config.run_as_users.push("player2".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_windows_runtime() {
+        let mut config = Config::default_config();
+        assert_eq!(config.runtime(), WindowsRuntime::None);
+        config.use_proton = true;
+        assert_eq!(config.runtime(), WindowsRuntime::Proton);
+
+        config.set_runtime(WindowsRuntime::Wine);
+        assert!(!config.use_proton);
+        let loaded: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(loaded.runtime(), WindowsRuntime::Wine);
+    }
+
+    #[test]
+    fn test_profile_store_round_trip() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        let store = ProfileStore::new(temp_dir.path().join("profiles"));
+        assert!(store.list_profiles().unwrap().is_empty());
+
+        let mut config = Config::default_config();
+        config.game_paths.push(PathBuf::from("/games/borderlands/Borderlands.exe"));
+        config.set_instances(4);
+        config.use_proton = true;
+        store.save_profile("Borderlands 4P", &config).expect("Failed to save profile");
+
+        let loaded = store.load_profile("Borderlands 4P").expect("Failed to load profile");
+        assert_eq!(loaded.game_paths, config.game_paths);
+        assert_eq!(loaded.instance_count(), 4);
+        assert!(loaded.use_proton);
+        assert_eq!(store.list_profiles().unwrap(), vec!["Borderlands 4P".to_string()]);
+
+        assert!(matches!(store.load_profile("Missing"), Err(ConfigError::ProfileNotFound(_))));
+        assert!(matches!(store.load_profile("../config"), Err(ConfigError::InvalidProfileName(_))));
     }
 
     #[test]
@@ -237,7 +1575,7 @@ mod tests {
         let mut config = Config::default_config();
         config.game_paths.push(PathBuf::from("/path/to/game"));
         config.input_mappings = vec!["Device A".to_string(), "Device B".to_string()];
-        config.window_layout = "vertical".to_string();
+        config.window_layout = LayoutConfig::Grid { cols: 3, rows: 2 };
         config.network_ports = vec![1234, 5678];
         config.use_proton = true;
 
@@ -257,7 +1595,7 @@ mod tests {
         // Assert that the loaded configuration matches the saved configuration
         assert_eq!(loaded_config.game_paths, vec![PathBuf::from("/path/to/game")]);
         assert_eq!(loaded_config.input_mappings, vec!["Device A".to_string(), "Device B".to_string()]);
-        assert_eq!(loaded_config.window_layout, "vertical".to_string());
+        assert_eq!(loaded_config.window_layout, LayoutConfig::Grid { cols: 3, rows: 2 });
         assert_eq!(loaded_config.network_ports, vec![1234, 5678]);
         assert_eq!(loaded_config.use_proton, true);
 
@@ -306,6 +1644,30 @@ mod tests {
          // temp_dir is automatically cleaned up when it goes out of scope
     }
 
+    #[test]
+    fn test_failed_save_keeps_previous_config() {
+        let temp_dir = tempdir().expect("Failed to create temporary directory");
+        // Long enough that the temporary file's name, which adds a prefix
+        // and suffix around it, exceeds the filesystem's name limit
+        let config_path = temp_dir.path().join(format!("{}.toml", "c".repeat(245)));
+
+        let mut config = Config::default_config();
+        config.keep_above = true;
+        fs::write(&config_path, toml::to_string_pretty(&config).unwrap()).unwrap();
+
+        // A temporary file that can't be created makes the save fail before
+        // the old config is touched
+        config.keep_above = false;
+        assert!(config.save(&config_path).is_err());
+        assert!(Config::load(&config_path).unwrap().keep_above);
+
+        let config_path = temp_dir.path().join("config.toml");
+        config.save(&config_path).unwrap();
+        assert!(!Config::load(&config_path).unwrap().keep_above);
+        // Only the two configs are left behind, no temporary files
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+    }
+
      #[test]
      fn test_load_invalid_toml() {
          // setup_logger();
@@ -0,0 +1,327 @@
+//! Control Socket
+//!
+//! A running session listens on a Unix domain socket so it can be stopped or
+//! queried without signals, e.g. from scripts or `hydra-coop-launcher ctl`.
+//! The protocol is one command per connection: the client writes a command
+//! name on a single line and reads back a single-line reply starting with
+//! `ok:` or `error:`.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use log::{debug, info, warn};
+
+/// File name of the control socket inside `$XDG_RUNTIME_DIR`
+pub const SOCKET_NAME: &str = "hydra-coop.sock";
+
+/// How long a client gets to send its command, and the CLI to get a reply
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the accept loop checks whether it should stop
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Commands understood by the control socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Stop the session as if Ctrl+C had been pressed
+    Shutdown,
+    /// Report how many instances are running and whether input is paused
+    Status,
+    /// Stop forwarding controller input to the game instances
+    PauseInput,
+    /// Resume forwarding controller input
+    ResumeInput,
+}
+
+impl ControlCommand {
+    pub const ALL: [ControlCommand; 4] = [
+        ControlCommand::Shutdown,
+        ControlCommand::Status,
+        ControlCommand::PauseInput,
+        ControlCommand::ResumeInput,
+    ];
+
+    /// The name sent over the socket
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ControlCommand::Shutdown => "shutdown",
+            ControlCommand::Status => "status",
+            ControlCommand::PauseInput => "pause-input",
+            ControlCommand::ResumeInput => "resume-input",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| command.as_str() == name)
+    }
+}
+
+/// Default location of the control socket, or `None` if `$XDG_RUNTIME_DIR`
+/// is not set.
+pub fn socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir().map(|dir| dir.join(SOCKET_NAME))
+}
+
+/// Session state shared between the session loop and the control thread
+#[derive(Debug, Default)]
+pub struct SessionControl {
+    shutdown_requested: AtomicBool,
+    running_instances: AtomicUsize,
+    total_instances: AtomicUsize,
+    /// Shared with `InputMux`, see [`crate::input_mux::InputMux::pause_flag`]
+    input_paused: Arc<AtomicBool>,
+    /// See [`crate::input_mux::InputMux::degraded_flag`]
+    degraded: Arc<AtomicBool>,
+    /// Input codes the virtual devices were created without, see
+    /// [`crate::input_mux::CapabilityReport`]
+    skipped_input_codes: AtomicUsize,
+}
+
+impl SessionControl {
+    pub fn new(total_instances: usize, input_paused: Arc<AtomicBool>) -> Self {
+        SessionControl {
+            shutdown_requested: AtomicBool::new(false),
+            running_instances: AtomicUsize::new(total_instances),
+            total_instances: AtomicUsize::new(total_instances),
+            input_paused,
+            degraded: Arc::new(AtomicBool::new(false)),
+            skipped_input_codes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Flag `status` checks for background threads that died
+    pub fn set_degraded_flag(&mut self, degraded: Arc<AtomicBool>) {
+        self.degraded = degraded;
+    }
+
+    /// Records how many input codes uinput rejected, for `status`
+    pub fn set_skipped_input_codes(&self, skipped: usize) {
+        self.skipped_input_codes.store(skipped, Ordering::SeqCst);
+    }
+
+    /// Whether a `shutdown` command has been received
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown_requested.load(Ordering::SeqCst)
+    }
+
+    /// Records how many instances are still running, for `status`
+    pub fn set_running_instances(&self, running: usize) {
+        self.running_instances.store(running, Ordering::SeqCst);
+    }
+
+    fn handle(&self, command: ControlCommand) -> String {
+        match command {
+            ControlCommand::Shutdown => {
+                info!("Shutdown requested over the control socket.");
+                self.shutdown_requested.store(true, Ordering::SeqCst);
+                "ok: shutting down".to_string()
+            }
+            ControlCommand::Status => format!(
+                "ok: {}/{} instance(s) running, input {}{}{}",
+                self.running_instances.load(Ordering::SeqCst),
+                self.total_instances.load(Ordering::SeqCst),
+                if self.input_paused.load(Ordering::SeqCst) { "paused" } else { "active" },
+                match self.skipped_input_codes.load(Ordering::SeqCst) {
+                    0 => String::new(),
+                    skipped => format!(" ({} input code(s) unavailable to games, see the log)", skipped),
+                },
+                if self.degraded.load(Ordering::SeqCst) { ", degraded (a background thread failed, see the log)" } else { "" }
+            ),
+            ControlCommand::PauseInput => {
+                info!("Input paused over the control socket.");
+                self.input_paused.store(true, Ordering::SeqCst);
+                "ok: input paused".to_string()
+            }
+            ControlCommand::ResumeInput => {
+                info!("Input resumed over the control socket.");
+                self.input_paused.store(false, Ordering::SeqCst);
+                "ok: input resumed".to_string()
+            }
+        }
+    }
+}
+
+/// Listens on the control socket and applies commands to a [`SessionControl`]
+/// on a dedicated thread. The socket is removed when the server stops.
+pub struct ControlServer {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ControlServer {
+    /// Binds `path` and starts serving. A socket file left behind by a crashed
+    /// session is replaced; one that another session still answers on is not.
+    pub fn start(path: &Path, control: Arc<SessionControl>) -> io::Result<Self> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("another Hydra session is listening on {}", path.display()),
+                ));
+            }
+            debug!("Removing stale control socket {}", path.display());
+            std::fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        info!("Control socket listening on {}", path.display());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::spawn(move || run_accept_loop(listener, control, stop))
+        };
+
+        Ok(ControlServer {
+            path: path.to_path_buf(),
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Stops the control thread and removes the socket file.
+    pub fn stop(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        self.stop.store(true, Ordering::SeqCst);
+        if thread.join().is_err() {
+            warn!("Control socket thread panicked");
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            debug!("Could not remove control socket {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run_accept_loop(listener: UnixListener, control: Arc<SessionControl>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = serve_client(stream, &control) {
+                    warn!("Control socket client error: {}", e);
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                warn!("Control socket accept failed: {}", e);
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        }
+    }
+    debug!("Control socket thread exited.");
+}
+
+fn serve_client(stream: UnixStream, control: &SessionControl) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let name = line.trim();
+
+    let reply = match ControlCommand::parse(name) {
+        Some(command) => control.handle(command),
+        None => format!(
+            "error: unknown command '{}' (expected one of: {})",
+            name,
+            ControlCommand::ALL.map(ControlCommand::as_str).join(", ")
+        ),
+    };
+    (&stream).write_all(format!("{}\n", reply).as_bytes())
+}
+
+/// Sends `command` to the session listening on `path` and returns its reply.
+pub fn send_command(path: &Path, command: ControlCommand) -> io::Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.write_all(format!("{}\n", command.as_str()).as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    Ok(reply.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_command_names_round_trip() {
+        for command in ControlCommand::ALL {
+            assert_eq!(ControlCommand::parse(command.as_str()), Some(command));
+        }
+        assert_eq!(ControlCommand::parse("reboot"), None);
+    }
+
+    #[test]
+    fn test_server_applies_commands() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SOCKET_NAME);
+        let paused = Arc::new(AtomicBool::new(false));
+        let control = Arc::new(SessionControl::new(2, paused.clone()));
+        control.set_running_instances(1);
+        let mut server = ControlServer::start(&path, control.clone()).unwrap();
+
+        assert_eq!(
+            send_command(&path, ControlCommand::Status).unwrap(),
+            "ok: 1/2 instance(s) running, input active"
+        );
+        send_command(&path, ControlCommand::PauseInput).unwrap();
+        assert!(paused.load(Ordering::SeqCst));
+        send_command(&path, ControlCommand::ResumeInput).unwrap();
+        assert!(!paused.load(Ordering::SeqCst));
+        assert!(!control.shutdown_requested());
+        assert_eq!(send_command(&path, ControlCommand::Shutdown).unwrap(), "ok: shutting down");
+        assert!(control.shutdown_requested());
+
+        server.stop();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_status_reports_degraded_session() {
+        let degraded = Arc::new(AtomicBool::new(false));
+        let mut control = SessionControl::new(2, Arc::new(AtomicBool::new(false)));
+        control.set_degraded_flag(degraded.clone());
+        assert_eq!(control.handle(ControlCommand::Status), "ok: 2/2 instance(s) running, input active");
+        degraded.store(true, Ordering::SeqCst);
+        assert!(control.handle(ControlCommand::Status).ends_with("input active, degraded (a background thread failed, see the log)"));
+    }
+
+    #[test]
+    fn test_status_reports_skipped_input_codes() {
+        let control = SessionControl::new(1, Arc::new(AtomicBool::new(false)));
+        control.set_skipped_input_codes(3);
+        assert_eq!(
+            control.handle(ControlCommand::Status),
+            "ok: 1/1 instance(s) running, input active (3 input code(s) unavailable to games, see the log)"
+        );
+    }
+
+    #[test]
+    fn test_start_replaces_stale_socket_but_not_live_one() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SOCKET_NAME);
+        // A socket file nobody listens on, as left by a crash
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let control = Arc::new(SessionControl::default());
+        let _server = ControlServer::start(&path, control.clone()).unwrap();
+        let err = ControlServer::start(&path, control).err().expect("second server must not start");
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+    }
+}
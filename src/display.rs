@@ -0,0 +1,94 @@
+//! Display Server Detection
+//!
+//! Window management talks to an X server. Under a Wayland session it can only
+//! reach games running through XWayland, and without any X server it can't
+//! work at all. This module works out which situation we are in from the
+//! session environment so callers can warn or skip layout up front.
+
+use std::env;
+use std::fmt;
+use crate::errors::{HydraError, Result};
+
+/// The kind of graphical session Hydra is running in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionKind {
+    /// A plain X11 session; window management works fully
+    X11,
+    /// A Wayland session with XWayland; only X11 game windows can be arranged
+    XWaylandOnly,
+    /// A Wayland session without XWayland; windows can't be arranged
+    WaylandNative,
+    /// No display server at all
+    Headless,
+}
+
+impl SessionKind {
+    /// Whether `WindowManager` can reach the game windows at all
+    pub fn supports_window_management(self) -> bool {
+        matches!(self, SessionKind::X11 | SessionKind::XWaylandOnly)
+    }
+
+    /// Fails with `HydraError::Unsupported` when windows can't be arranged.
+    pub fn require_window_management(self) -> Result<()> {
+        if self.supports_window_management() {
+            Ok(())
+        } else {
+            Err(HydraError::unsupported(format!("Window management under {}", self)))
+        }
+    }
+}
+
+impl fmt::Display for SessionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionKind::X11 => write!(f, "X11"),
+            SessionKind::XWaylandOnly => write!(f, "Wayland with XWayland"),
+            SessionKind::WaylandNative => write!(f, "Wayland without XWayland"),
+            SessionKind::Headless => write!(f, "no display server"),
+        }
+    }
+}
+
+/// Detects the current session from `DISPLAY`, `WAYLAND_DISPLAY` and
+/// `XDG_SESSION_TYPE`.
+pub fn detect_session() -> SessionKind {
+    let var = |name| env::var(name).ok();
+    classify(
+        var("DISPLAY").as_deref(),
+        var("WAYLAND_DISPLAY").as_deref(),
+        var("XDG_SESSION_TYPE").as_deref(),
+    )
+}
+
+fn classify(display: Option<&str>, wayland_display: Option<&str>, session_type: Option<&str>) -> SessionKind {
+    let has_x11 = display.is_some_and(|d| !d.is_empty());
+    let is_wayland = wayland_display.is_some_and(|d| !d.is_empty())
+        || session_type.is_some_and(|t| t.eq_ignore_ascii_case("wayland"));
+    match (is_wayland, has_x11) {
+        (false, true) => SessionKind::X11,
+        (true, true) => SessionKind::XWaylandOnly,
+        (true, false) => SessionKind::WaylandNative,
+        (false, false) => SessionKind::Headless,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_session() {
+        assert_eq!(classify(Some(":0"), None, Some("x11")), SessionKind::X11);
+        assert_eq!(classify(Some(":0"), Some("wayland-0"), Some("wayland")), SessionKind::XWaylandOnly);
+        assert_eq!(classify(None, Some("wayland-0"), None), SessionKind::WaylandNative);
+        // Session type alone is enough to tell a Wayland login from X11
+        assert_eq!(classify(Some(""), None, Some("wayland")), SessionKind::WaylandNative);
+        assert_eq!(classify(None, None, Some("tty")), SessionKind::Headless);
+        assert!(SessionKind::XWaylandOnly.supports_window_management());
+        assert!(!SessionKind::WaylandNative.supports_window_management());
+        assert!(matches!(
+            SessionKind::Headless.require_window_management(),
+            Err(HydraError::Unsupported { .. })
+        ));
+    }
+}
@@ -6,6 +6,7 @@
 
 use thiserror::Error;
 use std::io;
+use std::time::Duration;
 
 /// Main error type for the Hydra Co-op Launcher application
 #[derive(Error, Debug)]
@@ -31,6 +32,24 @@ pub enum HydraError {
     #[error("Adaptive config error: {0}")]
     AdaptiveConfig(#[from] crate::adaptive_config::AdaptiveConfigError),
 
+    #[error("Launch error: {0}")]
+    Launch(#[from] crate::universal_launcher::LaunchError),
+
+    #[error("Session manifest error: {0}")]
+    Session(#[from] crate::session::SessionError),
+
+    #[error("Port allocation error: {0}")]
+    Ports(#[from] crate::ports::PortError),
+
+    #[error("Wine integration error: {0}")]
+    Wine(#[from] crate::wine_integration::WineError),
+
+    #[error("Flatpak integration error: {0}")]
+    Flatpak(#[from] crate::flatpak::FlatpakError),
+
+    #[error("XInput error: {0}")]
+    XInput(#[from] crate::xinput::XInputError),
+
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
     
@@ -42,11 +61,29 @@ pub enum HydraError {
     
     #[error("Validation error: {0}")]
     Validation(String),
+
+    /// The operation was stopped on request, e.g. by Ctrl+C
+    #[error("Cancelled")]
+    Cancelled,
+
+    /// Gave up waiting for something that never happened
+    #[error("Timed out after {after:?} waiting for {what}")]
+    Timeout { what: String, after: Duration },
+
+    /// The operation can't work in this environment, e.g. under Wayland
+    #[error("{feature} is not supported here")]
+    Unsupported { feature: String },
 }
 
 /// Result type alias for convenience
 pub type Result<T> = std::result::Result<T, HydraError>;
 
+impl From<crate::config::ValidationError> for HydraError {
+    fn from(err: crate::config::ValidationError) -> Self {
+        HydraError::Config(crate::config::ConfigError::Validation(err))
+    }
+}
+
 impl HydraError {
     /// Create a new application error
     pub fn application(msg: impl Into<String>) -> Self {
@@ -57,4 +94,69 @@ impl HydraError {
     pub fn validation(msg: impl Into<String>) -> Self {
         HydraError::Validation(msg.into())
     }
-}
\ No newline at end of file
+
+    /// Create a new timeout error
+    pub fn timeout(what: impl Into<String>, after: Duration) -> Self {
+        HydraError::Timeout { what: what.into(), after }
+    }
+
+    /// Create a new unsupported-feature error
+    pub fn unsupported(feature: impl Into<String>) -> Self {
+        HydraError::Unsupported { feature: feature.into() }
+    }
+
+    /// Converts a failed window search, turning a cancelled search into
+    /// `Cancelled` and windows that never appeared within `timeout` into
+    /// `Timeout`.
+    pub fn from_window_search(err: crate::window_manager::WindowManagerError, timeout: Duration) -> Self {
+        use crate::window_manager::WindowManagerError;
+        match err {
+            WindowManagerError::Cancelled => HydraError::Cancelled,
+            WindowManagerError::WindowNotFound(pids) => {
+                HydraError::timeout(format!("game windows of PIDs {:?}", pids), timeout)
+            }
+            err => HydraError::WindowManager(err),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+    use crate::window_manager::WindowManagerError;
+
+    #[test]
+    fn test_module_errors_convert_with_source() {
+        let err = HydraError::from(crate::wine_integration::WineError::WineNotFound);
+        assert!(matches!(err, HydraError::Wine(_)));
+        assert!(err.source().is_some());
+
+        let err = HydraError::from(crate::xinput::XInputError::Unsupported("no extension".to_string()));
+        assert!(matches!(err, HydraError::XInput(_)));
+        assert_eq!(err.to_string(), "XInput error: XInput 2 is not available: no extension");
+
+        // Validation errors keep the configuration error in the chain
+        let err = HydraError::from(crate::config::ValidationError::InvalidInstanceCount(9));
+        assert!(matches!(err, HydraError::Config(_)));
+        let validation = err.source().and_then(|config| config.source());
+        assert!(validation.is_some_and(|e| e.to_string().starts_with("Invalid instance count: 9")));
+    }
+
+    #[test]
+    fn test_window_search_errors_map_to_kinds() {
+        let timeout = Duration::from_secs(30);
+        assert!(matches!(
+            HydraError::from_window_search(WindowManagerError::Cancelled, timeout),
+            HydraError::Cancelled
+        ));
+
+        let err = HydraError::from_window_search(WindowManagerError::WindowNotFound(vec![42]), timeout);
+        assert!(matches!(err, HydraError::Timeout { after, .. } if after == timeout));
+        assert_eq!(err.to_string(), "Timed out after 30s waiting for game windows of PIDs [42]");
+
+        assert!(matches!(
+            HydraError::from_window_search(WindowManagerError::MonitorDetectionError("none".to_string()), timeout),
+            HydraError::WindowManager(_)
+        ));
+    }
+}
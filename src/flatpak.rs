@@ -0,0 +1,188 @@
+//! Flatpak Integration
+//!
+//! Games installed through Flatpak live under `/var/lib/flatpak` or
+//! `~/.local/share/flatpak` and only run inside their sandbox, started with
+//! `flatpak run`. This module recognises such games by their path and finds
+//! the executable of an installed app id.
+
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use log::debug;
+
+/// Error type for Flatpak integration operations
+#[derive(Debug)]
+pub enum FlatpakError {
+    /// No installation has the app, or it has no command to run
+    AppNotFound(String),
+}
+
+impl std::fmt::Display for FlatpakError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlatpakError::AppNotFound(app_id) => write!(
+                f,
+                "Flatpak app '{}' is not installed for this user or system-wide; check the id with `flatpak list --app`",
+                app_id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FlatpakError {}
+
+/// A game packaged as a Flatpak app
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatpakApp {
+    /// Reverse-DNS app id, e.g. `org.openttd.OpenTTD`
+    pub app_id: String,
+    /// Architecture of the deployed build; `None` lets flatpak pick
+    pub arch: Option<String>,
+    /// Branch of the deployed build, usually `stable`; `None` lets flatpak pick
+    pub branch: Option<String>,
+    /// Program to run inside the sandbox (under `/app`) when it isn't the
+    /// app's own command
+    pub command: Option<PathBuf>,
+}
+
+impl FlatpakApp {
+    /// The app `path` belongs to, if it is inside a Flatpak installation:
+    /// a file of a deployed app (`<installation>/app/<id>/<arch>/<branch>/<commit>/files/...`,
+    /// or `.../app/<id>/current/active/files/...`) or an exported launcher
+    /// (`<installation>/exports/bin/<id>`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let components: Vec<&str> = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        let start = components.windows(2).position(|pair| pair[0] == "flatpak" && matches!(pair[1], "app" | "exports"))?;
+        let rest = &components[start + 1..];
+        if let ["exports", "bin", app_id] = rest {
+            return Some(FlatpakApp { app_id: app_id.to_string(), arch: None, branch: None, command: None });
+        }
+        let ["app", app_id, deploy @ ..] = rest else { return None };
+        let (arch, branch, deploy) = match deploy {
+            ["current", "active", deploy @ ..] => (None, None, deploy),
+            [arch, branch, _commit, deploy @ ..] => (Some(arch.to_string()), Some(branch.to_string()), deploy),
+            _ => return None,
+        };
+        // Only files/ is visible inside the sandbox, mounted at /app
+        let command = match deploy {
+            ["files", inner @ ..] if !inner.is_empty() => Some(Path::new("/app").join(inner.join("/"))),
+            _ => None,
+        };
+        Some(FlatpakApp { app_id: app_id.to_string(), arch, branch, command })
+    }
+}
+
+/// Flatpak installations, the user's first like `flatpak run` searches them
+fn installations() -> Vec<PathBuf> {
+    let user = env::var_os("FLATPAK_USER_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::data_dir().map(|dir| dir.join("flatpak")));
+    user.into_iter().chain([PathBuf::from("/var/lib/flatpak")]).collect()
+}
+
+/// Executable of the installed app `app_id`: the command its metadata names,
+/// with symlinks resolved so the path also records the arch and branch.
+pub fn resolve_app(app_id: &str) -> Result<PathBuf, FlatpakError> {
+    resolve_app_in(&installations(), app_id).ok_or_else(|| FlatpakError::AppNotFound(app_id.to_string()))
+}
+
+fn resolve_app_in(installations: &[PathBuf], app_id: &str) -> Option<PathBuf> {
+    installations.iter().find_map(|installation| {
+        let deploy = installation.join("app").join(app_id).join("current").join("active");
+        let metadata = fs::read_to_string(deploy.join("metadata")).ok()?;
+        let command = app_command(&metadata)?;
+        let inner = match command.strip_prefix("/app/") {
+            Some(inner) => PathBuf::from(inner),
+            // flatpak looks bare names up on PATH, which starts with /app/bin
+            None if !command.starts_with('/') => Path::new("bin").join(command),
+            // A program of the runtime rather than of the app
+            None => return None,
+        };
+        let executable = deploy.join("files").join(inner);
+        debug!("Flatpak app {} runs {}", app_id, executable.display());
+        fs::canonicalize(&executable).ok()
+    })
+}
+
+/// `command` from the `[Application]` group of an app's metadata file
+fn app_command(metadata: &str) -> Option<&str> {
+    let mut in_application = false;
+    for line in metadata.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if let Some(command) = line.strip_prefix("command=").filter(|_| in_application) {
+            return Some(command.trim()).filter(|command| !command.is_empty());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            FlatpakApp::from_path(Path::new(
+                "/var/lib/flatpak/app/org.example.Game/x86_64/stable/0a1b2c/files/bin/game"
+            )),
+            Some(FlatpakApp {
+                app_id: "org.example.Game".to_string(),
+                arch: Some("x86_64".to_string()),
+                branch: Some("stable".to_string()),
+                command: Some(PathBuf::from("/app/bin/game")),
+            })
+        );
+        assert_eq!(
+            FlatpakApp::from_path(Path::new("/home/ann/.local/share/flatpak/app/org.example.Game/current/active/files/lib/game/run")),
+            Some(FlatpakApp {
+                app_id: "org.example.Game".to_string(),
+                arch: None,
+                branch: None,
+                command: Some(PathBuf::from("/app/lib/game/run")),
+            })
+        );
+        assert_eq!(
+            FlatpakApp::from_path(Path::new("/var/lib/flatpak/exports/bin/org.example.Game")),
+            Some(FlatpakApp { app_id: "org.example.Game".to_string(), arch: None, branch: None, command: None })
+        );
+        assert_eq!(FlatpakApp::from_path(Path::new("/home/ann/Games/flatpak/game.x86_64")), None);
+        assert_eq!(FlatpakApp::from_path(Path::new("/usr/bin/game")), None);
+    }
+
+    #[test]
+    fn test_resolve_app_in() {
+        let user = tempdir().unwrap();
+        let root = tempdir().unwrap();
+        let system = root.path().join("flatpak");
+        let deploy = system.join("app/org.example.Game/x86_64/stable/0a1b2c");
+        fs::create_dir_all(deploy.join("files/bin")).unwrap();
+        fs::write(deploy.join("files/bin/game"), b"").unwrap();
+        fs::write(
+            deploy.join("metadata"),
+            "[Application]\nname=org.example.Game\nruntime=org.freedesktop.Platform/x86_64/23.08\ncommand=game\n\n[Context]\nshared=network;\n",
+        )
+        .unwrap();
+        let app_dir = system.join("app/org.example.Game");
+        std::os::unix::fs::symlink("x86_64/stable", app_dir.join("current")).unwrap();
+        std::os::unix::fs::symlink("0a1b2c", app_dir.join("x86_64/stable/active")).unwrap();
+
+        let installations = [user.path().to_path_buf(), system];
+        let executable = resolve_app_in(&installations, "org.example.Game").unwrap();
+        assert_eq!(executable, fs::canonicalize(deploy.join("files/bin/game")).unwrap());
+        assert_eq!(
+            FlatpakApp::from_path(&executable).map(|app| (app.branch, app.command)),
+            Some((Some("stable".to_string()), Some(PathBuf::from("/app/bin/game"))))
+        );
+        assert_eq!(resolve_app_in(&installations, "org.example.Missing"), None);
+        assert_eq!(app_command("[Context]\ncommand=nope\n"), None);
+    }
+}
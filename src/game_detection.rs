@@ -4,18 +4,19 @@
 //! that works with any game without requiring game-specific handlers.
 
 use std::path::{Path, PathBuf};
-use std::fs;
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use log::{info, debug};
-use crate::errors::Result;
 
 /// Error type for game detection operations.
 #[derive(Debug)]
 pub enum GameDetectionError {
     Io(io::Error),
     AnalysisFailed(String),
+    /// A profile couldn't be written as, or read from, TOML
+    ProfileFormat(String),
 }
 
 impl std::fmt::Display for GameDetectionError {
@@ -23,6 +24,7 @@ impl std::fmt::Display for GameDetectionError {
         match self {
             GameDetectionError::Io(e) => write!(f, "game detection I/O error: {}", e),
             GameDetectionError::AnalysisFailed(msg) => write!(f, "game analysis failed: {}", msg),
+            GameDetectionError::ProfileFormat(msg) => write!(f, "invalid game profile: {}", msg),
         }
     }
 }
@@ -37,8 +39,57 @@ impl From<io::Error> for GameDetectionError {
     fn from(e: io::Error) -> Self { GameDetectionError::Io(e) }
 }
 
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Number of bytes sampled from each end of an executable for its game ID
+const GAME_ID_SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// Folds `bytes` into a running 64-bit FNV-1a hash.
+/// Start from the value returned by [`fnv1a_new`].
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Initial state for [`fnv1a`].
+pub(crate) fn fnv1a_new() -> u64 {
+    FNV_OFFSET
+}
+
+/// Computes a stable identifier for a game executable.
+///
+/// The ID combines the file name with a hash of the file size and the first
+/// and last 64 KiB, so two different games that share a name (`game.exe`)
+/// get different IDs while hashing stays fast for large binaries.
+pub fn compute_game_id(executable_path: &Path) -> Result<String, GameDetectionError> {
+    let mut file = File::open(executable_path)?;
+    let size = file.metadata()?.len();
+
+    let mut hash = fnv1a(fnv1a_new(), &size.to_le_bytes());
+    let mut head = Vec::new();
+    (&mut file).take(GAME_ID_SAMPLE_BYTES).read_to_end(&mut head)?;
+    hash = fnv1a(hash, &head);
+
+    if size > GAME_ID_SAMPLE_BYTES * 2 {
+        file.seek(SeekFrom::End(-(GAME_ID_SAMPLE_BYTES as i64)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        hash = fnv1a(hash, &tail);
+    }
+
+    let name = executable_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown");
+    Ok(format!("{}-{:016x}", name, hash))
+}
+
 /// Universal game profile that can be applied to any game
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GameProfile {
     /// Game executable name or pattern
     pub executable_pattern: String,
@@ -56,10 +107,103 @@ pub struct GameProfile {
     pub environment_vars: HashMap<String, String>,
     /// Working directory strategy
     pub working_dir_strategy: WorkingDirStrategy,
+    /// How the game talks to other instances over the network
+    #[serde(default)]
+    pub network_mode: NetworkMode,
+    /// Why more than one copy of the game probably can't run, if anything
+    #[serde(default)]
+    pub instance_blockers: Vec<InstanceBlocker>,
+}
+
+impl GameProfile {
+    /// The profile as a TOML document, for sharing a profile that works.
+    pub fn to_toml(&self) -> Result<String, GameDetectionError> {
+        toml::to_string_pretty(self).map_err(|e| GameDetectionError::ProfileFormat(e.to_string()))
+    }
+
+    /// Reads a profile written by [`GameProfile::to_toml`].
+    pub fn from_toml(toml: &str) -> Result<Self, GameDetectionError> {
+        toml::from_str(toml).map_err(|e| GameDetectionError::ProfileFormat(e.to_string()))
+    }
+}
+
+/// An executable in a game's folder that may be the game itself, see
+/// `GameDetector::find_candidate_executables`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutableCandidate {
+    pub path: PathBuf,
+    /// Higher means more likely the game rather than a launcher or helper
+    pub score: i32,
+    /// What the score is based on, for showing to the user
+    pub reasons: Vec<String>,
+}
+
+/// How many folders below the game folder are searched for executables;
+/// enough for Unreal's `<Game>/Binaries/Win64`
+const CANDIDATE_SEARCH_DEPTH: usize = 4;
+
+/// How much better another executable must score than the chosen one
+/// before `GameDetector::suggest_executable` proposes it
+const SUGGESTION_MARGIN: i32 = 30;
+
+/// Name fragments of programs shipped alongside games that are never the
+/// game itself
+const HELPER_NAME_PATTERNS: [&str; 9] = [
+    "crashhandler",
+    "crashreport",
+    "redist",
+    "vcredist",
+    "dxsetup",
+    "uninstall",
+    "unins000",
+    "prereq",
+    "setup",
+];
+
+/// Anti-cheat systems that refuse a second copy of the game and block
+/// injected input: (name, files or folders the game ships it as)
+const ANTI_CHEAT_INDICATORS: [(&str, &[&str]); 4] = [
+    ("EasyAntiCheat", &["EasyAntiCheat", "EasyAntiCheat_x64.dll", "start_protected_game.exe"]),
+    ("BattlEye", &["BattlEye", "BEService.exe", "BEService_x64.exe"]),
+    ("nProtect GameGuard", &["GameGuard"]),
+    ("XIGNCODE3", &["XIGNCODE"]),
+];
+
+/// Executable names (lowercase) of games that hold a single-instance lock
+/// no separation works around
+const SINGLE_INSTANCE_EXECUTABLES: [&str; 6] = [
+    "gta5.exe",
+    "rdr2.exe",
+    "eldenring.exe",
+    "destiny2.exe",
+    "r5apex.exe",
+    "fortniteclient-win64-shipping.exe",
+];
+
+/// Why a game probably can't run more than one copy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstanceBlocker {
+    /// The game ships this anti-cheat system
+    AntiCheat(String),
+    /// The executable, by name, is known to lock out a second copy
+    KnownSingleInstance(String),
+}
+
+impl std::fmt::Display for InstanceBlocker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstanceBlocker::AntiCheat(name) => {
+                write!(f, "{} detected; multi-instance and input injection are likely blocked", name)
+            }
+            InstanceBlocker::KnownSingleInstance(exe_name) => {
+                write!(f, "{} is known to run only one copy at a time; the other copies will likely fail to start", exe_name)
+            }
+        }
+    }
 }
 
 /// Detected game engine types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum GameEngine {
     Unity,
     UnrealEngine,
@@ -70,8 +214,32 @@ pub enum GameEngine {
     Unknown,
 }
 
+impl GameEngine {
+    /// Launch arguments that set the engine's window resolution, in terms of
+    /// the `${WIDTH}` and `${HEIGHT}` template variables. Empty when the
+    /// engine has no known command-line option for it.
+    pub fn resolution_args(&self) -> &'static [&'static str] {
+        match self {
+            GameEngine::UnrealEngine => &["-ResX=${WIDTH}", "-ResY=${HEIGHT}"],
+            GameEngine::Unity => &["-screen-width", "${WIDTH}", "-screen-height", "${HEIGHT}"],
+            _ => &[],
+        }
+    }
+
+    /// True if `arg` is one of the engine's resolution options with a fixed
+    /// value, which `resolution_args` replaces. Unity's options take their
+    /// value as the next argument.
+    pub fn is_resolution_arg(&self, arg: &str) -> bool {
+        match self {
+            GameEngine::UnrealEngine => arg.starts_with("-ResX=") || arg.starts_with("-ResY="),
+            GameEngine::Unity => arg == "-screen-width" || arg == "-screen-height",
+            _ => false,
+        }
+    }
+}
+
 /// Multi-instance support levels
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MultiInstanceSupport {
     /// Game natively supports multiple instances
     Native,
@@ -83,8 +251,20 @@ pub enum MultiInstanceSupport {
     Unsupported,
 }
 
+/// Networking transports a game may use for multiplayer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NetworkMode {
+    /// Plain UDP sockets that the network emulator can relay
+    #[default]
+    RawUdp,
+    /// Steam networking (P2P via the Steamworks API); needs a Steam emulator
+    Steamworks,
+    /// The game needs no networking between instances
+    None,
+}
+
 /// Working directory strategies for different games
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum WorkingDirStrategy {
     /// Use the game's installation directory
     GameDirectory,
@@ -110,9 +290,22 @@ impl GameDetector {
     }
 
     /// Detect and analyze a game executable to create a universal profile
-    pub fn detect_game(&mut self, executable_path: &Path) -> Result<GameProfile> {
+    pub fn detect_game(&mut self, executable_path: &Path) -> Result<GameProfile, GameDetectionError> {
+        let game_dir = executable_path.parent().unwrap_or(Path::new("."));
+        let exe_name = executable_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+        self.detect_game_in_dir(game_dir, exe_name)
+    }
+
+    /// Detect the game whose executable is `exe_name` in `game_dir`. The
+    /// engine and Steamworks checks only look at `game_dir`, so pointing this
+    /// at a copy of a game's folder layout gives the same profile as the game.
+    pub fn detect_game_in_dir(&mut self, game_dir: &Path, exe_name: &str) -> Result<GameProfile, GameDetectionError> {
+        let executable_path = game_dir.join(exe_name);
         // Check cache first
-        if let Some(cached_profile) = self.profile_cache.get(executable_path) {
+        if let Some(cached_profile) = self.profile_cache.get(&executable_path) {
             debug!("Using cached profile for {}", executable_path.display());
             return Ok(cached_profile.clone());
         }
@@ -120,11 +313,7 @@ impl GameDetector {
         info!("Analyzing game executable: {}", executable_path.display());
 
         let mut profile = GameProfile {
-            executable_pattern: executable_path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
+            executable_pattern: exe_name.to_string(),
             engine: None,
             default_ports: vec![7777, 7778, 7779, 7780], // Common game ports
             default_layout: "horizontal".to_string(),
@@ -132,19 +321,33 @@ impl GameDetector {
             launch_args: Vec::new(),
             environment_vars: HashMap::new(),
             working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+            network_mode: NetworkMode::RawUdp,
+            instance_blockers: Vec::new(),
         };
 
         // Detect game engine
-        profile.engine = self.detect_engine(executable_path)?;
+        profile.engine = self.detect_engine(game_dir, exe_name)?;
 
         // Configure based on detected engine
         self.configure_for_engine(&mut profile);
 
+        // Steamworks games never open raw sockets between instances
+        if self.check_steamworks_indicators(game_dir) {
+            info!("Detected bundled Steamworks API; game uses Steam networking.");
+            profile.network_mode = NetworkMode::Steamworks;
+            profile.multi_instance_support = MultiInstanceSupport::RequiresWorkarounds;
+        }
+
+        profile.instance_blockers = self.check_instance_blockers(game_dir, exe_name);
+        if !profile.instance_blockers.is_empty() {
+            profile.multi_instance_support = MultiInstanceSupport::Unsupported;
+        }
+
         // Analyze executable for additional hints
-        self.analyze_executable(&mut profile, executable_path)?;
+        self.analyze_executable(&mut profile, &executable_path)?;
 
         // Cache the profile
-        self.profile_cache.insert(executable_path.to_path_buf(), profile.clone());
+        self.profile_cache.insert(executable_path.clone(), profile.clone());
 
         info!("Generated universal profile for {}: engine={:?}, support={:?}", 
                executable_path.display(), profile.engine, profile.multi_instance_support);
@@ -152,10 +355,17 @@ impl GameDetector {
         Ok(profile)
     }
 
-    /// Detect the game engine by analyzing the executable and its directory
-    fn detect_engine(&self, executable_path: &Path) -> Result<Option<GameEngine>> {
-        let game_dir = executable_path.parent().unwrap_or(Path::new("."));
-        
+    /// Detect `executable_path` and write its profile to `path` as TOML.
+    pub fn export_profile(&mut self, executable_path: &Path, path: &Path) -> Result<GameProfile, GameDetectionError> {
+        let profile = self.detect_game(executable_path)?;
+        crate::utils::write_atomic(path, profile.to_toml()?.as_bytes())?;
+        info!("Exported the profile of {} to {}", executable_path.display(), path.display());
+        Ok(profile)
+    }
+
+    /// Detect the game engine from the files in `game_dir` and the executable's name
+    fn detect_engine(&self, game_dir: &Path, exe_name: &str) -> Result<Option<GameEngine>, GameDetectionError> {
+
         // Check for engine-specific files and directories
         if self.check_unity_indicators(game_dir) {
             return Ok(Some(GameEngine::Unity));
@@ -174,11 +384,7 @@ impl GameDetector {
         }
 
         // Check executable name patterns
-        let exe_name = executable_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_lowercase();
+        let exe_name = exe_name.to_lowercase();
 
         if exe_name.contains("unity") {
             return Ok(Some(GameEngine::Unity));
@@ -303,6 +509,33 @@ impl GameDetector {
         false
     }
 
+    /// Check for a bundled Steamworks API library
+    fn check_steamworks_indicators(&self, game_dir: &Path) -> bool {
+        let steamworks_indicators = [
+            "steam_api.dll",
+            "steam_api64.dll",
+            "libsteam_api.so",
+        ];
+
+        steamworks_indicators
+            .iter()
+            .any(|indicator| game_dir.join(indicator).is_file())
+    }
+
+    /// Anti-cheat shipped in `game_dir` and known single-instance
+    /// executables, which keep a second copy from running
+    fn check_instance_blockers(&self, game_dir: &Path, exe_name: &str) -> Vec<InstanceBlocker> {
+        let mut blockers: Vec<InstanceBlocker> = ANTI_CHEAT_INDICATORS
+            .iter()
+            .filter(|(_, indicators)| indicators.iter().any(|indicator| game_dir.join(indicator).exists()))
+            .map(|(name, _)| InstanceBlocker::AntiCheat(name.to_string()))
+            .collect();
+        if SINGLE_INSTANCE_EXECUTABLES.contains(&exe_name.to_lowercase().as_str()) {
+            blockers.push(InstanceBlocker::KnownSingleInstance(exe_name.to_string()));
+        }
+        blockers
+    }
+
     /// Configure profile based on detected engine
     fn configure_for_engine(&self, profile: &mut GameProfile) {
         match profile.engine {
@@ -345,7 +578,7 @@ impl GameDetector {
     }
 
     /// Analyze executable for additional configuration hints
-    fn analyze_executable(&self, profile: &mut GameProfile, executable_path: &Path) -> std::result::Result<(), GameDetectionError> {
+    fn analyze_executable(&self, profile: &mut GameProfile, executable_path: &Path) -> Result<(), GameDetectionError> {
         // Check if it's a Windows executable
         if crate::proton_integration::is_windows_binary(executable_path)
             .map_err(|e| GameDetectionError::AnalysisFailed(e.to_string()))?
@@ -369,6 +602,86 @@ impl GameDetector {
         Ok(())
     }
 
+    /// Executables in `game_dir` and the folders below it, most likely to be
+    /// the game first. Launchers and helpers such as crash reporters and
+    /// installers rank last.
+    pub fn find_candidate_executables(&self, game_dir: &Path) -> Vec<ExecutableCandidate> {
+        let mut candidates = Vec::new();
+        let mut pending = vec![(game_dir.to_path_buf(), 0)];
+        while let Some((dir, depth)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else { continue };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                // Unreal's own tools live under Engine/
+                let searched = !hidden && depth < CANDIDATE_SEARCH_DEPTH && entry.file_name() != "Engine";
+                match entry.file_type() {
+                    Ok(kind) if kind.is_dir() && searched => pending.push((path, depth + 1)),
+                    Ok(kind) if kind.is_file() && is_executable_file(&path) => {
+                        candidates.push(self.score_executable(&path));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+        candidates
+    }
+
+    /// A better choice than `chosen` from the executables around it, when
+    /// `chosen` looks like a launcher or helper rather than the game.
+    pub fn suggest_executable(&self, chosen: &Path) -> Option<ExecutableCandidate> {
+        let game_dir = chosen.parent()?;
+        let chosen_score = self.score_executable(chosen).score;
+        self.find_candidate_executables(game_dir)
+            .into_iter()
+            .next()
+            .filter(|best| best.path != chosen && best.score - chosen_score >= SUGGESTION_MARGIN)
+    }
+
+    /// Rates how likely `path` is to be the game binary.
+    fn score_executable(&self, path: &Path) -> ExecutableCandidate {
+        let mut score = 0;
+        let mut reasons = Vec::new();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+        let stem = path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        if name.contains("-shipping") {
+            score += 50;
+            reasons.push("Unreal Engine shipping build".to_string());
+        }
+        if name.contains("launcher") {
+            score -= 40;
+            reasons.push("named like a launcher".to_string());
+        }
+        if HELPER_NAME_PATTERNS.iter().any(|pattern| name.contains(pattern)) {
+            score -= 100;
+            reasons.push("a helper program (crash reporter, installer or redistributable)".to_string());
+        }
+        if dir.join(format!("{}_Data", stem)).is_dir() {
+            score += 40;
+            reasons.push(format!("has Unity's {}_Data folder", stem));
+        }
+        let binaries_dir = dir.parent().and_then(|parent| parent.file_name()).is_some_and(|name| name == "Binaries");
+        if binaries_dir {
+            score += 10;
+            reasons.push("in Unreal's Binaries folder".to_string());
+        }
+        if self.check_unity_indicators(dir) || self.check_godot_indicators(dir) || self.check_gamemaker_indicators(dir) {
+            score += 20;
+            reasons.push("next to game engine files".to_string());
+        }
+        // Games are usually the largest executable around
+        let size_mb = fs::metadata(path).map(|meta| meta.len() / (1024 * 1024)).unwrap_or(0);
+        if size_mb > 0 {
+            score += size_mb.min(30) as i32;
+            reasons.push(format!("{} MB", size_mb));
+        }
+
+        ExecutableCandidate { path: path.to_path_buf(), score, reasons }
+    }
+
     /// Get recommended configuration for a game
     pub fn get_recommended_config(&self, profile: &GameProfile, num_instances: usize) -> GameConfiguration {
         let mut ports = profile.default_ports.clone();
@@ -391,12 +704,26 @@ impl GameDetector {
                 MultiInstanceSupport::RequiresWorkarounds => InstanceSeparation::Full,
                 MultiInstanceSupport::Unsupported => InstanceSeparation::Full,
             },
+            instance_args: Vec::new(),
         }
     }
 }
 
+/// Windows executables, and Linux binaries with the executable bit.
+pub fn is_executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("exe") => true,
+        Some("x86_64" | "x86") | None => fs::metadata(path)
+            .map(|meta| meta.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false),
+        Some(_) => false,
+    }
+}
+
 /// Configuration generated for a specific game and instance count
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameConfiguration {
     pub ports: Vec<u16>,
     pub layout: String,
@@ -404,10 +731,15 @@ pub struct GameConfiguration {
     pub environment_vars: HashMap<String, String>,
     pub working_dir_strategy: WorkingDirStrategy,
     pub instance_separation: InstanceSeparation,
+    /// Extra arguments for each instance, indexed by instance. Like all
+    /// arguments and environment values they may use `${...}` variables, see
+    /// `template::expand_template`
+    #[serde(default)]
+    pub instance_args: Vec<Vec<String>>,
 }
 
 /// Strategies for separating game instances
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InstanceSeparation {
     /// No separation needed (game handles it natively)
     None,
@@ -415,6 +747,13 @@ pub enum InstanceSeparation {
     Environment,
     /// Full separation (directories, configs, etc.)
     Full,
+    /// Run instance `i` as `users[i]` through `sudo -u`, so each instance gets
+    /// its own home directory and per-user game data
+    SeparateUsers { users: Vec<String> },
+    /// Separate like `Environment`, and show instance `i` only the input
+    /// device `devices[i]`: SDL is pointed at it, and where bubblewrap is
+    /// available the instance gets a `/dev/input` holding just that node
+    InputIsolation { devices: Vec<Option<PathBuf>> },
 }
 
 impl Default for GameDetector {
@@ -442,6 +781,45 @@ mod tests {
         assert!(detector.check_unity_indicators(game_dir));
     }
 
+    /// A game folder layout under `tests/fixtures`
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    #[test]
+    fn test_detect_fixture_games() {
+        for (dir, exe_name) in [
+            ("unity_game", "Game.x86_64"),
+            ("godot_game", "game.x86_64"),
+            ("unreal_steam_game", "Game.exe"),
+            ("battleye_game", "Game.exe"),
+        ] {
+            let profile = GameDetector::new().detect_game_in_dir(&fixture(dir), exe_name).unwrap();
+            let expected = fs::read_to_string(fixture(&format!("{dir}.toml"))).unwrap();
+            assert_eq!(profile, GameProfile::from_toml(&expected).unwrap(), "profile detected for {dir}");
+        }
+    }
+
+    #[test]
+    fn test_is_resolution_arg() {
+        assert!(GameEngine::UnrealEngine.is_resolution_arg("-ResX=1920"));
+        assert!(GameEngine::Unity.is_resolution_arg("-screen-width"));
+        assert!(GameEngine::Unity.is_resolution_arg("-screen-height"));
+        assert!(!GameEngine::Unity.is_resolution_arg("-screen-fullscreen"));
+        assert!(!GameEngine::Godot.is_resolution_arg("-screen-width"));
+    }
+
+    #[test]
+    fn test_known_single_instance_executable() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("GTA5.exe"), b"MZ").unwrap();
+
+        let profile = GameDetector::new().detect_game_in_dir(temp_dir.path(), "GTA5.exe").unwrap();
+        assert_eq!(profile.multi_instance_support, MultiInstanceSupport::Unsupported);
+        assert_eq!(profile.instance_blockers, vec![InstanceBlocker::KnownSingleInstance("GTA5.exe".to_string())]);
+        assert!(profile.instance_blockers[0].to_string().contains("only one copy"));
+    }
+
     #[test]
     fn test_game_profile_generation() {
         let temp_dir = tempdir().unwrap();
@@ -453,5 +831,91 @@ mod tests {
         
         assert_eq!(profile.executable_pattern, "TestGame.exe");
         assert!(!profile.default_ports.is_empty());
+        assert_eq!(profile.network_mode, NetworkMode::RawUdp);
+    }
+
+    #[test]
+    fn test_export_profile_round_trips() {
+        let temp_dir = tempdir().unwrap();
+        let exe_path = temp_dir.path().join("TestGame.exe");
+        fs::File::create(&exe_path).unwrap();
+        fs::File::create(temp_dir.path().join("UnityPlayer.dll")).unwrap();
+
+        let mut detector = GameDetector::new();
+        let profile_path = temp_dir.path().join("TestGame.toml");
+        let mut exported = detector.export_profile(&exe_path, &profile_path).unwrap();
+        let imported = GameProfile::from_toml(&fs::read_to_string(&profile_path).unwrap()).unwrap();
+        assert_eq!(imported.executable_pattern, "TestGame.exe");
+        assert!(matches!(imported.engine, Some(GameEngine::Unity)));
+        assert_eq!(imported.default_ports, exported.default_ports);
+        assert_eq!(imported.launch_args, exported.launch_args);
+
+        exported.engine = Some(GameEngine::Custom("Source".to_string()));
+        exported.environment_vars.insert("SDL_VIDEODRIVER".to_string(), "x11".to_string());
+        let imported = GameProfile::from_toml(&exported.to_toml().unwrap()).unwrap();
+        assert!(matches!(imported.engine, Some(GameEngine::Custom(name)) if name == "Source"));
+        assert_eq!(imported.environment_vars["SDL_VIDEODRIVER"], "x11");
+
+        assert!(matches!(GameProfile::from_toml("engine = 3"), Err(GameDetectionError::ProfileFormat(_))));
+    }
+
+    #[test]
+    fn test_compute_game_id_distinguishes_same_name() {
+        let dir_a = tempdir().unwrap();
+        let dir_b = tempdir().unwrap();
+        let exe_a = dir_a.path().join("game.exe");
+        let exe_b = dir_b.path().join("game.exe");
+        fs::write(&exe_a, b"first game").unwrap();
+        fs::write(&exe_b, b"second game").unwrap();
+
+        let id_a = compute_game_id(&exe_a).unwrap();
+        assert!(id_a.starts_with("game.exe-"));
+        assert_ne!(id_a, compute_game_id(&exe_b).unwrap());
+
+        fs::write(&exe_b, b"first game").unwrap();
+        assert_eq!(id_a, compute_game_id(&exe_b).unwrap());
+    }
+
+    #[test]
+    fn test_find_candidate_executables() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp_dir = tempdir().unwrap();
+        let game_dir = temp_dir.path();
+        let shipping_dir = game_dir.join("MyGame/Binaries/Win64");
+        fs::create_dir_all(&shipping_dir).unwrap();
+        fs::create_dir_all(game_dir.join("Engine/Binaries/Win64")).unwrap();
+        fs::write(game_dir.join("MyGame.exe"), b"MZ launcher").unwrap();
+        fs::write(game_dir.join("GameLauncher.exe"), b"MZ launcher").unwrap();
+        fs::write(game_dir.join("UnityCrashHandler64.exe"), b"MZ").unwrap();
+        fs::write(game_dir.join("Engine/Binaries/Win64/CrashReportClient.exe"), b"MZ").unwrap();
+        fs::write(game_dir.join("readme.txt"), b"not a program").unwrap();
+        fs::write(shipping_dir.join("MyGame-Win64-Shipping.exe"), b"MZ game").unwrap();
+        let script = game_dir.join("start");
+        fs::write(&script, b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let detector = GameDetector::new();
+        let candidates = detector.find_candidate_executables(game_dir);
+        let names: Vec<_> = candidates.iter().map(|c| c.path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["MyGame-Win64-Shipping.exe", "MyGame.exe", "start", "GameLauncher.exe", "UnityCrashHandler64.exe"]);
+        assert!(candidates[0].reasons.iter().any(|r| r.contains("shipping")));
+
+        let suggestion = detector.suggest_executable(&game_dir.join("MyGame.exe")).unwrap();
+        assert_eq!(suggestion.path, shipping_dir.join("MyGame-Win64-Shipping.exe"));
+        assert_eq!(detector.suggest_executable(&suggestion.path), None);
+    }
+
+    #[test]
+    fn test_steamworks_detection() {
+        let temp_dir = tempdir().unwrap();
+        let exe_path = temp_dir.path().join("TestGame.exe");
+        fs::File::create(&exe_path).unwrap();
+        fs::File::create(temp_dir.path().join("steam_api64.dll")).unwrap();
+
+        let mut detector = GameDetector::new();
+        let profile = detector.detect_game(&exe_path).unwrap();
+
+        assert_eq!(profile.network_mode, NetworkMode::Steamworks);
+        assert!(matches!(profile.multi_instance_support, MultiInstanceSupport::RequiresWorkarounds));
     }
 }
\ No newline at end of file
@@ -1,50 +1,90 @@
 //! GTK4 front-end for the Hydra Co-op Launcher.
 //!
 //! The window is a single scrollable page with five sections:
-//!   1. Game            — pick the executable
+//!   1. Game            — pick the executable and review its detected settings
 //!   2. Players         — number of players and per-player input devices
-//!   3. Layout          — horizontal / vertical / 2x2 grid
-//!   4. Options         — Proton toggle
+//!   3. Layout          — horizontal / vertical / 2x2 grid / tabbed, previewed
+//!   4. Options         — Windows runtime and Wine prefix cleanup
 //!   5. Log             — live status output
 //!
 //! "Save" writes the current choices to ~/.config/hydra-coop/config.toml.
 //! "Launch" runs the core logic on a background thread and streams log
 //! updates back to the UI.
 
-use std::cell::RefCell;
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::sync::atomic::AtomicBool;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, SystemTime};
 
 use gtk::gdk;
+use gtk::gio;
 use gtk::glib;
-use gtk::pango;
 use gtk::prelude::*;
 use gtk::{
-    Align, Application, ApplicationWindow, Box as GtkBox, Button, CheckButton, ComboBoxText,
-    CssProvider, FileChooserAction, FileChooserDialog, Frame, HeaderBar, Label, MessageDialog,
+    Align, Application, ApplicationWindow, Box as GtkBox, Button, ComboBoxText,
+    CssProvider, DrawingArea, FileChooserAction, FileChooserDialog, Frame, HeaderBar, Label, MessageDialog,
     MessageType, Orientation, PolicyType, ResponseType, ScrolledWindow, Separator, Spinner,
     TextBuffer, TextView, ToggleButton,
 };
-use log::{error, info};
-
-use crate::config::Config;
-use crate::input_mux::{DeviceIdentifier, InputAssignment};
-use crate::run_core_logic;
-use crate::window_manager::Layout;
+use log::{error, info, warn};
+
+use crate::config::{remember_recent_game, Config, LayoutConfig};
+use crate::errors::HydraError;
+use crate::game_detection::GameDetector;
+use crate::universal_launcher::{LaunchError, WindowsRuntime};
+use crate::input_mux::{DeviceIdentifier, InputAssignment, InputMuxError};
+use crate::ports::PortStrategy;
+use crate::{format_age, format_size, open_adaptive_store, open_window_manager, run_core_logic, supervise_session};
+use crate::window_manager::{Layout, Monitor, WindowManager};
+
+/// Wine prefixes unused for this long are deleted by "Delete unused"
+const UNUSED_PREFIX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Fill colours (RGB) of the players' windows in the layout preview
+const PLAYER_COLORS: [(f64, f64, f64); 4] = [
+    (0.27, 0.52, 0.96),
+    (0.91, 0.33, 0.30),
+    (0.30, 0.75, 0.42),
+    (0.96, 0.70, 0.22),
+];
 
 /// All mutable UI state the signal handlers need.
 struct GuiState {
     window: ApplicationWindow,
-    available_devices: Vec<DeviceIdentifier>,
-    file_path_label: Label,
+    /// Config loaded at startup; fields the GUI doesn't edit are kept from here.
+    base_config: Config,
+    /// Devices offered in the input combos; refreshed when devices come and go
+    available_devices: RefCell<Vec<DeviceIdentifier>>,
+    /// `available_devices` grouped by physical device, primary interface first
+    controllers: RefCell<Vec<Vec<DeviceIdentifier>>>,
+    /// Watches /dev/input so the combos follow hotplugged controllers
+    device_monitor: RefCell<Option<gio::FileMonitor>>,
+    device_refresh_pending: Cell<bool>,
+    games_combo: ComboBoxText,
+    remove_game_button: Button,
+    review_button: Button,
+    /// Ports from an applied recommendation, used instead of consecutive
+    /// ports from the config's first one
+    recommended_ports: RefCell<Option<Vec<u16>>>,
+    /// Recently used games offered in `games_combo`, most recent first
+    recent_games: RefCell<Vec<PathBuf>>,
+    /// The game picked in `games_combo`
     game_path: RefCell<Option<PathBuf>>,
     players_combo: ComboBoxText,
+    refresh_devices_button: Button,
+    assign_by_button: Button,
     input_rows: RefCell<Vec<ComboBoxText>>,
     input_rows_box: GtkBox,
     layout_toggle: LayoutToggle,
-    proton_checkbox: CheckButton,
+    layout_preview: DrawingArea,
+    /// Works out the preview's window positions with the config's window
+    /// settings; `None` without an X display
+    preview_window_manager: Option<WindowManager>,
+    /// Monitors found at startup, drawn by the preview
+    preview_monitors: Vec<Monitor>,
+    runtime_combo: ComboBoxText,
     launch_button: Button,
     save_button: Button,
     status_label: Label,
@@ -52,50 +92,63 @@ struct GuiState {
     log_buffer: TextBuffer,
 }
 
-/// The three layout-mode toggle buttons grouped together.
+/// The layout-mode toggle buttons grouped together.
 struct LayoutToggle {
     horizontal: ToggleButton,
     vertical: ToggleButton,
     grid: ToggleButton,
+    tabbed: ToggleButton,
+    /// Keeps a grid or custom layout from the config file, which the other
+    /// buttons can't express; hidden when the config has none
+    configured: ToggleButton,
+    configured_layout: RefCell<Option<LayoutConfig>>,
 }
 
 impl LayoutToggle {
     fn selected(&self) -> Layout {
-        if self.vertical.is_active() {
-            Layout::Vertical
-        } else if self.grid.is_active() {
-            Layout::Grid2x2
-        } else {
-            Layout::Horizontal
-        }
+        self.selected_config().layout()
     }
 
-    fn set_from_str(&self, value: &str) {
+    fn set_from_config(&self, value: &LayoutConfig) {
         match value {
-            "vertical" => self.vertical.set_active(true),
-            "grid2x2" => self.grid.set_active(true),
+            LayoutConfig::Vertical => self.vertical.set_active(true),
+            LayoutConfig::Grid2x2 => self.grid.set_active(true),
+            LayoutConfig::Tabbed => self.tabbed.set_active(true),
+            LayoutConfig::Grid { .. } | LayoutConfig::Custom { .. } => {
+                self.configured.set_label(match value {
+                    LayoutConfig::Custom { .. } => "Custom",
+                    _ => "Grid from config",
+                });
+                self.configured.set_visible(true);
+                self.configured.set_active(true);
+                *self.configured_layout.borrow_mut() = Some(value.clone());
+            }
             _ => self.horizontal.set_active(true),
         }
     }
 
-    fn as_config_string(&self) -> &'static str {
+    fn selected_config(&self) -> LayoutConfig {
         if self.vertical.is_active() {
-            "vertical"
+            LayoutConfig::Vertical
         } else if self.grid.is_active() {
-            "grid2x2"
+            LayoutConfig::Grid2x2
+        } else if self.tabbed.is_active() {
+            LayoutConfig::Tabbed
+        } else if self.configured.is_active() {
+            self.configured_layout.borrow().clone().unwrap_or_default()
         } else {
-            "horizontal"
+            LayoutConfig::Horizontal
         }
     }
 }
 
 pub fn run_gui(
-    available_devices: Vec<DeviceIdentifier>,
+    controllers: Vec<Vec<DeviceIdentifier>>,
     initial_config: Config,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> crate::Result<()> {
     let app = Application::new(Some("com.hydra.coop.launcher"), Default::default());
 
-    let devices = Rc::new(available_devices);
+    let devices = Rc::new(controllers);
     let initial_config = Rc::new(initial_config);
 
     app.connect_activate(move |app| {
@@ -124,7 +177,7 @@ fn load_custom_css() {
 
 fn build_main_window(
     app: &Application,
-    devices: &Rc<Vec<DeviceIdentifier>>,
+    devices: &Rc<Vec<Vec<DeviceIdentifier>>>,
     initial_config: &Config,
 ) -> Rc<GuiState> {
     let window = ApplicationWindow::new(app);
@@ -153,19 +206,19 @@ fn build_main_window(
     content.set_margin_end(24);
 
     // --- Game selection -----------------------------------------------------
-    let (game_frame, file_path_label, browse_button) = build_game_section();
+    let (game_frame, games_combo, browse_button, remove_game_button, review_button) = build_game_section();
     content.append(&game_frame);
 
     // --- Players ------------------------------------------------------------
-    let (players_frame, players_combo, input_rows_box) = build_players_section();
+    let (players_frame, players_combo, refresh_devices_button, assign_by_button, input_rows_box) = build_players_section();
     content.append(&players_frame);
 
     // --- Layout -------------------------------------------------------------
-    let (layout_frame, layout_toggle) = build_layout_section();
+    let (layout_frame, layout_toggle, layout_preview) = build_layout_section();
     content.append(&layout_frame);
 
     // --- Options ------------------------------------------------------------
-    let (options_frame, proton_checkbox) = build_options_section();
+    let (options_frame, runtime_combo, prefixes_button) = build_options_section();
     content.append(&options_frame);
 
     // --- Actions ------------------------------------------------------------
@@ -180,16 +233,38 @@ fn build_main_window(
     root.append(&scrolled);
     window.set_child(Some(&root));
 
+    let preview_window_manager = open_window_manager(initial_config, initial_config.window_layout.layout())
+        .inspect_err(|e| info!("No layout preview: {}", e))
+        .ok();
+    let preview_monitors = preview_window_manager
+        .as_ref()
+        .and_then(|window_manager| window_manager.monitors().ok())
+        .unwrap_or_default();
+    layout_preview.set_visible(!preview_monitors.is_empty());
+
     let state = Rc::new(GuiState {
         window,
-        available_devices: devices.as_ref().clone(),
-        file_path_label: file_path_label.clone(),
+        base_config: initial_config.clone(),
+        available_devices: RefCell::new(devices.concat()),
+        controllers: RefCell::new(devices.as_ref().clone()),
+        device_monitor: RefCell::new(None),
+        device_refresh_pending: Cell::new(false),
+        games_combo,
+        remove_game_button: remove_game_button.clone(),
+        review_button: review_button.clone(),
+        recommended_ports: RefCell::new(None),
+        recent_games: RefCell::new(initial_config.game_paths.clone()),
         game_path: RefCell::new(initial_config.primary_game_path().cloned()),
         players_combo: players_combo.clone(),
+        refresh_devices_button,
+        assign_by_button,
         input_rows: RefCell::new(Vec::new()),
         input_rows_box,
         layout_toggle,
-        proton_checkbox,
+        layout_preview: layout_preview.clone(),
+        preview_window_manager,
+        preview_monitors,
+        runtime_combo,
         launch_button,
         save_button,
         status_label,
@@ -202,11 +277,27 @@ fn build_main_window(
         let state = Rc::clone(&state);
         browse_button.connect_clicked(move |_| on_browse_clicked(&state));
     }
+    {
+        let state = Rc::clone(&state);
+        remove_game_button.connect_clicked(move |_| on_remove_game_clicked(&state));
+    }
+    {
+        let state = Rc::clone(&state);
+        review_button.connect_clicked(move |_| on_review_clicked(&state));
+    }
+    {
+        let state = Rc::clone(&state);
+        prefixes_button.connect_clicked(move |_| on_prefixes_clicked(&state));
+    }
+    {
+        let state = Rc::clone(&state);
+        layout_preview.set_draw_func(move |_, cr, width, height| draw_layout_preview(&state, cr, width, height));
+    }
 
     state
 }
 
-fn build_game_section() -> (Frame, Label, Button) {
+fn build_game_section() -> (Frame, ComboBoxText, Button, Button, Button) {
     let frame = section_frame("1. Game", "Pick the game executable you want to co-op.");
     let inner = GtkBox::new(Orientation::Horizontal, 12);
     set_frame_padding(&inner);
@@ -215,19 +306,25 @@ fn build_game_section() -> (Frame, Label, Button) {
     browse.add_css_class("suggested-action");
     browse.set_tooltip_text(Some("Choose the game's .exe or Linux binary"));
 
-    let path_label = Label::new(Some("No game selected"));
-    path_label.set_ellipsize(pango::EllipsizeMode::Middle);
-    path_label.set_halign(Align::Start);
-    path_label.set_hexpand(true);
-    path_label.add_css_class("file-path-label");
+    let games = ComboBoxText::new();
+    games.set_hexpand(true);
+    games.set_tooltip_text(Some("Recently used games"));
+
+    let remove = Button::with_label("Remove");
+    remove.set_tooltip_text(Some("Take the selected game off the list"));
+
+    let review = Button::with_label("Review…");
+    review.set_tooltip_text(Some("Show the settings detected and learned for this game, and apply them"));
 
     inner.append(&browse);
-    inner.append(&path_label);
+    inner.append(&games);
+    inner.append(&remove);
+    inner.append(&review);
     frame.set_child(Some(&inner));
-    (frame, path_label, browse)
+    (frame, games, browse, remove, review)
 }
 
-fn build_players_section() -> (Frame, ComboBoxText, GtkBox) {
+fn build_players_section() -> (Frame, ComboBoxText, Button, Button, GtkBox) {
     let frame = section_frame(
         "2. Players",
         "Choose how many players and which input device each will use.",
@@ -248,8 +345,18 @@ fn build_players_section() -> (Frame, ComboBoxText, GtkBox) {
     combo.set_active(Some(1));
     combo.set_tooltip_text(Some("How many copies of the game to launch"));
 
+    let refresh = Button::with_label("Refresh Devices");
+    refresh.set_halign(Align::End);
+    refresh.set_hexpand(true);
+    refresh.set_tooltip_text(Some("Look again for controllers plugged in since the launcher opened"));
+
+    let assign = Button::with_label("Press to Assign");
+    assign.set_tooltip_text(Some("Each player in turn presses a button on their controller or keyboard to claim it"));
+
     header_row.append(&count_label);
     header_row.append(&combo);
+    header_row.append(&refresh);
+    header_row.append(&assign);
     inner.append(&header_row);
     inner.append(&Separator::new(Orientation::Horizontal));
 
@@ -257,14 +364,15 @@ fn build_players_section() -> (Frame, ComboBoxText, GtkBox) {
     inner.append(&rows_box);
 
     frame.set_child(Some(&inner));
-    (frame, combo, rows_box)
+    (frame, combo, refresh, assign, rows_box)
 }
 
-fn build_layout_section() -> (Frame, LayoutToggle) {
+fn build_layout_section() -> (Frame, LayoutToggle, DrawingArea) {
     let frame = section_frame("3. Layout", "How the game windows are arranged on screen.");
 
+    let column = GtkBox::new(Orientation::Vertical, 12);
+    set_frame_padding(&column);
     let inner = GtkBox::new(Orientation::Horizontal, 12);
-    set_frame_padding(&inner);
 
     let horizontal = ToggleButton::with_label("Horizontal");
     horizontal.set_active(true);
@@ -281,10 +389,30 @@ fn build_layout_section() -> (Frame, LayoutToggle) {
     grid.set_tooltip_text(Some("Four quadrants — use for 3–4 players"));
     grid.set_group(Some(&horizontal));
 
+    let tabbed = ToggleButton::with_label("Tabbed");
+    tabbed.add_css_class("layout-radio");
+    tabbed.set_tooltip_text(Some("Every window fills one monitor; a hotkey (Scroll Lock by default) switches between them"));
+    tabbed.set_group(Some(&horizontal));
+
+    let configured = ToggleButton::with_label("From config");
+    configured.add_css_class("layout-radio");
+    configured.set_tooltip_text(Some("The layout set by window_layout in the config file"));
+    configured.set_group(Some(&horizontal));
+    configured.set_visible(false);
+
     inner.append(&horizontal);
     inner.append(&vertical);
     inner.append(&grid);
-    frame.set_child(Some(&inner));
+    inner.append(&tabbed);
+    inner.append(&configured);
+    column.append(&inner);
+
+    let preview = DrawingArea::new();
+    preview.set_content_height(180);
+    preview.set_hexpand(true);
+    preview.set_tooltip_text(Some("Where each player's window goes on your monitors"));
+    column.append(&preview);
+    frame.set_child(Some(&column));
 
     (
         frame,
@@ -292,22 +420,110 @@ fn build_layout_section() -> (Frame, LayoutToggle) {
             horizontal,
             vertical,
             grid,
+            tabbed,
+            configured,
+            configured_layout: RefCell::new(None),
         },
+        preview,
     )
 }
 
-fn build_options_section() -> (Frame, CheckButton) {
+/// Draws the monitors to scale with a numbered cell for each player's
+/// window in the selected layout.
+fn draw_layout_preview(state: &GuiState, cr: &gtk::cairo::Context, width: i32, height: i32) {
+    let monitors = &state.preview_monitors;
+    let Some(window_manager) = &state.preview_window_manager else { return };
+    if monitors.is_empty() {
+        return;
+    }
+    let slots = window_manager.layout_slots(monitors, state.layout_toggle.selected(), selected_player_count(state));
+
+    // Fit the desktop's bounding box into the drawing area, centred.
+    let left = monitors.iter().map(|m| m.x).min().unwrap_or(0) as f64;
+    let top = monitors.iter().map(|m| m.y).min().unwrap_or(0) as f64;
+    let right = monitors.iter().map(|m| m.x + m.width).max().unwrap_or(1) as f64;
+    let bottom = monitors.iter().map(|m| m.y + m.height).max().unwrap_or(1) as f64;
+    let margin = 8.0;
+    let scale = ((width as f64 - 2.0 * margin) / (right - left)).min((height as f64 - 2.0 * margin) / (bottom - top));
+    if scale <= 0.0 {
+        return;
+    }
+    let offset_x = (width as f64 - (right - left) * scale) / 2.0;
+    let offset_y = (height as f64 - (bottom - top) * scale) / 2.0;
+    let to_area = |x: i32, y: i32| (offset_x + (x as f64 - left) * scale, offset_y + (y as f64 - top) * scale);
+
+    cr.select_font_face("Sans", gtk::cairo::FontSlant::Normal, gtk::cairo::FontWeight::Bold);
+    for (index, monitor) in monitors.iter().enumerate() {
+        let (x, y) = to_area(monitor.x, monitor.y);
+        cr.rectangle(x, y, monitor.width as f64 * scale, monitor.height as f64 * scale);
+        cr.set_source_rgb(0.16, 0.16, 0.18);
+        let _ = cr.fill_preserve();
+        cr.set_source_rgb(0.55, 0.55, 0.6);
+        cr.set_line_width(2.0);
+        let _ = cr.stroke();
+
+        cr.set_font_size(11.0);
+        cr.move_to(x + 6.0, y + 14.0);
+        let name = monitor.name.clone().unwrap_or_else(|| format!("Monitor {}", index + 1));
+        let _ = cr.show_text(&name);
+    }
+
+    for (player, (_, rect)) in slots.iter().enumerate() {
+        let (x, y) = to_area(rect.x, rect.y);
+        let (w, h) = (rect.width as f64 * scale, rect.height as f64 * scale);
+        let (r, g, b) = PLAYER_COLORS[player % PLAYER_COLORS.len()];
+        cr.rectangle(x + 2.0, y + 2.0, (w - 4.0).max(1.0), (h - 4.0).max(1.0));
+        cr.set_source_rgba(r, g, b, 0.45);
+        let _ = cr.fill_preserve();
+        cr.set_source_rgb(r, g, b);
+        cr.set_line_width(1.5);
+        let _ = cr.stroke();
+
+        let label = format!("P{}", player + 1);
+        cr.set_font_size((h / 3.0).clamp(10.0, 28.0));
+        if let Ok(extents) = cr.text_extents(&label) {
+            cr.move_to(x + (w - extents.width()) / 2.0, y + (h + extents.height()) / 2.0);
+            cr.set_source_rgb(1.0, 1.0, 1.0);
+            let _ = cr.show_text(&label);
+        }
+    }
+}
+
+fn build_options_section() -> (Frame, ComboBoxText, Button) {
     let frame = section_frame("4. Options", "Extra flags that apply to every instance.");
-    let inner = GtkBox::new(Orientation::Vertical, 8);
+    let inner = GtkBox::new(Orientation::Horizontal, 12);
     set_frame_padding(&inner);
 
-    let proton = CheckButton::with_label("Use Proton (required for Windows .exe games)");
-    proton.set_tooltip_text(Some(
-        "Enable when launching a Windows executable. Requires Proton installed via Steam.",
+    let label = Label::new(Some("Run Windows games with"));
+    label.add_css_class("setting-label");
+    label.set_halign(Align::Start);
+
+    let runtime = ComboBoxText::new();
+    runtime.append(Some("none"), "Nothing (native Linux game)");
+    runtime.append(Some("proton"), "Proton (installed via Steam)");
+    runtime.append(Some("wine"), "Wine (installed on the system)");
+    runtime.set_active_id(Some("none"));
+    runtime.set_tooltip_text(Some(
+        "Windows .exe games need Proton or Wine. Proton comes with Steam; Wine from your package manager.",
     ));
-    inner.append(&proton);
+
+    let prefixes = Button::with_label("Wine prefixes…");
+    prefixes.set_tooltip_text(Some("See how much space the Proton and Wine prefixes of your games take, and delete them"));
+
+    inner.append(&label);
+    inner.append(&runtime);
+    inner.append(&prefixes);
     frame.set_child(Some(&inner));
-    (frame, proton)
+    (frame, runtime, prefixes)
+}
+
+/// The Windows runtime picked in the options section
+fn selected_runtime(state: &GuiState) -> WindowsRuntime {
+    match state.runtime_combo.active_id().as_deref() {
+        Some("proton") => WindowsRuntime::Proton,
+        Some("wine") => WindowsRuntime::Wine,
+        _ => WindowsRuntime::None,
+    }
 }
 
 fn build_action_buttons() -> (GtkBox, Button, Button) {
@@ -404,9 +620,44 @@ fn wire_signals(state: Rc<GuiState>) {
                     rebuild_input_rows(&state, n);
                 }
             }
+            state.layout_preview.queue_draw();
+        });
+    }
+
+    {
+        let toggle = &state.layout_toggle;
+        for button in [&toggle.horizontal, &toggle.vertical, &toggle.grid, &toggle.tabbed, &toggle.configured] {
+            let state = Rc::clone(&state);
+            button.connect_toggled(move |_| state.layout_preview.queue_draw());
+        }
+    }
+
+    {
+        let combo = state.games_combo.clone();
+        let state = Rc::clone(&state);
+        combo.connect_changed(move |combo| {
+            if let Some(id) = combo.active_id() {
+                *state.game_path.borrow_mut() = Some(PathBuf::from(id.as_str()));
+                // A recommendation applied for another game no longer fits
+                *state.recommended_ports.borrow_mut() = None;
+            }
         });
     }
 
+    {
+        let button = state.refresh_devices_button.clone();
+        let state = Rc::clone(&state);
+        button.connect_clicked(move |_| refresh_input_devices(&state));
+    }
+
+    {
+        let button = state.assign_by_button.clone();
+        let state = Rc::clone(&state);
+        button.connect_clicked(move |_| on_assign_by_button_clicked(&state));
+    }
+
+    watch_input_devices(&state);
+
     {
         let button = state.save_button.clone();
         let state = Rc::clone(&state);
@@ -435,8 +686,8 @@ fn on_browse_clicked(state: &Rc<GuiState>) {
         if response == ResponseType::Accept {
             if let Some(file) = dialog.file() {
                 if let Some(path) = file.path() {
-                    state.file_path_label.set_text(&path.to_string_lossy());
-                    *state.game_path.borrow_mut() = Some(path);
+                    select_game(&state, path.clone());
+                    offer_better_executable(&state, &path);
                 }
             }
         }
@@ -445,6 +696,275 @@ fn on_browse_clicked(state: &Rc<GuiState>) {
     dialog.show();
 }
 
+fn select_game(state: &Rc<GuiState>, path: PathBuf) {
+    remember_recent_game(&mut state.recent_games.borrow_mut(), path.clone());
+    *state.game_path.borrow_mut() = Some(path);
+    show_recent_games(state);
+}
+
+/// Asks whether to use a different executable when `chosen` looks like a
+/// launcher and the game binary is nearby.
+fn offer_better_executable(state: &Rc<GuiState>, chosen: &Path) {
+    let Some(better) = GameDetector::new().suggest_executable(chosen) else {
+        return;
+    };
+    let better_name = better.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let dialog = MessageDialog::builder()
+        .transient_for(&state.window)
+        .modal(true)
+        .message_type(MessageType::Question)
+        .text("This may only open the game's launcher")
+        .secondary_text(format!(
+            "The game itself is probably {} ({}). Launching the launcher usually starts just one copy.",
+            better.path.display(),
+            better.reasons.join(", ")
+        ))
+        .build();
+    dialog.add_button("Keep my choice", ResponseType::Cancel);
+    dialog.add_button(&format!("Use {}", better_name), ResponseType::Accept);
+
+    let state = Rc::clone(state);
+    dialog.connect_response(move |dialog, response| {
+        dialog.close();
+        if response == ResponseType::Accept {
+            select_game(&state, better.path.clone());
+        }
+    });
+    dialog.show();
+}
+
+/// Detects the selected game, overlays what earlier launches taught the
+/// adaptive store, and offers to apply the result to the widgets.
+fn on_review_clicked(state: &Rc<GuiState>) {
+    let Some(path) = state.game_path.borrow().clone() else {
+        show_error(&state.window, "No game selected", "Pick a game first.");
+        return;
+    };
+    let player_count = selected_player_count(state);
+
+    let mut detector = GameDetector::new();
+    let profile = match detector.detect_game(&path) {
+        Ok(profile) => profile,
+        Err(e) => {
+            show_error(&state.window, "Could not analyse the game", &e.to_string());
+            return;
+        }
+    };
+    let mut game_config = detector.get_recommended_config(&profile, player_count);
+
+    let threshold = state.base_config.adaptive_confidence_threshold;
+    let learned = match open_adaptive_store(&state.base_config) {
+        None => "Learning from earlier launches is turned off.".to_string(),
+        Some(mut store) => {
+            let recommendation = store
+                .game_id_for(&path)
+                .ok()
+                .and_then(|game_id| store.get_recommended_config(&game_id, &profile));
+            match recommendation {
+                Some(recommendation) if recommendation.confidence >= threshold => {
+                    recommendation.apply_to(&mut game_config);
+                    format!("Learned from earlier launches (confidence {:.2}), included above.", recommendation.confidence)
+                }
+                Some(recommendation) => format!(
+                    "Learned from earlier launches with confidence {:.2}, below the {:.2} needed to use it.",
+                    recommendation.confidence, threshold
+                ),
+                None => "Nothing learned from earlier launches yet.".to_string(),
+            }
+        }
+    };
+    let windows_binary = crate::proton_integration::is_windows_binary(&path).unwrap_or(false);
+
+    let list = |items: Vec<String>| if items.is_empty() { "none".to_string() } else { items.join(" ") };
+    let mut environment: Vec<String> =
+        game_config.environment_vars.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+    environment.sort();
+    let mut summary = format!(
+        "Engine: {}\nMultiple copies: {:?}\nPorts: {}\nLayout: {}\nLaunch arguments: {}\nEnvironment: {}\n\n{}",
+        profile.engine.as_ref().map_or("not recognised".to_string(), |engine| format!("{:?}", engine)),
+        profile.multi_instance_support,
+        list(game_config.ports.iter().map(u16::to_string).collect()),
+        game_config.layout,
+        list(game_config.launch_args.clone()),
+        list(environment),
+        learned
+    );
+    if windows_binary && selected_runtime(state) == WindowsRuntime::None {
+        summary.push_str("\nThis is a Windows program; applying selects Proton.");
+    }
+    for blocker in &profile.instance_blockers {
+        summary.push_str(&format!("\nWarning: {}.", blocker));
+    }
+
+    let dialog = MessageDialog::builder()
+        .transient_for(&state.window)
+        .modal(true)
+        .message_type(MessageType::Info)
+        .text(format!("Detected settings for {}", path.file_name().unwrap_or_default().to_string_lossy()))
+        .secondary_text(summary)
+        .build();
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Apply", ResponseType::Accept);
+
+    let state = Rc::clone(state);
+    dialog.connect_response(move |dialog, response| {
+        dialog.close();
+        if response != ResponseType::Accept {
+            return;
+        }
+        match game_config.layout.parse::<LayoutConfig>() {
+            Ok(layout) => state.layout_toggle.set_from_config(&layout),
+            Err(e) => warn!("Not applying the recommended layout '{}': {}", game_config.layout, e),
+        }
+        *state.recommended_ports.borrow_mut() = Some(game_config.ports.clone());
+        if windows_binary && selected_runtime(&state) == WindowsRuntime::None {
+            state.runtime_combo.set_active_id(Some("proton"));
+        }
+        append_log(&state, "Applied the detected settings; arguments and environment are added at launch\n");
+    });
+    dialog.show();
+}
+
+/// Lists the Wine prefixes Hydra created, one row per game, with buttons to
+/// delete a game's prefixes or every prefix left unused for a month.
+fn on_prefixes_clicked(state: &Rc<GuiState>) {
+    let data_dir = match crate::utils::get_data_dir() {
+        Ok(dir) => Rc::new(dir),
+        Err(e) => {
+            show_error(&state.window, "Could not find Hydra's data directory", &e.to_string());
+            return;
+        }
+    };
+
+    let dialog = gtk::Window::builder()
+        .transient_for(&state.window)
+        .modal(true)
+        .title("Wine prefixes")
+        .default_width(600)
+        .build();
+    let content = GtkBox::new(Orientation::Vertical, 12);
+    set_frame_padding(&content);
+    let rows = GtkBox::new(Orientation::Vertical, 8);
+    let actions = GtkBox::new(Orientation::Horizontal, 12);
+    actions.set_halign(Align::End);
+    let clean = Button::with_label("Delete unused for 30 days");
+    let close = Button::with_label("Close");
+    actions.append(&clean);
+    actions.append(&close);
+    content.append(&rows);
+    content.append(&Separator::new(Orientation::Horizontal));
+    content.append(&actions);
+    dialog.set_child(Some(&content));
+
+    show_prefixes(state, &rows, &data_dir);
+    {
+        let state = Rc::clone(state);
+        let rows = rows.clone();
+        let data_dir = Rc::clone(&data_dir);
+        clean.connect_clicked(move |_| {
+            match crate::proton_integration::clean_prefixes(&data_dir, UNUSED_PREFIX_AGE) {
+                Ok(removed) => log_removed_prefixes(&state, &removed),
+                Err(e) => show_error(&state.window, "Could not delete the prefixes", &e.to_string()),
+            }
+            show_prefixes(&state, &rows, &data_dir);
+        });
+    }
+    {
+        let dialog = dialog.clone();
+        close.connect_clicked(move |_| dialog.close());
+    }
+    dialog.present();
+}
+
+/// Fills `rows` with one line per game that has prefixes, and its delete button.
+fn show_prefixes(state: &Rc<GuiState>, rows: &GtkBox, data_dir: &Rc<PathBuf>) {
+    while let Some(child) = rows.first_child() {
+        rows.remove(&child);
+    }
+
+    // (game, prefix count, total size, last used)
+    let mut games: Vec<(String, usize, u64, SystemTime)> = Vec::new();
+    for prefix in crate::proton_integration::list_managed_prefixes(data_dir) {
+        match games.iter_mut().find(|game| game.0 == prefix.game) {
+            Some(game) => {
+                game.1 += 1;
+                game.2 += prefix.size;
+                game.3 = game.3.max(prefix.last_used);
+            }
+            None => games.push((prefix.game, 1, prefix.size, prefix.last_used)),
+        }
+    }
+    if games.is_empty() {
+        let empty = Label::new(Some("Hydra hasn't created any Wine prefixes yet."));
+        empty.set_halign(Align::Start);
+        rows.append(&empty);
+        return;
+    }
+
+    for (game, count, size, last_used) in games {
+        let row = GtkBox::new(Orientation::Horizontal, 12);
+        let label = Label::new(Some(&format!(
+            "{} — {} prefix(es), {}, last used {}",
+            game,
+            count,
+            format_size(size),
+            format_age(last_used)
+        )));
+        label.set_hexpand(true);
+        label.set_halign(Align::Start);
+        let delete = Button::with_label("Delete");
+        delete.add_css_class("destructive-action");
+        delete.set_tooltip_text(Some("Delete this game's prefixes; they are created again at the next launch"));
+        {
+            let state = Rc::clone(state);
+            let rows = rows.clone();
+            let data_dir = Rc::clone(data_dir);
+            delete.connect_clicked(move |_| {
+                match crate::proton_integration::remove_game_prefixes(&data_dir, &game) {
+                    Ok(removed) => log_removed_prefixes(&state, &removed),
+                    Err(e) => show_error(&state.window, "Could not delete the prefixes", &e.to_string()),
+                }
+                show_prefixes(&state, &rows, &data_dir);
+            });
+        }
+        row.append(&label);
+        row.append(&delete);
+        rows.append(&row);
+    }
+}
+
+fn log_removed_prefixes(state: &Rc<GuiState>, removed: &[crate::proton_integration::PrefixInfo]) {
+    let total: u64 = removed.iter().map(|prefix| prefix.size).sum();
+    append_log(state, &format!("Deleted {} Wine prefix(es), freeing {}\n", removed.len(), format_size(total)));
+}
+
+fn on_remove_game_clicked(state: &Rc<GuiState>) {
+    let Some(path) = state.game_path.borrow_mut().take() else {
+        return;
+    };
+    state.recent_games.borrow_mut().retain(|game| *game != path);
+    let next = state.recent_games.borrow().first().cloned();
+    *state.game_path.borrow_mut() = next;
+    show_recent_games(state);
+    append_log(state, &format!("Removed {} from the recent games\n", path.display()));
+}
+
+/// Fills the games dropdown from `recent_games` and selects `game_path`.
+fn show_recent_games(state: &GuiState) {
+    let games = state.recent_games.borrow().clone();
+    let selected = state.game_path.borrow().clone();
+    state.games_combo.remove_all();
+    for game in &games {
+        let id = game.to_string_lossy();
+        state.games_combo.append(Some(&id), &id);
+    }
+    state.review_button.set_sensitive(selected.is_some());
+    if let Some(path) = selected {
+        state.games_combo.set_active_id(Some(&path.to_string_lossy()));
+    }
+    state.remove_game_button.set_sensitive(!games.is_empty());
+}
+
 fn rebuild_input_rows(state: &Rc<GuiState>, num_players: usize) {
     // Clear existing rows.
     while let Some(child) = state.input_rows_box.first_child() {
@@ -462,9 +982,15 @@ fn rebuild_input_rows(state: &Rc<GuiState>, num_players: usize) {
 
         let combo = ComboBoxText::new();
         combo.append(Some("auto"), "Auto-detect");
-        for device in state.available_devices.iter() {
+        // One entry per controller; picking it routes its primary interface
+        for interfaces in state.controllers.borrow().iter() {
+            let device = &interfaces[0];
             if let Ok(id) = serde_json::to_string(device) {
-                combo.append(Some(&id), &device.name);
+                let label = match interfaces.len() {
+                    1 => device.name.clone(),
+                    n => format!("{} ({} interfaces)", device.name, n),
+                };
+                combo.append(Some(&id), &label);
             }
         }
         combo.set_active_id(Some("auto"));
@@ -478,12 +1004,149 @@ fn rebuild_input_rows(state: &Rc<GuiState>, num_players: usize) {
     }
 }
 
+/// Asks each player in turn to press a button and selects the device they
+/// pressed in their combo. The presses are read on a background thread.
+fn on_assign_by_button_clicked(state: &Rc<GuiState>) {
+    let num_players = selected_player_count(state);
+    state.assign_by_button.set_sensitive(false);
+    state.launch_button.set_sensitive(false);
+
+    let (tx, rx) = mpsc::channel::<AssignMessage>();
+    std::thread::spawn(move || {
+        let mut mux = crate::input_mux::InputMux::new();
+        if let Err(e) = mux.enumerate_devices() {
+            let _ = tx.send(AssignMessage::Failed(e.to_string()));
+            return;
+        }
+        let mut pressed: Vec<DeviceIdentifier> = Vec::new();
+        for player in 0..num_players {
+            let _ = tx.send(AssignMessage::Prompt(player));
+            let deadline = std::time::Instant::now() + crate::defaults::BUTTON_ASSIGN_TIMEOUT;
+            loop {
+                let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                match mux.detect_next_pressed(remaining) {
+                    Some(device) if pressed.contains(&device) => continue,
+                    Some(device) => {
+                        pressed.push(device.clone());
+                        let _ = tx.send(AssignMessage::Assigned(player, device));
+                        break;
+                    }
+                    None => {
+                        let _ = tx.send(AssignMessage::Failed(format!("Nobody pressed a button for player {}.", player + 1)));
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    let state = Rc::clone(state);
+    glib::timeout_add_local(Duration::from_millis(100), move || {
+        loop {
+            match rx.try_recv() {
+                Ok(AssignMessage::Prompt(player)) => {
+                    set_status(&state, &format!("Player {}, press any button on your controller or keyboard…", player + 1), true);
+                }
+                Ok(AssignMessage::Assigned(player, device)) => {
+                    if let (Some(combo), Ok(id)) = (state.input_rows.borrow().get(player), serde_json::to_string(&device)) {
+                        if !combo.set_active_id(Some(&id)) {
+                            combo.append(Some(&id), &device.name);
+                            combo.set_active_id(Some(&id));
+                        }
+                    }
+                    append_log(&state, &format!("Player {}: {}\n", player + 1, device.name));
+                }
+                Ok(AssignMessage::Failed(message)) => {
+                    append_log(&state, &format!("Assigning by button stopped: {message}\n"));
+                    show_error(&state.window, "Assigning by button stopped", &message);
+                }
+                Err(mpsc::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                Err(mpsc::TryRecvError::Disconnected) => break,
+            }
+        }
+        set_status(&state, "Ready.", false);
+        state.assign_by_button.set_sensitive(true);
+        state.launch_button.set_sensitive(true);
+        glib::ControlFlow::Break
+    });
+}
+
+enum AssignMessage {
+    /// Waiting for this player to press a button
+    Prompt(usize),
+    /// This player pressed a button on this device
+    Assigned(usize, DeviceIdentifier),
+    Failed(String),
+}
+
+/// Enumerates the input devices again and rebuilds the per-player combos,
+/// keeping each player's choice if that device is still there.
+fn refresh_input_devices(state: &Rc<GuiState>) {
+    let controllers = crate::enumerate_controllers();
+    if *state.controllers.borrow() == controllers {
+        return;
+    }
+    let selections: Vec<_> = state.input_rows.borrow().iter().map(|combo| combo.active_id()).collect();
+    *state.available_devices.borrow_mut() = controllers.concat();
+    *state.controllers.borrow_mut() = controllers;
+    rebuild_input_rows(state, selections.len());
+    for (combo, selection) in state.input_rows.borrow().iter().zip(selections) {
+        // A device that went away leaves its combo on Auto-detect
+        if let Some(id) = selection {
+            combo.set_active_id(Some(&id));
+        }
+    }
+    let count = state.available_devices.borrow().len();
+    append_log(state, &format!("Input devices changed; {} available\n", count));
+}
+
+/// Refreshes the input devices whenever a device node appears or disappears
+/// under /dev/input. Events are batched, and the refresh waits a moment so
+/// udev can set the new node's permissions first.
+fn watch_input_devices(state: &Rc<GuiState>) {
+    let monitor = match gio::File::for_path("/dev/input")
+        .monitor_directory(gio::FileMonitorFlags::NONE, None::<&gio::Cancellable>)
+    {
+        Ok(monitor) => monitor,
+        Err(e) => {
+            info!("Not watching for new input devices ({}); use Refresh Devices instead.", e);
+            return;
+        }
+    };
+    let weak = Rc::downgrade(state);
+    monitor.connect_changed(move |_, file, _, event| {
+        if !matches!(event, gio::FileMonitorEvent::Created | gio::FileMonitorEvent::Deleted) {
+            return;
+        }
+        if !file.basename().is_some_and(|name| name.to_string_lossy().starts_with("event")) {
+            return;
+        }
+        let Some(state) = weak.upgrade() else { return };
+        if state.device_refresh_pending.replace(true) {
+            return;
+        }
+        let weak = Rc::downgrade(&state);
+        glib::timeout_add_local_once(Duration::from_millis(500), move || {
+            if let Some(state) = weak.upgrade() {
+                state.device_refresh_pending.set(false);
+                refresh_input_devices(&state);
+            }
+        });
+    });
+    *state.device_monitor.borrow_mut() = Some(monitor);
+}
+
 fn on_save_clicked(state: &Rc<GuiState>) {
     let config = collect_config(state);
+    if let Err(e) = config.validate() {
+        show_error(&state.window, "Could not save", &format!("The settings aren't valid yet: {e}"));
+        return;
+    }
     match save_config_to_disk(&config) {
         Ok(path) => {
             append_log(state, &format!("Saved configuration to {}\n", path.display()));
             set_status(state, &format!("Saved to {}", path.display()), false);
+            show_info(&state.window, "Configuration saved", &format!("Saved to {}", path.display()));
         }
         Err(e) => {
             error!("Failed to save config: {e}");
@@ -511,9 +1174,45 @@ fn on_launch_clicked(state: &Rc<GuiState>) {
     }
 
     let config = collect_config(state);
-    let assignments = collect_assignments(state);
+    if !config.allow_no_input && crate::enumerate_input_devices().is_empty() {
+        confirm_keyboard_only(state, game_path, config);
+        return;
+    }
+    start_launch(state, game_path, config);
+}
+
+/// Asks whether to launch although no input device can be opened, launching
+/// keyboard-only if the user agrees.
+fn confirm_keyboard_only(state: &Rc<GuiState>, game_path: PathBuf, config: Config) {
+    let dialog = MessageDialog::builder()
+        .transient_for(&state.window)
+        .modal(true)
+        .message_type(MessageType::Warning)
+        .text("No input devices found")
+        .secondary_text(
+            "Hydra couldn't open any keyboard, mouse or controller, so players can't get \
+             their own input. Check the permissions from Step 4 of the README, or launch \
+             anyway and control the games with the desktop's keyboard and mouse.",
+        )
+        .build();
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Launch Keyboard-Only", ResponseType::Accept);
+
+    let state = Rc::clone(state);
+    dialog.connect_response(move |dialog, response| {
+        dialog.close();
+        if response == ResponseType::Accept {
+            let config = Config { allow_no_input: true, ..config.clone() };
+            start_launch(&state, game_path.clone(), config);
+        }
+    });
+    dialog.show();
+}
+
+fn start_launch(state: &Rc<GuiState>, game_path: PathBuf, config: Config) {
+    let assignments = collect_input_assignments(state);
     let layout = state.layout_toggle.selected();
-    let use_proton = state.proton_checkbox.is_active();
+    let runtime = selected_runtime(state);
     let num_players = assignments.len();
 
     state.launch_button.set_sensitive(false);
@@ -532,31 +1231,28 @@ fn on_launch_clicked(state: &Rc<GuiState>) {
         let tx = tx.clone();
         std::thread::spawn(move || {
             let _ = tx.send(LaunchMessage::Log("Starting background services…\n".to_string()));
-            let result = run_core_logic(
-                &game_path,
-                num_players,
-                &assignments,
-                layout,
-                use_proton,
-                &config,
-            );
+            let stop = Arc::new(AtomicBool::new(false));
+            let result = run_core_logic(&game_path, num_players, &assignments, layout, runtime, &config, None, &stop);
             match result {
-                Ok((mut net, mut mux, mut launcher)) => {
+                Ok(session) => {
                     let _ = tx.send(LaunchMessage::Running);
                     // Keep background services alive until all instances exit.
-                    loop {
-                        if !launcher.any_running() {
-                            break;
-                        }
-                        std::thread::sleep(Duration::from_millis(500));
-                    }
-                    let _ = net.stop_relay();
-                    let _ = mux.stop_capture();
-                    launcher.shutdown_instances();
+                    supervise_session(session, num_players, layout, &config, &stop, |_, _, _| {});
                     let _ = tx.send(LaunchMessage::Finished);
                 }
                 Err(e) => {
-                    let _ = tx.send(LaunchMessage::Failed(format!("{e}")));
+                    let title = match e {
+                        HydraError::Cancelled => "Launch cancelled",
+                        HydraError::Timeout { .. } => "Launch timed out",
+                        HydraError::Unsupported { .. } => "Not supported",
+                        HydraError::InputMux(InputMuxError::NoInputDevices) => "No input devices",
+                        HydraError::Launch(LaunchError::ProtonUnavailable(_)) => "Proton not ready",
+                        HydraError::Launch(LaunchError::WineUnavailable(_)) => "Wine not found",
+                        HydraError::Launch(LaunchError::HookFailed { .. }) => "Pre-launch hook failed",
+                        HydraError::Launch(LaunchError::AlreadyRunning { .. }) => "Game already running",
+                        _ => "Launch failed",
+                    };
+                    let _ = tx.send(LaunchMessage::Failed { title, message: format!("{e}") });
                 }
             }
         });
@@ -579,10 +1275,10 @@ fn on_launch_clicked(state: &Rc<GuiState>) {
                     finished = true;
                     break;
                 }
-                Ok(LaunchMessage::Failed(err)) => {
-                    set_status(&state, "Launch failed.", false);
-                    append_log(&state, &format!("ERROR: {err}\n"));
-                    show_error(&state.window, "Launch failed", &err);
+                Ok(LaunchMessage::Failed { title, message }) => {
+                    set_status(&state, &format!("{title}."), false);
+                    append_log(&state, &format!("ERROR: {message}\n"));
+                    show_error(&state.window, title, &message);
                     finished = true;
                     break;
                 }
@@ -608,60 +1304,88 @@ enum LaunchMessage {
     Log(String),
     Running,
     Finished,
-    Failed(String),
+    /// `title` tells a cancelled or timed-out launch apart from other failures
+    Failed { title: &'static str, message: String },
 }
 
 fn collect_config(state: &Rc<GuiState>) -> Config {
-    let game_path = state.game_path.borrow().clone();
-    let player_count = state
-        .players_combo
-        .active_text()
-        .and_then(|t| t.parse::<usize>().ok())
-        .unwrap_or(crate::defaults::MAX_INSTANCES.min(2));
-
-    let mut input_mappings = Vec::with_capacity(player_count);
-    for combo in state.input_rows.borrow().iter() {
-        let value = combo
-            .active_text()
-            .map(|t| t.to_string())
-            .unwrap_or_else(|| "Auto-detect".to_string());
-        input_mappings.push(value);
-    }
-    while input_mappings.len() < player_count {
-        input_mappings.push("Auto-detect".to_string());
+    let mut game_paths = state.recent_games.borrow().clone();
+    if let Some(path) = state.game_path.borrow().clone() {
+        remember_recent_game(&mut game_paths, path);
     }
+    let player_count = selected_player_count(state);
 
-    let mut network_ports = Vec::with_capacity(player_count);
-    for i in 0..player_count {
-        network_ports.push(7777 + i as u16);
+    let mut input_mappings = Vec::with_capacity(player_count);
+    for (i, combo) in state.input_rows.borrow().iter().enumerate() {
+        input_mappings.push(input_mapping_for(i, combo.active_id().as_deref()));
     }
+    // The player count decides how many instances there are
+    input_mappings.resize(player_count, "Auto-detect".to_string());
+
+    // An applied recommendation's ports, or consecutive ports from the
+    // loaded config's first one
+    let base_port = state.base_config.network_ports.first().copied().unwrap_or(7777);
+    let network_ports = match state.recommended_ports.borrow().as_ref() {
+        Some(ports) => PortStrategy::Fixed(ports.clone()).allocate(player_count).unwrap_or_else(|_| ports.clone()),
+        None => (0..player_count).map(|i| base_port.saturating_add(i as u16)).collect(),
+    };
 
-    Config {
-        game_paths: game_path.into_iter().collect(),
+    let mut config = Config {
+        game_paths,
+        instances: player_count,
         input_mappings,
-        window_layout: state.layout_toggle.as_config_string().to_string(),
+        window_layout: state.layout_toggle.selected_config(),
         network_ports,
-        use_proton: state.proton_checkbox.is_active(),
+        ..state.base_config.clone()
+    };
+    config.set_runtime(selected_runtime(state));
+    config
+}
+
+/// The number of players picked in the players section
+fn selected_player_count(state: &GuiState) -> usize {
+    state
+        .players_combo
+        .active_text()
+        .and_then(|t| t.parse::<usize>().ok())
+        .unwrap_or(crate::defaults::MAX_INSTANCES.min(2))
+}
+
+/// The device chosen for each player, decoded from the combos' ids (the
+/// device serialized as JSON, see `rebuild_input_rows`).
+fn collect_input_assignments(state: &GuiState) -> Vec<(usize, InputAssignment)> {
+    state
+        .input_rows
+        .borrow()
+        .iter()
+        .enumerate()
+        .map(|(i, combo)| (i, input_assignment_for(i, combo.active_id().as_deref())))
+        .collect()
+}
+
+fn input_assignment_for(player: usize, combo_id: Option<&str>) -> InputAssignment {
+    match combo_id {
+        None | Some("") | Some("auto") | Some("Auto-detect") => InputAssignment::AutoDetect,
+        Some(id) => match serde_json::from_str::<DeviceIdentifier>(id) {
+            Ok(device) => InputAssignment::Device(device),
+            Err(e) => {
+                warn!("Could not read player {}'s device selection ({}); using auto-detect", player + 1, e);
+                InputAssignment::AutoDetect
+            }
+        },
     }
 }
 
-fn collect_assignments(state: &Rc<GuiState>) -> Vec<(usize, InputAssignment)> {
-    let rows = state.input_rows.borrow();
-    let mut out = Vec::with_capacity(rows.len());
-    for (i, combo) in rows.iter().enumerate() {
-        let active_id = combo.active_id();
-        let assignment = match active_id.as_deref() {
-            Some("auto") | None => InputAssignment::AutoDetect,
-            Some(id) => serde_json::from_str::<DeviceIdentifier>(id)
-                .map(InputAssignment::Device)
-                .unwrap_or(InputAssignment::AutoDetect),
-        };
-        out.push((i, assignment));
+/// The input mapping saved for a combo selection: the chosen device's name,
+/// not the combo's label, which may carry an interface count.
+fn input_mapping_for(player: usize, combo_id: Option<&str>) -> String {
+    match input_assignment_for(player, combo_id) {
+        InputAssignment::Device(device) => device.name,
+        _ => "Auto-detect".to_string(),
     }
-    out
 }
 
-fn save_config_to_disk(config: &Config) -> Result<PathBuf, Box<dyn std::error::Error>> {
+fn save_config_to_disk(config: &Config) -> crate::Result<PathBuf> {
     let path = crate::get_config_path()?;
     config.save(&path)?;
     Ok(path)
@@ -672,10 +1396,9 @@ fn save_config_to_disk(config: &Config) -> Result<PathBuf, Box<dyn std::error::E
 // ---------------------------------------------------------------------------
 
 fn populate_from_config(state: &Rc<GuiState>, config: &Config) {
-    if let Some(path) = config.primary_game_path() {
-        state.file_path_label.set_text(&path.to_string_lossy());
-        *state.game_path.borrow_mut() = Some(path.clone());
-    }
+    *state.recent_games.borrow_mut() = config.game_paths.clone();
+    *state.game_path.borrow_mut() = config.primary_game_path().cloned();
+    show_recent_games(state);
 
     let count = config.instance_count().clamp(1, crate::defaults::MAX_INSTANCES);
     state.players_combo.set_active(Some((count - 1) as u32));
@@ -690,8 +1413,9 @@ fn populate_from_config(state: &Rc<GuiState>, config: &Config) {
         // Try to match by device name; fall back to auto.
         if desired == "Auto-detect" {
             combo.set_active_id(Some("auto"));
-        } else if let Some(dev) = state.available_devices.iter().find(|d| &d.name == desired) {
-            if let Ok(id) = serde_json::to_string(dev) {
+        } else if let Some(interfaces) = state.controllers.borrow().iter().find(|group| group.iter().any(|d| &d.name == desired)) {
+            // A saved secondary interface selects its controller
+            if let Ok(id) = serde_json::to_string(&interfaces[0]) {
                 combo.set_active_id(Some(&id));
             }
         } else {
@@ -699,8 +1423,13 @@ fn populate_from_config(state: &Rc<GuiState>, config: &Config) {
         }
     }
 
-    state.layout_toggle.set_from_str(&config.window_layout);
-    state.proton_checkbox.set_active(config.use_proton);
+    state.layout_toggle.set_from_config(&config.window_layout);
+    let runtime_id = match config.runtime() {
+        WindowsRuntime::None => "none",
+        WindowsRuntime::Proton => "proton",
+        WindowsRuntime::Wine => "wine",
+    };
+    state.runtime_combo.set_active_id(Some(runtime_id));
 }
 
 // ---------------------------------------------------------------------------
@@ -722,6 +1451,19 @@ fn set_status(state: &Rc<GuiState>, text: &str, busy: bool) {
     info!("{}", text);
 }
 
+fn show_info(parent: &ApplicationWindow, title: &str, message: &str) {
+    let dialog = MessageDialog::builder()
+        .transient_for(parent)
+        .modal(true)
+        .message_type(MessageType::Info)
+        .buttons(gtk::ButtonsType::Ok)
+        .text(title)
+        .secondary_text(message)
+        .build();
+    dialog.connect_response(|d, _| d.close());
+    dialog.show();
+}
+
 fn show_error(parent: &ApplicationWindow, title: &str, message: &str) {
     let dialog = MessageDialog::builder()
         .transient_for(parent)
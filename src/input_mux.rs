@@ -1,17 +1,19 @@
-use evdev::Device;
+use evdev::{AbsInfo, AbsoluteAxisType, AttributeSetRef, Device, EventType, InputEvent, Key, PropType, RelativeAxisType, Synchronization};
 use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::os::fd::{AsRawFd, BorrowedFd};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::sync::{Arc, Mutex};
 use log::{info, warn, error, debug};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::sync::atomic::{AtomicBool, Ordering};
 use serde::{Deserialize, Serialize};
+use crate::xinput::XInputMasters;
 
 /// Custom error type for input multiplexing operations.
 #[derive(Debug)]
@@ -20,6 +22,14 @@ pub enum InputMuxError {
     EvdevError(evdev::Error),
     GenericError(String),
     AlreadyRunning,
+    /// Instances were requested but no input device could be opened
+    NoInputDevices,
+    /// Capture threads that panicked, with what they panicked with
+    CaptureThreadsPanicked(Vec<(DeviceIdentifier, ThreadOutcome)>),
+    /// Devices mapped to instances that have no virtual device
+    MissingVirtualDevices(Vec<(DeviceIdentifier, usize)>),
+    /// An entry of an assignment string that can't be understood, and why
+    InvalidAssignment { entry: String, reason: String },
 }
 
 impl std::fmt::Display for InputMuxError {
@@ -29,6 +39,31 @@ impl std::fmt::Display for InputMuxError {
             InputMuxError::EvdevError(e) => write!(f, "evdev error: {}", e),
             InputMuxError::GenericError(msg) => write!(f, "Input multiplexer error: {}", msg),
             InputMuxError::AlreadyRunning => write!(f, "Input capture is already running"),
+            InputMuxError::NoInputDevices => write!(
+                f,
+                "No usable input devices found; check access to /dev/input, or set allow_no_input = true to launch keyboard-only"
+            ),
+            InputMuxError::CaptureThreadsPanicked(threads) => {
+                write!(f, "Capture thread(s) panicked:")?;
+                for (identifier, outcome) in threads {
+                    write!(f, " '{}' ({})", identifier.name, outcome)?;
+                }
+                Ok(())
+            }
+            InputMuxError::MissingVirtualDevices(missing) => {
+                write!(f, "No virtual input device for")?;
+                for (i, (identifier, instance_index)) in missing.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{} player {} (assigned '{}')", separator, instance_index + 1, identifier.name)?;
+                }
+                write!(f, "; assign devices only to players that are launched")
+            }
+            InputMuxError::InvalidAssignment { entry, reason } => write!(
+                f,
+                "Invalid input assignment '{}': {}. Write INSTANCE=DEVICE entries separated by ';', \
+                 where DEVICE is auto, none, id:VENDOR:PRODUCT, phys:PATH or a device name",
+                entry, reason
+            ),
         }
     }
 }
@@ -66,6 +101,141 @@ pub struct DeviceIdentifier { // Made pub
     pub vendor_id: u16, // Made pub
     pub product_id: u16, // Made pub
     pub version: u16, // Made pub
+    /// Unique id the device reports (`uniq`), such as a Bluetooth
+    /// controller's address; many devices have none
+    #[serde(default)]
+    pub uniq: Option<String>,
+}
+
+/// A player's virtual device as games see it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VirtualController {
+    /// Position in creation order. Devices are created in player order, so
+    /// games that number controllers by enumeration order give player 1's
+    /// device the first slot
+    pub slot: usize,
+    /// Device node (`/dev/input/eventN`), when the kernel reported one in time
+    pub node: Option<PathBuf>,
+    pub vendor_id: u16,
+    pub product_id: u16,
+}
+
+/// Which of the requested input codes a player's virtual device was created
+/// with. uinput rejects codes the running kernel doesn't know, and a game
+/// never sees a button or axis that was skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapabilityReport {
+    pub instance: usize,
+    pub registered_keys: usize,
+    pub registered_relative_axes: usize,
+    pub registered_absolute_axes: usize,
+    pub skipped_keys: Vec<Key>,
+    pub skipped_relative_axes: Vec<RelativeAxisType>,
+    pub skipped_absolute_axes: Vec<AbsoluteAxisType>,
+}
+
+impl CapabilityReport {
+    /// Number of requested codes the device was created without
+    pub fn skipped(&self) -> usize {
+        self.skipped_keys.len() + self.skipped_relative_axes.len() + self.skipped_absolute_axes.len()
+    }
+}
+
+impl std::fmt::Display for CapabilityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "player {}: {} keys, {} relative axes, {} absolute axes registered",
+            self.instance + 1,
+            self.registered_keys,
+            self.registered_relative_axes,
+            self.registered_absolute_axes
+        )?;
+        if self.skipped() > 0 {
+            let skipped: Vec<String> = self
+                .skipped_keys
+                .iter()
+                .map(|key| format!("{:?}", key))
+                .chain(self.skipped_relative_axes.iter().map(|axis| format!("{:?}", axis)))
+                .chain(self.skipped_absolute_axes.iter().map(|axis| format!("{:?}", axis)))
+                .collect();
+            write!(f, "; skipped {}", skipped.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits `codes` into those `accepts` takes and those it rejects, keeping
+/// their order. An error from `accepts` ends the split.
+fn partition_supported<T: Copy>(
+    codes: &[T],
+    mut accepts: impl FnMut(T) -> io::Result<bool>,
+) -> io::Result<(Vec<T>, Vec<T>)> {
+    let mut supported = Vec::new();
+    let mut skipped = Vec::new();
+    for &code in codes {
+        if accepts(code)? {
+            supported.push(code);
+        } else {
+            skipped.push(code);
+        }
+    }
+    Ok((supported, skipped))
+}
+
+/// Registers `codes` one at a time on a throwaway uinput builder to find
+/// those uinput accepts. A rejected code takes the builder with it, so the
+/// next code starts on a fresh one; only failing to open `/dev/uinput` is an
+/// error.
+fn probe_uinput<T: Copy>(
+    codes: &[T],
+    register: impl Fn(VirtualDeviceBuilder<'static>, T) -> io::Result<VirtualDeviceBuilder<'static>>,
+) -> io::Result<(Vec<T>, Vec<T>)> {
+    let mut probe = None;
+    partition_supported(codes, |code| {
+        let builder = match probe.take() {
+            Some(builder) => builder,
+            None => VirtualDeviceBuilder::new()?,
+        };
+        match register(builder, code) {
+            Ok(builder) => {
+                probe = Some(builder);
+                Ok(true)
+            }
+            Err(e) => {
+                debug!("uinput rejected an input code: {}", e);
+                Ok(false)
+            }
+        }
+    })
+}
+
+/// How input from assigned physical devices reaches the game instances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputBackendKind {
+    /// Forward events to a virtual uinput device per instance
+    #[default]
+    Uinput,
+    /// Attach keyboards and mice to a master pointer/keyboard pair per
+    /// instance on the X server, see `xinput::XInputMasters`. Devices X
+    /// doesn't know, and every device when XInput2 can't be set up, fall
+    /// back to uinput.
+    XInput2,
+}
+
+/// Where the frames forwarded to virtual devices end, i.e. where each
+/// SYN_REPORT goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Wherever the physical device sent one, so events it reported
+    /// together (a diagonal on the D-pad, both stick axes) arrive together
+    #[default]
+    Source,
+    /// After every key or button event and at the end of each read, as
+    /// older versions did. Only for games that relied on that grouping.
+    AfterKeys,
 }
 
 /// Represents different ways to assign input devices to game instances
@@ -77,7 +247,74 @@ pub enum InputAssignment {
     Device(DeviceIdentifier),
     /// No device assigned to this instance
     None,
+    /// Send this device's input to every instance at once, alongside whatever
+    /// each instance is otherwise assigned. The instance index is ignored.
+    Mirror { device: DeviceIdentifier },
 }
+
+/// Parses an assignment string such as `0=id:045e:028e;1=auto;2=none` into
+/// explicit assignments for instances `0..num_instances`. Each entry maps an
+/// instance (counted from 0) to `auto`, `none`, `id:VENDOR:PRODUCT` in hex,
+/// `phys:PATH`, `name:NAME` or a bare device name. Devices are looked up in
+/// `available`; when several match, each entry takes the first one no earlier
+/// entry took. A device that isn't connected leaves its instance without
+/// input. Instances without an entry are left out of the result.
+pub fn parse_assignments(
+    spec: &str,
+    num_instances: usize,
+    available: &[DeviceIdentifier],
+) -> Result<Vec<(usize, InputAssignment)>, InputMuxError> {
+    let invalid = |entry: &str, reason: String| InputMuxError::InvalidAssignment { entry: entry.to_string(), reason };
+    let mut assignments: Vec<(usize, InputAssignment)> = Vec::new();
+    for entry in spec.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (instance, device) = entry.split_once('=').ok_or_else(|| invalid(entry, "missing '='".to_string()))?;
+        let instance: usize = instance
+            .trim()
+            .parse()
+            .map_err(|_| invalid(entry, format!("'{}' is not an instance number", instance.trim())))?;
+        if instance >= num_instances {
+            return Err(invalid(entry, format!("there are only instances 0 to {}", num_instances.saturating_sub(1))));
+        }
+        if assignments.iter().any(|&(assigned, _)| assigned == instance) {
+            return Err(invalid(entry, format!("instance {} is assigned twice", instance)));
+        }
+
+        let device = device.trim();
+        let taken = |candidate: &&DeviceIdentifier| {
+            !assignments.iter().any(|(_, assignment)| matches!(assignment, InputAssignment::Device(d) if d == *candidate))
+        };
+        let found = match device.split_once(':') {
+            _ if device.eq_ignore_ascii_case("auto") => {
+                assignments.push((instance, InputAssignment::AutoDetect));
+                continue;
+            }
+            _ if device.eq_ignore_ascii_case("none") => {
+                assignments.push((instance, InputAssignment::None));
+                continue;
+            }
+            Some(("id", ids)) => {
+                let (vendor, product) = ids
+                    .split_once(':')
+                    .and_then(|(vendor, product)| Some((u16::from_str_radix(vendor, 16).ok()?, u16::from_str_radix(product, 16).ok()?)))
+                    .ok_or_else(|| invalid(entry, "use id:VENDOR:PRODUCT with hexadecimal IDs".to_string()))?;
+                available.iter().filter(taken).find(|d| d.vendor_id == vendor && d.product_id == product)
+            }
+            Some(("phys", phys)) => available.iter().filter(taken).find(|d| d.phys.as_deref() == Some(phys)),
+            Some(("name", name)) => available.iter().filter(taken).find(|d| d.name == name),
+            _ => available.iter().filter(taken).find(|d| d.name == device),
+        };
+        let assignment = match found {
+            Some(identifier) => InputAssignment::Device(identifier.clone()),
+            None => {
+                warn!("No device matches '{}'; instance {} will have no input", device, instance);
+                InputAssignment::None
+            }
+        };
+        assignments.push((instance, assignment));
+    }
+    Ok(assignments)
+}
+
 impl From<&Device> for DeviceIdentifier {
     fn from(device: &Device) -> Self {
         let input_id = device.input_id();
@@ -88,77 +325,405 @@ impl From<&Device> for DeviceIdentifier {
             vendor_id: input_id.vendor(),
             product_id: input_id.product(),
             version: input_id.version(),
+            uniq: device.unique_name().filter(|uniq| !uniq.is_empty()).map(str::to_string),
+        }
+    }
+}
+
+/// What kind of input one evdev interface provides. Controllers such as the
+/// DualShock 4 show up as several interfaces (buttons and sticks, motion
+/// sensors, touchpad) and this tells them apart, see
+/// [`InputMux::primary_interface`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct DeviceClass {
+    /// Gamepad (`BTN_SOUTH`) or joystick (`BTN_TRIGGER`) buttons
+    pub has_gamepad_buttons: bool,
+    /// Letter keys
+    pub has_keyboard: bool,
+    /// X and Y axes that aren't motion sensors or a touchpad
+    pub has_abs_sticks: bool,
+    /// Accelerometer or gyroscope
+    pub has_motion_sensors: bool,
+    pub has_touchpad: bool,
+}
+
+impl DeviceClass {
+    fn classify(
+        keys: Option<&AttributeSetRef<Key>>,
+        axes: Option<&AttributeSetRef<AbsoluteAxisType>>,
+        properties: &AttributeSetRef<PropType>,
+    ) -> Self {
+        let has_key = |key| keys.is_some_and(|keys| keys.contains(key));
+        let has_axis = |axis| axes.is_some_and(|axes| axes.contains(axis));
+        let has_motion_sensors = properties.contains(PropType::ACCELEROMETER);
+        let has_touchpad = has_axis(AbsoluteAxisType::ABS_MT_POSITION_X) || has_key(Key::BTN_TOOL_FINGER);
+        DeviceClass {
+            has_gamepad_buttons: has_key(Key::BTN_SOUTH) || has_key(Key::BTN_TRIGGER),
+            has_keyboard: has_key(Key::KEY_A) && has_key(Key::KEY_Z),
+            has_abs_sticks: has_axis(AbsoluteAxisType::ABS_X)
+                && has_axis(AbsoluteAxisType::ABS_Y)
+                && !has_motion_sensors
+                && !has_touchpad,
+            has_motion_sensors,
+            has_touchpad,
+        }
+    }
+
+    /// How strongly the interface looks like the one players press buttons
+    /// on; the primary interface of a controller ranks highest.
+    fn rank(&self) -> u8 {
+        4 * u8::from(self.has_gamepad_buttons) + 2 * u8::from(self.has_abs_sticks) + u8::from(self.has_keyboard)
+    }
+}
+
+impl From<&Device> for DeviceClass {
+    fn from(device: &Device) -> Self {
+        DeviceClass::classify(device.supported_keys(), device.supported_absolute_axes(), device.properties())
+    }
+}
+
+/// Whether `a` and `b` are interfaces of the same physical device: the same
+/// bus and IDs, and the same unique id where both have one, else the same
+/// physical path apart from the interface number (`usb-0000:00:14.0-2/input3`).
+/// Bluetooth controllers all share their adapter's path, so only their
+/// unique ids (their addresses) tell them apart.
+fn same_controller(a: &DeviceIdentifier, b: &DeviceIdentifier) -> bool {
+    fn port(phys: &str) -> &str {
+        phys.rsplit_once('/').map_or(phys, |(port, _)| port)
+    }
+    if (a.bustype, a.vendor_id, a.product_id) != (b.bustype, b.vendor_id, b.product_id) {
+        return false;
+    }
+    match (&a.uniq, &b.uniq) {
+        (Some(uniq_a), Some(uniq_b)) => uniq_a == uniq_b,
+        _ => matches!((&a.phys, &b.phys), (Some(pa), Some(pb)) if !pa.is_empty() && port(pa) == port(pb)),
+    }
+}
+
+/// Groups the interfaces in `classes` by physical device, see
+/// [`InputMux::controllers`].
+fn group_interfaces(classes: &HashMap<DeviceIdentifier, DeviceClass>) -> Vec<Vec<DeviceIdentifier>> {
+    let mut interfaces: Vec<(&DeviceIdentifier, &DeviceClass)> = classes.iter().collect();
+    interfaces.sort_by(|(a, class_a), (b, class_b)| {
+        class_b.rank().cmp(&class_a.rank()).then_with(|| (&a.name, &a.phys).cmp(&(&b.name, &b.phys)))
+    });
+    let mut groups: Vec<Vec<DeviceIdentifier>> = Vec::new();
+    for (interface, _) in interfaces {
+        match groups.iter_mut().find(|group| same_controller(&group[0], interface)) {
+            Some(group) => group.push(interface.clone()),
+            None => groups.push(vec![interface.clone()]),
+        }
+    }
+    groups.sort_by(|a, b| (&a[0].name, &a[0].phys).cmp(&(&b[0].name, &b[0].phys)));
+    groups
+}
+
+/// Everything evdev reports about one input device, see
+/// `InputMux::device_capabilities`. Codes are evdev's names, e.g. `BTN_SOUTH`
+/// or `ABS_X`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceCapabilities {
+    pub device: DeviceIdentifier,
+    pub keys: Vec<String>,
+    pub relative_axes: Vec<String>,
+    pub absolute_axes: Vec<AbsoluteAxis>,
+    /// Force feedback effects the device can play
+    pub force_feedback: Vec<String>,
+}
+
+/// An absolute axis and its `AbsInfo`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AbsoluteAxis {
+    pub axis: String,
+    pub value: i32,
+    pub minimum: i32,
+    pub maximum: i32,
+    pub fuzz: i32,
+    pub flat: i32,
+    pub resolution: i32,
+}
+
+impl AbsoluteAxis {
+    /// `axis` with its range from `info`; an axis whose range couldn't be
+    /// read is still listed, with zeroes
+    fn new(axis: AbsoluteAxisType, info: Option<AbsInfo>) -> Self {
+        AbsoluteAxis {
+            axis: format!("{:?}", axis),
+            value: info.map_or(0, |info| info.value()),
+            minimum: info.map_or(0, |info| info.minimum()),
+            maximum: info.map_or(0, |info| info.maximum()),
+            fuzz: info.map_or(0, |info| info.fuzz()),
+            flat: info.map_or(0, |info| info.flat()),
+            resolution: info.map_or(0, |info| info.resolution()),
+        }
+    }
+}
+
+/// evdev's names for `codes`, e.g. `BTN_SOUTH`
+fn code_names<T: std::fmt::Debug>(codes: impl Iterator<Item = T>) -> Vec<String> {
+    codes.map(|code| format!("{:?}", code)).collect()
+}
+
+impl From<&Device> for DeviceCapabilities {
+    fn from(device: &Device) -> Self {
+        // Axis ranges are read from the device
+        let abs_state = device.get_abs_state().ok();
+        let absolute_axes = device
+            .supported_absolute_axes()
+            .map(|axes| {
+                axes.iter()
+                    .map(|axis| {
+                        let info = abs_state.as_ref().and_then(|state| state.get(axis.0 as usize)).map(|info| {
+                            AbsInfo::new(info.value, info.minimum, info.maximum, info.fuzz, info.flat, info.resolution)
+                        });
+                        AbsoluteAxis::new(axis, info)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        DeviceCapabilities {
+            device: DeviceIdentifier::from(device),
+            keys: device.supported_keys().map(|keys| code_names(keys.iter())).unwrap_or_default(),
+            relative_axes: device.supported_relative_axes().map(|axes| code_names(axes.iter())).unwrap_or_default(),
+            absolute_axes,
+            force_feedback: device.supported_ff().map(|effects| code_names(effects.iter())).unwrap_or_default(),
         }
     }
 }
 
+/// How a capture thread ended, as reported by `InputMux::stop_capture`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThreadOutcome {
+    /// Ran until capture was stopped
+    Stopped,
+    /// Gave up early, e.g. because its device was unplugged
+    Failed(String),
+    /// Panicked with this message
+    Panicked(String),
+}
+
+impl std::fmt::Display for ThreadOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ThreadOutcome::Stopped => write!(f, "stopped"),
+            ThreadOutcome::Failed(reason) => write!(f, "failed: {}", reason),
+            ThreadOutcome::Panicked(message) => write!(f, "panicked: {}", message),
+        }
+    }
+}
+
+/// Default for how long a capture thread waits for input before rechecking
+/// whether it should stop, see [`InputMux::set_poll_timeout`]
+pub const DEFAULT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How often capture threads log their input-to-inject latency (debug level)
+const LATENCY_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Splits `events` into frames, each ending where the source sent a
+/// SYN_REPORT. An unterminated tail is kept in `partial` and continued by the
+/// next call. Frames exclude the SYN_REPORT itself because
+/// `VirtualDevice::emit` appends one; SYN_DROPPED markers are discarded, as
+/// evdev has already resynchronised the device state around them.
+fn take_frames(partial: &mut Vec<InputEvent>, events: impl IntoIterator<Item = InputEvent>) -> Vec<Vec<InputEvent>> {
+    let mut frames = Vec::new();
+    for event in events {
+        if event.event_type() == EventType::SYNCHRONIZATION {
+            match Synchronization(event.code()) {
+                Synchronization::SYN_REPORT => {
+                    if !partial.is_empty() {
+                        frames.push(std::mem::take(partial));
+                    }
+                    continue;
+                }
+                Synchronization::SYN_DROPPED => continue,
+                _ => {}
+            }
+        }
+        partial.push(event);
+    }
+    frames
+}
+
+/// Splits `events` the way [`SyncMode::AfterKeys`] does: a frame ends after
+/// each key or button event and at the end of `events`. The source's own
+/// SYN events are dropped.
+fn take_frames_after_keys(events: impl IntoIterator<Item = InputEvent>) -> Vec<Vec<InputEvent>> {
+    let mut frames = Vec::new();
+    let mut frame = Vec::new();
+    for event in events {
+        match event.event_type() {
+            EventType::SYNCHRONIZATION => {}
+            EventType::KEY => {
+                frame.push(event);
+                frames.push(std::mem::take(&mut frame));
+            }
+            _ => frame.push(event),
+        }
+    }
+    if !frame.is_empty() {
+        frames.push(frame);
+    }
+    frames
+}
+
+/// The frames a capture thread forwards for one read of `events`: host
+/// reserved keys are left out and the rest split into frames per
+/// `sync_mode`. `partial` carries an unfinished frame over to the next read.
+pub(crate) fn frames_to_forward(
+    partial: &mut Vec<InputEvent>,
+    events: impl IntoIterator<Item = InputEvent>,
+    sync_mode: SyncMode,
+    reserved_keys: &HashSet<Key>,
+) -> Vec<Vec<InputEvent>> {
+    let events = events.into_iter().filter(|event| !is_host_reserved(event, reserved_keys));
+    match sync_mode {
+        SyncMode::Source => take_frames(partial, events),
+        SyncMode::AfterKeys => take_frames_after_keys(events),
+    }
+}
+
+/// True for a key event whose key is in `reserved`, which stays with the
+/// host instead of being forwarded, see [`InputMux::set_host_reserved_keys`].
+fn is_host_reserved(event: &InputEvent, reserved: &HashSet<Key>) -> bool {
+    event.event_type() == EventType::KEY && reserved.contains(&Key(event.code()))
+}
+
+/// True for a key or button going down at or after `since`; releases and
+/// autorepeats don't count.
+fn is_fresh_press(event: &InputEvent, since: SystemTime) -> bool {
+    event.event_type() == EventType::KEY && event.value() == 1 && event.timestamp() >= since
+}
+
+/// Running input-to-inject latency figures for one capture thread
+struct LatencyStats {
+    frames: u32,
+    total: Duration,
+    max: Duration,
+    since: Instant,
+}
+
+impl LatencyStats {
+    fn new() -> Self {
+        LatencyStats { frames: 0, total: Duration::ZERO, max: Duration::ZERO, since: Instant::now() }
+    }
+
+    /// Adds the latency of a frame whose last event the kernel stamped at
+    /// `event_time`, and logs a summary once per interval.
+    fn record(&mut self, device_name: &str, event_time: SystemTime) {
+        let Ok(latency) = SystemTime::now().duration_since(event_time) else {
+            return;
+        };
+        self.frames += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+        if self.since.elapsed() >= LATENCY_LOG_INTERVAL {
+            debug!(
+                "Input latency for '{}': avg {:?}, max {:?} over {} frame(s)",
+                device_name,
+                self.total / self.frames,
+                self.max,
+                self.frames
+            );
+            *self = LatencyStats::new();
+        }
+    }
+}
 
 /// Per-thread capture loop. Owns one physical Device, polls its fd in level-triggered
-/// mode so the loop can wake on events without busy-spinning, then forwards each
-/// fetched event to the virtual device for the assigned instance.
+/// mode so the loop can wake on events without busy-spinning, then forwards every
+/// complete frame fetched on a wake to the virtual device of each target instance
+/// (one for a normal assignment, all of them for a mirrored device), each frame
+/// followed by its own SYN_REPORT. `sync_mode` decides where frames end. Keys
+/// in `reserved_keys` are left out. While `paused_flag` is set, frames are
+/// dropped instead. `poll_timeout` bounds how
+/// long a stop request can go unnoticed.
+#[allow(clippy::too_many_arguments)]
 fn run_capture_loop(
-    mut device: Device,
+    device: &mut Device,
     identifier: DeviceIdentifier,
-    instance_index: usize,
-    virtual_devices: HashMap<usize, Arc<Mutex<VirtualDevice>>>,
+    mut targets: Vec<(usize, Arc<Mutex<VirtualDevice>>)>,
     running_flag: Arc<std::sync::atomic::AtomicBool>,
-) {
-    let vd_arc = match virtual_devices.get(&instance_index) {
-        Some(arc) => arc.clone(),
-        None => {
-            error!("Capture thread: virtual device for instance {} not found. Exiting thread for device '{}'.", instance_index, identifier.name);
-            return;
-        }
-    };
+    stop_flag: Arc<std::sync::atomic::AtomicBool>,
+    paused_flag: Arc<std::sync::atomic::AtomicBool>,
+    poll_timeout: Duration,
+    sync_mode: SyncMode,
+    reserved_keys: Arc<HashSet<Key>>,
+) -> ThreadOutcome {
+    if targets.is_empty() {
+        error!("Capture thread: no virtual devices to route to. Exiting thread for device '{}'.", identifier.name);
+        return ThreadOutcome::Failed("no virtual devices to route to".to_string());
+    }
 
     let poller = match polling::Poller::new() {
         Ok(p) => p,
         Err(e) => {
             error!("Capture thread for '{}': failed to create poller: {}", identifier.name, e);
-            return;
+            return ThreadOutcome::Failed(format!("failed to create poller: {}", e));
         }
     };
     // SAFETY: we delete the device from the poller before dropping it (at thread exit
     // when `device` drops, after the loop returns and `poller` is dropped).
     if let Err(e) = unsafe {
         poller.add_with_mode(
-            &device,
+            &*device,
             polling::Event::readable(0),
             polling::PollMode::Level,
         )
     } {
         error!("Capture thread for '{}': failed to register device with poller: {}", identifier.name, e);
-        return;
+        return ThreadOutcome::Failed(format!("failed to register device with poller: {}", e));
     }
 
     let mut events = polling::Events::new();
-    let wait_timeout = Duration::from_millis(100);
+    let mut outcome = ThreadOutcome::Stopped;
+    let mut partial_frame: Vec<InputEvent> = Vec::new();
+    let mut latency = log::log_enabled!(log::Level::Debug).then(LatencyStats::new);
 
-    while running_flag.load(Ordering::SeqCst) {
+    while running_flag.load(Ordering::SeqCst) && !stop_flag.load(Ordering::SeqCst) {
         events.clear();
-        match poller.wait(&mut events, Some(wait_timeout)) {
+        match poller.wait(&mut events, Some(poll_timeout)) {
             Ok(0) => continue,
             Ok(_) => {}
             Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
             Err(e) => {
                 error!("Capture thread for '{}': poller error: {}", identifier.name, e);
+                outcome = ThreadOutcome::Failed(format!("poller error: {}", e));
                 break;
             }
         }
 
         match device.fetch_events() {
             Ok(iter) => {
-                let batch: Vec<_> = iter.collect();
-                if batch.is_empty() {
+                let frames = frames_to_forward(&mut partial_frame, iter, sync_mode, &reserved_keys);
+                if frames.is_empty() || paused_flag.load(Ordering::SeqCst) {
                     continue;
                 }
-                let mut vd = vd_arc.lock().unwrap();
-                if let Err(e) = vd.emit(&batch) {
-                    error!("Failed to inject events for '{}' to instance {}: {}", identifier.name, instance_index, e);
-                    if e.kind() == io::ErrorKind::BrokenPipe {
-                        error!("Broken pipe on virtual device for instance {}. Stopping capture for '{}'.", instance_index, identifier.name);
-                        break;
+                // emit() terminates each frame with its own SYN_REPORT, so every
+                // target sees the same frames.
+                targets.retain(|(instance_index, vd_arc)| {
+                    let mut vd = vd_arc.lock().unwrap();
+                    for frame in &frames {
+                        match vd.emit(frame) {
+                            Ok(()) => {}
+                            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                                error!("Broken pipe on virtual device for instance {}. Stopping routing from '{}' to it.", instance_index, identifier.name);
+                                return false;
+                            }
+                            Err(e) => {
+                                error!("Failed to inject events for '{}' to instance {}: {}", identifier.name, instance_index, e);
+                            }
+                        }
                     }
+                    true
+                });
+                if let (Some(stats), Some(last)) = (&mut latency, frames.last().and_then(|frame| frame.last())) {
+                    stats.record(&identifier.name, last.timestamp());
+                }
+                if targets.is_empty() {
+                    error!("No virtual devices left for '{}'. Stopping capture.", identifier.name);
+                    outcome = ThreadOutcome::Failed("no virtual devices left".to_string());
+                    break;
                 }
             }
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
@@ -168,6 +733,7 @@ fn run_capture_loop(
                 if matches!(e.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::NotFound) {
                     warn!("Device '{}' appears disconnected. Stopping capture for this device.", identifier.name);
                 }
+                outcome = ThreadOutcome::Failed(format!("error reading events: {}", e));
                 break;
             }
         }
@@ -178,20 +744,129 @@ fn run_capture_loop(
     let fd = unsafe { BorrowedFd::borrow_raw(device.as_raw_fd()) };
     let _ = poller.delete(fd);
     info!("Capture thread for device '{}' exited.", identifier.name);
+    outcome
+}
+
+/// Best-effort text of a panic payload
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string())
 }
 
 pub struct InputMux {
     // Map DeviceIdentifier to the opened evdev::Device
     devices: HashMap<DeviceIdentifier, Device>,
+    // What each enumerated device provides, kept while capture threads own the devices
+    device_classes: HashMap<DeviceIdentifier, DeviceClass>,
     // Map DeviceIdentifier to the instance index (0, 1, 2...)
     instance_map: HashMap<DeviceIdentifier, usize>,
     // Map instance index to its virtual uinput device (Arc+Mutex for cross-thread access)
     virtual_devices: HashMap<usize, Arc<Mutex<VirtualDevice>>>,
+    // Devices whose input goes to every instance, see `InputAssignment::Mirror`
+    mirror_devices: Vec<DeviceIdentifier>,
     // Flag to signal capture threads to stop
     running: Arc<AtomicBool>,
+    // How long capture threads wait for input before rechecking `running`
+    poll_timeout: Duration,
+    // Where forwarded frames end, see `set_sync_mode`
+    sync_mode: SyncMode,
+    // Keys never forwarded, see `set_host_reserved_keys`
+    host_reserved_keys: Arc<HashSet<Key>>,
+    // While set, capture threads drop events instead of forwarding them
+    paused: Arc<AtomicBool>,
+    // Set once a capture thread panics, see `degraded_flag`
+    degraded: Arc<AtomicBool>,
     // Store join handles for capture threads to wait on
-    capture_threads: Option<Vec<JoinHandle<()>>>, // Use Option to manage running state
+    capture_threads: Option<Vec<(DeviceIdentifier, JoinHandle<ThreadOutcome>)>>, // Use Option to manage running state
+    // Stops a single capture thread, see `reassign`
+    capture_stops: HashMap<DeviceIdentifier, Arc<AtomicBool>>,
+    // Devices handed back by capture threads that have exited
+    released_devices: Arc<Mutex<HashMap<DeviceIdentifier, Device>>>,
+    // Template for virtual device names, see `set_device_naming`
+    device_name_template: String,
+    // Name each virtual device after the physical device mapped to it
+    mimic_physical_names: bool,
+    // Short per-session id substituted for `{session}`
+    session_id: String,
+    // Name each virtual device was created with, by instance index
+    virtual_device_names: HashMap<usize, String>,
+    // Node and IDs of each virtual device, by instance index
+    virtual_controllers: HashMap<usize, VirtualController>,
+    // Codes each virtual device was created with, see `capability_reports`
+    capability_reports: Vec<CapabilityReport>,
+    // How assigned devices reach the instances, see `set_backend`
+    backend: InputBackendKind,
+    // Game window of each instance, focused for its X keyboard
+    xinput_windows: Vec<(usize, u32)>,
+    // Per-instance X master devices while XInput2 routing is active
+    xinput: Option<XInputMasters>,
+}
+
+/// Name prefix of virtual devices named by the default template
+pub const VIRTUAL_DEVICE_PREFIX: &str = "HydraCoop";
 
+/// Default virtual device name template, see [`InputMux::set_device_naming`]
+pub const DEFAULT_DEVICE_NAME_TEMPLATE: &str = "HydraCoop P{instance} {session}";
+
+/// Vendor ID of virtual devices that don't mimic a physical device (pid.codes)
+pub const VIRTUAL_VENDOR_ID: u16 = 0x1209;
+
+/// Product ID of player 1's virtual device; later players count up from it,
+/// so each instance's device can be told apart by its IDs alone
+pub const VIRTUAL_PRODUCT_BASE: u16 = 0x0001;
+
+/// uinput rejects names longer than this (UINPUT_MAX_NAME_SIZE minus the NUL)
+const MAX_DEVICE_NAME_LEN: usize = 79;
+
+/// Fills in a device name template: `{instance}` becomes the 1-based player
+/// number and `{session}` the session id.
+fn render_device_name(template: &str, instance_index: usize, session_id: &str) -> String {
+    let mut name = template
+        .replace("{instance}", &(instance_index + 1).to_string())
+        .replace("{session}", session_id);
+    if name.len() > MAX_DEVICE_NAME_LEN {
+        let mut end = MAX_DEVICE_NAME_LEN;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+    }
+    name
+}
+
+/// See [`InputMux::controller_env`]. `controllers` are every instance's
+/// virtual devices and `physical` the (vendor, product) IDs of the physical
+/// devices.
+fn controller_env(own: &VirtualController, controllers: &[&VirtualController], physical: &[(u16, u16)]) -> Vec<(String, String)> {
+    let own_ids = (own.vendor_id, own.product_id);
+    let mut ignored: Vec<(u16, u16)> = Vec::new();
+    for ids in controllers.iter().map(|controller| (controller.vendor_id, controller.product_id)).chain(physical.iter().copied()) {
+        if ids != own_ids && ids != (0, 0) && !ignored.contains(&ids) {
+            ignored.push(ids);
+        }
+    }
+
+    let mut env = Vec::new();
+    if let Some(node) = &own.node {
+        env.push(("SDL_JOYSTICK_DEVICE".to_string(), node.to_string_lossy().into_owned()));
+    }
+    if !ignored.is_empty() {
+        let list: Vec<String> = ignored.iter().map(|(vendor, product)| format!("0x{:04x}/0x{:04x}", vendor, product)).collect();
+        env.push(("SDL_GAMECONTROLLER_IGNORE_DEVICES".to_string(), list.join(",")));
+    }
+    env
+}
+
+/// Four hex digits that differ between concurrently running sessions.
+pub(crate) fn new_session_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    format!("{:04x}", (nanos ^ std::process::id().rotate_left(16) ^ std::process::id()) & 0xffff)
 }
 
 impl InputMux {
@@ -199,13 +874,159 @@ impl InputMux {
         info!("Creating new InputMux instance.");
         InputMux {
             devices: HashMap::new(),
+            device_classes: HashMap::new(),
             instance_map: HashMap::new(),
             virtual_devices: HashMap::new(),
+            mirror_devices: Vec::new(),
             running: Arc::new(AtomicBool::new(false)), // Initially not running
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            sync_mode: SyncMode::default(),
+            host_reserved_keys: Arc::new(HashSet::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            degraded: Arc::new(AtomicBool::new(false)),
             capture_threads: None,
+            capture_stops: HashMap::new(),
+            released_devices: Arc::new(Mutex::new(HashMap::new())),
+            device_name_template: DEFAULT_DEVICE_NAME_TEMPLATE.to_string(),
+            mimic_physical_names: false,
+            session_id: new_session_id(),
+            virtual_device_names: HashMap::new(),
+            virtual_controllers: HashMap::new(),
+            capability_reports: Vec::new(),
+            backend: InputBackendKind::default(),
+            xinput_windows: Vec::new(),
+            xinput: None,
         }
     }
 
+    /// Configures how virtual devices are named. `{instance}` in the template
+    /// becomes the 1-based player number and `{session}` a short id unique to
+    /// this session. With `mimic_physical_names`, a virtual device instead
+    /// copies the name and IDs of the physical device assigned to it, for
+    /// games that only accept controllers they recognise; this needs
+    /// `assign_devices` to run before `create_virtual_devices`.
+    pub fn set_device_naming(&mut self, template: &str, mimic_physical_names: bool) {
+        self.device_name_template = if template.trim().is_empty() {
+            DEFAULT_DEVICE_NAME_TEMPLATE.to_string()
+        } else {
+            template.to_string()
+        };
+        self.mimic_physical_names = mimic_physical_names;
+    }
+
+    /// Uses `session_id` for `{session}` in device names, so they match the
+    /// id the game instances were launched with.
+    pub fn set_session_id(&mut self, session_id: &str) {
+        self.session_id = session_id.to_string();
+    }
+
+    /// Sets how long capture threads wait for input before checking whether
+    /// capture was stopped. Input is forwarded as soon as it arrives either
+    /// way; shorter timeouts only make `stop_capture` return sooner.
+    pub fn set_poll_timeout(&mut self, poll_timeout: Duration) {
+        self.poll_timeout = poll_timeout.max(Duration::from_millis(1));
+    }
+
+    /// Chooses where the SYN_REPORTs go in the events forwarded to virtual
+    /// devices. Takes effect for capture threads started afterwards.
+    pub fn set_sync_mode(&mut self, sync_mode: SyncMode) {
+        self.sync_mode = sync_mode;
+    }
+
+    /// Keys that are never forwarded to a virtual device, so pressing them
+    /// only affects the host session (desktop shortcuts, volume keys) and no
+    /// game. Takes effect for capture threads started afterwards.
+    pub fn set_host_reserved_keys(&mut self, keys: impl IntoIterator<Item = Key>) {
+        self.host_reserved_keys = Arc::new(keys.into_iter().collect());
+    }
+
+    /// Chooses how `capture_events` routes assigned devices. With
+    /// `InputBackendKind::XInput2`, `windows` lists each instance's game
+    /// window as `(instance, window)`, to focus for the instance's keyboard.
+    pub fn set_backend(&mut self, backend: InputBackendKind, windows: Vec<(usize, u32)>) {
+        self.backend = backend;
+        self.xinput_windows = windows;
+    }
+
+    /// Hands the devices assigned to single instances over to per-instance
+    /// X master devices. Falls back to uinput for all of them if that fails.
+    fn start_xinput_routing(&mut self) {
+        let assignments: Vec<(usize, DeviceIdentifier)> = self
+            .instance_map
+            .iter()
+            .map(|(identifier, &instance_index)| (instance_index, identifier.clone()))
+            .collect();
+        match XInputMasters::create(&assignments) {
+            Ok(masters) => {
+                if let Err(e) = masters.focus_windows(&self.xinput_windows) {
+                    warn!("Could not focus the game windows for their players' keyboards: {}", e);
+                }
+                self.xinput = Some(masters);
+            }
+            Err(e) => warn!("XInput2 routing unavailable ({}); using uinput instead.", e),
+        }
+    }
+
+    /// Physical device assigned to an instance, if any
+    fn assigned_device(&self, instance_index: usize) -> Option<&DeviceIdentifier> {
+        self.instance_map
+            .iter()
+            .find(|(_, &index)| index == instance_index)
+            .map(|(identifier, _)| identifier)
+    }
+
+    /// Name to create the virtual device for `instance_index` with
+    fn virtual_device_name(&self, instance_index: usize) -> String {
+        if self.mimic_physical_names {
+            if let Some(identifier) = self.assigned_device(instance_index) {
+                return render_device_name(&identifier.name, instance_index, &self.session_id);
+            }
+        }
+        render_device_name(&self.device_name_template, instance_index, &self.session_id)
+    }
+
+    /// Name the virtual device for `instance_index` was created with.
+    pub fn get_virtual_device_name(&self, instance_index: usize) -> Option<&str> {
+        self.virtual_device_names.get(&instance_index).map(String::as_str)
+    }
+
+    /// Node and IDs of the virtual device for `instance_index`.
+    pub fn virtual_controller(&self, instance_index: usize) -> Option<&VirtualController> {
+        self.virtual_controllers.get(&instance_index)
+    }
+
+    /// What `create_virtual_devices` could register on each virtual device
+    pub fn capability_reports(&self) -> &[CapabilityReport] {
+        &self.capability_reports
+    }
+
+    /// Environment for instance `instance_index` that makes SDL games see
+    /// only that instance's virtual device as a controller, so it gets the
+    /// first slot: `SDL_JOYSTICK_DEVICE` points at its node and
+    /// `SDL_GAMECONTROLLER_IGNORE_DEVICES` lists every other virtual and
+    /// physical device. Devices with the same IDs as the instance's own (two
+    /// identical pads with mimicked names) can't be hidden this way.
+    pub fn controller_env(&self, instance_index: usize) -> Vec<(String, String)> {
+        let mut controllers: Vec<&VirtualController> = self.virtual_controllers.values().collect();
+        controllers.sort_by_key(|controller| controller.slot);
+        let physical: Vec<(u16, u16)> = self
+            .devices
+            .keys()
+            .map(|identifier| (identifier.vendor_id, identifier.product_id))
+            .collect();
+        match self.virtual_controllers.get(&instance_index) {
+            Some(own) => controller_env(own, &controllers, &physical),
+            None => Vec::new(),
+        }
+    }
+
+    /// Kernel sysfs name (e.g. `input42`) of the virtual device for `instance_index`.
+    pub fn get_virtual_device_sysname(&self, instance_index: usize) -> Option<String> {
+        let device = self.virtual_devices.get(&instance_index)?;
+        let syspath = device.lock().ok()?.get_syspath().ok()?;
+        syspath.file_name().map(|name| name.to_string_lossy().into_owned())
+    }
+
     /// Enumerates connected input devices in /dev/input.
     /// Requires read permissions on /dev/input/event* files.
     pub fn enumerate_devices(&mut self) -> Result<(), InputMuxError> {
@@ -225,6 +1046,7 @@ impl InputMux {
 
         // Clear previously enumerated devices before re-enumerating
         self.devices.clear();
+        self.device_classes.clear();
 
         // Use ? for fs::read_dir error propagation
         for entry in fs::read_dir(input_dir)? {
@@ -239,8 +1061,10 @@ impl InputMux {
                 match Device::open(&path) {
                     Ok(device) => {
                         let identifier = DeviceIdentifier::from(&device);
+                        let class = DeviceClass::from(&device);
                         info!("Found device: {}", identifier.name);
-                        debug!("Device details: {:?}", identifier);
+                        debug!("Device details: {:?}, {:?}", identifier, class);
+                        self.device_classes.insert(identifier.clone(), class);
                         self.devices.insert(identifier, device);
                     }
                     Err(e) => {
@@ -262,13 +1086,108 @@ impl InputMux {
         Ok(())
     }
 
+    /// Waits up to `timeout` for a key or button press on any enumerated
+    /// device and returns the device it came from, so each player can claim
+    /// a device by pressing one of its buttons. A press on a secondary
+    /// interface, such as a touchpad click, reports the controller's primary
+    /// interface. Presses from before the call are ignored. `None` when
+    /// nothing was pressed in time.
+    pub fn detect_next_pressed(&mut self, timeout: Duration) -> Option<DeviceIdentifier> {
+        let since = SystemTime::now();
+        let deadline = Instant::now() + timeout;
+        let poller = match polling::Poller::new() {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("Cannot wait for a button press: failed to create poller: {}", e);
+                return None;
+            }
+        };
+        let mut devices: Vec<(&DeviceIdentifier, &mut Device)> = self.devices.iter_mut().collect();
+        for (key, (identifier, device)) in devices.iter().enumerate() {
+            // SAFETY: every device is deleted from the poller below, while the
+            // devices are still borrowed and their fds valid.
+            if let Err(e) = unsafe { poller.add_with_mode(&**device, polling::Event::readable(key), polling::PollMode::Level) } {
+                warn!("Not watching '{}' for button presses: {}", identifier.name, e);
+            }
+        }
+
+        let mut events = polling::Events::new();
+        let mut pressed = None;
+        'wait: loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            events.clear();
+            match poller.wait(&mut events, Some(remaining)) {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    warn!("Stopped waiting for a button press: {}", e);
+                    break;
+                }
+            }
+            for key in events.iter().map(|event| event.key) {
+                let (identifier, device) = &mut devices[key];
+                let fd = device.as_raw_fd();
+                match device.fetch_events() {
+                    Ok(mut iter) => {
+                        if iter.any(|event| is_fresh_press(&event, since)) {
+                            pressed = Some((*identifier).clone());
+                            break 'wait;
+                        }
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(e) => {
+                        // An unplugged device stays readable; stop watching it
+                        debug!("Not watching '{}' for button presses any more: {}", identifier.name, e);
+                        // SAFETY: the device is still alive and its fd valid.
+                        let _ = poller.delete(unsafe { BorrowedFd::borrow_raw(fd) });
+                    }
+                }
+            }
+        }
+
+        for (_, device) in &devices {
+            // SAFETY: the device is still alive and its fd valid.
+            let _ = poller.delete(unsafe { BorrowedFd::borrow_raw(device.as_raw_fd()) });
+        }
+        pressed.map(|device| self.primary_interface(&device))
+    }
+
+    /// What `device` provides, if it has been enumerated.
+    pub fn device_class(&self, device: &DeviceIdentifier) -> Option<DeviceClass> {
+        self.device_classes.get(device).copied()
+    }
+
+    /// The enumerated devices grouped by physical device, sorted by name.
+    /// Each group starts with its primary interface: the one with gamepad
+    /// buttons, else sticks, else keys, which is the one to route.
+    pub fn controllers(&self) -> Vec<Vec<DeviceIdentifier>> {
+        group_interfaces(&self.device_classes)
+    }
+
+    /// The primary interface of the physical device `device` belongs to;
+    /// `device` itself if it is the primary or hasn't been enumerated.
+    pub fn primary_interface(&self, device: &DeviceIdentifier) -> DeviceIdentifier {
+        self.controllers()
+            .into_iter()
+            .find(|group| group.contains(device))
+            .map_or_else(|| device.clone(), |group| group[0].clone())
+    }
+
     /// Creates virtual uinput devices for each game instance using evdev's built-in
     /// VirtualDeviceBuilder.  Each device mirrors the union of capabilities from all
     /// enumerated physical devices so that every key, axis, and button works in-game.
-    /// Requires write permissions on /dev/uinput.
-    pub fn create_virtual_devices(&mut self, num_instances: usize) -> Result<(), InputMuxError> {
+    /// Codes uinput rejects are left out rather than failing the session; the
+    /// returned reports list them per instance. Requires write permissions on
+    /// /dev/uinput.
+    pub fn create_virtual_devices(&mut self, num_instances: usize) -> Result<Vec<CapabilityReport>, InputMuxError> {
         info!("Creating {} virtual input device(s)...", num_instances);
         self.virtual_devices.clear();
+        self.virtual_device_names.clear();
+        self.virtual_controllers.clear();
+        self.capability_reports.clear();
 
         // --- collect the union of all physical-device capabilities ---
         let mut all_keys: Vec<evdev::Key> = Vec::new();
@@ -310,14 +1229,42 @@ impl InputMux {
         let has_real_caps =
             !all_keys.is_empty() || !all_rel_axes.is_empty() || !all_abs_axes.is_empty();
 
+        // Older kernels reject codes newer devices report; find them once so
+        // each device is created with what the kernel accepts
+        let (all_keys, skipped_keys) = probe_uinput(&all_keys, |builder, key| {
+            builder.with_keys(&evdev::AttributeSet::from_iter([key]))
+        })
+        .map_err(InputMuxError::IoError)?;
+        let (all_rel_axes, skipped_relative_axes) = probe_uinput(&all_rel_axes, |builder, axis| {
+            builder.with_relative_axes(&evdev::AttributeSet::from_iter([axis]))
+        })
+        .map_err(InputMuxError::IoError)?;
+        let (all_abs_axes, skipped_abs_axes) = probe_uinput(&all_abs_axes, |builder, (axis, abs_info)| {
+            builder.with_absolute_axis(&evdev::UinputAbsSetup::new(axis, abs_info))
+        })
+        .map_err(InputMuxError::IoError)?;
+        let skipped_absolute_axes: Vec<AbsoluteAxisType> = skipped_abs_axes.into_iter().map(|(axis, _)| axis).collect();
+
         // --- create one virtual device per instance ---
         for i in 0..num_instances {
-            let device_name = format!("HydraCoop Virtual Device {}", i);
+            let device_name = self.virtual_device_name(i);
             debug!("Creating virtual device: {}", device_name);
 
+            let mimicked = self.assigned_device(i).filter(|_| self.mimic_physical_names);
+            let input_id = match mimicked {
+                Some(identifier) => evdev::InputId::new(
+                    evdev::BusType(identifier.bustype),
+                    identifier.vendor_id,
+                    identifier.product_id,
+                    identifier.version,
+                ),
+                None => evdev::InputId::new(evdev::BusType::BUS_USB, VIRTUAL_VENDOR_ID, VIRTUAL_PRODUCT_BASE + i as u16, 1),
+            };
+            let (vendor_id, product_id) = (input_id.vendor(), input_id.product());
             let mut builder = VirtualDeviceBuilder::new()
                 .map_err(InputMuxError::IoError)?
-                .name(&device_name);
+                .name(&device_name)
+                .input_id(input_id);
 
             if has_real_caps {
                 if !all_keys.is_empty() {
@@ -357,27 +1304,80 @@ impl InputMux {
                     .map_err(InputMuxError::IoError)?;
             }
 
-            let virtual_device = builder.build().map_err(InputMuxError::IoError)?;
-            info!("Created virtual device for instance {}", i);
+            let mut virtual_device = builder.build().map_err(InputMuxError::IoError)?;
+            // The kernel numbers devices as they are created, so creating
+            // them one after another keeps their nodes in player order
+            let node = virtual_device
+                .enumerate_dev_nodes_blocking()
+                .ok()
+                .and_then(|mut nodes| nodes.find_map(|node| node.ok()));
+            info!("Created virtual device '{}' for instance {}", device_name, i);
+            self.virtual_controllers.insert(
+                i,
+                VirtualController { slot: i, node, vendor_id, product_id },
+            );
             self.virtual_devices.insert(i, Arc::new(Mutex::new(virtual_device)));
+            self.virtual_device_names.insert(i, device_name);
+
+            let report = if has_real_caps {
+                CapabilityReport {
+                    instance: i,
+                    registered_keys: all_keys.len(),
+                    registered_relative_axes: all_rel_axes.len(),
+                    registered_absolute_axes: all_abs_axes.len(),
+                    skipped_keys: skipped_keys.clone(),
+                    skipped_relative_axes: skipped_relative_axes.clone(),
+                    skipped_absolute_axes: skipped_absolute_axes.clone(),
+                }
+            } else {
+                // The default set above
+                CapabilityReport { instance: i, registered_keys: 2, registered_relative_axes: 2, ..CapabilityReport::default() }
+            };
+            if report.skipped() > 0 {
+                warn!(
+                    "uinput did not accept every input code; games won't see these buttons or axes ({})",
+                    report
+                );
+            } else {
+                debug!("Virtual device capabilities: {}", report);
+            }
+            self.capability_reports.push(report);
         }
 
         info!("Finished creating virtual devices ({} created).", self.virtual_devices.len());
-        Ok(())
+        Ok(self.capability_reports.clone())
     }
 
 
-    /// Captures events from mapped physical devices and injects them into the
-    /// corresponding virtual devices for each instance.
-    /// This function spawns a thread for each mapped physical device.
-    pub fn capture_events(&mut self, assignments: &[(usize, InputAssignment)]) -> Result<(), InputMuxError> {
-        // Clear existing mappings
+    /// Resolves `assignments` to physical devices. `capture_events` does this
+    /// itself; call it earlier when virtual devices should mimic physical ones.
+    pub fn assign_devices(&mut self, assignments: &[(usize, InputAssignment)]) {
         self.instance_map.clear();
-        
-        // Process input assignments
-        let auto_detect_queue: Vec<DeviceIdentifier> = self.devices.keys().cloned().collect();
+        self.mirror_devices.clear();
+
+        // Auto-detection hands out one interface per physical device,
+        // gamepads first.
+        let mut auto_detect_queue: Vec<DeviceIdentifier> = self
+            .controllers()
+            .into_iter()
+            .map(|mut group| group.swap_remove(0))
+            .filter(|device| self.devices.contains_key(device))
+            .collect();
+        auto_detect_queue.sort_by_key(|device| std::cmp::Reverse(self.device_class(device).map_or(0, |class| class.rank())));
         let mut used_devices: std::collections::HashSet<DeviceIdentifier> = std::collections::HashSet::new();
-        
+
+        // Claim mirrored devices first so auto-detection can't hand them to one player.
+        for (_, assignment) in assignments {
+            if let InputAssignment::Mirror { device } = assignment {
+                if !self.devices.contains_key(device) {
+                    warn!("Device '{}' not available for mirroring", device.name);
+                } else if used_devices.insert(device.clone()) {
+                    self.mirror_devices.push(device.clone());
+                    info!("Mirroring device '{}' to every instance", device.name);
+                }
+            }
+        }
+
         for &(instance_index, ref assignment) in assignments {
             match assignment {
                 InputAssignment::Device(device_id) => {
@@ -404,17 +1404,27 @@ impl InputMux {
                 InputAssignment::None => {
                     info!("No input device assigned to instance {}", instance_index);
                 }
+                InputAssignment::Mirror { .. } => {}
             }
         }
-        
+    }
+
+    /// Captures events from mapped physical devices and injects them into the
+    /// corresponding virtual devices for each instance.
+    /// This function spawns a thread for each mapped physical device.
+    pub fn capture_events(&mut self, assignments: &[(usize, InputAssignment)]) -> Result<(), InputMuxError> {
+        self.assign_devices(assignments);
+
         if self.running.load(Ordering::SeqCst) {
             warn!("Input capture is already running.");
             return Err(InputMuxError::AlreadyRunning);
         }
 
+        // Whether running without input is acceptable was decided before
+        // launch (see `Config::allow_no_input`).
         if self.devices.is_empty() {
             warn!("No input devices enumerated. Skipping event capture.");
-            return Ok(()); // Or return an error if no devices is considered a fatal issue
+            return Ok(());
         }
 
         if self.virtual_devices.is_empty() {
@@ -422,11 +1432,21 @@ impl InputMux {
             return Err(InputMuxError::GenericError("No virtual devices available for routing".to_string()));
         }
 
-        if self.instance_map.is_empty() {
+        if self.instance_map.is_empty() && self.mirror_devices.is_empty() {
             warn!("No devices mapped to instances. Skipping event capture.");
             return Ok(()); // No mapping, nothing to capture/route
         }
 
+        // A capture thread without a target would quietly drop its device's input
+        let missing = self.unroutable_mappings();
+        if !missing.is_empty() {
+            return Err(InputMuxError::MissingVirtualDevices(missing));
+        }
+
+        if self.backend == InputBackendKind::XInput2 && self.xinput.is_none() {
+            self.start_xinput_routing();
+        }
+
         info!("Starting input event capture and routing...");
         self.running.store(true, Ordering::SeqCst);
 
@@ -435,73 +1455,254 @@ impl InputMux {
         // Take ownership of mapped devices for their capture threads. evdev's Device
         // is not Clone and fetch_events requires &mut self, so each thread must own
         // its physical device exclusively. Unmapped devices remain in self.devices.
-        let mapped_identifiers: Vec<DeviceIdentifier> = self.instance_map.keys().cloned().collect();
-        for identifier in mapped_identifiers {
-            let instance_index = match self.instance_map.get(&identifier).copied() {
-                Some(i) => i,
-                None => continue,
-            };
-            let device = match self.devices.remove(&identifier) {
-                Some(d) => d,
-                None => {
-                    error!("Mapped device identifier {:?} not found among enumerated devices.", identifier);
-                    continue;
-                }
-            };
+        let mut routes: Vec<(DeviceIdentifier, Vec<usize>)> = self
+            .instance_map
+            .iter()
+            .filter(|(identifier, _)| !self.xinput.as_ref().is_some_and(|masters| masters.is_attached(identifier)))
+            .map(|(identifier, &instance_index)| (identifier.clone(), vec![instance_index]))
+            .collect();
+        let mut all_instances: Vec<usize> = self.virtual_devices.keys().copied().collect();
+        all_instances.sort_unstable();
+        for identifier in &self.mirror_devices {
+            routes.push((identifier.clone(), all_instances.clone()));
+        }
 
-            let virtual_devices = self.virtual_devices.clone();
-            let running_flag = self.running.clone();
-            let id_for_thread = identifier.clone();
+        for (identifier, instances) in routes {
+            if let Some(handle) = self.spawn_capture_thread(&identifier, &instances) {
+                join_handles.push((identifier, handle));
+            }
+        }
 
-            info!("Starting capture thread for device: {} (mapped to instance {})", id_for_thread.name, instance_index);
+        self.capture_threads = Some(join_handles);
 
-            let handle = thread::spawn(move || {
-                run_capture_loop(device, id_for_thread, instance_index, virtual_devices, running_flag);
+        info!("Input event capture threads started.");
+        Ok(())
+    }
+
+    /// Starts the thread forwarding `identifier`'s events to `instances`. The
+    /// device is handed back through `released_devices` when the thread exits.
+    fn spawn_capture_thread(&mut self, identifier: &DeviceIdentifier, instances: &[usize]) -> Option<JoinHandle<ThreadOutcome>> {
+        let Some(mut device) = self.devices.remove(identifier) else {
+            error!("Mapped device identifier {:?} not found among enumerated devices.", identifier);
+            return None;
+        };
+        let targets: Vec<(usize, Arc<Mutex<VirtualDevice>>)> = instances
+            .iter()
+            .filter_map(|&instance_index| {
+                let vd = self.virtual_devices.get(&instance_index).cloned();
+                if vd.is_none() {
+                    error!("Virtual device for instance {} not found; '{}' won't reach it.", instance_index, identifier.name);
+                }
+                vd.map(|vd| (instance_index, vd))
+            })
+            .collect();
+
+        let running_flag = self.running.clone();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        self.capture_stops.insert(identifier.clone(), stop_flag.clone());
+        let paused_flag = self.paused.clone();
+        let poll_timeout = self.poll_timeout;
+        let sync_mode = self.sync_mode;
+        let reserved_keys = self.host_reserved_keys.clone();
+        let released = self.released_devices.clone();
+        let degraded = self.degraded.clone();
+        let id_for_thread = identifier.clone();
+
+        info!("Starting capture thread for device: {} (mapped to instance(s) {:?})", id_for_thread.name, instances);
+
+        let spawned = thread::Builder::new().name(format!("hydra-capture-{}", identifier.name)).spawn(move || {
+            let capture = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                run_capture_loop(
+                    &mut device,
+                    id_for_thread.clone(),
+                    targets,
+                    running_flag,
+                    stop_flag,
+                    paused_flag,
+                    poll_timeout,
+                    sync_mode,
+                    reserved_keys,
+                )
+            }));
+            let outcome = capture.unwrap_or_else(|payload| {
+                let message = panic_message(payload.as_ref());
+                error!(
+                    "Capture thread for '{}' panicked: {}; its input no longer reaches the game",
+                    id_for_thread.name, message
+                );
+                degraded.store(true, Ordering::SeqCst);
+                ThreadOutcome::Panicked(message)
             });
-            join_handles.push(handle);
+            released.lock().unwrap_or_else(|e| e.into_inner()).insert(id_for_thread, device);
+            outcome
+        });
+        match spawned {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                error!("Could not start the capture thread for '{}': {}", identifier.name, e);
+                None
+            }
         }
+    }
 
-        self.capture_threads = Some(join_handles);
+    /// Which instance each device is routed to, in instance order. Mirrored
+    /// devices aren't included.
+    pub fn current_assignments(&self) -> Vec<(DeviceIdentifier, usize)> {
+        let mut assignments: Vec<(DeviceIdentifier, usize)> = self
+            .instance_map
+            .iter()
+            .map(|(identifier, &instance_index)| (identifier.clone(), instance_index))
+            .collect();
+        assignments.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.name.cmp(&b.0.name)));
+        assignments
+    }
 
-        info!("Input event capture threads started.");
+    /// Routes `device` to `new_instance` from now on. While capture is
+    /// running, only that device's capture thread is restarted; the other
+    /// devices keep forwarding. A mirrored device stops being mirrored.
+    pub fn reassign(&mut self, device: &DeviceIdentifier, new_instance: usize) -> Result<(), InputMuxError> {
+        if !self.virtual_devices.contains_key(&new_instance) {
+            return Err(InputMuxError::MissingVirtualDevices(vec![(device.clone(), new_instance)]));
+        }
+        let known = self.instance_map.contains_key(device)
+            || self.mirror_devices.contains(device)
+            || self.devices.contains_key(device);
+        if !known {
+            return Err(InputMuxError::GenericError(format!("'{}' is not an enumerated input device", device.name)));
+        }
+        if self.xinput.as_ref().is_some_and(|masters| masters.is_attached(device)) {
+            return Err(InputMuxError::GenericError(format!(
+                "'{}' is routed by the X server; restart the session to move it",
+                device.name
+            )));
+        }
+
+        self.mirror_devices.retain(|mirrored| mirrored != device);
+        let previous = self.instance_map.insert(device.clone(), new_instance);
+        info!(
+            "Reassigning '{}' from {} to player {}",
+            device.name,
+            previous.map_or("no player".to_string(), |instance| format!("player {}", instance + 1)),
+            new_instance + 1
+        );
+
+        if !self.running.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Some(stop) = self.capture_stops.remove(device) {
+            stop.store(true, Ordering::SeqCst);
+        }
+        let handles = self.capture_threads.get_or_insert_with(Vec::new);
+        if let Some(position) = handles.iter().position(|(identifier, _)| identifier == device) {
+            let (_, handle) = handles.remove(position);
+            match handle.join() {
+                Ok(outcome) => debug!("Capture thread for '{}' {} before reassignment", device.name, outcome),
+                Err(payload) => warn!("Capture thread for '{}' panicked: {}", device.name, panic_message(payload.as_ref())),
+            }
+        }
+        self.reclaim_released_devices();
+        if let Some(handle) = self.spawn_capture_thread(device, &[new_instance]) {
+            self.capture_threads.get_or_insert_with(Vec::new).push((device.clone(), handle));
+        }
         Ok(())
     }
 
-    /// Signals the capture threads to stop and waits for them to finish.
-    pub fn stop_capture(&mut self) -> Result<(), InputMuxError> {
+    /// Moves devices whose capture threads have exited back into `devices`.
+    fn reclaim_released_devices(&mut self) {
+        self.devices.extend(self.released_devices.lock().unwrap().drain());
+    }
+
+    /// Mapped devices whose instance has no virtual device, in instance order.
+    fn unroutable_mappings(&self) -> Vec<(DeviceIdentifier, usize)> {
+        let mut missing: Vec<(DeviceIdentifier, usize)> = self
+            .instance_map
+            .iter()
+            .filter(|(_, instance_index)| !self.virtual_devices.contains_key(instance_index))
+            .map(|(identifier, &instance_index)| (identifier.clone(), instance_index))
+            .collect();
+        missing.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.name.cmp(&b.0.name)));
+        missing
+    }
+
+    /// Flag that pauses input forwarding while set. Capture threads keep
+    /// reading their devices but drop the events, so nothing is replayed
+    /// when input resumes.
+    pub fn pause_flag(&self) -> Arc<AtomicBool> {
+        self.paused.clone()
+    }
+
+    /// Set once a capture thread panics: the session keeps running, but
+    /// that device's input is lost. Shared with the network emulator's relay,
+    /// see `NetEmulator::set_degraded_flag`.
+    pub fn degraded_flag(&self) -> Arc<AtomicBool> {
+        self.degraded.clone()
+    }
+
+    /// Signals the capture threads to stop, waits for them to finish, and
+    /// reports how each device's thread ended. Fails with
+    /// `CaptureThreadsPanicked` if any of them panicked.
+    pub fn stop_capture(&mut self) -> Result<Vec<(DeviceIdentifier, ThreadOutcome)>, InputMuxError> {
         if !self.running.load(Ordering::SeqCst) {
             info!("Input capture is not running.");
-            return Ok(());
+            return Ok(Vec::new());
         }
 
         info!("Stopping input event capture...");
         self.running.store(false, Ordering::SeqCst); // Signal threads to stop
+        // Hand keyboards and mice routed by XInput2 back to the desktop
+        self.xinput = None;
 
         // Wait for the threads to finish
+        let mut outcomes = Vec::new();
         if let Some(handles) = self.capture_threads.take() {
-            for handle in handles {
-                if let Err(e) = handle.join() {
-                    error!("Failed to join capture thread: {:?}", e);
+            for (identifier, handle) in handles {
+                let outcome = handle
+                    .join()
+                    .unwrap_or_else(|payload| ThreadOutcome::Panicked(panic_message(payload.as_ref())));
+                match &outcome {
+                    ThreadOutcome::Stopped => debug!("Capture thread for '{}' stopped.", identifier.name),
+                    ThreadOutcome::Failed(_) => warn!("Capture thread for '{}' {}", identifier.name, outcome),
+                    ThreadOutcome::Panicked(_) => error!("Capture thread for '{}' {}", identifier.name, outcome),
                 }
+                outcomes.push((identifier, outcome));
             }
             info!("All capture threads joined.");
+            self.capture_stops.clear();
+            self.reclaim_released_devices();
         } else {
              warn!("No capture threads found to join.");
         }
-        Ok(())
+
+        let panicked: Vec<(DeviceIdentifier, ThreadOutcome)> = outcomes
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, ThreadOutcome::Panicked(_)))
+            .cloned()
+            .collect();
+        if !panicked.is_empty() {
+            return Err(InputMuxError::CaptureThreadsPanicked(panicked));
+        }
+        Ok(outcomes)
     }
 
     /// List of enumerated input devices that are currently available.
     pub fn get_available_devices(&self) -> Vec<DeviceIdentifier> {
         self.devices.keys().cloned().collect()
     }
+
+    /// Full capabilities of each enumerated device, sorted by name, for
+    /// attaching to bug reports.
+    pub fn device_capabilities(&self) -> Vec<DeviceCapabilities> {
+        let mut capabilities: Vec<DeviceCapabilities> = self.devices.values().map(DeviceCapabilities::from).collect();
+        capabilities.sort_by(|a, b| a.device.name.cmp(&b.device.name).then_with(|| a.device.phys.cmp(&b.device.phys)));
+        capabilities
+    }
 }
 
 // Implement Drop to stop capture threads when InputMux goes out of scope
 impl Drop for InputMux {
     fn drop(&mut self) {
         if let Err(e) = self.stop_capture() {
-            warn!("Error stopping input capture during drop: {}", e);
+            error!("Error stopping input capture during drop: {}", e);
         }
         info!("InputMux instance dropped.");
     }
@@ -518,6 +1719,376 @@ mod tests {
         let _ = env_logger::builder().is_test(true).try_init();
     }
 
+    fn device(name: &str) -> DeviceIdentifier {
+        DeviceIdentifier {
+            name: name.to_string(),
+            phys: None,
+            bustype: 3,
+            vendor_id: 0x046d,
+            product_id: 0xc21d,
+            version: 1,
+            uniq: None,
+        }
+    }
+
+    #[test]
+    fn test_device_class() {
+        use evdev::AttributeSet;
+        let keys = |keys: &[Key]| keys.iter().collect::<AttributeSet<Key>>();
+        let axes = |axes: &[AbsoluteAxisType]| axes.iter().collect::<AttributeSet<AbsoluteAxisType>>();
+        let no_props = AttributeSet::<PropType>::new();
+
+        let gamepad_keys = keys(&[Key::BTN_SOUTH, Key::BTN_EAST]);
+        let sticks = axes(&[AbsoluteAxisType::ABS_X, AbsoluteAxisType::ABS_Y, AbsoluteAxisType::ABS_RX]);
+        let gamepad = DeviceClass::classify(Some(&gamepad_keys), Some(&sticks), &no_props);
+        assert!(gamepad.has_gamepad_buttons && gamepad.has_abs_sticks && !gamepad.has_keyboard);
+
+        let motion_props = [PropType::ACCELEROMETER].iter().collect::<AttributeSet<PropType>>();
+        let motion = DeviceClass::classify(None, Some(&sticks), &motion_props);
+        assert!(motion.has_motion_sensors && !motion.has_abs_sticks);
+
+        let touch_keys = keys(&[Key::BTN_LEFT, Key::BTN_TOOL_FINGER]);
+        let touch_axes = axes(&[AbsoluteAxisType::ABS_X, AbsoluteAxisType::ABS_Y, AbsoluteAxisType::ABS_MT_POSITION_X]);
+        let touchpad = DeviceClass::classify(Some(&touch_keys), Some(&touch_axes), &no_props);
+        assert!(touchpad.has_touchpad && !touchpad.has_abs_sticks && !touchpad.has_gamepad_buttons);
+
+        let keyboard_keys = keys(&[Key::KEY_A, Key::KEY_Z, Key::KEY_SPACE]);
+        let keyboard = DeviceClass::classify(Some(&keyboard_keys), None, &no_props);
+        assert!(keyboard.has_keyboard);
+
+        assert!(gamepad.rank() > keyboard.rank() && keyboard.rank() > motion.rank());
+        assert_eq!(motion.rank(), touchpad.rank());
+    }
+
+    #[test]
+    fn test_controllers_group_interfaces() {
+        let interface = |name: &str, phys: Option<&str>, product_id| DeviceIdentifier {
+            name: name.to_string(),
+            phys: phys.map(str::to_string),
+            bustype: 3,
+            vendor_id: 0x054c,
+            product_id,
+            version: 1,
+            uniq: None,
+        };
+        let buttons = interface("Wireless Controller", Some("usb-0000:00:14.0-2/input3"), 0x09cc);
+        let motion = interface("Wireless Controller Motion Sensors", Some("usb-0000:00:14.0-2/input3"), 0x09cc);
+        let touchpad = interface("Wireless Controller Touchpad", Some("usb-0000:00:14.0-2/input3"), 0x09cc);
+        let other_pad = interface("Wireless Controller", Some("usb-0000:00:14.0-3/input3"), 0x09cc);
+        let unknown = interface("Wireless Controller", None, 0x09cc);
+
+        let mut input_mux = InputMux::new();
+        let pad = DeviceClass { has_gamepad_buttons: true, has_abs_sticks: true, ..DeviceClass::default() };
+        input_mux.device_classes = HashMap::from([
+            (motion.clone(), DeviceClass { has_motion_sensors: true, ..DeviceClass::default() }),
+            (touchpad.clone(), DeviceClass { has_touchpad: true, ..DeviceClass::default() }),
+            (buttons.clone(), pad),
+            (other_pad.clone(), pad),
+            (unknown.clone(), pad),
+        ]);
+
+        let controllers = input_mux.controllers();
+        assert_eq!(controllers.len(), 3);
+        assert!(controllers.contains(&vec![buttons.clone(), motion.clone(), touchpad.clone()]));
+        assert!(controllers.contains(&vec![other_pad.clone()]));
+        assert!(controllers.contains(&vec![unknown.clone()]));
+
+        assert_eq!(input_mux.primary_interface(&motion), buttons);
+        assert_eq!(input_mux.primary_interface(&buttons), buttons);
+        assert_eq!(input_mux.primary_interface(&other_pad), other_pad);
+        assert_eq!(input_mux.primary_interface(&device("Not enumerated")), device("Not enumerated"));
+        assert_eq!(input_mux.device_class(&touchpad).map(|class| class.has_touchpad), Some(true));
+
+        // Two identical Bluetooth pads share the adapter's path; their own
+        // addresses keep them apart while each pad's interfaces stay together
+        let bluetooth = |name: &str, address: &str| DeviceIdentifier {
+            bustype: 5,
+            uniq: Some(address.to_string()),
+            ..interface(name, Some("aa:bb:cc:dd:ee:ff"), 0x09cc)
+        };
+        let first = bluetooth("Wireless Controller", "11:22:33:44:55:01");
+        let first_motion = bluetooth("Wireless Controller Motion Sensors", "11:22:33:44:55:01");
+        let second = bluetooth("Wireless Controller", "11:22:33:44:55:02");
+        input_mux.device_classes = HashMap::from([
+            (first.clone(), pad),
+            (first_motion.clone(), DeviceClass { has_motion_sensors: true, ..DeviceClass::default() }),
+            (second.clone(), pad),
+        ]);
+        let controllers = input_mux.controllers();
+        assert_eq!(controllers.len(), 2);
+        assert!(controllers.contains(&vec![first.clone(), first_motion.clone()]));
+        assert!(controllers.contains(&vec![second.clone()]));
+    }
+
+    #[test]
+    fn test_render_device_name() {
+        assert_eq!(render_device_name(DEFAULT_DEVICE_NAME_TEMPLATE, 0, "a1b2"), "HydraCoop P1 a1b2");
+        assert_eq!(render_device_name("Pad {instance}", 3, "a1b2"), "Pad 4");
+        assert_eq!(render_device_name(&"x".repeat(100), 0, "a1b2").len(), MAX_DEVICE_NAME_LEN);
+    }
+
+    #[test]
+    fn test_virtual_device_name_mimics_assigned_device() {
+        let mut input_mux = InputMux::new();
+        input_mux.set_device_naming("", true);
+        input_mux.instance_map.insert(device("Logitech Gamepad F310"), 1);
+
+        assert!(input_mux.virtual_device_name(0).starts_with("HydraCoop P1 "));
+        assert_eq!(input_mux.virtual_device_name(1), "Logitech Gamepad F310");
+
+        input_mux.set_device_naming("Player {instance}", false);
+        assert_eq!(input_mux.virtual_device_name(1), "Player 2");
+    }
+
+    #[test]
+    fn test_reassign_requires_target_virtual_device() {
+        let mut input_mux = InputMux::new();
+        input_mux.instance_map.insert(device("Logitech Gamepad F310"), 0);
+        assert_eq!(input_mux.current_assignments(), vec![(device("Logitech Gamepad F310"), 0)]);
+
+        let result = input_mux.reassign(&device("Logitech Gamepad F310"), 2);
+        assert!(matches!(result, Err(InputMuxError::MissingVirtualDevices(_))));
+        assert_eq!(input_mux.current_assignments(), vec![(device("Logitech Gamepad F310"), 0)]);
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let pad = |name: &str, phys: &str, product_id| DeviceIdentifier {
+            name: name.to_string(),
+            phys: Some(phys.to_string()),
+            bustype: 3,
+            vendor_id: 0x045e,
+            product_id,
+            version: 1,
+            uniq: None,
+        };
+        let available = vec![
+            pad("Xbox 360 Controller", "usb-1/input0", 0x028e),
+            pad("Xbox 360 Controller", "usb-2/input0", 0x028e),
+            pad("Xbox One Controller", "usb-3/input0", 0x02ea),
+        ];
+
+        let assignments = parse_assignments("0=id:045e:028e; 1=auto;2=none;3=id:045E:028E", 5, &available).unwrap();
+        assert_eq!(
+            assignments,
+            vec![
+                (0, InputAssignment::Device(available[0].clone())),
+                (1, InputAssignment::AutoDetect),
+                (2, InputAssignment::None),
+                (3, InputAssignment::Device(available[1].clone())),
+            ]
+        );
+        let assignments = parse_assignments("1=phys:usb-2/input0;0=Xbox One Controller;2=name:Missing Pad", 3, &available).unwrap();
+        assert_eq!(
+            assignments,
+            vec![
+                (1, InputAssignment::Device(available[1].clone())),
+                (0, InputAssignment::Device(available[2].clone())),
+                (2, InputAssignment::None),
+            ]
+        );
+
+        for (spec, reason) in [
+            ("0", "missing '='"),
+            ("x=auto", "'x' is not an instance number"),
+            ("2=auto", "there are only instances 0 to 1"),
+            ("0=auto;0=none", "instance 0 is assigned twice"),
+            ("0=id:xbox", "use id:VENDOR:PRODUCT with hexadecimal IDs"),
+        ] {
+            match parse_assignments(spec, 2, &available) {
+                Err(InputMuxError::InvalidAssignment { reason: actual, .. }) => assert_eq!(actual, reason, "{spec}"),
+                other => panic!("{spec}: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_capability_report_lists_rejected_codes() {
+        let requested = [Key::BTN_SOUTH, Key::BTN_TRIGGER_HAPPY1, Key::BTN_EAST];
+        let (registered, skipped) =
+            partition_supported(&requested, |key| Ok(key != Key::BTN_TRIGGER_HAPPY1)).unwrap();
+        assert_eq!(registered, vec![Key::BTN_SOUTH, Key::BTN_EAST]);
+        assert_eq!(skipped, vec![Key::BTN_TRIGGER_HAPPY1]);
+        assert!(partition_supported(&requested, |_| Err(io::Error::from(io::ErrorKind::PermissionDenied))).is_err());
+
+        let report = CapabilityReport {
+            instance: 1,
+            registered_keys: registered.len(),
+            registered_absolute_axes: 4,
+            skipped_keys: skipped,
+            skipped_absolute_axes: vec![AbsoluteAxisType::ABS_MT_TOOL_Y],
+            ..CapabilityReport::default()
+        };
+        assert_eq!(report.skipped(), 2);
+        assert_eq!(
+            report.to_string(),
+            "player 2: 2 keys, 0 relative axes, 4 absolute axes registered; skipped BTN_TRIGGER_HAPPY1, ABS_MT_TOOL_Y"
+        );
+        assert_eq!(CapabilityReport::default().to_string(), "player 1: 0 keys, 0 relative axes, 0 absolute axes registered");
+    }
+
+    #[test]
+    fn test_unroutable_mappings() {
+        let mut input_mux = InputMux::new();
+        assert!(input_mux.unroutable_mappings().is_empty());
+
+        input_mux.instance_map.insert(device("Xbox Wireless Controller"), 3);
+        input_mux.instance_map.insert(device("Logitech Gamepad F310"), 1);
+        let missing = input_mux.unroutable_mappings();
+        assert_eq!(missing, vec![(device("Logitech Gamepad F310"), 1), (device("Xbox Wireless Controller"), 3)]);
+        assert_eq!(
+            InputMuxError::MissingVirtualDevices(missing).to_string(),
+            "No virtual input device for player 2 (assigned 'Logitech Gamepad F310'), \
+             player 4 (assigned 'Xbox Wireless Controller'); assign devices only to players that are launched"
+        );
+    }
+
+    #[test]
+    fn test_controller_env_hides_other_devices() {
+        let controller = |slot: usize| VirtualController {
+            slot,
+            node: Some(PathBuf::from(format!("/dev/input/event{}", 20 + slot))),
+            vendor_id: VIRTUAL_VENDOR_ID,
+            product_id: VIRTUAL_PRODUCT_BASE + slot as u16,
+        };
+        let controllers = [controller(0), controller(1), controller(2)];
+        let all: Vec<&VirtualController> = controllers.iter().collect();
+        let physical = [(0x045e, 0x028e), (0x045e, 0x028e), (0, 0)];
+
+        assert_eq!(
+            controller_env(&controllers[1], &all, &physical),
+            vec![
+                ("SDL_JOYSTICK_DEVICE".to_string(), "/dev/input/event21".to_string()),
+                (
+                    "SDL_GAMECONTROLLER_IGNORE_DEVICES".to_string(),
+                    "0x1209/0x0001,0x1209/0x0003,0x045e/0x028e".to_string()
+                ),
+            ]
+        );
+
+        // A mimicked controller can't be told apart from the pad it copies
+        let mimic = VirtualController { slot: 0, node: None, vendor_id: 0x045e, product_id: 0x028e };
+        assert!(controller_env(&mimic, &[&mimic], &physical).is_empty());
+    }
+
+    #[test]
+    fn test_mirror_assignment() {
+        // Session manifests store assignments as JSON
+        let assignment = InputAssignment::Mirror { device: device("Logitech Gamepad F310") };
+        let json = serde_json::to_string(&assignment).unwrap();
+        assert_eq!(serde_json::from_str::<InputAssignment>(&json).unwrap(), assignment);
+
+        // A device that wasn't enumerated can't be mirrored
+        let mut input_mux = InputMux::new();
+        input_mux.assign_devices(&[(0, assignment), (1, InputAssignment::AutoDetect)]);
+        assert!(input_mux.mirror_devices.is_empty());
+        assert!(input_mux.instance_map.is_empty());
+    }
+
+    #[test]
+    fn test_take_frames_splits_on_syn_report() {
+        let abs = |code, value| InputEvent::new(EventType::ABSOLUTE, code, value);
+        let syn = |code| InputEvent::new(EventType::SYNCHRONIZATION, code, 0);
+        let codes = |frames: &[Vec<InputEvent>]| -> Vec<Vec<(u16, i32)>> {
+            frames.iter().map(|f| f.iter().map(|e| (e.code(), e.value())).collect()).collect()
+        };
+
+        let mut partial = Vec::new();
+        let frames = take_frames(&mut partial, vec![abs(0, 10), abs(1, 20), syn(0), abs(0, 11)]);
+        assert_eq!(codes(&frames), vec![vec![(0, 10), (1, 20)]]);
+        assert_eq!(partial.len(), 1, "unterminated frame is carried over");
+
+        // SYN_DROPPED is discarded; the carried-over event starts the next frame.
+        let frames = take_frames(&mut partial, vec![syn(3), abs(1, 21), syn(0), syn(0)]);
+        assert_eq!(codes(&frames), vec![vec![(0, 11), (1, 21)]]);
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn test_take_frames_after_keys() {
+        let abs = |code, value| InputEvent::new(EventType::ABSOLUTE, code, value);
+        let key = |code, value| InputEvent::new(EventType::KEY, code, value);
+        let syn = InputEvent::new(EventType::SYNCHRONIZATION, 0, 0);
+        let types = |frames: &[Vec<InputEvent>]| -> Vec<Vec<EventType>> {
+            frames.iter().map(|f| f.iter().map(|e| e.event_type()).collect()).collect()
+        };
+
+        let frames = take_frames_after_keys(vec![abs(0, 10), syn, abs(1, 20), key(304, 1), syn, abs(0, 11)]);
+        assert_eq!(
+            types(&frames),
+            vec![
+                vec![EventType::ABSOLUTE, EventType::ABSOLUTE, EventType::KEY],
+                vec![EventType::ABSOLUTE],
+            ]
+        );
+        assert!(take_frames_after_keys(vec![syn]).is_empty());
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            input_sync: SyncMode,
+        }
+        let parse = |toml: &str| toml::from_str::<Wrapper>(toml).unwrap().input_sync;
+        assert_eq!(parse("input_sync = \"source\""), SyncMode::Source);
+        assert_eq!(parse("input_sync = \"after_keys\""), SyncMode::AfterKeys);
+    }
+
+    #[test]
+    fn test_host_reserved_keys_are_not_forwarded() {
+        let key = |code: Key, value| InputEvent::new(EventType::KEY, code.code(), value);
+        let syn = InputEvent::new(EventType::SYNCHRONIZATION, 0, 0);
+        let reserved: HashSet<Key> = [Key::KEY_LEFTMETA, Key::KEY_VOLUMEUP].into_iter().collect();
+
+        let events = vec![key(Key::KEY_LEFTMETA, 1), syn, key(Key::KEY_A, 1), key(Key::KEY_VOLUMEUP, 1), syn, key(Key::KEY_LEFTMETA, 0), syn];
+        let mut partial = Vec::new();
+        let frames = take_frames(&mut partial, events.into_iter().filter(|event| !is_host_reserved(event, &reserved)));
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].iter().map(|e| Key(e.code())).collect::<Vec<_>>(), vec![Key::KEY_A]);
+
+        // Only keys are matched, not other events sharing the code
+        assert!(!is_host_reserved(&InputEvent::new(EventType::ABSOLUTE, Key::KEY_LEFTMETA.code(), 1), &reserved));
+    }
+
+    #[test]
+    fn test_is_fresh_press() {
+        // Event times only have microsecond precision
+        let since = SystemTime::now() - Duration::from_millis(1);
+        let key = |code, value| InputEvent::new_now(EventType::KEY, code, value);
+        assert!(is_fresh_press(&key(304, 1), since), "BTN_SOUTH going down");
+        assert!(!is_fresh_press(&key(304, 0), since), "release");
+        assert!(!is_fresh_press(&key(30, 2), since), "autorepeat");
+        assert!(!is_fresh_press(&InputEvent::new_now(EventType::ABSOLUTE, 0, 1), since), "stick movement");
+        // Buffered from before the prompt, e.g. the Enter that started Hydra
+        assert!(!is_fresh_press(&InputEvent::new(EventType::KEY, 28, 1), since));
+    }
+
+    #[test]
+    fn test_detect_next_pressed_times_out_without_devices() {
+        let mut input_mux = InputMux::new();
+        let started = Instant::now();
+        assert_eq!(input_mux.detect_next_pressed(Duration::from_millis(50)), None);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_stop_capture_reports_panicked_threads() {
+        setup_logger();
+        let mut input_mux = InputMux::new();
+        input_mux.running.store(true, Ordering::SeqCst);
+        input_mux.capture_threads = Some(vec![
+            (device("Pad A"), thread::spawn(|| ThreadOutcome::Stopped)),
+            (device("Pad B"), thread::spawn(|| -> ThreadOutcome { panic!("evdev exploded") })),
+        ]);
+
+        match input_mux.stop_capture() {
+            Err(InputMuxError::CaptureThreadsPanicked(threads)) => assert_eq!(
+                threads,
+                vec![(device("Pad B"), ThreadOutcome::Panicked("evdev exploded".to_string()))]
+            ),
+            other => panic!("Expected CaptureThreadsPanicked, got {:?}", other),
+        }
+        assert!(input_mux.capture_threads.is_none());
+    }
+
     // Basic test for enumeration (might require running with sufficient permissions)
     #[test]
     #[ignore] // Ignore this test by default as it requires special permissions/environment
@@ -541,6 +2112,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_device_capabilities_json() {
+        let capabilities = DeviceCapabilities {
+            device: device("Gamepad"),
+            keys: code_names([Key::BTN_SOUTH, Key::BTN_EAST].into_iter()),
+            relative_axes: code_names([RelativeAxisType::REL_WHEEL].into_iter()),
+            absolute_axes: vec![
+                AbsoluteAxis::new(AbsoluteAxisType::ABS_X, Some(AbsInfo::new(3, -32768, 32767, 16, 128, 0))),
+                AbsoluteAxis::new(AbsoluteAxisType::ABS_RZ, None),
+            ],
+            force_feedback: code_names([evdev::FFEffectType::FF_RUMBLE].into_iter()),
+        };
+
+        let json = serde_json::to_value(&capabilities).unwrap();
+        assert_eq!(json["device"]["name"], "Gamepad");
+        assert_eq!(json["keys"], serde_json::json!(["BTN_SOUTH", "BTN_EAST"]));
+        assert_eq!(json["relative_axes"], serde_json::json!(["REL_WHEEL"]));
+        assert_eq!(
+            json["absolute_axes"][0],
+            serde_json::json!({"axis": "ABS_X", "value": 3, "minimum": -32768, "maximum": 32767, "fuzz": 16, "flat": 128, "resolution": 0})
+        );
+        assert_eq!(json["absolute_axes"][1]["axis"], "ABS_RZ");
+        assert_eq!(json["absolute_axes"][1]["maximum"], 0);
+        assert_eq!(json["force_feedback"], serde_json::json!(["FF_RUMBLE"]));
+    }
+
+    #[test]
+    #[ignore] // Needs readable input devices
+    fn test_device_capabilities() {
+        let mut input_mux = InputMux::new();
+        input_mux.enumerate_devices().unwrap();
+        let capabilities = input_mux.device_capabilities();
+        assert_eq!(capabilities.len(), input_mux.devices.len());
+        for device in &capabilities {
+            let json = serde_json::to_value(device).unwrap();
+            assert_eq!(json["device"]["name"], device.device.name.as_str());
+            for axis in &device.absolute_axes {
+                assert!(axis.axis.starts_with("ABS_"), "unexpected axis name {}", axis.axis);
+            }
+        }
+    }
+
     // Add tests for creating virtual devices (requires /dev/uinput access)
      #[test]
      #[ignore] // Requires root or appropriate permissions for /dev/uinput
@@ -558,6 +2171,7 @@ mod tests {
              assert_eq!(input_mux.virtual_devices.len(), num_instances);
              for i in 0..num_instances {
                  assert!(input_mux.virtual_devices.contains_key(&i));
+                 assert_eq!(input_mux.virtual_controller(i).unwrap().product_id, VIRTUAL_PRODUCT_BASE + i as u16);
              }
          }
      }
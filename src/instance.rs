@@ -0,0 +1,179 @@
+//! Launched Instances
+//!
+//! Everything the session setup knows about each game instance once it is
+//! running, gathered in one place so the network relay, window layout and
+//! input routing all agree on which instance is which.
+
+use std::path::PathBuf;
+use crate::config::Config;
+use crate::errors::{HydraError, Result};
+use crate::input_mux::{InputAssignment, VirtualController};
+use crate::session::InstanceRecord;
+
+/// One launched game instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceContext {
+    /// 0-based instance index
+    pub id: usize,
+    /// Id of the instance in the network emulator
+    pub net_id: u8,
+    pub pid: u32,
+    /// Port the game instance was given, see `PortStrategy`
+    pub game_port: Option<u16>,
+    /// Port of the network emulator socket bound for this instance, once the
+    /// relay is set up
+    pub emulator_port: Option<u16>,
+    pub working_dir: PathBuf,
+    /// Input device routed to this instance
+    pub input: InputAssignment,
+    /// The virtual device the instance's input arrives on; its `slot` is the
+    /// controller slot the game sees it in
+    pub virtual_controller: Option<VirtualController>,
+    /// Text only this instance's window title contains, see
+    /// `Config::window_title_marker`
+    pub title_marker: Option<String>,
+}
+
+impl InstanceContext {
+    /// What the session manifest records about this instance.
+    pub fn record(&self) -> InstanceRecord {
+        InstanceRecord {
+            id: self.id,
+            pid: self.pid,
+            game_port: self.game_port,
+            emulator_port: self.emulator_port,
+            working_dir: self.working_dir.clone(),
+        }
+    }
+}
+
+/// Builds the context of each launched instance from its `(id, pid,
+/// working_dir)`. Instance `i` gets `ports[i]`, the port it was launched
+/// with. Instances without an entry in `input_assignments` get `AutoDetect`;
+/// `Mirror` entries aren't any one instance's, see `input_assignments`.
+pub fn build_contexts(
+    launched: impl IntoIterator<Item = (usize, u32, PathBuf)>,
+    input_assignments: &[(usize, InputAssignment)],
+    ports: &[u16],
+    config: &Config,
+) -> Result<Vec<InstanceContext>> {
+    launched
+        .into_iter()
+        .map(|(id, pid, working_dir)| {
+            let net_id = u8::try_from(id).map_err(|_| {
+                HydraError::validation(format!("Instance {} is beyond what the network emulator supports", id))
+            })?;
+            let input = input_assignments
+                .iter()
+                .find(|(instance, assignment)| *instance == id && !matches!(assignment, InputAssignment::Mirror { .. }))
+                .map(|(_, assignment)| assignment.clone())
+                .unwrap_or(InputAssignment::AutoDetect);
+            Ok(InstanceContext {
+                id,
+                net_id,
+                pid,
+                game_port: ports.get(id).copied(),
+                emulator_port: None,
+                working_dir,
+                input,
+                virtual_controller: None,
+                title_marker: config
+                    .window_title_marker
+                    .as_ref()
+                    .map(|marker| marker.replace("{instance}", &(id + 1).to_string())),
+            })
+        })
+        .collect()
+}
+
+/// PIDs of the instances, in instance order.
+pub fn pids(instances: &[InstanceContext]) -> Vec<u32> {
+    instances.iter().map(|instance| instance.pid).collect()
+}
+
+/// The instances' input assignments, in the form `InputMux` takes them,
+/// followed by the devices `requested` mirrors to every instance.
+pub fn input_assignments(
+    instances: &[InstanceContext],
+    requested: &[(usize, InputAssignment)],
+) -> Vec<(usize, InputAssignment)> {
+    let mirrors = requested.iter().filter(|(_, assignment)| matches!(assignment, InputAssignment::Mirror { .. }));
+    instances
+        .iter()
+        .map(|instance| (instance.id, instance.input.clone()))
+        .chain(mirrors.cloned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_mux::DeviceIdentifier;
+
+    fn launched(count: usize) -> Vec<(usize, u32, PathBuf)> {
+        (0..count)
+            .map(|id| (id, 1000 + id as u32, PathBuf::from(format!("/games/instance_{}", id))))
+            .collect()
+    }
+
+    #[test]
+    fn test_build_contexts() {
+        let mut config = Config::default_config();
+        config.window_title_marker = Some("[P{instance}]".to_string());
+        let keyboard = InputAssignment::Device(DeviceIdentifier {
+            name: "Keyboard".to_string(),
+            phys: None,
+            bustype: 3,
+            vendor_id: 1,
+            product_id: 2,
+            version: 0,
+            uniq: None,
+        });
+
+        let contexts = build_contexts(launched(2), &[(1, keyboard.clone())], &[7777], &config).unwrap();
+        assert_eq!(contexts.len(), 2);
+        assert_eq!(contexts[0].game_port, Some(7777));
+        assert_eq!(contexts[1].game_port, None);
+        assert_eq!(contexts[0].input, InputAssignment::AutoDetect);
+        assert_eq!(contexts[1].input, keyboard);
+        assert_eq!(contexts[1].title_marker.as_deref(), Some("[P2]"));
+        assert_eq!(pids(&contexts), vec![1000, 1001]);
+        assert_eq!(input_assignments(&contexts, &[])[1], (1, keyboard));
+
+        let record = contexts[1].record();
+        assert_eq!((record.id, record.pid), (1, 1001));
+        assert_eq!(record.working_dir, PathBuf::from("/games/instance_1"));
+    }
+
+    #[test]
+    fn test_mirrored_devices_are_kept() {
+        let config = Config::default_config();
+        let pad = |name: &str| DeviceIdentifier {
+            name: name.to_string(),
+            phys: None,
+            bustype: 3,
+            vendor_id: 0x045e,
+            product_id: 0x028e,
+            version: 0,
+            uniq: None,
+        };
+        let mirror = InputAssignment::Mirror { device: pad("Coach Pad") };
+        let requested = [(0, mirror.clone()), (0, InputAssignment::Device(pad("Player Pad")))];
+
+        let contexts = build_contexts(launched(2), &requested, &[], &config).unwrap();
+        assert_eq!(contexts[0].input, InputAssignment::Device(pad("Player Pad")));
+        assert_eq!(contexts[1].input, InputAssignment::AutoDetect);
+
+        let assignments = input_assignments(&contexts, &requested);
+        assert_eq!(assignments.len(), 3);
+        // What `capture_events` and the session manifest are given
+        assert_eq!(assignments[2], (0, mirror));
+    }
+
+    #[test]
+    fn test_build_contexts_rejects_ids_beyond_u8() {
+        let config = Config::default_config();
+        let result = build_contexts([(256, 1, PathBuf::from("/tmp"))], &[], &[], &config);
+        assert!(matches!(result, Err(HydraError::Validation(_))));
+    }
+}
@@ -5,19 +5,31 @@
 //! co-operative gameplay by launching and managing multiple instances of a game.
 
 pub mod adaptive_config;
+pub mod bench;
 pub mod cli;
 pub mod config;
+pub mod control;
+pub mod display;
 pub mod errors;
+pub mod flatpak;
 pub mod game_detection;
 pub mod input_mux;
+pub mod instance;
+pub mod lint;
 pub mod logging;
 pub mod net_emulator;
+pub mod orphans;
+pub mod ports;
 pub mod proton_integration;
+pub mod session;
+pub mod template;
 pub mod universal_launcher;
 pub mod window_manager;
+pub mod wine_integration;
+pub mod xinput;
 
-// The `gui` module is binary-only (src/main.rs declares it) because it
-// depends on binary-only helpers such as `run_core_logic`.
+// The `gui` and `tui` modules are binary-only (src/main.rs declares them)
+// because they depend on binary-only helpers such as `run_core_logic`.
 
 // Re-export commonly used types
 pub use adaptive_config::AdaptiveConfigManager;
@@ -25,7 +37,7 @@ pub use config::Config;
 pub use errors::{HydraError, Result};
 pub use game_detection::{GameConfiguration, GameDetector, GameProfile};
 pub use input_mux::{DeviceIdentifier, InputAssignment, InputMux};
-pub use universal_launcher::{GameInstance, UniversalLauncher};
+pub use universal_launcher::{GameInstance, LaunchError, UniversalLauncher};
 pub use window_manager::Layout;
 
 /// Application metadata
@@ -41,8 +53,22 @@ pub mod defaults {
     pub const MAX_INSTANCES: usize = 8;
     pub const DEFAULT_INSTANCES: usize = 2;
     pub const WINDOW_SEARCH_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const WINDOW_SEARCH_INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+    pub const WINDOW_SEARCH_MAX_BACKOFF: Duration = Duration::from_millis(500);
+    /// Window aspect ratio used when preserving aspect ratios without an override
+    pub const ASPECT_RATIO: f64 = 16.0 / 9.0;
+    /// Frame height assumed for decorated windows whose window manager
+    /// doesn't report `_NET_FRAME_EXTENTS`
+    pub const TITLEBAR_HEIGHT: u32 = 30;
     pub const NETWORK_TIMEOUT: Duration = Duration::from_millis(100);
     pub const INPUT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+    /// An instance that exits sooner than this after launch counts as a crash
+    pub const EARLY_CRASH_WINDOW: Duration = Duration::from_secs(20);
+    pub const MAX_INSTANCE_RESTARTS: u32 = 3;
+    /// How long game instances get to exit after SIGTERM before they are killed
+    pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+    /// How long each player has to press a button when assigning devices by button
+    pub const BUTTON_ASSIGN_TIMEOUT: Duration = Duration::from_secs(30);
 }
 
 /// Utility functions
@@ -72,6 +98,92 @@ pub mod utils {
         Ok(())
     }
 
+    /// Replace the file at `path` with `contents` without ever leaving it
+    /// half-written: the data goes to a uniquely named file next to it, is
+    /// synced to disk, and is then renamed over `path`, which is atomic on
+    /// the same filesystem. The directory is synced too, so the rename
+    /// survives a crash. Two writers never share a temporary file.
+    pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut prefix = std::ffi::OsString::from(".");
+        prefix.push(path.file_name().unwrap_or_default());
+        let mut file = tempfile::Builder::new()
+            .prefix(&prefix)
+            .suffix(".tmp")
+            // What File::create would give it, rather than tempfile's 0600
+            .permissions(std::fs::Permissions::from_mode(0o666))
+            .tempfile_in(dir)?;
+        file.write_all(contents)?;
+        file.as_file().sync_all()?;
+        file.persist(path).map_err(|e| e.error)?;
+        std::fs::File::open(dir)?.sync_all()
+    }
+
+    /// Expand a user-supplied path: a leading `~` becomes the home directory,
+    /// `$VAR` and `${VAR}` are replaced from the environment (unset variables
+    /// are left as written), and relative paths are resolved against the
+    /// current directory.
+    pub fn expand_path(raw: &str) -> PathBuf {
+        let mut expanded = String::with_capacity(raw.len());
+        let mut chars = raw.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                expanded.push(c);
+                continue;
+            }
+            let braced = chars.peek() == Some(&'{');
+            if braced {
+                chars.next();
+            }
+            let mut name = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_ascii_alphanumeric() || next == '_' {
+                    name.push(next);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let closed = braced && chars.peek() == Some(&'}');
+            if closed {
+                chars.next();
+            }
+            match std::env::var(&name) {
+                Ok(value) if !name.is_empty() && (closed || !braced) => expanded.push_str(&value),
+                _ => {
+                    expanded.push('$');
+                    if braced {
+                        expanded.push('{');
+                    }
+                    expanded.push_str(&name);
+                    if closed {
+                        expanded.push('}');
+                    }
+                }
+            }
+        }
+
+        let path = match expanded.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => match dirs::home_dir() {
+                Some(home) => home.join(rest.trim_start_matches('/')),
+                None => PathBuf::from(&expanded),
+            },
+            _ => PathBuf::from(&expanded),
+        };
+
+        if path.is_relative() {
+            if let Ok(cwd) = std::env::current_dir() {
+                return cwd.join(path);
+            }
+        }
+        path
+    }
+
     /// Validate that a file exists and is executable
     pub fn validate_executable(path: &Path) -> Result<()> {
         if !path.exists() {
@@ -102,4 +214,164 @@ pub mod utils {
 
         Ok(())
     }
+
+    /// Validate a game executable and return the path to launch.
+    ///
+    /// Symlinks are followed to the real file. Windows binaries are accepted
+    /// as-is for Proton, and a script with a valid shebang is accepted without
+    /// the executable bit (with a warning).
+    pub fn resolve_executable(path: &Path) -> Result<PathBuf> {
+        let link_metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(HydraError::validation(format!(
+                    "Executable not found: {}",
+                    path.display()
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let resolved = if link_metadata.file_type().is_symlink() {
+            std::fs::canonicalize(path).map_err(|_| {
+                HydraError::validation(format!(
+                    "Broken symlink: {} points to a file that does not exist",
+                    path.display()
+                ))
+            })?
+        } else {
+            path.to_path_buf()
+        };
+
+        let metadata = std::fs::metadata(&resolved)?;
+        if metadata.is_dir() {
+            return Err(HydraError::validation(format!(
+                "Path is a directory, not an executable: {}",
+                path.display()
+            )));
+        }
+
+        // Windows binaries run through Proton and need no exec bit.
+        if is_windows_binary(&resolved) {
+            return Ok(resolved);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if metadata.permissions().mode() & 0o111 == 0 {
+                if !has_valid_shebang(&resolved) {
+                    return Err(HydraError::validation(format!(
+                        "File is not executable: {}",
+                        path.display()
+                    )));
+                }
+                log::warn!(
+                    "{} is a script without the executable bit; run `chmod +x` on it if launching fails",
+                    resolved.display()
+                );
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn is_windows_binary(path: &Path) -> bool {
+        let windows_ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "exe" | "bat" | "msi"));
+        windows_ext || read_prefix(path, 2).is_some_and(|magic| magic == b"MZ")
+    }
+
+    /// True if the file starts with `#!` followed by an interpreter that exists.
+    fn has_valid_shebang(path: &Path) -> bool {
+        let Some(prefix) = read_prefix(path, 256) else {
+            return false;
+        };
+        let Some(line) = prefix.strip_prefix(b"#!") else {
+            return false;
+        };
+        let line = String::from_utf8_lossy(line);
+        let interpreter = line.lines().next().unwrap_or("").split_whitespace().next();
+        interpreter.is_some_and(|interpreter| Path::new(interpreter).is_file())
+    }
+
+    fn read_prefix(path: &Path, len: usize) -> Option<Vec<u8>> {
+        use std::io::Read;
+        let mut buf = Vec::with_capacity(len);
+        std::fs::File::open(path)
+            .ok()?
+            .take(len as u64)
+            .read_to_end(&mut buf)
+            .ok()?;
+        Some(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::utils::{resolve_executable, write_atomic};
+    use std::fs;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use tempfile::tempdir;
+
+    fn error_message(result: crate::Result<std::path::PathBuf>) -> String {
+        result.expect_err("expected a validation error").to_string()
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_the_file() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+        assert!(write_atomic(&temp_dir.path().join("missing/config.toml"), b"x").is_err());
+    }
+
+    #[test]
+    fn test_resolve_executable_follows_symlinks() {
+        let temp_dir = tempdir().unwrap();
+        let real = temp_dir.path().join("game.x86_64");
+        fs::write(&real, b"\x7fELF").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o755)).unwrap();
+        let link = temp_dir.path().join("game");
+        symlink(&real, &link).unwrap();
+
+        assert_eq!(resolve_executable(&link).unwrap(), real.canonicalize().unwrap());
+
+        let broken = temp_dir.path().join("broken");
+        symlink(temp_dir.path().join("missing"), &broken).unwrap();
+        assert!(error_message(resolve_executable(&broken)).contains("Broken symlink"));
+    }
+
+    #[test]
+    fn test_resolve_executable_error_cases() {
+        let temp_dir = tempdir().unwrap();
+        assert!(error_message(resolve_executable(&temp_dir.path().join("missing"))).contains("not found"));
+        assert!(error_message(resolve_executable(temp_dir.path())).contains("is a directory"));
+
+        let data = temp_dir.path().join("data.bin");
+        fs::write(&data, b"not a program").unwrap();
+        fs::set_permissions(&data, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(error_message(resolve_executable(&data)).contains("not executable"));
+    }
+
+    #[test]
+    fn test_resolve_executable_accepts_scripts_and_windows_binaries() {
+        let temp_dir = tempdir().unwrap();
+        let script = temp_dir.path().join("start.sh");
+        fs::write(&script, b"#!/bin/sh\nexec ./game\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(resolve_executable(&script).unwrap(), script);
+
+        let exe = temp_dir.path().join("Game.EXE");
+        fs::write(&exe, b"MZ").unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(resolve_executable(&exe).unwrap(), exe);
+    }
 }
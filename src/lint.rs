@@ -0,0 +1,310 @@
+//! Configuration Linting
+//!
+//! `hydra-coop-launcher lint` checks the config against this machine before
+//! anything is launched. Beyond `Config::validate`, it looks at what only
+//! shows up at launch time: games that were moved, controllers that aren't
+//! plugged in, ports that clash, a runtime that isn't installed, a layout
+//! without room for every player, and separation settings that cancel out.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, UdpSocket};
+use crate::config::{Config, ConfigError, ValidationError};
+use crate::game_detection::{GameDetector, GameProfile, MultiInstanceSupport};
+use crate::ports::PortStrategy;
+use crate::universal_launcher::WindowsRuntime;
+use crate::window_manager::Layout;
+
+/// How serious a [`Finding`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Launching will fail or a player won't be able to play
+    Error,
+    /// Launching works, but probably not the way it was meant to
+    Warning,
+}
+
+/// One problem `lint` found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    fn error(message: impl Into<String>) -> Self {
+        Finding { severity: Severity::Error, message: message.into() }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Finding { severity: Severity::Warning, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}", label, self.message)
+    }
+}
+
+/// What `lint` checks the config against
+#[derive(Debug, Clone, Default)]
+pub struct SystemState {
+    /// Names of the input devices that can be opened right now
+    pub input_devices: Vec<String>,
+    pub proton_found: bool,
+    pub wine_found: bool,
+    /// Configured ports something else already holds
+    pub ports_in_use: Vec<u16>,
+    /// What detection makes of the primary game, if it exists
+    pub profile: Option<GameProfile>,
+}
+
+impl SystemState {
+    /// Looks around this machine for what `config` needs. `input_devices`
+    /// comes from the caller, which already knows how to enumerate them.
+    pub fn probe(config: &Config, input_devices: Vec<String>) -> Self {
+        let ports_in_use = configured_ports(config)
+            .into_iter()
+            .filter(|&port| UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).is_err())
+            .collect();
+        let profile = config
+            .primary_game_path()
+            .filter(|path| path.is_file())
+            .and_then(|path| GameDetector::new().detect_game(path).ok());
+        SystemState {
+            input_devices,
+            proton_found: crate::proton_integration::find_proton_path().is_ok(),
+            wine_found: crate::wine_integration::find_wine_path().is_ok(),
+            ports_in_use,
+            profile,
+        }
+    }
+}
+
+/// Everything wrong with `config` on a machine in `system`'s state, errors first.
+pub fn lint(config: &Config, system: &SystemState) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    check_validation(config, &mut findings);
+    check_game_paths(config, &mut findings);
+    check_inputs(config, system, &mut findings);
+    check_ports(config, system, &mut findings);
+    check_runtime(config, system, &mut findings);
+    check_layout(config, &mut findings);
+    check_separation(config, system, &mut findings);
+    findings.sort_by_key(|finding| finding.severity);
+    findings
+}
+
+/// `Config::validate`'s verdict. A missing game is reported by
+/// `check_game_paths` with more detail.
+fn check_validation(config: &Config, findings: &mut Vec<Finding>) {
+    match config.validate() {
+        Ok(()) | Err(ConfigError::Validation(ValidationError::InvalidGamePath(_))) => {}
+        Err(e) => findings.push(Finding::error(e.to_string())),
+    }
+}
+
+fn check_game_paths(config: &Config, findings: &mut Vec<Finding>) {
+    for (i, path) in config.game_paths.iter().enumerate() {
+        if path.is_file() {
+            continue;
+        }
+        if i == 0 {
+            findings.push(Finding::error(format!("The game {} does not exist; pick it again", path.display())));
+        } else {
+            findings.push(Finding::warning(format!(
+                "The recently used game {} no longer exists",
+                path.display()
+            )));
+        }
+    }
+}
+
+fn check_inputs(config: &Config, system: &SystemState, findings: &mut Vec<Finding>) {
+    if system.input_devices.is_empty() {
+        if !config.allow_no_input {
+            findings.push(Finding::error(
+                "No usable input devices found; check access to /dev/input, or set allow_no_input = true",
+            ));
+        }
+        return;
+    }
+    for (i, name) in config.input_mappings.iter().enumerate().take(config.instances) {
+        // Auto-detection and players without input don't name a device
+        let no_device = matches!(name.to_lowercase().as_str(), "" | "auto" | "auto-detect" | "none");
+        if !no_device && !system.input_devices.contains(name) {
+            findings.push(Finding::warning(format!(
+                "Player {}'s input '{}' is not connected; plug it in or they will have no input",
+                i + 1,
+                name
+            )));
+        }
+    }
+}
+
+/// Ports the instances will use, or none when the system picks them.
+fn configured_ports(config: &Config) -> Vec<u16> {
+    match config.port_strategy() {
+        PortStrategy::Ephemeral => Vec::new(),
+        strategy => strategy.allocate(config.instances).unwrap_or_default(),
+    }
+}
+
+fn check_ports(config: &Config, system: &SystemState, findings: &mut Vec<Finding>) {
+    if !config.enable_network_emulation {
+        return;
+    }
+    let mut players_by_port: HashMap<u16, Vec<usize>> = HashMap::new();
+    for (i, port) in configured_ports(config).into_iter().enumerate() {
+        players_by_port.entry(port).or_default().push(i + 1);
+    }
+    let mut shared: Vec<(u16, Vec<usize>)> = players_by_port.into_iter().filter(|(_, players)| players.len() > 1).collect();
+    shared.sort_unstable();
+    for (port, players) in shared {
+        let players: Vec<String> = players.iter().map(usize::to_string).collect();
+        findings.push(Finding::error(format!(
+            "Players {} share network port {}; give each player its own port",
+            players.join(", "),
+            port
+        )));
+    }
+    for port in &system.ports_in_use {
+        findings.push(Finding::warning(format!(
+            "Network port {} is already in use by another program; that player's game may not be able to use it",
+            port
+        )));
+    }
+}
+
+fn check_runtime(config: &Config, system: &SystemState, findings: &mut Vec<Finding>) {
+    match config.runtime() {
+        WindowsRuntime::Proton if !system.proton_found => findings.push(Finding::error(
+            "Proton is selected but not installed; install it from Steam's Tools library or set PROTON_PATH",
+        )),
+        WindowsRuntime::Wine if !system.wine_found => {
+            findings.push(Finding::error("Wine is selected but not installed; install wine or set WINE_PATH"))
+        }
+        _ => {}
+    }
+}
+
+fn check_layout(config: &Config, findings: &mut Vec<Finding>) {
+    let layout = config.window_layout.layout();
+    let places = match layout {
+        Layout::Grid2x2 => 4,
+        Layout::Grid3x1 => 3,
+        Layout::Grid { cols, rows } => cols as usize * rows as usize,
+        _ => return,
+    };
+    if config.instances > places {
+        findings.push(Finding::warning(format!(
+            "The {} layout has room for {} windows but {} players are configured; the extra windows will cover others",
+            layout.name(),
+            places,
+            config.instances
+        )));
+    }
+}
+
+fn check_separation(config: &Config, system: &SystemState, findings: &mut Vec<Finding>) {
+    if !config.run_as_users.is_empty() {
+        if config.isolate_input {
+            findings.push(Finding::warning(
+                "isolate_input has no effect together with run_as_users; every player's game will see every input device",
+            ));
+        }
+        if config.firejail.as_ref().is_some_and(|firejail| firejail.private_home) {
+            findings.push(Finding::warning(
+                "run_as_users already gives each player a home directory; firejail's private_home replaces it with an empty one",
+            ));
+        }
+    }
+    let Some(profile) = &system.profile else { return };
+    for blocker in &profile.instance_blockers {
+        findings.push(Finding::warning(blocker.to_string()));
+    }
+    if profile.multi_instance_support == MultiInstanceSupport::Unsupported && config.run_as_users.is_empty() {
+        findings.push(Finding::warning(
+            "This game probably allows only one copy per user; set run_as_users to run each player's copy as a different user",
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn messages(findings: &[Finding], severity: Severity) -> Vec<&str> {
+        findings.iter().filter(|finding| finding.severity == severity).map(|finding| finding.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_lint_clean_config() {
+        let temp_dir = tempdir().unwrap();
+        let game = temp_dir.path().join("game.x86_64");
+        fs::write(&game, b"\x7fELF").unwrap();
+        let mut config = Config::default_config();
+        config.select_game_path(game);
+        config.set_instances(3);
+        config.input_mappings = vec!["Pad A".to_string(), "Auto-detect".to_string(), "none".to_string()];
+        let system = SystemState { input_devices: vec!["Pad A".to_string()], ..SystemState::default() };
+        assert_eq!(lint(&config, &system), Vec::new());
+    }
+
+    #[test]
+    fn test_lint_reports_problems() {
+        let temp_dir = tempdir().unwrap();
+        let game = temp_dir.path().join("game.exe");
+        fs::write(&game, b"MZ").unwrap();
+        let mut config = Config::default_config();
+        config.game_paths = vec![game, temp_dir.path().join("old-game")];
+        config.set_instances(3);
+        config.input_mappings = vec!["Pad A".to_string(), "Pad B".to_string(), "auto".to_string()];
+        config.port_strategy = Some(PortStrategy::Fixed(vec![7777, 7777, 7778]));
+        config.set_runtime(WindowsRuntime::Proton);
+        config.window_layout = crate::config::LayoutConfig::Grid { cols: 1, rows: 2 };
+        config.run_as_users = vec!["p1".to_string(), "p2".to_string(), "p3".to_string()];
+        config.isolate_input = true;
+        let system = SystemState {
+            input_devices: vec!["Pad A".to_string()],
+            ports_in_use: vec![7778],
+            ..SystemState::default()
+        };
+
+        let findings = lint(&config, &system);
+        assert_eq!(
+            messages(&findings, Severity::Error),
+            vec![
+                "Players 1, 2 share network port 7777; give each player its own port",
+                "Proton is selected but not installed; install it from Steam's Tools library or set PROTON_PATH",
+            ]
+        );
+        let warnings = messages(&findings, Severity::Warning);
+        assert_eq!(warnings.len(), 5, "{:?}", warnings);
+        assert!(warnings[0].contains("old-game no longer exists"));
+        assert!(warnings[1].starts_with("Player 2's input 'Pad B' is not connected"));
+        assert!(warnings[2].starts_with("Network port 7778 is already in use"));
+        assert!(warnings[3].starts_with("The grid layout has room for 2 windows but 3 players"));
+        assert!(warnings[4].starts_with("isolate_input has no effect together with run_as_users"));
+    }
+
+    #[test]
+    fn test_lint_missing_game_and_devices() {
+        let mut config = Config::default_config();
+        config.game_paths = vec!["/nonexistent/game".into()];
+        let findings = lint(&config, &SystemState::default());
+        assert_eq!(
+            messages(&findings, Severity::Error),
+            vec![
+                "The game /nonexistent/game does not exist; pick it again",
+                "No usable input devices found; check access to /dev/input, or set allow_no_input = true",
+            ]
+        );
+    }
+}
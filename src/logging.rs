@@ -1,5 +1,6 @@
 use log::{LevelFilter, SetLoggerError};
 use std::env;
+use std::sync::mpsc::Sender;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Initialise the logging system.
@@ -8,6 +9,16 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 /// If `LOG_PATH` is set, log output is written to **both** stdout and that file
 /// (append mode, created automatically with parent directories).
 pub fn init() -> Result<(), SetLoggerError> {
+    init_with(std::io::stdout().into())
+}
+
+/// Like [`init`], but log lines are sent to `sink` instead of stdout, for
+/// screens that draw the whole terminal themselves. `LOG_PATH` still applies.
+pub fn init_to_channel(sink: Sender<String>) -> Result<(), SetLoggerError> {
+    init_with(sink.into())
+}
+
+fn init_with(output: fern::Output) -> Result<(), SetLoggerError> {
     let log_level_str = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
     let level = parse_level(&log_level_str);
 
@@ -29,7 +40,7 @@ pub fn init() -> Result<(), SetLoggerError> {
     let mut dispatch = fern::Dispatch::new()
         .format(fmt)
         .level(level)
-        .chain(std::io::stdout());
+        .chain(output);
 
     if let Ok(path_str) = env::var("LOG_PATH") {
         // Ensure the parent directory exists before opening the file.
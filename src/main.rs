@@ -4,54 +4,71 @@
 //!  1. Initialize logging (respecting --debug / RUST_LOG).
 //!  2. Parse CLI arguments.
 //!  3. Load user configuration (config.toml) and adaptive config.
-//!  4. Dispatch to GUI (default) or CLI mode.
+//!  4. Dispatch to GUI (default), CLI mode, the terminal UI, or a replay of a
+//!     session manifest.
 //!  5. In either mode, run_core_logic() launches instances, starts the
 //!     network emulator, arranges windows, and begins input multiplexing.
 
-mod adaptive_config;
-mod cli;
-mod config;
-mod errors;
-mod game_detection;
 mod gui;
-mod input_mux;
-mod logging;
-mod net_emulator;
-mod proton_integration;
-mod universal_launcher;
-mod window_manager;
-
-use std::collections::HashMap;
+mod tui;
+
+// Everything but the two front ends comes from the library crate, so the
+// binary and the library share one copy of each module.
+use hydra_coop_launcher::{
+    adaptive_config, bench, cli, config, control, defaults, display, errors, flatpak, game_detection, input_mux,
+    instance, lint, logging, net_emulator, orphans, ports, proton_integration, session, universal_launcher, utils,
+    window_manager, wine_integration, APP_NAME, APP_VERSION,
+};
+
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 use std::{env, io, thread};
 
 use clap::ArgMatches;
 use log::{debug, error, info, warn};
 
-use config::Config;
+use adaptive_config::AdaptiveConfigManager;
+use config::{Config, ConfigError, ProfileStore};
+use control::{ControlCommand, ControlServer, SessionControl};
+use display::SessionKind;
 use errors::{HydraError, Result};
-use input_mux::{InputAssignment, InputMux};
+use game_detection::{GameConfiguration, GameDetector, NetworkMode};
+use input_mux::{CapabilityReport, InputAssignment, InputBackendKind, InputMux, SyncMode};
 use logging::init as init_logging;
 use net_emulator::NetEmulator;
-use universal_launcher::UniversalLauncher;
-use window_manager::{Layout, WindowManager};
+use instance::InstanceContext;
+use session::SessionManifest;
+use universal_launcher::{UniversalLauncher, WindowsRuntime};
+use window_manager::{Hotkey, Layout, WindowManager, WindowSearchTiming};
+
+/// What `run_core_logic` leaves running for `supervise_session`: the relay,
+/// the input router, the instances, the window manager kept for the session
+/// if it is needed, and the launch still to be recorded as a success
+pub(crate) type Session = (NetEmulator, InputMux, UniversalLauncher, Option<WindowManager>, Option<PendingSuccess>);
 
 /// Launches instances, wires up the virtual network, arranges windows, and
 /// starts input multiplexing. Callable from both CLI and GUI entry points.
+/// The window manager is returned when windows were kept on top, are
+/// switched with the tabbed layout's hotkey, or carry player labels, so the
+/// caller can keep the labels up and release the windows at shutdown.
+/// `replay` is the configuration of a recorded session to launch the game
+/// with again, see `--from-session`.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn run_core_logic(
     game_executable_path: &Path,
     num_instances: usize,
     input_assignments: &[(usize, InputAssignment)],
     layout: Layout,
-    use_proton: bool,
+    runtime: WindowsRuntime,
     config: &Config,
-) -> Result<(NetEmulator, InputMux, UniversalLauncher)> {
+    replay: Option<&GameConfiguration>,
+    cancel: &Arc<AtomicBool>,
+) -> Result<Session> {
     if num_instances == 0 {
         return Err(HydraError::validation(
             "Number of instances must be at least 1",
@@ -70,56 +87,726 @@ pub(crate) fn run_core_logic(
         num_instances,
         game_executable_path.display()
     );
-    debug!("layout={:?} use_proton={} assignments={:?}", layout, use_proton, input_assignments);
+    debug!("layout={:?} runtime={:?} assignments={:?}", layout, runtime, input_assignments);
+
+    let session_kind = display::detect_session();
+    debug!("Display session: {}", session_kind);
+
+    // Follow symlinks so the game runs from (and is identified by) its real location.
+    let resolved_executable = crate::utils::resolve_executable(game_executable_path)?;
+    let game_executable_path = resolved_executable.as_path();
+
+    // Don't start games nobody could control.
+    check_input_devices(config)?;
 
     // Launch game instances via the universal launcher (handles Proton wineprefixes internally).
     let mut launcher = UniversalLauncher::new();
-    let pids = launcher.launch_game_instances(game_executable_path, num_instances, use_proton)?;
+    if config.use_goldberg {
+        launcher.set_goldberg_path(config.goldberg_path.clone());
+    }
+    launcher.set_run_as_users(config.run_as_users.clone());
+    launcher.set_instance_args(config.per_instance_args.clone());
+    launcher.set_replay(replay.cloned());
+    launcher.set_loopback_per_instance(config.loopback_per_instance && config.enable_network_emulation);
+    launcher.set_firejail(config.firejail.clone());
+    launcher.set_launch_policy(config.launch_policy);
+    let reserved_ports = config.port_strategy().reserve(num_instances)?;
+    let ports: Vec<u16> = reserved_ports.iter().map(|reserved| reserved.port).collect();
+    debug!("Instance ports: {:?}", ports);
+    launcher.set_ports(reserved_ports);
+    launcher.set_already_running_policy(config.already_running);
+    launcher.set_launch_concurrency(config.launch_concurrency, Duration::from_millis(config.launch_delay_ms));
+    launcher.set_proton_wait(Duration::from_secs(config.proton_wait_secs));
+    launcher.set_shutdown_grace(Duration::from_secs(config.shutdown_grace_secs));
+    launcher.set_cancel_flag(cancel.clone());
+    if config.proton_log {
+        launcher.set_proton_log_dir(proton_log_dir(config));
+    }
+    match crate::utils::get_data_dir() {
+        Ok(dir) => launcher.set_prefix_index_dir(Some(dir)),
+        Err(e) => warn!("Not recording WINEPREFIXes for `prefixes`: {}", e),
+    }
+    launcher.set_hooks(config.pre_launch_hook.clone(), config.post_launch_hook.clone());
+    launcher.set_instance_copy_patterns(config.instance_copy_include.clone(), config.instance_copy_exclude.clone());
+    if config.match_layout_resolution {
+        launcher.set_instance_resolutions(layout_resolutions(config, layout, num_instances, session_kind));
+    }
+    let profile = launcher.detect_profile(game_executable_path)?;
 
-    // Initialise the virtual network emulator and register each instance.
-    let mut net_emulator = NetEmulator::new();
-    let mut emulator_ports: HashMap<u8, u16> = HashMap::new();
-    for (i, pid) in pids.iter().enumerate() {
-        let id = i as u8;
-        match net_emulator.add_instance(id) {
-            Ok(port) => {
-                emulator_ports.insert(id, port);
-                debug!("Instance {} (pid {}) bound to emulator port {}", id, pid, port);
+    // Overlay what earlier launches of this game taught us, if confident enough.
+    let mut adaptive = open_adaptive_store(config)
+        .and_then(|mut store| match store.game_id_for(game_executable_path) {
+            Ok(game_id) => Some((store, game_id)),
+            Err(e) => {
+                warn!("Could not identify game for adaptive learning: {}", e);
+                None
             }
-            Err(e) => error!("Failed to register instance {} in net emulator: {}", id, e),
+        });
+    if let Some((store, game_id)) = &adaptive {
+        match store.get_recommended_config(game_id, &profile) {
+            Some(recommendation) if recommendation.confidence >= config.adaptive_confidence_threshold => {
+                info!(
+                    "Applying adaptive recommendation (confidence {:.2}, threshold {:.2})",
+                    recommendation.confidence, config.adaptive_confidence_threshold
+                );
+                launcher.set_recommendation(Some(recommendation));
+            }
+            Some(recommendation) => info!(
+                "Adaptive recommendation below threshold (confidence {:.2}, threshold {:.2}); using detected defaults",
+                recommendation.confidence, config.adaptive_confidence_threshold
+            ),
+            None => debug!("No adaptive recommendation for {}", game_id),
         }
     }
 
-    // Route traffic destined for each instance's configured game port to that
-    // instance's emulator socket on localhost.
-    for j in 0..num_instances {
-        if let (Some(&emulator_port), Some(&game_port)) =
-            (emulator_ports.get(&(j as u8)), config.network_ports.get(j))
-        {
-            let from: SocketAddr = format!("127.0.0.1:{}", game_port)
-                .parse()
-                .expect("invalid game address");
-            let to: SocketAddr = format!("127.0.0.1:{}", emulator_port)
-                .parse()
-                .expect("invalid emulator address");
-            debug!("Mapping {} -> {}", from, to);
-            net_emulator.add_mapping(from, to);
+    // Create the players' virtual devices before the games start, so they
+    // are numbered in player order and each game can be told which is its own.
+    let mut input_mux = InputMux::new();
+    input_mux.enumerate_devices()?;
+    input_mux.set_device_naming(&config.virtual_device_name_template, config.mimic_controller_names);
+    input_mux.set_session_id(launcher.session_id());
+    if let Some(ms) = config.input_poll_timeout_ms {
+        input_mux.set_poll_timeout(Duration::from_millis(ms));
+    }
+    input_mux.set_sync_mode(config.input_sync);
+    input_mux.set_host_reserved_keys(config.host_reserved_keys.iter().filter_map(|key| key.parse().ok()));
+    input_mux.assign_devices(input_assignments);
+    input_mux.create_virtual_devices(num_instances)?;
+    for instance in 0..num_instances {
+        if let Some(name) = input_mux.get_virtual_device_name(instance) {
+            match input_mux.get_virtual_device_sysname(instance) {
+                Some(sysname) => info!("Player {} input: virtual device '{}' ({})", instance + 1, name, sysname),
+                None => info!("Player {} input: virtual device '{}'", instance + 1, name),
+            }
         }
     }
-    net_emulator.start_relay()?;
+    if config.per_instance_controllers {
+        launcher.set_instance_env((0..num_instances).map(|instance| input_mux.controller_env(instance)).collect());
+    }
+    if config.isolate_input {
+        launcher.set_input_isolation(
+            (0..num_instances)
+                .map(|instance| input_mux.virtual_controller(instance).and_then(|controller| controller.node.clone()))
+                .collect(),
+        );
+    }
+
+    let launch_started = Instant::now();
+    let report = match launcher.launch_game_instances(game_executable_path, num_instances, runtime) {
+        // Cancelling can cut a launch short in ways that look like failures
+        Err(e) if cancel.load(Ordering::SeqCst) => {
+            debug!("Launch ended after being cancelled: {}", e);
+            info!("Launch cancelled; stopping the instances started so far.");
+            launcher.shutdown_instances();
+            return Err(HydraError::Cancelled);
+        }
+        result => result?,
+    };
+    for (instance_id, e) in &report.failures {
+        warn!("Player {} is sitting this session out: {}", instance_id + 1, e);
+    }
+    if cancel.load(Ordering::SeqCst) {
+        info!("Launch cancelled; stopping the instances started so far.");
+        launcher.shutdown_instances();
+        return Err(HydraError::Cancelled);
+    }
+    let launch_time = launch_started.elapsed();
+
+    let mut instances = instance::build_contexts(
+        launcher
+            .instances()
+            .iter()
+            .map(|inst| (inst.id, inst.pid(), inst.working_dir.clone())),
+        input_assignments,
+        &ports,
+        config,
+    )?;
+    for instance in &mut instances {
+        instance.virtual_controller = input_mux.virtual_controller(instance.id).cloned();
+    }
+    let pids = instance::pids(&instances);
+
+    // Games that don't use raw UDP, or with emulation switched off, get an
+    // idle emulator so shutdown stays uniform.
+    let mut net_emulator = NetEmulator::new();
+    net_emulator.set_degraded_flag(input_mux.degraded_flag());
+    match profile.network_mode {
+        _ if !config.enable_network_emulation => info!("Network emulation is disabled; skipping the network emulator."),
+        NetworkMode::RawUdp => start_net_emulator(&mut net_emulator, &mut instances, config)?,
+        NetworkMode::Steamworks => {
+            info!("Game uses Steamworks networking; skipping the network emulator.");
+            if !config.use_goldberg {
+                info!(
+                    "For LAN play between instances, set goldberg_path and use_goldberg \
+                     in the config to stage the Goldberg Steam emulator."
+                );
+            }
+        }
+        NetworkMode::None => info!("Game needs no networking; skipping the network emulator."),
+    }
 
     // Arrange game windows according to the selected layout.
-    let window_manager = WindowManager::new()?;
-    window_manager.set_layout(&pids, layout)?;
+    let mut kept_window_manager = None;
+    let mut game_windows = Vec::new();
+    let mut windows_placed = true;
+    match session_kind.require_window_management() {
+        _ if config.headless => info!("Headless mode: skipping window layout."),
+        Ok(()) => {
+            let mut window_manager = open_window_manager(config, layout)?;
+            window_manager.set_keep_above(config.keep_above);
+            window_manager.set_layout_failure_policy(config.on_layout_failure);
+            window_manager.set_cancel_flag(cancel.clone());
+            // Look for the windows the way that worked last time first
+            window_manager.set_preferred_match_strategy(
+                adaptive.as_ref().and_then(|(store, game_id)| store.window_match_strategy(game_id)),
+            );
+            let dims = layout == Layout::Tabbed && config.dim_unfocused_opacity.is_some();
+            if !config.window_opacity.is_empty() || dims {
+                match window_manager.compositor_running() {
+                    Ok(true) => {}
+                    Ok(false) => warn!("Window opacity needs a compositor and none is running; the game windows will stay opaque."),
+                    Err(e) => debug!("Could not check for a compositor: {}", e),
+                }
+            }
+            let title_markers: Option<Vec<String>> =
+                instances.iter().map(|instance| instance.title_marker.clone()).collect();
+            let placed = match title_markers {
+                Some(title_markers) => window_manager.set_layout_with_titles(&pids, &title_markers, layout),
+                None => window_manager.set_layout(&pids, layout),
+            };
+            let unplaced = match placed {
+                Ok(unplaced) => unplaced,
+                Err(e) => {
+                    let err = HydraError::from_window_search(e, window_manager.search_timing().timeout);
+                    if matches!(err, HydraError::Cancelled) {
+                        info!("Launch cancelled while waiting for game windows; stopping the instances.");
+                        if let Err(e) = net_emulator.stop_relay() {
+                            error!("Error stopping network relay: {e}");
+                        }
+                        launcher.shutdown_instances();
+                    }
+                    return Err(err);
+                }
+            };
+            for instance in instances.iter().filter(|instance| unplaced.contains(&instance.pid)) {
+                warn!("Player {}'s window (PID {}) was not found in time; it was left where the game put it.", instance.id + 1, instance.pid);
+            }
+            windows_placed = unplaced.is_empty();
+            if let (Some((store, game_id)), Some(strategy)) = (&mut adaptive, window_manager.match_strategy()) {
+                if let Err(e) = store.record_window_match(game_id, strategy) {
+                    warn!("Could not record how the game windows were found: {}", e);
+                }
+            }
+            if log::log_enabled!(log::Level::Debug) {
+                for instance in instances.iter().filter(|instance| !unplaced.contains(&instance.pid)) {
+                    if let Ok(Some(monitor)) = window_manager.window_monitor(instance.pid) {
+                        debug!("Player {}'s window is on monitor {}", instance.id + 1, monitor + 1);
+                    }
+                }
+            }
+            if config.input_backend == InputBackendKind::XInput2 {
+                game_windows = find_game_windows(&window_manager, &instances);
+            }
+            if layout == Layout::Tabbed {
+                let started = config.tab_cycle_hotkey.parse::<Hotkey>().and_then(|hotkey| window_manager.start_tab_cycle(&hotkey));
+                if let Err(e) = started {
+                    warn!("Game windows can't be switched with a hotkey: {}", e);
+                }
+            }
+            if config.keep_above || layout == Layout::Tabbed || config.label_windows {
+                kept_window_manager = Some(window_manager);
+            }
+        }
+        Err(e) => warn!("Skipping window layout: {}.", e),
+    }
 
-    // Initialise the input multiplexer and begin routing events.
-    let mut input_mux = InputMux::new();
-    input_mux.enumerate_devices()?;
-    input_mux.create_virtual_devices(num_instances)?;
-    input_mux.capture_events(input_assignments)?;
+    // Begin routing events to the instances that started.
+    input_mux.set_backend(config.input_backend, game_windows);
+    let input_assignments = instance::input_assignments(&instances, input_assignments);
+    input_mux.capture_events(&input_assignments)?;
+    for (device, instance_index) in input_mux.current_assignments() {
+        debug!("'{}' is routed to player {}", device.name, instance_index + 1);
+    }
+
+    // Learn from this launch once the games have shown they keep running,
+    // see `PendingSuccess::settle`
+    let pending_success = match (adaptive, launcher.launch_config()) {
+        (Some((store, game_id)), Some(game_config)) if windows_placed => Some(PendingSuccess {
+            store,
+            game_id,
+            profile: profile.clone(),
+            game_config: game_config.clone(),
+            launch_time,
+            instances: launcher.instances().len(),
+            placed_at: Instant::now(),
+        }),
+        _ => None,
+    };
+
+    // Record what this session consisted of. Failing to write it is not fatal.
+    if let Some(game_config) = launcher.launch_config().cloned() {
+        let manifest = SessionManifest {
+            created_at: SessionManifest::now(),
+            launcher_pid: Some(std::process::id()),
+            game_path: game_executable_path.to_path_buf(),
+            game_hash: session::hash_file(game_executable_path).ok(),
+            profile,
+            game_config,
+            use_proton: runtime == WindowsRuntime::Proton,
+            windows_runtime: runtime,
+            layout,
+            input_assignments,
+            instances: instances.iter().map(InstanceContext::record).collect(),
+        };
+        write_session_manifest(&manifest);
+    }
 
     info!("Core logic initialised; background services running.");
-    Ok((net_emulator, input_mux, launcher))
+    Ok((net_emulator, input_mux, launcher, kept_window_manager, pending_success))
+}
+
+/// A launch to record in the adaptive store as a success once its instances
+/// have outlived the early-crash window with their windows in place
+pub(crate) struct PendingSuccess {
+    store: AdaptiveConfigManager,
+    game_id: String,
+    profile: game_detection::GameProfile,
+    game_config: game_detection::GameConfiguration,
+    launch_time: Duration,
+    /// Number of instances that were started
+    instances: usize,
+    placed_at: Instant,
+}
+
+impl PendingSuccess {
+    /// Records the launch once every instance has been running for
+    /// `EARLY_CRASH_WINDOW` since its window was placed, and gives up on it
+    /// if one exits or is restarted before then. Hands itself back while
+    /// still waiting.
+    fn settle(mut self, launcher: &UniversalLauncher, running_instances: usize) -> Option<Self> {
+        if running_instances < self.instances || launcher.instances().iter().any(|instance| instance.restarts > 0) {
+            info!("An instance exited soon after launch; not learning from this launch.");
+            return None;
+        }
+        if self.placed_at.elapsed() < defaults::EARLY_CRASH_WINDOW {
+            return Some(self);
+        }
+        if let Err(e) = self.store.record_success(self.game_id, &self.profile, &self.game_config, self.launch_time) {
+            warn!("Could not record launch success: {}", e);
+        }
+        None
+    }
+}
+
+/// Where Proton logs go: the configured directory, or `proton-logs` in the
+/// data directory rather than the home directory Proton would use.
+fn proton_log_dir(config: &Config) -> Option<PathBuf> {
+    if let Some(dir) = &config.proton_log_dir {
+        return Some(dir.clone());
+    }
+    match crate::utils::get_data_dir() {
+        Ok(dir) => Some(dir.join("proton-logs")),
+        Err(e) => {
+            warn!("Not writing Proton logs: {}", e);
+            None
+        }
+    }
+}
+
+/// The window of each instance, for focusing its player's keyboard.
+fn find_game_windows(window_manager: &WindowManager, instances: &[InstanceContext]) -> Vec<(usize, u32)> {
+    instances
+        .iter()
+        .filter_map(|instance| match window_manager.find_windows_by_pid(instance.pid) {
+            Ok(windows) => windows.first().map(|&window| (instance.id, window)),
+            Err(e) => {
+                debug!("No window found for instance {}: {}", instance.id, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Opens the adaptive store in the data directory. Returns `None` when
+/// learning is disabled or the store can't be opened.
+pub(crate) fn open_adaptive_store(config: &Config) -> Option<AdaptiveConfigManager> {
+    if !config.adaptive_enabled {
+        info!("Adaptive learning disabled; using detected defaults.");
+        return None;
+    }
+    match adaptive_store_path().and_then(AdaptiveConfigManager::new) {
+        Ok(mut store) => {
+            store.set_save_interval(Duration::from_secs(config.adaptive_save_interval_secs));
+            Some(store)
+        }
+        Err(e) => {
+            warn!("Could not load adaptive config: {}", e);
+            None
+        }
+    }
+}
+
+/// Location of the adaptive store, creating the data directory if needed.
+fn adaptive_store_path() -> Result<PathBuf> {
+    let dir = crate::utils::get_data_dir()?;
+    crate::utils::ensure_dir_exists(&dir)?;
+    Ok(dir.join(adaptive_config::ADAPTIVE_CONFIG_FILE))
+}
+
+/// `adaptive-stats` subcommand: prints what adaptive learning has recorded.
+fn run_adaptive_stats() -> Result<()> {
+    let path = adaptive_store_path()?;
+    let store = AdaptiveConfigManager::new(path.clone())?;
+    let stats = store.get_stats();
+
+    println!("Adaptive store: {}", path.display());
+    println!("Games learned:    {}", stats.total_games);
+    println!("Total successes:  {}", stats.total_successes);
+    println!("Total failures:   {}", stats.total_failures);
+    println!("Patterns learned: {}", stats.patterns_learned);
+    println!("Avg success rate: {:.1}%", stats.avg_success_rate * 100.0);
+
+    let adaptations = store.adaptations();
+    if adaptations.is_empty() {
+        return Ok(());
+    }
+    println!();
+    println!("{:<48} {:>9} {:>10} {:>16}", "GAME", "SUCCESSES", "CONFIDENCE", "LAST SUCCESS");
+    for adaptation in adaptations {
+        let days = adaptation
+            .last_success
+            .elapsed()
+            .map(|age| age.as_secs() / 86400)
+            .unwrap_or(0);
+        println!(
+            "{:<48} {:>9} {:>10.2} {:>16}",
+            adaptation.game_id,
+            adaptation.success_count,
+            store.confidence(adaptation),
+            format!("{} day(s) ago", days)
+        );
+    }
+    Ok(())
+}
+
+/// Prints the version and the tools Hydra depends on, for bug reports.
+fn run_info() -> Result<()> {
+    let found = |path: Option<PathBuf>| match path {
+        Some(path) => path.display().to_string(),
+        None => "not found".to_string(),
+    };
+
+    println!("{} {}", crate::APP_NAME, crate::APP_VERSION);
+    println!("Display server: {}", display::detect_session());
+
+    let protons = proton_integration::find_proton_installations();
+    if protons.is_empty() {
+        println!("Proton:         not found");
+    } else {
+        println!("Proton:");
+        for proton in protons {
+            let version = proton
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            println!("  {:<20} {}", version, proton.display());
+        }
+    }
+    for incomplete in proton_integration::incomplete_proton_installations() {
+        println!("  incomplete:          {} (no proton script)", incomplete.display());
+    }
+    println!("Steam running:  {}", if proton_integration::is_steam_running() { "yes" } else { "no" });
+    println!("Wine:           {}", found(wine_integration::find_wine_path().ok()));
+
+    println!("gamescope:      {}", found(find_in_path("gamescope")));
+    println!("mangohud:       {}", found(find_in_path("mangohud")));
+    println!("firejail:       {}", found(find_in_path("firejail")));
+
+    let uinput = Path::new("/dev/uinput");
+    match std::fs::OpenOptions::new().write(true).open(uinput) {
+        Ok(_) => println!("uinput:         {} is writable", uinput.display()),
+        Err(e) => println!(
+            "uinput:         {} is not usable ({}); see Step 4 of the README",
+            uinput.display(),
+            e
+        ),
+    }
+    Ok(())
+}
+
+/// `lint`: reports what in the config would get in the way of a launch on
+/// this machine. Fails when any of it is an error.
+fn run_lint() -> Result<()> {
+    let config_path = get_config_path()?;
+    if !config_path.is_file() {
+        println!("No config file at {}; Hydra will use its defaults.", config_path.display());
+        return Ok(());
+    }
+    let config = Config::load(&config_path)?;
+    let devices = enumerate_input_devices().into_iter().map(|device| device.name).collect();
+    let findings = lint::lint(&config, &lint::SystemState::probe(&config, devices));
+
+    println!("Checked {}", config_path.display());
+    if findings.is_empty() {
+        println!("No problems found.");
+        return Ok(());
+    }
+    for finding in &findings {
+        println!("  {}", finding);
+    }
+    let errors = findings.iter().filter(|finding| finding.severity == lint::Severity::Error).count();
+    println!("{} error(s), {} warning(s)", errors, findings.len() - errors);
+    if errors > 0 {
+        return Err(HydraError::validation(format!("{} problem(s) in {} would stop a launch", errors, config_path.display())));
+    }
+    Ok(())
+}
+
+/// `bench`: floods the network relay and runs synthetic events through the
+/// input routing, then prints what they managed.
+fn run_bench(matches: &ArgMatches) -> Result<()> {
+    let options = bench::NetBenchOptions {
+        instances: *matches.get_one::<u8>("instances").expect("has a default"),
+        rate: *matches.get_one::<u32>("rate").expect("has a default"),
+        duration: Duration::from_secs(*matches.get_one::<u64>("seconds").expect("has a default")),
+        payload: usize::from(*matches.get_one::<u16>("payload").expect("has a default")),
+    };
+    println!(
+        "Network relay: {} instance(s), {} packets/s of {} bytes for {:?}",
+        options.instances, options.rate, options.payload, options.duration
+    );
+    let net = bench::bench_net(options)?;
+    println!("  sent        {} in {:?}", net.sent, net.elapsed);
+    println!("  received    {} ({:.0} packets/s)", net.received, net.throughput());
+    println!("  lost        {:.2}% (relay dropped {})", net.loss() * 100.0, net.relay.dropped);
+    println!("  latency     {}", net.latency);
+
+    let events = *matches.get_one::<usize>("input_events").expect("has a default");
+    for sync_mode in [SyncMode::Source, SyncMode::AfterKeys] {
+        let input = bench::bench_input(events, sync_mode);
+        println!("Input routing ({:?}): {} events in {} frames, {:?}", sync_mode, input.events, input.frames, input.elapsed);
+        println!("  per event   {}", input.per_event);
+    }
+    Ok(())
+}
+
+/// `--enumerate-json`: writes what evdev reports about each input device to
+/// `path`, for bug reports about devices that don't route.
+fn run_enumerate_json(path: &Path) -> Result<()> {
+    let mut mux = InputMux::new();
+    mux.enumerate_devices()?;
+    let capabilities = mux.device_capabilities();
+    let json = serde_json::to_string_pretty(&capabilities)
+        .map_err(|e| HydraError::application(format!("Could not serialize device capabilities: {e}")))?;
+    std::fs::write(path, json + "\n")?;
+    println!("Wrote the capabilities of {} input device(s) to {}", capabilities.len(), path.display());
+    Ok(())
+}
+
+/// `--export-profile`: writes the game's detected profile to `path`, refined
+/// by adaptive learning when its recommendation is confident enough.
+fn run_export_profile(config: &Config, game_executable_path: &Path, path: &Path) -> Result<()> {
+    let mut detector = GameDetector::new();
+    let mut profile = detector.detect_game(game_executable_path)?;
+    let recommendation = open_adaptive_store(config).and_then(|mut store| {
+        let game_id = store.game_id_for(game_executable_path).ok()?;
+        store.get_recommended_config(&game_id, &profile)
+    });
+    match recommendation {
+        Some(recommendation) if recommendation.confidence >= config.adaptive_confidence_threshold => {
+            recommendation.apply_to_profile(&mut profile);
+            crate::utils::write_atomic(path, profile.to_toml()?.as_bytes())?;
+            println!(
+                "Wrote the profile of {}, refined by earlier launches (confidence {:.2}), to {}",
+                game_executable_path.display(),
+                recommendation.confidence,
+                path.display()
+            );
+        }
+        _ => {
+            detector.export_profile(game_executable_path, path)?;
+            println!("Wrote the detected profile of {} to {}", game_executable_path.display(), path.display());
+        }
+    }
+    Ok(())
+}
+
+/// First executable called `name` in `PATH`.
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path).map(|dir| dir.join(name)).find(|candidate| {
+        std::fs::metadata(candidate)
+            .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    })
+}
+
+/// Writes the manifest to `session.json` in the data directory, logging
+/// rather than failing if that isn't possible.
+fn write_session_manifest(manifest: &SessionManifest) {
+    match crate::utils::get_data_dir() {
+        Ok(dir) => {
+            if let Err(e) = session::save(manifest, &dir.join(session::SESSION_FILE_NAME)) {
+                warn!("Could not write session manifest: {}", e);
+            }
+        }
+        Err(e) => warn!("Could not determine data directory for session manifest: {}", e),
+    }
+}
+
+/// Watchdog pass for the supervision loops: relaunches instances that crashed
+/// right after starting. Their new windows are moved back into their slot
+/// from a thread of their own once they open, so the loop carries on.
+fn restart_crashed_instances(
+    launcher: &mut UniversalLauncher,
+    num_instances: usize,
+    layout: Layout,
+    config: &Config,
+) {
+    let restarted = launcher.restart_early_crashes(
+        crate::defaults::EARLY_CRASH_WINDOW,
+        crate::defaults::MAX_INSTANCE_RESTARTS,
+    );
+    if restarted.is_empty() {
+        return;
+    }
+    if config.headless || !display::detect_session().supports_window_management() {
+        for (instance_id, pid) in restarted {
+            info!("Restarted instance {} as pid {}", instance_id, pid);
+        }
+        return;
+    }
+    // A relaunched window opens on top of the others; put it back in line.
+    let restack = config.restack_windows.then(|| {
+        let mut instances: Vec<(usize, u32)> = launcher.instances().iter().map(|instance| (instance.id, instance.pid())).collect();
+        instances.sort_unstable();
+        instances.into_iter().map(|(_, pid)| pid).collect::<Vec<u32>>()
+    });
+
+    // Waiting for the new windows to open would hold up the session loop.
+    let config = config.clone();
+    let placing = thread::Builder::new().name("hydra-place-restarted".to_string()).spawn(move || {
+        let mut window_manager = match open_window_manager(&config, layout) {
+            Ok(window_manager) => window_manager,
+            Err(e) => {
+                warn!("Restarted instance(s) {:?} but could not re-apply their layout slots: {}", restarted, e);
+                return;
+            }
+        };
+        window_manager.set_keep_above(config.keep_above);
+        for (instance_id, pid) in restarted {
+            match window_manager.place_window(pid, instance_id, num_instances, layout) {
+                Ok(()) => info!("Restarted instance {} as pid {}", instance_id, pid),
+                Err(e) => warn!("Restarted instance {} but could not re-apply its layout slot: {}", instance_id, e),
+            }
+        }
+        if let Some(pids) = restack {
+            if let Err(e) = window_manager.restack(&pids) {
+                debug!("Could not restack the game windows after a restart: {}", e);
+            }
+        }
+    });
+    if let Err(e) = placing {
+        warn!("Could not start placing the restarted instances' windows: {}", e);
+    }
+}
+
+/// Window sizes the instances will get in `layout`, or none when their
+/// windows won't be arranged.
+fn layout_resolutions(config: &Config, layout: Layout, num_instances: usize, session_kind: SessionKind) -> Vec<(u32, u32)> {
+    if config.headless || session_kind.require_window_management().is_err() {
+        return Vec::new();
+    }
+    match open_window_manager(config, layout).and_then(|wm| Ok(wm.slot_sizes(layout, num_instances)?)) {
+        Ok(sizes) => {
+            debug!("Starting instances at their layout sizes: {:?}", sizes);
+            sizes
+        }
+        Err(e) => {
+            warn!("Could not work out window sizes for the layout; games pick their own resolution: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Connects to the X server with the window search timing from `config` and
+/// its decoration setting for `layout`.
+pub(crate) fn open_window_manager(config: &Config, layout: Layout) -> Result<WindowManager> {
+    let defaults = WindowSearchTiming::default();
+    let mut window_manager = WindowManager::new()?;
+    window_manager.set_aspect_ratios(config.instance_aspect_ratios());
+    window_manager.set_keep_decorations(!config.removes_window_decorations(layout));
+    window_manager.set_custom_rects(config.window_layout.custom_rects().to_vec());
+    window_manager.set_label_windows(config.label_windows);
+    window_manager.set_restack_windows(config.restack_windows);
+    window_manager.set_window_opacities(config.window_opacity.clone());
+    window_manager.set_dim_unfocused(config.dim_unfocused_opacity);
+    window_manager.set_search_timing(WindowSearchTiming {
+        timeout: config.window_search_timeout_secs.map(Duration::from_secs).unwrap_or(defaults.timeout),
+        initial_backoff: config
+            .window_search_initial_backoff_ms
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.initial_backoff),
+        max_backoff: config
+            .window_search_max_backoff_ms
+            .map(Duration::from_millis)
+            .unwrap_or(defaults.max_backoff),
+    });
+    Ok(window_manager)
+}
+
+/// Binds an emulator socket for instance `net_id` and, when its game port is
+/// known, routes traffic destined for that port to the socket on localhost.
+/// With a loopback address per instance, the address alone identifies the
+/// instance, whatever port its game sends from. Returns the emulator port.
+fn add_relay_instance(
+    net_emulator: &NetEmulator,
+    net_id: u8,
+    game_port: Option<u16>,
+) -> std::result::Result<u16, net_emulator::NetEmulatorError> {
+    let emulator_port = net_emulator.add_instance(net_id)?;
+    if let Some(game_port) = game_port {
+        let instance_ip = net_emulator.instance_ip(net_id);
+        let from_port = if net_emulator.loopback_per_instance() { 0 } else { game_port };
+        let from = SocketAddr::from((instance_ip, from_port));
+        let to = SocketAddr::from((instance_ip, emulator_port));
+        debug!("Mapping {} -> {}", from, to);
+        net_emulator.add_mapping(from, to);
+    }
+    Ok(emulator_port)
+}
+
+/// Registers each launched instance with the network emulator, maps the
+/// configured game ports onto the emulator sockets, adds the routes from
+/// `net_routes_file`, and starts the relay. Records the emulator port bound
+/// for each instance.
+fn start_net_emulator(
+    net_emulator: &mut NetEmulator,
+    instances: &mut [InstanceContext],
+    config: &Config,
+) -> Result<()> {
+    net_emulator.set_loopback_per_instance(config.loopback_per_instance);
+    for instance in instances.iter_mut() {
+        match add_relay_instance(net_emulator, instance.net_id, instance.game_port) {
+            Ok(port) => {
+                instance.emulator_port = Some(port);
+                debug!("Instance {} (pid {}) bound to emulator port {}", instance.id, instance.pid, port);
+            }
+            Err(e) => error!("Failed to register instance {} in net emulator: {}", instance.id, e),
+        }
+    }
+    if let Some(path) = &config.net_routes_file {
+        net_emulator.load_routes(path)?;
+    }
+    if let Some(path) = &config.net_capture_path {
+        if let Err(e) = net_emulator.enable_capture(path) {
+            warn!("Could not start packet capture at {}: {}", path.display(), e);
+        }
+    }
+    net_emulator.start_relay()?;
+    Ok(())
 }
 
 fn main() {
@@ -138,19 +825,50 @@ fn main() {
 
 fn run_application() -> Result<()> {
     // Seed RUST_LOG before the logger is installed so --debug works immediately.
-    let debug_flag = *parse_args_for_logging().get_one("debug").unwrap_or(&false);
+    let early_matches = parse_args_for_logging();
+    let debug_flag = *early_matches.get_one("debug").unwrap_or(&false);
     if debug_flag {
         env::set_var("RUST_LOG", "debug");
     } else if env::var("RUST_LOG").is_err() {
         env::set_var("RUST_LOG", "info");
     }
 
-    init_logging().map_err(HydraError::Logging)?;
+    // The terminal UI shows the log itself; printing it would scribble over the screen.
+    let tui_logs = if *early_matches.get_one("tui").unwrap_or(&false) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        logging::init_to_channel(sender).map_err(HydraError::Logging)?;
+        Some(receiver)
+    } else {
+        init_logging().map_err(HydraError::Logging)?;
+        None
+    };
     info!("Starting {} v{}", crate::APP_NAME, crate::APP_VERSION);
 
     let matches: ArgMatches = cli::build_cli().get_matches();
+    match matches.subcommand() {
+        Some(("adaptive-stats", _)) => return run_adaptive_stats(),
+        Some(("info", _)) => return run_info(),
+        Some(("lint", _)) => return run_lint(),
+        Some(("bench", bench_matches)) => return run_bench(bench_matches),
+        Some(("ctl", ctl_matches)) => return run_ctl(ctl_matches),
+        Some(("prefixes", prefixes_matches)) => return run_prefixes(prefixes_matches),
+        Some(("layout", layout_matches)) => return run_layout(layout_matches),
+        Some(("netemu", netemu_matches)) => return run_netemu(netemu_matches),
+        _ => {}
+    }
+    if let Some(path) = matches.get_one::<String>("enumerate_json") {
+        return run_enumerate_json(&crate::utils::expand_path(path));
+    }
+    reap_orphans(tui_logs.is_none());
+    if let Some(logs) = tui_logs {
+        return run_tui_mode(&matches, logs);
+    }
+    if let Some(session_path) = matches.get_one::<String>("from_session") {
+        return run_from_session(&crate::utils::expand_path(session_path));
+    }
     let use_gui_flag = matches.get_flag("gui");
-    let cli_args_provided = matches.contains_id("game_executable");
+    let cli_args_provided =
+        matches.contains_id("game_executable") || matches.contains_id("flatpak") || matches.contains_id("profile");
 
     if use_gui_flag || !cli_args_provided {
         run_gui_mode()
@@ -159,72 +877,202 @@ fn run_application() -> Result<()> {
     }
 }
 
+fn run_tui_mode(matches: &ArgMatches, logs: std::sync::mpsc::Receiver<String>) -> Result<()> {
+    info!("Starting TUI mode.");
+
+    let mut config = load_configuration();
+    check_display_session(&config);
+    if let Some(path) = matches.get_one::<String>("game_executable") {
+        config.select_game_path(crate::utils::expand_path(path));
+    }
+    if let Some(app_id) = matches.get_one::<String>("flatpak") {
+        config.select_game_path(flatpak::resolve_app(app_id)?);
+    }
+    if let Some(n) = matches.get_one::<u32>("instances") {
+        config.set_instances(*n as usize);
+    }
+
+    tui::run_tui(enumerate_controllers(), config, logs)
+}
+
 fn run_gui_mode() -> Result<()> {
     info!("Starting GUI mode.");
 
-    let available_devices = enumerate_input_devices();
-    info!("Found {} usable input device(s).", available_devices.len());
+    let controllers = enumerate_controllers();
+    info!("Found {} usable input device(s).", controllers.len());
 
     let config = load_configuration();
+    check_display_session(&config);
 
-    gui::run_gui(available_devices, config)
-        .map_err(|e| HydraError::application(format!("GUI failed: {e}")))
+    gui::run_gui(controllers, config)
+}
+
+/// Adds `path` to the recently used games in the config file, so the GUI
+/// offers it next time. A config file that fails to load is left alone.
+pub(crate) fn remember_game(path: &Path) {
+    let Ok(config_path) = get_config_path() else { return };
+    let mut config = match Config::load(&config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            debug!("Not adding {} to the recent games: {}", path.display(), e);
+            return;
+        }
+    };
+    if config.primary_game_path().map(PathBuf::as_path) == Some(path) {
+        return;
+    }
+    config.select_game_path(path.to_path_buf());
+    if let Err(e) = config.save(&config_path) {
+        warn!("Could not add {} to the recent games: {}", path.display(), e);
+    }
 }
 
 fn run_cli_mode(matches: &ArgMatches) -> Result<()> {
     info!("Starting CLI mode.");
 
-    let game_executable_path = Path::new(
-        matches
-            .get_one::<String>("game_executable")
-            .expect("game_executable is required in CLI mode"),
-    );
-    let num_instances = *matches
-        .get_one::<u32>("instances")
-        .expect("instances is required in CLI mode") as usize;
-    let device_names: Vec<&str> = matches
-        .get_many::<String>("input_devices")
-        .map(|v| v.map(String::as_str).collect())
-        .unwrap_or_default();
-    let layout_str = matches
-        .get_one::<String>("layout")
-        .map(String::as_str)
-        .unwrap_or("horizontal");
-    let layout = Layout::from(layout_str);
+    // A profile supplies every setting; explicit flags override it field by field.
+    let profile = match matches.get_one::<String>("profile") {
+        Some(name) => Some(load_profile(name)?),
+        None => None,
+    };
+    let mut config = profile.clone().unwrap_or_else(load_configuration);
 
-    let mut config = load_configuration();
+    let game_executable_path: PathBuf = match (matches.get_one::<String>("game_executable"), matches.get_one::<String>("flatpak")) {
+        (Some(path), _) => crate::utils::expand_path(path),
+        (None, Some(app_id)) => flatpak::resolve_app(app_id)?,
+        (None, None) => config
+            .primary_game_path()
+            .cloned()
+            .ok_or_else(|| HydraError::validation("The profile has no game executable"))?,
+    };
+    // Only look through the game's folders when what was given can't be run
+    let game_executable_path = if game_executable_path.is_dir() {
+        let best = GameDetector::new()
+            .find_candidate_executables(&game_executable_path)
+            .into_iter()
+            .next()
+            .ok_or_else(|| HydraError::validation(format!("No game executable found in {}", game_executable_path.display())))?;
+        info!("Launching {}, the likeliest game in {} ({})", best.path.display(), game_executable_path.display(), best.reasons.join(", "));
+        best.path
+    } else {
+        if !game_detection::is_executable_file(&game_executable_path) {
+            if let Some(better) = GameDetector::new().suggest_executable(&game_executable_path) {
+                warn!(
+                    "{} doesn't look like a program; the game itself is probably {} ({}). Pass that with --game-executable if the launch doesn't work.",
+                    game_executable_path.display(),
+                    better.path.display(),
+                    better.reasons.join(", ")
+                );
+            }
+        }
+        game_executable_path
+    };
+    let game_executable_path = game_executable_path.as_path();
+    if let Some(path) = matches.get_one::<String>("export_profile") {
+        return run_export_profile(&config, game_executable_path, &crate::utils::expand_path(path));
+    }
+    let num_instances = match (matches.get_one::<u32>("instances"), &profile) {
+        (Some(n), _) => *n as usize,
+        (None, Some(profile)) => profile.instance_count(),
+        (None, None) => {
+            return Err(HydraError::validation("--instances is required unless --profile is given"))
+        }
+    };
+    let pressed_devices = if matches.get_flag("assign_by_button") && !matches.get_flag("print_config") {
+        Some(assign_by_button(num_instances)?)
+    } else {
+        None
+    };
+    let device_names: Vec<String> = match (&pressed_devices, matches.get_many::<String>("input_devices")) {
+        (Some(devices), _) => devices.iter().map(|device| device.name.clone()).collect(),
+        (None, Some(names)) => names.cloned().collect(),
+        (None, None) => profile
+            .as_ref()
+            .map(|p| p.input_mappings.clone())
+            .unwrap_or_default(),
+    };
+    if let Some(name) = matches.get_one::<String>("layout") {
+        config.window_layout = name.parse()?;
+    }
+    let layout = config.window_layout.layout();
 
     // Make the config consistent with the CLI inputs before validating. Without
     // this, first-time CLI runs would fail validation because the default
     // config has no game_paths, input_mappings for this player count, or ports.
-    config.game_paths = vec![game_executable_path.to_path_buf()];
-    if config.input_mappings.len() < num_instances {
-        config
-            .input_mappings
-            .resize(num_instances, "Auto-detect".to_string());
+    config.select_game_path(game_executable_path.to_path_buf());
+    config.set_instances(num_instances);
+    fill_network_ports(&mut config, num_instances);
+
+    if let Some(path) = matches.get_one::<String>("capture_packets") {
+        config.net_capture_path = Some(crate::utils::expand_path(path));
     }
-    if config.network_ports.len() < num_instances {
-        let mut next_port = config.network_ports.last().copied().unwrap_or(7776) + 1;
-        while config.network_ports.len() < num_instances {
-            config.network_ports.push(next_port);
-            next_port += 1;
-        }
+    if let Some(users) = matches.get_many::<String>("run_as") {
+        config.run_as_users = users.cloned().collect();
+    }
+    if matches.get_flag("headless") {
+        config.headless = true;
+    }
+    check_display_session(&config);
+    if matches.get_flag("no_network") {
+        config.enable_network_emulation = false;
     }
 
     config.validate()?;
-    let use_proton = matches.get_flag("proton") || config.use_proton;
 
-    // Resolve device names to identifiers.
+    // Resolve device names to identifiers. Explicit --assign entries take
+    // precedence over the positional names.
     let available_devices = enumerate_input_devices();
+    let explicit_assignments = match matches.get_one::<String>("assign") {
+        Some(spec) => input_mux::parse_assignments(spec, num_instances, &available_devices)?,
+        None => Vec::new(),
+    };
+    let runtime = if matches.get_flag("proton") {
+        WindowsRuntime::Proton
+    } else if matches.get_flag("wine") {
+        WindowsRuntime::Wine
+    } else {
+        config.runtime()
+    };
+
+    // Record in the config what this launch resolved to, for saving it as
+    // a profile and for --print-config
+    for (i, device) in device_names.iter().take(num_instances).enumerate() {
+        config.input_mappings[i] = device.clone();
+    }
+    for (i, assignment) in &explicit_assignments {
+        config.input_mappings[*i] = match assignment {
+            InputAssignment::Device(device) | InputAssignment::Mirror { device } => device.name.clone(),
+            InputAssignment::AutoDetect => "Auto-detect".to_string(),
+            InputAssignment::None => "none".to_string(),
+        };
+    }
+    config.set_runtime(runtime);
+
+    // Nothing is saved or asked for when only printing the configuration.
+    if matches.get_flag("print_config") {
+        print!("{}", config.describe_effective());
+        return Ok(());
+    }
+    if let Some(name) = matches.get_one::<String>("save_profile") {
+        profile_store()?.save_profile(name, &config)?;
+        info!("Saved profile '{}'", name);
+    }
+
     let mut assignments: Vec<(usize, InputAssignment)> = Vec::new();
     for i in 0..num_instances {
-        let assignment = match device_names.get(i) {
-            Some(&"Auto-detect") | Some(&"auto") | Some(&"auto-detect") => {
+        // Pressed devices are known exactly, even when two have the same name
+        if let Some(device) = pressed_devices.as_ref().and_then(|devices| devices.get(i)) {
+            assignments.push((i, InputAssignment::Device(device.clone())));
+            continue;
+        }
+        let assignment = match device_names.get(i).map(String::as_str) {
+            Some("Auto-detect") | Some("auto") | Some("auto-detect") => {
                 InputAssignment::AutoDetect
             }
+            Some("none") => InputAssignment::None,
             Some(name) => available_devices
                 .iter()
-                .find(|d| d.name == *name)
+                .find(|d| d.name == name)
                 .cloned()
                 .map(InputAssignment::Device)
                 .unwrap_or_else(|| {
@@ -235,45 +1083,553 @@ fn run_cli_mode(matches: &ArgMatches) -> Result<()> {
         };
         assignments.push((i, assignment));
     }
+    for (i, assignment) in explicit_assignments {
+        assignments[i] = (i, assignment);
+    }
+    if let Some(name) = matches.get_one::<String>("mirror_input") {
+        match available_devices.iter().find(|d| d.name == *name) {
+            Some(device) => assignments.push((0, InputAssignment::Mirror { device: device.clone() })),
+            None => warn!("Device '{}' not found; its input will not be mirrored", name),
+        }
+    }
 
-    let (mut net_emulator, mut input_mux, mut launcher) = run_core_logic(
+    run_until_exit(
         game_executable_path,
         num_instances,
         &assignments,
         layout,
-        use_proton,
+        runtime,
         &config,
-    )?;
+        None,
+    )
+}
 
-    info!("Running. Press Ctrl+C to shut down.");
-    let running = Arc::new(AtomicBool::new(true));
+/// Adds ports after the config's last one until every instance has one.
+pub(crate) fn fill_network_ports(config: &mut Config, num_instances: usize) {
+    let mut next_port = config.network_ports.last().copied().unwrap_or(7776) + 1;
+    while config.network_ports.len() < num_instances {
+        config.network_ports.push(next_port);
+        next_port += 1;
+    }
+}
+
+/// `--assign-by-button`: asks each player in turn to press a button and
+/// returns the devices pressed, in player order.
+fn assign_by_button(num_instances: usize) -> Result<Vec<input_mux::DeviceIdentifier>> {
+    let mut mux = InputMux::new();
+    mux.enumerate_devices()?;
+    let mut pressed: Vec<input_mux::DeviceIdentifier> = Vec::new();
+    for player in 1..=num_instances {
+        println!("Player {}, press any button on your controller or keyboard…", player);
+        let deadline = Instant::now() + crate::defaults::BUTTON_ASSIGN_TIMEOUT;
+        loop {
+            let device = mux
+                .detect_next_pressed(deadline.saturating_duration_since(Instant::now()))
+                .ok_or_else(|| HydraError::Timeout {
+                    what: format!("player {} to press a button", player),
+                    after: crate::defaults::BUTTON_ASSIGN_TIMEOUT,
+                })?;
+            match pressed.iter().position(|taken| *taken == device) {
+                Some(owner) => println!("'{}' is already player {}'s; player {}, press a button on another device.", device.name, owner + 1, player),
+                None => {
+                    println!("Player {}: {}", player, device.name);
+                    pressed.push(device);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(pressed)
+}
+
+/// Looks for processes and virtual input devices left behind by a previous
+/// session that didn't shut down cleanly, and terminates them after asking.
+/// Without a terminal to ask on, or when `ask` is false, they are only
+/// reported. Nothing is terminated unless the control socket shows that no
+/// session is running any more: the processes may belong to one started
+/// alongside this one.
+fn reap_orphans(ask: bool) {
+    let Ok(data_dir) = crate::utils::get_data_dir() else {
+        return;
+    };
+    let manifest_path = data_dir.join(session::SESSION_FILE_NAME);
+    let orphans = match session::load(&manifest_path) {
+        Ok(manifest) => orphans::find_orphans(&manifest),
+        Err(e) => {
+            debug!("No previous session to check for orphans: {}", e);
+            Vec::new()
+        }
+    };
+    let stale_devices = orphans::stale_virtual_devices();
+    if orphans.is_empty() && stale_devices.is_empty() {
+        return;
+    }
+
+    warn!("The previous session did not shut down cleanly. Still running:");
+    for orphan in &orphans {
+        warn!("  PID {}: {}", orphan.pid, orphan.description);
+    }
+    for device in &stale_devices {
+        warn!("  virtual input device '{}'", device);
+    }
+    if orphans.is_empty() {
+        warn!("The virtual devices belong to another running Hydra process; close it to remove them.");
+        return;
+    }
+    let Some(socket) = control::socket_path() else {
+        warn!("XDG_RUNTIME_DIR is not set, so Hydra can't tell whether that session is still running; end the processes manually if it isn't.");
+        return;
+    };
+    if let Ok(status) = control::send_command(&socket, ControlCommand::Status) {
+        warn!("A Hydra session is still running ({}); leaving these alone. Stop it with `hydra-coop-launcher ctl shutdown` if they are its.", status);
+        return;
+    }
+
+    use std::io::{BufRead, IsTerminal, Write};
+    if !ask || !io::stdin().is_terminal() {
+        warn!("Start Hydra from a terminal without --tui to clean these up, or end the processes manually.");
+        return;
+    }
+    print!("Terminate them before continuing? [y/N] ");
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+        info!("Leaving leftover processes running.");
+        return;
+    }
+
+    for orphan in &orphans {
+        if let Err(e) = orphans::terminate(orphan.pid) {
+            error!("Could not terminate PID {}: {}", orphan.pid, e);
+        }
+    }
+    let remaining = orphans::stale_virtual_devices();
+    if !remaining.is_empty() {
+        warn!("Virtual input devices still present: {}", remaining.join(", "));
+    }
+}
+
+/// Profiles live in `profiles/` under the config directory.
+fn profile_store() -> Result<ProfileStore> {
+    Ok(ProfileStore::new(crate::utils::get_config_dir()?.join("profiles")))
+}
+
+/// Loads a named profile, listing the available ones if it doesn't exist.
+fn load_profile(name: &str) -> Result<Config> {
+    let store = profile_store()?;
+    match store.load_profile(name) {
+        Ok(config) => {
+            info!("Loaded profile '{}'", name);
+            Ok(config)
+        }
+        Err(e @ ConfigError::ProfileNotFound(_)) => {
+            let available = store.list_profiles().unwrap_or_default();
+            if available.is_empty() {
+                warn!("No profiles have been saved yet; create one with --save-profile.");
+            } else {
+                warn!("Available profiles: {}", available.join(", "));
+            }
+            Err(e.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Re-launches the session recorded in a manifest with the same game,
+/// instance count, ports, launch arguments, environment, input assignments
+/// and layout.
+fn run_from_session(manifest_path: &Path) -> Result<()> {
+    info!("Replaying session from {}", manifest_path.display());
+    let manifest = session::load(manifest_path)?;
+
+    if let Some(recorded) = &manifest.game_hash {
+        match session::hash_file(&manifest.game_path) {
+            Ok(current) if &current != recorded => warn!(
+                "{} has changed since the session was recorded.",
+                manifest.game_path.display()
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Could not hash {}: {}", manifest.game_path.display(), e),
+        }
+    }
+
+    let num_instances = manifest.instances.len();
+    let mut config = load_configuration();
+    check_display_session(&config);
+    config.game_paths = vec![manifest.game_path.clone()];
+    config.instances = num_instances;
+    config.input_mappings = vec!["Auto-detect".to_string(); num_instances];
+    // The recorded arguments are replayed through the launcher, see `set_replay`
+    config.per_instance_args = Vec::new();
+    let recorded_ports: Vec<u16> = manifest.instances.iter().filter_map(|i| i.game_port).collect();
+    if recorded_ports.len() == num_instances {
+        config.network_ports = recorded_ports;
+        config.port_strategy = None;
+    }
+    config.validate()?;
+
+    run_until_exit(
+        &manifest.game_path,
+        num_instances,
+        &manifest.input_assignments,
+        manifest.layout,
+        manifest.runtime(),
+        &config,
+        Some(&manifest.game_config),
+    )
+}
+
+/// Runs the core logic and supervises the session until every instance has
+/// exited or Ctrl+C is pressed, then tears the background services down.
+fn run_until_exit(
+    game_executable_path: &Path,
+    num_instances: usize,
+    assignments: &[(usize, InputAssignment)],
+    layout: Layout,
+    runtime: WindowsRuntime,
+    config: &Config,
+    replay: Option<&GameConfiguration>,
+) -> Result<()> {
+    // Installed before launching so Ctrl+C also cancels a launch in progress.
+    let stop_requested = Arc::new(AtomicBool::new(false));
     {
-        let running = running.clone();
+        let stop_requested = stop_requested.clone();
         ctrlc::set_handler(move || {
             info!("Ctrl+C received; initiating shutdown.");
-            running.store(false, Ordering::SeqCst);
+            stop_requested.store(true, Ordering::SeqCst);
         })
         .expect("failed to install Ctrl-C handler");
     }
 
-    while running.load(Ordering::SeqCst) {
-        if !launcher.any_running() {
+    let session = run_core_logic(
+        game_executable_path,
+        num_instances,
+        assignments,
+        layout,
+        runtime,
+        config,
+        replay,
+        &stop_requested,
+    )?;
+    // Only games that got as far as launching end up in the recent games.
+    remember_game(game_executable_path);
+
+    info!("Running. Press Ctrl+C to shut down.");
+    supervise_session(session, num_instances, layout, config, &stop_requested, |_, _, _| {});
+    Ok(())
+}
+
+/// Supervises a session `run_core_logic` started until every instance has
+/// exited, `stop` is set or the control socket asks for a shutdown, then
+/// tears it down. Crashed instances are restarted, window labels kept up and
+/// a launch that holds up recorded for adaptive learning along the way.
+/// `on_tick` runs on every pass with the input router, the relay and the
+/// number of instances still running, for front ends that show or change
+/// the session while it runs.
+pub(crate) fn supervise_session(
+    session: Session,
+    num_instances: usize,
+    layout: Layout,
+    config: &Config,
+    stop: &AtomicBool,
+    mut on_tick: impl FnMut(&mut InputMux, &NetEmulator, usize),
+) {
+    let (mut net_emulator, mut input_mux, mut launcher, window_manager, mut pending_success) = session;
+    let control = session_control(num_instances, &input_mux);
+    let _control_server = start_control_server(&control);
+
+    while !stop.load(Ordering::SeqCst) && !control.shutdown_requested() {
+        if config.restart_on_early_crash {
+            restart_crashed_instances(&mut launcher, num_instances, layout, config);
+        }
+        refresh_window_labels(window_manager.as_ref());
+        let running_instances = launcher.running_count();
+        pending_success = pending_success.and_then(|pending| pending.settle(&launcher, running_instances));
+        control.set_running_instances(running_instances);
+        on_tick(&mut input_mux, &net_emulator, running_instances);
+        if running_instances == 0 {
             info!("All game instances exited; shutting down.");
             break;
         }
         thread::sleep(Duration::from_millis(250));
     }
 
+    stop_session(&mut net_emulator, &mut input_mux, &mut launcher, window_manager);
+}
+
+/// Puts the player labels back on game windows whose game retitled them.
+fn refresh_window_labels(window_manager: Option<&WindowManager>) {
+    if let Some(window_manager) = window_manager {
+        if let Err(e) = window_manager.reapply_window_labels() {
+            debug!("Could not refresh the game windows' player labels: {}", e);
+        }
+    }
+}
+
+/// Tears down what `run_core_logic` started: the relay, input capture,
+/// always-on-top windows, and finally the instances.
+fn stop_session(
+    net_emulator: &mut NetEmulator,
+    input_mux: &mut InputMux,
+    launcher: &mut UniversalLauncher,
+    window_manager: Option<WindowManager>,
+) {
     if let Err(e) = net_emulator.stop_relay() {
         error!("Error stopping network relay: {e}");
     }
     if let Err(e) = input_mux.stop_capture() {
         error!("Error stopping input capture: {e}");
     }
+    if let Some(window_manager) = window_manager {
+        if let Err(e) = window_manager.release_always_on_top() {
+            error!("Error releasing always-on-top windows: {e}");
+        }
+    }
     launcher.shutdown_instances();
+}
+
+/// Warns up front when the display session limits what window management
+/// can do. Headless sessions don't show windows, so they aren't warned about.
+fn check_display_session(config: &Config) {
+    if config.headless {
+        return;
+    }
+    match display::detect_session() {
+        SessionKind::X11 => debug!("Display session: X11"),
+        SessionKind::XWaylandOnly => warn!(
+            "Running under Wayland: only games using XWayland (including all Proton games) \
+             can be arranged; native Wayland windows will be left where they open."
+        ),
+        SessionKind::WaylandNative => warn!(
+            "Running under Wayland without XWayland: game windows cannot be arranged. \
+             Enable XWayland in your compositor for automatic layouts."
+        ),
+        SessionKind::Headless => warn!(
+            "No display server found (DISPLAY and WAYLAND_DISPLAY are unset): \
+             game windows cannot be shown or arranged."
+        ),
+    }
+}
+
+/// Session state for the control socket, reporting the session as degraded
+/// once one of `input_mux`'s capture threads or the relay has died, and the
+/// input codes its virtual devices were created without.
+fn session_control(num_instances: usize, input_mux: &InputMux) -> Arc<SessionControl> {
+    let mut control = SessionControl::new(num_instances, input_mux.pause_flag());
+    control.set_degraded_flag(input_mux.degraded_flag());
+    control.set_skipped_input_codes(input_mux.capability_reports().iter().map(CapabilityReport::skipped).sum());
+    Arc::new(control)
+}
+
+/// Opens the control socket for a running session. The session carries on
+/// without one if it can't be opened.
+fn start_control_server(control: &Arc<SessionControl>) -> Option<ControlServer> {
+    let Some(path) = control::socket_path() else {
+        warn!("XDG_RUNTIME_DIR is not set; the control socket is disabled.");
+        return None;
+    };
+    match ControlServer::start(&path, control.clone()) {
+        Ok(server) => Some(server),
+        Err(e) => {
+            warn!("Could not open control socket {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Sends a command to the running session and prints its reply.
+fn run_ctl(matches: &ArgMatches) -> Result<()> {
+    let name = matches.get_one::<String>("command").expect("command is required");
+    let command = ControlCommand::parse(name).expect("clap only accepts known commands");
+    let path = control::socket_path()
+        .ok_or_else(|| HydraError::application("XDG_RUNTIME_DIR is not set; cannot find the control socket"))?;
+
+    let reply = control::send_command(&path, command).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => HydraError::application(format!(
+            "No running Hydra session (nothing listening on {})",
+            path.display()
+        )),
+        _ => HydraError::Io(e),
+    })?;
+    println!("{}", reply);
+    if reply.starts_with("error") {
+        return Err(HydraError::application(reply));
+    }
+    Ok(())
+}
+
+/// `prefixes` subcommand: lists or deletes the WINEPREFIXes Hydra created.
+fn run_prefixes(matches: &ArgMatches) -> Result<()> {
+    let data_dir = crate::utils::get_data_dir()?;
+    let removed = match matches.subcommand() {
+        Some(("clean", clean)) => {
+            let days = *clean.get_one::<u64>("days").expect("days has a default");
+            proton_integration::clean_prefixes(&data_dir, Duration::from_secs(days * 24 * 60 * 60))?
+        }
+        Some(("remove", remove)) => {
+            let game = remove.get_one::<String>("game").expect("game is required");
+            let removed = proton_integration::remove_game_prefixes(&data_dir, game)?;
+            if removed.is_empty() {
+                println!("No WINEPREFIXes recorded for {}", game);
+            }
+            removed
+        }
+        _ => {
+            let prefixes = proton_integration::list_managed_prefixes(&data_dir);
+            if prefixes.is_empty() {
+                println!("Hydra hasn't created any WINEPREFIXes yet.");
+            }
+            for prefix in &prefixes {
+                println!(
+                    "{:<24} {:>10}  last used {}  {}",
+                    prefix.game,
+                    format_size(prefix.size),
+                    format_age(prefix.last_used),
+                    prefix.path.display()
+                );
+            }
+            return Ok(());
+        }
+    };
+    for prefix in &removed {
+        println!("Removed {} ({}, {})", prefix.path.display(), prefix.game, format_size(prefix.size));
+    }
+    let total: u64 = removed.iter().map(|prefix| prefix.size).sum();
+    println!("Freed {} in {} WINEPREFIX(es)", format_size(total), removed.len());
+    Ok(())
+}
+
+/// `layout` subcommand: arranges the windows of games started some other
+/// way, using the window settings from the config file. A tabbed layout
+/// keeps its switching hotkey working until Ctrl+C.
+fn run_layout(matches: &ArgMatches) -> Result<()> {
+    display::detect_session().require_window_management()?;
+    let mut config = load_configuration();
+    if let Some(name) = matches.get_one::<String>("layout") {
+        config.window_layout = name.parse()?;
+    }
+    let layout = config.window_layout.layout();
+    let pids: Vec<u32> = matches.get_many::<u32>("pids").expect("pids are required").copied().collect();
+
+    let mut window_manager = open_window_manager(&config, layout)?;
+    window_manager.set_keep_above(config.keep_above);
+    window_manager.set_layout_failure_policy(config.on_layout_failure);
+    let unplaced = window_manager
+        .set_layout(&pids, layout)
+        .map_err(|e| HydraError::from_window_search(e, window_manager.search_timing().timeout))?;
+    for pid in &unplaced {
+        warn!("No window found for PID {}; it was left where it is.", pid);
+    }
+    info!("Arranged {} window(s) in the {:?} layout.", pids.len() - unplaced.len(), layout);
+
+    if layout == Layout::Tabbed {
+        let hotkey = config.tab_cycle_hotkey.parse::<Hotkey>()?;
+        window_manager.start_tab_cycle(&hotkey)?;
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        {
+            let stop_requested = stop_requested.clone();
+            ctrlc::set_handler(move || stop_requested.store(true, Ordering::SeqCst))
+                .expect("failed to install Ctrl-C handler");
+        }
+        info!("Press {} to switch windows and Ctrl+C to stop.", config.tab_cycle_hotkey);
+        while !stop_requested.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
     Ok(())
 }
 
+/// `netemu` subcommand: runs the network relay without launching a game, so
+/// netcode can be tested against hand-started instances. Prints packet
+/// counts periodically and once more on Ctrl+C.
+fn run_netemu(matches: &ArgMatches) -> Result<()> {
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    {
+        let stop_requested = stop_requested.clone();
+        ctrlc::set_handler(move || stop_requested.store(true, Ordering::SeqCst))
+            .expect("failed to install Ctrl-C handler");
+    }
+
+    let mut net_emulator = NetEmulator::new();
+    net_emulator.set_loopback_per_instance(matches.get_flag("loopback_per_instance"));
+    let ports = matches.get_many::<u16>("port").into_iter().flatten();
+    for (net_id, &game_port) in ports.enumerate() {
+        let net_id = u8::try_from(net_id).map_err(|_| HydraError::validation("At most 256 --port values are supported"))?;
+        let emulator_port = add_relay_instance(&net_emulator, net_id, Some(game_port))?;
+        println!(
+            "Instance {}: game port {} -> relay {}",
+            net_id,
+            game_port,
+            SocketAddr::from((net_emulator.instance_ip(net_id), emulator_port))
+        );
+    }
+    let routes = matches.get_many::<(SocketAddr, SocketAddr)>("route").into_iter().flatten();
+    for &(from, to) in routes {
+        println!("Route: {} -> {}", from, to);
+        net_emulator.add_mapping(from, to);
+    }
+    if let Some(path) = matches.get_one::<String>("routes_file") {
+        for route in net_emulator.load_routes(&crate::utils::expand_path(path))? {
+            println!("Route: {} -> {}", route.from, route.to);
+        }
+    }
+    if let Some(path) = matches.get_one::<String>("capture") {
+        let path = crate::utils::expand_path(path);
+        net_emulator.enable_capture(&path)?;
+        println!("Capturing packets to {}", path.display());
+    }
+    net_emulator.start_relay()?;
+
+    let interval = Duration::from_secs(*matches.get_one::<u64>("stats_interval").expect("stats_interval has a default"));
+    println!("Relaying. Press Ctrl+C to stop.");
+    let mut last_report = Instant::now();
+    while !stop_requested.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(100));
+        if last_report.elapsed() >= interval {
+            print_relay_stats(&net_emulator.stats());
+            last_report = Instant::now();
+        }
+    }
+    net_emulator.stop_relay()?;
+    print_relay_stats(&net_emulator.stats());
+    Ok(())
+}
+
+fn print_relay_stats(stats: &net_emulator::RelayStats) {
+    println!(
+        "received {}, forwarded {} ({}), dropped {}",
+        stats.received,
+        stats.forwarded,
+        format_size(stats.bytes_forwarded),
+        stats.dropped
+    );
+}
+
+/// `bytes` in the largest unit that keeps it at or above 1.
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// How long ago `time` was, in days, or "today".
+pub(crate) fn format_age(time: SystemTime) -> String {
+    match time.elapsed().map(|age| age.as_secs() / (24 * 60 * 60)) {
+        Ok(0) | Err(_) => "today".to_string(),
+        Ok(1) => "1 day ago".to_string(),
+        Ok(days) => format!("{} days ago", days),
+    }
+}
+
 /// Load the main configuration from disk, falling back to defaults on any
 /// non-fatal error.
 fn load_configuration() -> Config {
@@ -298,10 +1654,16 @@ fn load_configuration() -> Config {
     }
 }
 
-fn enumerate_input_devices() -> Vec<input_mux::DeviceIdentifier> {
+pub(crate) fn enumerate_input_devices() -> Vec<input_mux::DeviceIdentifier> {
+    enumerate_controllers().concat()
+}
+
+/// The input devices grouped by physical device, each group's primary
+/// interface first, see `InputMux::controllers`.
+pub(crate) fn enumerate_controllers() -> Vec<Vec<input_mux::DeviceIdentifier>> {
     let mut mux = InputMux::new();
     match mux.enumerate_devices() {
-        Ok(()) => mux.get_available_devices(),
+        Ok(()) => mux.controllers(),
         Err(e) => {
             error!("Failed to enumerate input devices: {}", e);
             Vec::new()
@@ -309,9 +1671,22 @@ fn enumerate_input_devices() -> Vec<input_mux::DeviceIdentifier> {
     }
 }
 
+/// Fails if no input device can be opened, unless the config allows
+/// launching without any.
+fn check_input_devices(config: &Config) -> Result<()> {
+    if !enumerate_input_devices().is_empty() {
+        return Ok(());
+    }
+    if config.allow_no_input {
+        warn!("No usable input devices found; the games will only get the desktop's keyboard and mouse.");
+        return Ok(());
+    }
+    Err(input_mux::InputMuxError::NoInputDevices.into())
+}
+
 pub(crate) fn get_config_path() -> Result<PathBuf> {
     if let Ok(path) = env::var("CONFIG_PATH") {
-        return Ok(PathBuf::from(path));
+        return Ok(crate::utils::expand_path(&path));
     }
     let dir = crate::utils::get_config_dir()?;
     crate::utils::ensure_dir_exists(&dir)?;
@@ -324,42 +1699,9 @@ fn parse_args_for_logging() -> ArgMatches {
     use clap::{Arg, Command};
     Command::new("hydra-coop-launcher")
         .arg(Arg::new("debug").long("debug").action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("tui").long("tui").action(clap::ArgAction::SetTrue))
         .disable_help_flag(true)
         .disable_version_flag(true)
         .ignore_errors(true)
         .get_matches()
 }
-
-// ---------------------------------------------------------------------------
-// The items below are duplicated from lib.rs so that the binary can compile as
-// a standalone target (the binary cannot reference items from the library
-// crate directly without going through `hydra_coop_launcher::…`). Keep these
-// in sync with lib.rs.
-// ---------------------------------------------------------------------------
-
-pub(crate) const APP_NAME: &str = env!("CARGO_PKG_NAME");
-pub(crate) const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
-pub(crate) const APP_AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
-pub(crate) const APP_DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
-
-pub(crate) mod defaults {
-    pub const MAX_INSTANCES: usize = 8;
-}
-
-pub(crate) mod utils {
-    use crate::errors::{HydraError, Result};
-    use std::path::{Path, PathBuf};
-
-    pub fn get_config_dir() -> Result<PathBuf> {
-        dirs::config_dir()
-            .map(|d| d.join("hydra-coop"))
-            .ok_or_else(|| HydraError::application("Could not determine config directory"))
-    }
-
-    pub fn ensure_dir_exists(path: &Path) -> Result<()> {
-        if !path.exists() {
-            std::fs::create_dir_all(path)?;
-        }
-        Ok(())
-    }
-}
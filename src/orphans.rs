@@ -0,0 +1,240 @@
+//! Orphan Cleanup
+//!
+//! If a previous session crashed, its game instances and the launcher that
+//! owned the virtual input devices can keep running and interfere with the
+//! next launch. This module finds them using the last session manifest and
+//! `/proc`, and terminates them on request.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use crate::input_mux::VIRTUAL_DEVICE_PREFIX;
+use crate::session::SessionManifest;
+
+/// How long a process gets to exit after SIGTERM before it is killed
+const TERMINATE_GRACE: Duration = Duration::from_secs(3);
+
+/// A process left over from a previous session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanProcess {
+    pub pid: u32,
+    /// What the process was, for showing to the user
+    pub description: String,
+}
+
+/// Finds still-running processes from the session recorded in `manifest`.
+pub fn find_orphans(manifest: &SessionManifest) -> Vec<OrphanProcess> {
+    let own_exe = std::env::current_exe().ok();
+    find_orphans_in(Path::new("/proc"), manifest, own_exe.as_deref(), std::process::id())
+}
+
+/// [`find_orphans`] against an arbitrary procfs root.
+///
+/// A game PID only counts if it still runs the recorded executable, or carries
+/// the `HYDRA_INSTANCE_ID` we set (which catches Proton games, whose `exe` is
+/// Wine). The launcher PID only counts if it runs a binary named like ours.
+fn find_orphans_in(
+    proc_root: &Path,
+    manifest: &SessionManifest,
+    own_exe: Option<&Path>,
+    own_pid: u32,
+) -> Vec<OrphanProcess> {
+    let mut orphans = Vec::new();
+
+    for instance in &manifest.instances {
+        if instance.pid == own_pid {
+            continue;
+        }
+        let proc_dir = proc_root.join(instance.pid.to_string());
+        let runs_game = process_exe(&proc_dir).is_some_and(|exe| exe == manifest.game_path);
+        let marker = format!("HYDRA_INSTANCE_ID={}", instance.id);
+        let has_marker = fs::read(proc_dir.join("environ"))
+            .map(|environ| environ.split(|&b| b == 0).any(|var| var == marker.as_bytes()))
+            .unwrap_or(false);
+        if runs_game || has_marker {
+            orphans.push(OrphanProcess {
+                pid: instance.pid,
+                description: format!("game instance {} ({})", instance.id, manifest.game_path.display()),
+            });
+        }
+    }
+
+    if let (Some(pid), Some(own_exe)) = (manifest.launcher_pid, own_exe) {
+        let own_name = own_exe.file_name();
+        let exe = process_exe(&proc_root.join(pid.to_string()));
+        // A rebuilt binary shows up as "<name> (deleted)", so compare by prefix.
+        let same_binary = exe.as_ref().and_then(|exe| exe.file_name()).zip(own_name).is_some_and(|(exe, own)| {
+            exe.to_string_lossy().starts_with(own.to_string_lossy().as_ref())
+        });
+        if pid != own_pid && same_binary {
+            orphans.push(OrphanProcess {
+                pid,
+                description: "previous Hydra launcher (owns the virtual input devices)".to_string(),
+            });
+        }
+    }
+
+    orphans
+}
+
+fn process_exe(proc_dir: &Path) -> Option<PathBuf> {
+    fs::read_link(proc_dir.join("exe")).ok()
+}
+
+/// Names of Hydra virtual input devices that currently exist. At startup
+/// these can only belong to a launcher that is still running. Only devices
+/// named by the default template are recognised.
+pub fn stale_virtual_devices() -> Vec<String> {
+    stale_virtual_devices_in(Path::new("/sys/class/input"))
+}
+
+fn stale_virtual_devices_in(sys_input: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(sys_input) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("input"))
+        .filter_map(|entry| fs::read_to_string(entry.path().join("name")).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| name.starts_with(VIRTUAL_DEVICE_PREFIX))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Sends SIGTERM, then SIGKILL if the process hasn't exited after a grace period.
+pub fn terminate(pid: u32) -> io::Result<()> {
+    info!("Terminating orphaned process {}", pid);
+    signal(pid, "TERM")?;
+
+    let deadline = Instant::now() + TERMINATE_GRACE;
+    while Instant::now() < deadline {
+        if !Path::new("/proc").join(pid.to_string()).exists() {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    warn!("Process {} ignored SIGTERM; killing it", pid);
+    signal(pid, "KILL")
+}
+
+pub(crate) fn signal(pid: u32, signal: &str) -> io::Result<()> {
+    debug!("Sending SIG{} to {}", signal, pid);
+    let status = Command::new("kill").arg(format!("-{}", signal)).arg(pid.to_string()).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("kill -{} {} failed: {}", signal, pid, status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::os::unix::fs::symlink;
+    use tempfile::tempdir;
+    use crate::game_detection::{GameConfiguration, GameProfile, InstanceSeparation, MultiInstanceSupport, NetworkMode, WorkingDirStrategy};
+    use crate::session::InstanceRecord;
+    use crate::universal_launcher::WindowsRuntime;
+    use crate::window_manager::Layout;
+
+    fn manifest(game_path: &Path, pids: &[u32], launcher_pid: Option<u32>) -> SessionManifest {
+        SessionManifest {
+            created_at: 0,
+            launcher_pid,
+            game_path: game_path.to_path_buf(),
+            game_hash: None,
+            profile: GameProfile {
+                executable_pattern: "game".to_string(),
+                engine: None,
+                default_ports: vec![],
+                default_layout: "horizontal".to_string(),
+                multi_instance_support: MultiInstanceSupport::Configurable,
+                launch_args: vec![],
+                environment_vars: HashMap::new(),
+                working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+                network_mode: NetworkMode::RawUdp,
+                instance_blockers: Vec::new(),
+            },
+            game_config: GameConfiguration {
+                ports: vec![],
+                layout: "horizontal".to_string(),
+                launch_args: vec![],
+                environment_vars: HashMap::new(),
+                working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+                instance_separation: InstanceSeparation::Environment,
+                instance_args: Vec::new(),
+            },
+            use_proton: false,
+            windows_runtime: WindowsRuntime::None,
+            layout: Layout::Horizontal,
+            input_assignments: vec![],
+            instances: pids
+                .iter()
+                .enumerate()
+                .map(|(id, &pid)| InstanceRecord {
+                    id,
+                    pid,
+                    game_port: None,
+                    emulator_port: None,
+                    working_dir: PathBuf::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn fake_process(proc_root: &Path, pid: u32, exe: &Path, environ: &[&str]) {
+        let dir = proc_root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        symlink(exe, dir.join("exe")).unwrap();
+        fs::write(dir.join("environ"), environ.join("\0")).unwrap();
+    }
+
+    #[test]
+    fn test_find_orphans_matches_exe_or_marker() {
+        let proc_root = tempdir().unwrap();
+        let game = PathBuf::from("/games/test/game.x86_64");
+        let wine = PathBuf::from("/usr/bin/wine64-preloader");
+        let hydra = PathBuf::from("/opt/hydra/hydra-coop-launcher (deleted)");
+
+        fake_process(proc_root.path(), 100, &game, &[]);
+        fake_process(proc_root.path(), 101, &wine, &["HOME=/root", "HYDRA_INSTANCE_ID=1"]);
+        // PID reused by an unrelated program
+        fake_process(proc_root.path(), 102, Path::new("/usr/bin/vim"), &[]);
+        fake_process(proc_root.path(), 50, &hydra, &[]);
+
+        let manifest = manifest(&game, &[100, 101, 102, 103], Some(50));
+        let own_exe = Path::new("/opt/hydra/hydra-coop-launcher");
+        let pids: Vec<u32> = find_orphans_in(proc_root.path(), &manifest, Some(own_exe), 1)
+            .into_iter()
+            .map(|orphan| orphan.pid)
+            .collect();
+
+        assert_eq!(pids, vec![100, 101, 50]);
+    }
+
+    #[test]
+    fn test_stale_virtual_devices() {
+        let sys_input = tempdir().unwrap();
+        for (node, name) in [
+            ("input3", "Logitech Gamepad F310\n"),
+            ("input7", "HydraCoop P2 a1b2\n"),
+            ("input6", "HydraCoop P1 a1b2\n"),
+        ] {
+            fs::create_dir_all(sys_input.path().join(node)).unwrap();
+            fs::write(sys_input.path().join(node).join("name"), name).unwrap();
+        }
+
+        assert_eq!(
+            stale_virtual_devices_in(sys_input.path()),
+            vec!["HydraCoop P1 a1b2".to_string(), "HydraCoop P2 a1b2".to_string()]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Port Allocation
+//!
+//! Decides which network port each game instance uses. The chosen ports are
+//! templated into the game's arguments and environment (`${PORT}`) and
+//! handed to the network emulator, so both always agree.
+
+use std::io;
+use std::net::{Ipv4Addr, TcpListener, UdpSocket};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+/// How many times to look for a port free for both TCP and UDP
+const EPHEMERAL_ATTEMPTS: usize = 32;
+
+/// Error type for port allocation
+#[derive(Debug)]
+pub enum PortError {
+    /// No free port could be found
+    Bind(io::Error),
+    /// Consecutive ports from `start` would go past 65535
+    OutOfRange { start: u16, count: usize },
+}
+
+impl std::fmt::Display for PortError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortError::Bind(e) => write!(f, "Could not find a free network port: {}", e),
+            PortError::OutOfRange { start, count } => {
+                write!(f, "{} consecutive ports starting at {} go past port 65535", count, start)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PortError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PortError::Bind(e) => Some(e),
+            PortError::OutOfRange { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for PortError {
+    fn from(e: io::Error) -> Self {
+        PortError::Bind(e)
+    }
+}
+
+/// Where the instances' ports come from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortStrategy {
+    /// These ports, in instance order; instances beyond the list continue
+    /// from the last one
+    Fixed(Vec<u16>),
+    /// Consecutive ports from this one
+    SequentialFrom(u16),
+    /// Ports the system reports free right now
+    Ephemeral,
+}
+
+/// A port picked for an instance. An ephemeral port stays bound until it
+/// is released, so nothing else can take it before the game does.
+#[derive(Debug)]
+pub struct ReservedPort {
+    pub port: u16,
+    held: Option<(UdpSocket, TcpListener)>,
+}
+
+impl ReservedPort {
+    fn unheld(port: u16) -> Self {
+        ReservedPort { port, held: None }
+    }
+
+    /// Frees the port for the game to bind, returning it.
+    pub fn release(self) -> u16 {
+        drop(self.held);
+        self.port
+    }
+}
+
+impl PortStrategy {
+    /// Ports for `count` instances.
+    pub fn allocate(&self, count: usize) -> Result<Vec<u16>, PortError> {
+        Ok(self.reserve(count)?.into_iter().map(ReservedPort::release).collect())
+    }
+
+    /// Like [`PortStrategy::allocate`], but ephemeral ports stay bound until
+    /// released, which should be just before the instance that gets the
+    /// port starts.
+    pub fn reserve(&self, count: usize) -> Result<Vec<ReservedPort>, PortError> {
+        let ports = match self {
+            PortStrategy::Fixed(ports) => {
+                let mut ports: Vec<u16> = ports.iter().take(count).copied().collect();
+                if ports.len() < count {
+                    let missing = count - ports.len();
+                    let next = match ports.last() {
+                        None => 7777,
+                        // The listed ports carry on from the last one
+                        Some(&last) => last
+                            .checked_add(1)
+                            .ok_or(PortError::OutOfRange { start: last, count: missing + 1 })?,
+                    };
+                    ports.extend(sequential(next, missing)?);
+                }
+                ports
+            }
+            PortStrategy::SequentialFrom(start) => sequential(*start, count)?,
+            PortStrategy::Ephemeral => return ephemeral(count),
+        };
+        Ok(ports.into_iter().map(ReservedPort::unheld).collect())
+    }
+}
+
+fn sequential(start: u16, count: usize) -> Result<Vec<u16>, PortError> {
+    (0..count)
+        .map(|offset| {
+            u16::try_from(offset)
+                .ok()
+                .and_then(|offset| start.checked_add(offset))
+                .ok_or(PortError::OutOfRange { start, count })
+        })
+        .collect()
+}
+
+/// Asks the system for ports that are free for both UDP and TCP, since games
+/// may use either. The sockets stay bound until each port is released, so
+/// none is handed out twice or taken in the meantime.
+fn ephemeral(count: usize) -> Result<Vec<ReservedPort>, PortError> {
+    let mut held = Vec::with_capacity(count);
+    let mut attempts = 0;
+    while held.len() < count {
+        let udp = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        let port = udp.local_addr()?.port();
+        match TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)) {
+            Ok(tcp) => held.push(ReservedPort { port, held: Some((udp, tcp)) }),
+            Err(e) => {
+                debug!("Port {} is free for UDP but not TCP ({}); trying another", port, e);
+                attempts += 1;
+                if attempts >= EPHEMERAL_ATTEMPTS {
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+    Ok(held)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_and_sequential() {
+        assert_eq!(PortStrategy::Fixed(vec![7777, 7790, 7800]).allocate(2).unwrap(), vec![7777, 7790]);
+        assert_eq!(PortStrategy::Fixed(vec![27015]).allocate(3).unwrap(), vec![27015, 27016, 27017]);
+        assert_eq!(PortStrategy::Fixed(vec![]).allocate(2).unwrap(), vec![7777, 7778]);
+        assert_eq!(PortStrategy::SequentialFrom(40000).allocate(2).unwrap(), vec![40000, 40001]);
+        assert!(matches!(
+            PortStrategy::SequentialFrom(65535).allocate(2),
+            Err(PortError::OutOfRange { start: 65535, count: 2 })
+        ));
+        assert!(matches!(
+            PortStrategy::Fixed(vec![65534, 65535]).allocate(3),
+            Err(PortError::OutOfRange { start: 65535, count: 2 })
+        ));
+        assert_eq!(PortStrategy::Fixed(vec![65534]).allocate(2).unwrap(), vec![65534, 65535]);
+    }
+
+    #[test]
+    fn test_ephemeral_ports_are_distinct() {
+        let mut ports = PortStrategy::Ephemeral.allocate(4).unwrap();
+        assert!(ports.iter().all(|&port| port >= 1024));
+        ports.sort_unstable();
+        ports.dedup();
+        assert_eq!(ports.len(), 4);
+    }
+
+    #[test]
+    fn test_ephemeral_ports_stay_bound_until_released() {
+        let mut reserved = PortStrategy::Ephemeral.reserve(1).unwrap();
+        let port = reserved[0].port;
+        assert!(UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).is_err());
+        assert!(TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_err());
+
+        assert_eq!(reserved.remove(0).release(), port);
+        assert!(UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok());
+        assert!(TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            port_strategy: PortStrategy,
+        }
+        let parse = |toml: &str| toml::from_str::<Wrapper>(toml).unwrap().port_strategy;
+        assert_eq!(parse("port_strategy = \"ephemeral\""), PortStrategy::Ephemeral);
+        assert_eq!(parse("port_strategy = { sequential_from = 27015 }"), PortStrategy::SequentialFrom(27015));
+        assert_eq!(parse("port_strategy = { fixed = [7777, 7780] }"), PortStrategy::Fixed(vec![7777, 7780]));
+    }
+}
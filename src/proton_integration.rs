@@ -1,22 +1,65 @@
 use std::env;
 use std::fs::{self, File};
-use std::io::{self, Read};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use log::{info, error, warn, debug};
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 
+/// How often `find_proton_path_waiting` looks for Proton again
+const PROTON_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// File in the data directory that records which WINEPREFIX belongs to which game
+pub const PREFIX_INDEX_FILE: &str = "prefixes.json";
+
+/// Name of the directory each instance's WINEPREFIX is created in; those
+/// of instances running as another user are `wineprefix_<instance>`
+const PREFIX_DIR_NAME: &str = "wineprefix";
+
 // Custom error type for Proton integration operations
 #[derive(Debug)]
 pub enum ProtonError {
     IoError(io::Error),
-    ProtonNotFound(String),
+    /// No usable Proton was found. `incomplete` lists Proton directories
+    /// without their `proton` script, as an unfinished download leaves them.
+    ProtonNotFound { incomplete: Vec<PathBuf>, steam_running: bool },
+    /// The prefix index couldn't be read or written
+    PrefixIndex(String),
 }
 
 impl std::fmt::Display for ProtonError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             ProtonError::IoError(e) => write!(f, "Proton integration I/O error: {}", e),
-            ProtonError::ProtonNotFound(msg) => write!(f, "Proton not found: {}", msg),
+            ProtonError::ProtonNotFound { incomplete, steam_running } => match incomplete.first() {
+                Some(dir) if *steam_running => write!(
+                    f,
+                    "Proton is not fully installed yet ({} has no proton script). \
+                     Wait for Steam to finish downloading it (Steam → Downloads) and try again",
+                    dir.display()
+                ),
+                Some(dir) => write!(
+                    f,
+                    "Proton is not fully installed ({} has no proton script). \
+                     Start Steam to let it finish installing Proton, then try again",
+                    dir.display()
+                ),
+                None => {
+                    write!(
+                        f,
+                        "Proton not found. Install it via Steam (Library → Tools → 'Proton X.Y') \
+                         or set the PROTON_PATH environment variable to its location"
+                    )?;
+                    if !*steam_running {
+                        write!(f, ". Steam doesn't appear to be running")?;
+                    }
+                    Ok(())
+                }
+            },
+            ProtonError::PrefixIndex(msg) => write!(f, "WINEPREFIX index error: {}", msg),
         }
     }
 }
@@ -37,31 +80,151 @@ impl From<io::Error> for ProtonError {
     }
 }
 
-/// Checks if the given file is a likely Windows PE (Portable Executable) binary.
-/// This is a basic check based on the "MZ" header. It's not foolproof.
-pub fn is_windows_binary(file_path: &Path) -> Result<bool, ProtonError> {
-    debug!("Checking if file is a Windows binary: {}", file_path.display());
-    let mut file = match File::open(file_path) {
-        Ok(file) => file,
-        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
-             debug!("File not found, not a Windows binary check target: {}", file_path.display());
-             return Ok(false); // File not found, so not a Windows binary for our purpose
-        }
-        Err(e) => {
-             error!("Failed to open file for Windows binary check: {}: {}", file_path.display(), e);
-             return Err(ProtonError::IoError(e)); // Propagate other IO errors
+/// Executable file format, see [`analyze_binary`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// Windows Portable Executable (or a DOS program, which also starts with "MZ")
+    Pe,
+    /// Native Linux ELF
+    Elf,
+    Other,
+}
+
+/// CPU architecture an executable is built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryArch {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+    Unknown,
+}
+
+impl BinaryArch {
+    /// The architecture as `std::env::consts::ARCH` names it
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryArch::X86 => "x86",
+            BinaryArch::X86_64 => "x86_64",
+            BinaryArch::Arm => "arm",
+            BinaryArch::Aarch64 => "aarch64",
+            BinaryArch::Unknown => "unknown",
         }
+    }
+}
+
+/// What [`analyze_binary`] found out about an executable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryInfo {
+    pub format: BinaryFormat,
+    /// 32 or 64; `None` when the headers don't say
+    pub bits: Option<u8>,
+    pub arch: BinaryArch,
+}
+
+impl BinaryInfo {
+    const OTHER: BinaryInfo = BinaryInfo { format: BinaryFormat::Other, bits: None, arch: BinaryArch::Unknown };
+}
+
+/// Reads an executable's headers: the PE optional header magic and machine
+/// field of Windows programs, or the ELF class and `e_machine` of native
+/// ones. Files that are neither are `BinaryFormat::Other`.
+pub fn analyze_binary(file_path: &Path) -> Result<BinaryInfo, ProtonError> {
+    let mut file = File::open(file_path)?;
+    let mut header = [0u8; 64];
+    let len = read_up_to(&mut file, &mut header)?;
+    let header = &header[..len];
+
+    let info = if header.starts_with(b"\x7fELF") {
+        elf_info(header)
+    } else if header.starts_with(b"MZ") {
+        pe_info(&mut file, header)?
+    } else {
+        BinaryInfo::OTHER
+    };
+    debug!("Binary analysis of {}: {:?}", file_path.display(), info);
+    Ok(info)
+}
+
+/// ELF class (`e_ident[EI_CLASS]`) and machine, read in the file's byte order
+fn elf_info(header: &[u8]) -> BinaryInfo {
+    let bits = match header.get(4) {
+        Some(1) => Some(32),
+        Some(2) => Some(64),
+        _ => None,
+    };
+    let big_endian = header.get(5) == Some(&2);
+    let machine = header.get(18..20).map(|bytes| {
+        let bytes = [bytes[0], bytes[1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    });
+    let arch = match machine {
+        Some(3) => BinaryArch::X86,
+        Some(62) => BinaryArch::X86_64,
+        Some(40) => BinaryArch::Arm,
+        Some(183) => BinaryArch::Aarch64,
+        _ => BinaryArch::Unknown,
+    };
+    BinaryInfo { format: BinaryFormat::Elf, bits, arch }
+}
+
+/// Follows `e_lfanew` to the PE header for the COFF machine and the optional
+/// header magic (PE32 or PE32+). A DOS program without a PE header is still
+/// `BinaryFormat::Pe`, with nothing else known.
+fn pe_info(file: &mut File, header: &[u8]) -> io::Result<BinaryInfo> {
+    let unknown = BinaryInfo { format: BinaryFormat::Pe, bits: None, arch: BinaryArch::Unknown };
+    let Some(&[a, b, c, d]) = header.get(0x3C..0x40) else {
+        return Ok(unknown);
     };
+    file.seek(SeekFrom::Start(u32::from_le_bytes([a, b, c, d]).into()))?;
+    // "PE\0\0", the 20-byte COFF header, then the optional header's magic
+    let mut pe = [0u8; 26];
+    if read_up_to(file, &mut pe)? < pe.len() || &pe[..4] != b"PE\0\0" {
+        return Ok(unknown);
+    }
+    let arch = match u16::from_le_bytes([pe[4], pe[5]]) {
+        0x014c => BinaryArch::X86,
+        0x8664 => BinaryArch::X86_64,
+        0x01c0 | 0x01c4 => BinaryArch::Arm,
+        0xaa64 => BinaryArch::Aarch64,
+        _ => BinaryArch::Unknown,
+    };
+    let bits = match u16::from_le_bytes([pe[24], pe[25]]) {
+        0x010b => Some(32),
+        0x020b => Some(64),
+        _ => None,
+    };
+    Ok(BinaryInfo { format: BinaryFormat::Pe, bits, arch })
+}
 
-    let mut buffer = [0; 2];
-    if file.read_exact(&mut buffer).is_err() {
-         debug!("Failed to read enough bytes for MZ check: {}", file_path.display());
-        return Ok(false); // Couldn't read, assume not a Windows binary for this check
+/// Reads until `buf` is full or the file ends; returns how much was read.
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
     }
+    Ok(filled)
+}
 
-    let is_pe = buffer == [0x4D, 0x5A]; // "MZ" signature
-     debug!("MZ signature check for {}: {}", file_path.display(), is_pe);
-    Ok(is_pe)
+/// Checks if the given file is a likely Windows PE (Portable Executable)
+/// binary, see [`analyze_binary`]. A missing file is not one.
+pub fn is_windows_binary(file_path: &Path) -> Result<bool, ProtonError> {
+    match analyze_binary(file_path) {
+        Ok(info) => Ok(info.format == BinaryFormat::Pe),
+        Err(ProtonError::IoError(ref e)) if e.kind() == io::ErrorKind::NotFound => {
+            debug!("File not found, not a Windows binary check target: {}", file_path.display());
+            Ok(false)
+        }
+        Err(e) => {
+            error!("Failed to read {} to check for a Windows binary: {}", file_path.display(), e);
+            Err(e)
+        }
+    }
 }
 
 /// Attempts to find the Proton executable path.
@@ -75,17 +238,100 @@ pub fn is_windows_binary(file_path: &Path) -> Result<bool, ProtonError> {
 pub fn find_proton_path() -> Result<PathBuf, ProtonError> {
     info!("Attempting to find Proton executable.");
 
+    if let Some(path) = find_proton_installations().into_iter().next() {
+        info!("Found Proton at: {}", path.display());
+        return Ok(path);
+    }
+
+    error!("Proton executable not found in any known location.");
+    Err(ProtonError::ProtonNotFound {
+        incomplete: incomplete_proton_installations(),
+        steam_running: is_steam_running(),
+    })
+}
+
+/// Like [`find_proton_path`], but keeps looking for up to `wait` while Proton
+/// may still be on its way: an installation is incomplete or Steam is
+/// running. Useful on a first run, when Steam is still downloading Proton.
+/// Setting `cancel` stops the wait at the next look.
+pub fn find_proton_path_waiting(wait: Duration, cancel: Option<&AtomicBool>) -> Result<PathBuf, ProtonError> {
+    let deadline = Instant::now() + wait;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let cancelled = cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst));
+        match find_proton_path() {
+            Err(ProtonError::ProtonNotFound { incomplete, steam_running })
+                if !remaining.is_zero() && !cancelled && (steam_running || !incomplete.is_empty()) =>
+            {
+                info!("Proton isn't ready yet; looking again for up to {}s.", remaining.as_secs());
+                thread::sleep(PROTON_RETRY_INTERVAL.min(remaining));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Lists every Proton installation that can be found, in the order
+/// [`find_proton_path`] prefers them: `PROTON_PATH`, then the Steam
+/// installations, then extra libraries listed in `libraryfolders.vdf`.
+pub fn find_proton_installations() -> Vec<PathBuf> {
+    let mut found = Vec::new();
+
     // 1. Explicit override via environment variable.
-    if let Ok(proton_path_env) = env::var("PROTON_PATH") {
-        let path = PathBuf::from(&proton_path_env);
+    if let Some(path) = env::var_os("PROTON_PATH").map(PathBuf::from) {
         if path.exists() {
-            info!("Found Proton via PROTON_PATH: {}", path.display());
-            return Ok(path);
+            debug!("PROTON_PATH points at {}", path.display());
+            found.push(path);
+        } else {
+            warn!("PROTON_PATH='{}' does not exist — continuing search.", path.display());
         }
-        warn!("PROTON_PATH='{}' does not exist — continuing search.", proton_path_env);
     }
 
-    // 2. Search common Steam library locations.
+    // 2. The Steam libraries. ~/.steam/steam and ~/.local/share/Steam are
+    // usually the same directory.
+    let mut seen = Vec::new();
+    for proton in steam_libraries().iter().flat_map(|steamapps| proton_binaries_in(steamapps)) {
+        let canonical = fs::canonicalize(&proton).unwrap_or_else(|_| proton.clone());
+        if !seen.contains(&canonical) {
+            seen.push(canonical);
+            found.push(proton);
+        }
+    }
+    found
+}
+
+/// Proton directories in the Steam libraries that lack their `proton`
+/// script, usually because Steam hasn't finished installing them.
+pub fn incomplete_proton_installations() -> Vec<PathBuf> {
+    let mut incomplete = Vec::new();
+    let mut seen = Vec::new();
+    for dir in steam_libraries().iter().flat_map(|steamapps| proton_dirs_in(steamapps)) {
+        let canonical = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        if !dir.join("proton").exists() && !seen.contains(&canonical) {
+            seen.push(canonical);
+            incomplete.push(dir);
+        }
+    }
+    incomplete
+}
+
+/// Whether a Steam client process is running.
+pub fn is_steam_running() -> bool {
+    steam_running_in(Path::new("/proc"))
+}
+
+fn steam_running_in(proc_root: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        fs::read_to_string(entry.path().join("comm")).is_ok_and(|comm| comm.trim_end() == "steam")
+    })
+}
+
+/// The `steamapps/common` directories of every Steam installation and of the
+/// extra libraries listed in their `libraryfolders.vdf`.
+fn steam_libraries() -> Vec<PathBuf> {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/home"));
 
     let steam_roots: Vec<PathBuf> = vec![
@@ -97,44 +343,9 @@ pub fn find_proton_path() -> Result<PathBuf, ProtonError> {
         // Snap Steam
         home.join("snap/steam/common/.local/share/Steam"),
     ];
+    let mut libraries: Vec<PathBuf> = steam_roots.iter().map(|root| root.join("steamapps/common")).collect();
 
-    for steam_root in &steam_roots {
-        let steamapps = steam_root.join("steamapps/common");
-        if !steamapps.is_dir() {
-            continue;
-        }
-        debug!("Searching for Proton in {}", steamapps.display());
-
-        // Collect all Proton* subdirectories, then sort descending so we get the
-        // newest version first (e.g. "Proton 9.0" before "Proton 8.0").
-        let mut proton_dirs: Vec<PathBuf> = fs::read_dir(&steamapps)
-            .map(|entries| {
-                entries
-                    .filter_map(|e| e.ok())
-                    .map(|e| e.path())
-                    .filter(|p| {
-                        p.is_dir()
-                            && p.file_name()
-                                .and_then(|n| n.to_str())
-                                .map(|n| n.starts_with("Proton"))
-                                .unwrap_or(false)
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        proton_dirs.sort_by(|a, b| b.cmp(a)); // descending — newest version first
-
-        for dir in &proton_dirs {
-            let exe = dir.join("proton");
-            if exe.exists() {
-                info!("Found Proton at: {}", exe.display());
-                return Ok(exe);
-            }
-        }
-    }
-
-    // 3. Check additional Steam library folders listed in libraryfolders.vdf.
+    // Additional Steam library folders listed in libraryfolders.vdf.
     for steam_root in &steam_roots {
         let vdf = steam_root.join("steamapps/libraryfolders.vdf");
         if let Ok(contents) = fs::read_to_string(&vdf) {
@@ -146,43 +357,192 @@ pub fn find_proton_path() -> Result<PathBuf, ProtonError> {
                         .nth(3)
                         .unwrap_or("")
                         .replace("\\\\", "/");
-                    let alt_steamapps = PathBuf::from(&path_str).join("steamapps/common");
-                    if alt_steamapps.is_dir() {
-                        let mut proton_dirs: Vec<PathBuf> = fs::read_dir(&alt_steamapps)
-                            .map(|entries| {
-                                entries
-                                    .filter_map(|e| e.ok())
-                                    .map(|e| e.path())
-                                    .filter(|p| {
-                                        p.is_dir()
-                                            && p.file_name()
-                                                .and_then(|n| n.to_str())
-                                                .map(|n| n.starts_with("Proton"))
-                                                .unwrap_or(false)
-                                    })
-                                    .collect()
-                            })
-                            .unwrap_or_default();
-                        proton_dirs.sort_by(|a, b| b.cmp(a));
-                        for dir in &proton_dirs {
-                            let exe = dir.join("proton");
-                            if exe.exists() {
-                                info!("Found Proton in extra library at: {}", exe.display());
-                                return Ok(exe);
-                            }
-                        }
-                    }
+                    libraries.push(PathBuf::from(&path_str).join("steamapps/common"));
                 }
             }
         }
     }
 
-    error!("Proton executable not found in any known location.");
-    Err(ProtonError::ProtonNotFound(
-        "Proton not found. Install it via Steam (Library → Tools → 'Proton X.Y') \
-         or set the PROTON_PATH environment variable to its location."
-            .to_string(),
-    ))
+    libraries
+}
+
+/// `Proton*/proton` scripts directly inside a `steamapps/common` directory,
+/// newest version first by name.
+fn proton_binaries_in(steamapps: &Path) -> Vec<PathBuf> {
+    proton_dirs_in(steamapps)
+        .into_iter()
+        .map(|dir| dir.join("proton"))
+        .filter(|exe| exe.exists())
+        .collect()
+}
+
+/// `Proton*` directories inside a `steamapps/common` directory, newest
+/// version first by name.
+fn proton_dirs_in(steamapps: &Path) -> Vec<PathBuf> {
+    if !steamapps.is_dir() {
+        return Vec::new();
+    }
+    debug!("Searching for Proton in {}", steamapps.display());
+
+    // Collect all Proton* subdirectories, then sort descending so we get the
+    // newest version first (e.g. "Proton 9.0" before "Proton 8.0").
+    let mut proton_dirs: Vec<PathBuf> = fs::read_dir(steamapps)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.is_dir()
+                        && p.file_name()
+                            .is_some_and(|n| n.to_string_lossy().starts_with("Proton"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    proton_dirs.sort_by(|a, b| b.cmp(a)); // descending — newest version first
+    proton_dirs
+}
+
+/// A WINEPREFIX as recorded in the prefix index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PrefixEntry {
+    path: PathBuf,
+    game: String,
+    last_used: SystemTime,
+}
+
+/// A WINEPREFIX Hydra created for a game instance
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefixInfo {
+    pub path: PathBuf,
+    /// File name of the game executable the prefix was created for
+    pub game: String,
+    /// Disk space the prefix takes up, in bytes
+    pub size: u64,
+    /// When an instance was last launched with the prefix
+    pub last_used: SystemTime,
+}
+
+impl PrefixEntry {
+    fn info(&self) -> PrefixInfo {
+        PrefixInfo {
+            path: self.path.clone(),
+            game: self.game.clone(),
+            size: dir_size(&self.path),
+            last_used: self.last_used,
+        }
+    }
+}
+
+/// Records that `game` was just launched with `prefixes`, in the index in
+/// `base_dir`, so they can be listed and removed later.
+pub fn record_prefixes(base_dir: &Path, game: &str, prefixes: &[PathBuf]) -> Result<(), ProtonError> {
+    let mut entries = load_prefix_index(base_dir)?;
+    let now = SystemTime::now();
+    for prefix in prefixes {
+        match entries.iter_mut().find(|entry| entry.path == *prefix) {
+            Some(entry) => {
+                entry.game = game.to_string();
+                entry.last_used = now;
+            }
+            None => entries.push(PrefixEntry { path: prefix.clone(), game: game.to_string(), last_used: now }),
+        }
+    }
+    save_prefix_index(base_dir, &entries)
+}
+
+/// The WINEPREFIXes recorded in the index in `base_dir` that still exist,
+/// largest first.
+pub fn list_managed_prefixes(base_dir: &Path) -> Vec<PrefixInfo> {
+    let entries = match load_prefix_index(base_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Could not read the WINEPREFIX index: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut prefixes: Vec<PrefixInfo> =
+        entries.iter().filter(|entry| entry.path.is_dir()).map(PrefixEntry::info).collect();
+    prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.size));
+    prefixes
+}
+
+/// Deletes every recorded prefix of `game` and returns what was removed.
+pub fn remove_game_prefixes(base_dir: &Path, game: &str) -> Result<Vec<PrefixInfo>, ProtonError> {
+    remove_prefixes_where(base_dir, |entry| entry.game == game)
+}
+
+/// Deletes the recorded prefixes no instance has used for `unused_for`,
+/// forgets the ones that were deleted by hand, and returns what was removed.
+pub fn clean_prefixes(base_dir: &Path, unused_for: Duration) -> Result<Vec<PrefixInfo>, ProtonError> {
+    remove_prefixes_where(base_dir, |entry| {
+        entry.last_used.elapsed().map(|age| age >= unused_for).unwrap_or(false)
+    })
+}
+
+fn remove_prefixes_where(base_dir: &Path, remove: impl Fn(&PrefixEntry) -> bool) -> Result<Vec<PrefixInfo>, ProtonError> {
+    let (doomed, kept): (Vec<PrefixEntry>, Vec<PrefixEntry>) = load_prefix_index(base_dir)?
+        .into_iter()
+        .filter(|entry| entry.path.is_dir())
+        .partition(|entry| remove(entry));
+    let mut removed = Vec::new();
+    for entry in doomed {
+        // The index is a plain file; never delete anything but a prefix directory
+        if !is_prefix_dir_name(&entry.path) {
+            warn!("Not deleting {}: it isn't a WINEPREFIX Hydra created", entry.path.display());
+            continue;
+        }
+        let info = entry.info();
+        fs::remove_dir_all(&entry.path)?;
+        info!("Removed WINEPREFIX {} of {}", entry.path.display(), entry.game);
+        removed.push(info);
+    }
+    save_prefix_index(base_dir, &kept)?;
+    Ok(removed)
+}
+
+/// Whether `path` is named like a WINEPREFIX Hydra creates.
+fn is_prefix_dir_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    name == PREFIX_DIR_NAME
+        || name
+            .strip_prefix(PREFIX_DIR_NAME)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn load_prefix_index(base_dir: &Path) -> Result<Vec<PrefixEntry>, ProtonError> {
+    match fs::read_to_string(base_dir.join(PREFIX_INDEX_FILE)) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|e| ProtonError::PrefixIndex(e.to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_prefix_index(base_dir: &Path, entries: &[PrefixEntry]) -> Result<(), ProtonError> {
+    let json = serde_json::to_string_pretty(entries).map_err(|e| ProtonError::PrefixIndex(e.to_string()))?;
+    fs::create_dir_all(base_dir)?;
+    crate::utils::write_atomic(&base_dir.join(PREFIX_INDEX_FILE), json.as_bytes())?;
+    Ok(())
+}
+
+/// Total size of the files under `dir`. Symlinks aren't followed: a
+/// prefix's `dosdevices` links point at the whole filesystem.
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) if meta.is_file() => meta.len(),
+            _ => 0,
+        })
+        .sum()
 }
 
 #[cfg(test)]
@@ -191,6 +551,45 @@ mod tests {
     use tempfile::tempdir;
     use std::fs;
 
+    #[test]
+    fn test_managed_prefixes() {
+        let data_dir = tempdir().unwrap();
+        let games = tempdir().unwrap();
+        let prefix = |game: &str, instance: usize| {
+            let path = games.path().join(game).join(format!("instance_{}", instance)).join(PREFIX_DIR_NAME);
+            fs::create_dir_all(path.join("drive_c")).unwrap();
+            path
+        };
+        let borderlands = [prefix("Borderlands", 0), prefix("Borderlands", 1)];
+        fs::write(borderlands[0].join("drive_c/save.dat"), [0u8; 2048]).unwrap();
+        // dosdevices links would make the size the whole disk if followed
+        std::os::unix::fs::symlink("/", borderlands[0].join("z:")).unwrap();
+        // Instances running as another user keep theirs in that user's home
+        let run_as_prefix = games.path().join("home/.local/share/hydra-coop/wineprefix_1");
+        fs::create_dir_all(&run_as_prefix).unwrap();
+        let terraria = [prefix("Terraria", 0), run_as_prefix.clone()];
+        record_prefixes(data_dir.path(), "Borderlands.exe", &borderlands).unwrap();
+        record_prefixes(data_dir.path(), "Terraria.exe", &terraria).unwrap();
+        record_prefixes(data_dir.path(), "Terraria.exe", &terraria).unwrap();
+
+        let listed = list_managed_prefixes(data_dir.path());
+        assert_eq!(listed.len(), 4);
+        assert_eq!(listed[0].path, borderlands[0]);
+        assert_eq!(listed[0].size, 2048);
+        assert_eq!(listed[0].game, "Borderlands.exe");
+
+        let removed = remove_game_prefixes(data_dir.path(), "Borderlands.exe").unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!borderlands[0].exists() && !borderlands[1].exists());
+        assert!(games.path().join("Borderlands/instance_0").exists());
+        assert_eq!(list_managed_prefixes(data_dir.path()).len(), 2);
+
+        assert!(clean_prefixes(data_dir.path(), Duration::from_secs(3600)).unwrap().is_empty());
+        assert_eq!(clean_prefixes(data_dir.path(), Duration::ZERO).unwrap().len(), 2);
+        assert!(!run_as_prefix.exists());
+        assert!(list_managed_prefixes(data_dir.path()).is_empty());
+    }
+
     #[test]
     fn test_is_windows_binary_mz_header() {
         let temp_dir = tempdir().expect("Failed to create temp dir");
@@ -225,4 +624,95 @@ mod tests {
         let is_binary = is_windows_binary(&non_existent_file).expect("Error checking binary type for non-existent file");
         assert!(!is_binary);
     }
+
+    /// A minimal PE file: DOS header pointing at a PE header at 0x80
+    fn pe_file(machine: u16, magic: u16) -> Vec<u8> {
+        let mut data = vec![0u8; 0x80 + 26];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        data[0x80..0x84].copy_from_slice(b"PE\0\0");
+        data[0x84..0x86].copy_from_slice(&machine.to_le_bytes());
+        data[0x80 + 24..].copy_from_slice(&magic.to_le_bytes());
+        data
+    }
+
+    /// The first 20 bytes of a little-endian ELF file
+    fn elf_file(class: u8, machine: u16) -> Vec<u8> {
+        let mut data = vec![0x7f, b'E', b'L', b'F', class, 1, 1];
+        data.resize(18, 0);
+        data.extend_from_slice(&machine.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_analyze_binary() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let analyze = |name: &str, data: &[u8]| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, data).unwrap();
+            analyze_binary(&path).unwrap()
+        };
+
+        let pe32 = analyze("game32.exe", &pe_file(0x014c, 0x010b));
+        assert_eq!(pe32, BinaryInfo { format: BinaryFormat::Pe, bits: Some(32), arch: BinaryArch::X86 });
+        let pe64 = analyze("game64.exe", &pe_file(0x8664, 0x020b));
+        assert_eq!(pe64, BinaryInfo { format: BinaryFormat::Pe, bits: Some(64), arch: BinaryArch::X86_64 });
+        let elf32 = analyze("game.x86", &elf_file(1, 3));
+        assert_eq!(elf32, BinaryInfo { format: BinaryFormat::Elf, bits: Some(32), arch: BinaryArch::X86 });
+        let elf64 = analyze("game.arm64", &elf_file(2, 183));
+        assert_eq!(elf64, BinaryInfo { format: BinaryFormat::Elf, bits: Some(64), arch: BinaryArch::Aarch64 });
+
+        // An MZ header whose PE offset points past the end is still a Windows program
+        let mut truncated = pe_file(0x8664, 0x020b);
+        truncated.truncate(0x40);
+        let dos = analyze("truncated.exe", &truncated);
+        assert_eq!((dos.format, dos.bits), (BinaryFormat::Pe, None));
+        assert_eq!(analyze("script.sh", b"#!/bin/sh\n").format, BinaryFormat::Other);
+    }
+
+    #[test]
+    fn test_proton_binaries_newest_first() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        for name in ["Proton 8.0", "Proton 9.0", "Proton - Experimental", "Steam Linux Runtime"] {
+            let dir = temp_dir.path().join(name);
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join("proton"), b"#!/usr/bin/env python3\n").unwrap();
+        }
+        // A Proton directory without the script is skipped
+        fs::create_dir(temp_dir.path().join("Proton 7.0")).unwrap();
+
+        let names: Vec<String> = proton_binaries_in(temp_dir.path())
+            .iter()
+            .map(|exe| exe.parent().unwrap().file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["Proton 9.0", "Proton 8.0", "Proton - Experimental"]);
+        assert!(proton_binaries_in(&temp_dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_steam_running_in() {
+        let proc_root = tempdir().expect("Failed to create temp dir");
+        for (pid, comm) in [("100", "bash\n"), ("200", "steamwebhelper\n")] {
+            fs::create_dir(proc_root.path().join(pid)).unwrap();
+            fs::write(proc_root.path().join(pid).join("comm"), comm).unwrap();
+        }
+        assert!(!steam_running_in(proc_root.path()));
+
+        fs::create_dir(proc_root.path().join("300")).unwrap();
+        fs::write(proc_root.path().join("300/comm"), "steam\n").unwrap();
+        assert!(steam_running_in(proc_root.path()));
+    }
+
+    #[test]
+    fn test_proton_not_found_guidance() {
+        let downloading = ProtonError::ProtonNotFound {
+            incomplete: vec![PathBuf::from("/steamapps/common/Proton 9.0")],
+            steam_running: false,
+        };
+        assert!(downloading.to_string().contains("Start Steam to let it finish installing Proton"));
+
+        let missing = ProtonError::ProtonNotFound { incomplete: Vec::new(), steam_running: true };
+        assert!(missing.to_string().starts_with("Proton not found. Install it via Steam"));
+        assert!(!missing.to_string().contains("running"));
+    }
 }
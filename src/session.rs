@@ -0,0 +1,225 @@
+//! Session Manifests
+//!
+//! A session manifest is a JSON record of everything a launch consisted of:
+//! the game, its detected profile, the generated configuration, and what each
+//! instance ended up with. It is written to the data directory at launch and
+//! can be fed back through `--from-session` to replay an identical setup.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use log::{info, debug};
+use crate::game_detection::{fnv1a, fnv1a_new, GameConfiguration, GameProfile};
+use crate::input_mux::InputAssignment;
+use crate::universal_launcher::WindowsRuntime;
+use crate::window_manager::Layout;
+
+/// File name used for the manifest inside the data directory.
+pub const SESSION_FILE_NAME: &str = "session.json";
+
+/// Error type for session manifest operations.
+#[derive(Debug)]
+pub enum SessionError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::Io(e) => write!(f, "session manifest I/O error: {}", e),
+            SessionError::Json(e) => write!(f, "session manifest format error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SessionError::Io(e) => Some(e),
+            SessionError::Json(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for SessionError {
+    fn from(e: io::Error) -> Self {
+        SessionError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SessionError {
+    fn from(e: serde_json::Error) -> Self {
+        SessionError::Json(e)
+    }
+}
+
+/// Complete record of a launched session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    /// Launch time in seconds since the Unix epoch
+    pub created_at: u64,
+    /// PID of the launcher process that ran the session
+    #[serde(default)]
+    pub launcher_pid: Option<u32>,
+    /// Game executable that was launched
+    pub game_path: PathBuf,
+    /// Content hash of the game executable, if it could be read
+    pub game_hash: Option<String>,
+    /// Profile produced by game detection
+    pub profile: GameProfile,
+    /// Configuration the launcher generated from the profile
+    pub game_config: GameConfiguration,
+    /// Whether instances were started through Proton; kept for older
+    /// versions, see `runtime`
+    pub use_proton: bool,
+    /// What ran the game if it is a Windows program
+    #[serde(default)]
+    pub windows_runtime: WindowsRuntime,
+    /// Window layout applied to the instances
+    pub layout: Layout,
+    /// Input assignment requested for each instance
+    pub input_assignments: Vec<(usize, InputAssignment)>,
+    /// What each instance ended up with
+    pub instances: Vec<InstanceRecord>,
+}
+
+/// Per-instance launch details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceRecord {
+    pub id: usize,
+    pub pid: u32,
+    /// Port the game instance was configured to use
+    pub game_port: Option<u16>,
+    /// Port of the network emulator socket bound for this instance
+    pub emulator_port: Option<u16>,
+    pub working_dir: PathBuf,
+}
+
+impl SessionManifest {
+    /// Seconds since the Unix epoch, for stamping new manifests
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    /// The Windows runtime the session used, also for manifests written
+    /// before `windows_runtime` was recorded.
+    pub fn runtime(&self) -> WindowsRuntime {
+        self.windows_runtime.or_proton(self.use_proton)
+    }
+}
+
+/// Writes a manifest as pretty-printed JSON, creating parent directories.
+pub fn save(manifest: &SessionManifest, path: &Path) -> Result<(), SessionError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(manifest)?;
+    fs::write(path, json)?;
+    info!("Session manifest written to {}", path.display());
+    Ok(())
+}
+
+/// Reads a manifest previously written by [`save`].
+pub fn load(path: &Path) -> Result<SessionManifest, SessionError> {
+    debug!("Loading session manifest from {}", path.display());
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Hashes a file's contents with 64-bit FNV-1a and returns it as hex.
+/// Stable across runs and platforms, which is all identification needs.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buf = [0u8; 64 * 1024];
+    let mut hash = fnv1a_new();
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hash = fnv1a(hash, &buf[..read]);
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+    use crate::game_detection::{InstanceSeparation, MultiInstanceSupport, NetworkMode, WorkingDirStrategy};
+
+    #[test]
+    fn test_save_and_load_manifest() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("nested").join(SESSION_FILE_NAME);
+
+        let manifest = SessionManifest {
+            created_at: SessionManifest::now(),
+            launcher_pid: Some(4000),
+            game_path: PathBuf::from("/games/test/game.x86_64"),
+            game_hash: Some("0123456789abcdef".to_string()),
+            profile: GameProfile {
+                executable_pattern: "game.x86_64".to_string(),
+                engine: None,
+                default_ports: vec![7777],
+                default_layout: "horizontal".to_string(),
+                multi_instance_support: MultiInstanceSupport::Configurable,
+                launch_args: vec![],
+                environment_vars: HashMap::new(),
+                working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+                network_mode: NetworkMode::RawUdp,
+                instance_blockers: Vec::new(),
+            },
+            game_config: GameConfiguration {
+                ports: vec![7777, 7778],
+                layout: "horizontal".to_string(),
+                launch_args: vec![],
+                environment_vars: HashMap::new(),
+                working_dir_strategy: WorkingDirStrategy::SeparateDirectories,
+                instance_separation: InstanceSeparation::Environment,
+                instance_args: Vec::new(),
+            },
+            use_proton: false,
+            windows_runtime: WindowsRuntime::Wine,
+            layout: Layout::Vertical,
+            input_assignments: vec![(0, InputAssignment::AutoDetect), (1, InputAssignment::None)],
+            instances: vec![InstanceRecord {
+                id: 0,
+                pid: 4242,
+                game_port: Some(7777),
+                emulator_port: Some(40000),
+                working_dir: PathBuf::from("/games/test/instance_0"),
+            }],
+        };
+
+        save(&manifest, &path).unwrap();
+        let loaded = load(&path).unwrap();
+
+        assert_eq!(loaded.game_path, manifest.game_path);
+        assert_eq!(loaded.layout, Layout::Vertical);
+        assert_eq!(loaded.runtime(), WindowsRuntime::Wine);
+        assert_eq!(loaded.input_assignments, manifest.input_assignments);
+        assert_eq!(loaded.instances.len(), 1);
+        assert_eq!(loaded.instances[0].emulator_port, Some(40000));
+    }
+
+    #[test]
+    fn test_hash_file_is_stable() {
+        let temp_dir = tempdir().unwrap();
+        let a = temp_dir.path().join("a.bin");
+        let b = temp_dir.path().join("b.bin");
+        fs::write(&a, b"same contents").unwrap();
+        fs::write(&b, b"same contents").unwrap();
+
+        assert_eq!(hash_file(&a).unwrap(), hash_file(&b).unwrap());
+        fs::write(&b, b"other contents").unwrap();
+        assert_ne!(hash_file(&a).unwrap(), hash_file(&b).unwrap());
+    }
+}
@@ -0,0 +1,181 @@
+//! Per-Instance Templates
+//!
+//! Launch arguments and environment values can refer to the instance they
+//! are passed to, e.g. `-player=${INSTANCE}` or `SAVE_DIR=${WORKDIR}/saves`.
+//! `${NAME}` is replaced with the variable's value and `$$` stands for a
+//! literal `$`. Unknown variables are left as written, with a warning. The
+//! older `{instance}` and `{port}` still work as `${INSTANCE}` and `${PORT}`.
+
+use std::ffi::OsString;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use log::warn;
+
+/// Placeholders from before `${NAME}` variables, and the variable each
+/// stands for
+const LEGACY_PLACEHOLDERS: [(&str, &str); 2] = [("{instance}", "INSTANCE"), ("{port}", "PORT")];
+
+/// What a template can refer to for one game instance
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    /// 0-based instance index (`${INSTANCE_ID}`; `${INSTANCE}` is the
+    /// 1-based player number)
+    pub instance_id: usize,
+    /// The instance's network port (`${PORT}`), if it has one
+    pub port: Option<u16>,
+    /// Loopback address the instance should bind to (`${LOOPBACK_IP}`)
+    pub loopback: Ipv4Addr,
+    /// Size of the instance's window in the layout (`${WIDTH}`, `${HEIGHT}`),
+    /// when known before launch
+    pub resolution: Option<(u32, u32)>,
+    /// Directory the instance runs in (`${WORKDIR}`)
+    pub working_dir: PathBuf,
+    /// Number of instances in the session (`${INSTANCE_COUNT}`)
+    pub instance_count: usize,
+    /// Short id shared by everything in the session (`${SESSION_ID}`)
+    pub session_id: String,
+}
+
+/// The name `TemplateContext` had before `instance::InstanceContext` took it
+#[deprecated(note = "renamed to `TemplateContext`")]
+pub type InstanceContext = TemplateContext;
+
+impl TemplateContext {
+    /// The variable's value; an `OsString` because `${WORKDIR}` is a path,
+    /// which needn't be valid UTF-8.
+    fn lookup(&self, name: &str) -> Option<OsString> {
+        match name {
+            "INSTANCE_ID" => Some(self.instance_id.to_string().into()),
+            "INSTANCE" => Some((self.instance_id + 1).to_string().into()),
+            "PORT" => self.port.map(|port| port.to_string().into()),
+            "LOOPBACK_IP" => Some(self.loopback.to_string().into()),
+            "WIDTH" => self.resolution.map(|(width, _)| width.to_string().into()),
+            "HEIGHT" => self.resolution.map(|(_, height)| height.to_string().into()),
+            "WORKDIR" => Some(self.working_dir.clone().into_os_string()),
+            "INSTANCE_COUNT" => Some(self.instance_count.to_string().into()),
+            "SESSION_ID" => Some(self.session_id.clone().into()),
+            _ => None,
+        }
+    }
+}
+
+/// Expands the `${NAME}` variables in `s` for the instance described by `ctx`.
+/// A working directory that isn't valid UTF-8 is expanded lossily; use
+/// [`expand_template_os`] for values passed on to the game.
+pub fn expand_template(s: &str, ctx: &TemplateContext) -> String {
+    let expanded = expand_template_os(s, ctx);
+    expanded.into_string().unwrap_or_else(|expanded| expanded.to_string_lossy().into_owned())
+}
+
+/// Like [`expand_template`], but keeps `${WORKDIR}` byte for byte, for
+/// arguments and environment values handed to the game.
+pub fn expand_template_os(s: &str, ctx: &TemplateContext) -> OsString {
+    let mut out = OsString::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(dollar) = rest.find(['$', '{']) {
+        out.push(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        if rest[dollar..].starts_with('{') {
+            let legacy = LEGACY_PLACEHOLDERS
+                .iter()
+                .find(|(placeholder, _)| rest[dollar..].starts_with(placeholder))
+                .and_then(|(placeholder, name)| Some((placeholder.len(), ctx.lookup(name)?)));
+            match legacy {
+                Some((len, value)) => {
+                    out.push(value);
+                    rest = &rest[dollar + len..];
+                }
+                None => {
+                    out.push("{");
+                    rest = after;
+                }
+            }
+        } else if let Some(tail) = after.strip_prefix('$') {
+            out.push("$");
+            rest = tail;
+        } else if let Some((name, tail)) = after.strip_prefix('{').and_then(|inner| inner.split_once('}')) {
+            match ctx.lookup(name) {
+                Some(value) => out.push(value),
+                None => {
+                    warn!("Unknown variable ${{{}}} in \"{}\"; leaving it as is", name, s);
+                    out.push(&rest[dollar..dollar + name.len() + 3]);
+                }
+            }
+            rest = tail;
+        } else {
+            out.push("$");
+            rest = after;
+        }
+    }
+    out.push(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> TemplateContext {
+        TemplateContext {
+            instance_id: 1,
+            port: Some(27016),
+            loopback: Ipv4Addr::new(127, 0, 0, 3),
+            resolution: Some((960, 540)),
+            working_dir: PathBuf::from("/games/instance_1"),
+            instance_count: 2,
+            session_id: "beef".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand_known_variables() {
+        let ctx = context();
+        assert_eq!(expand_template("-player=${INSTANCE}", &ctx), "-player=2");
+        assert_eq!(expand_template("${INSTANCE_ID}/${INSTANCE_COUNT}", &ctx), "1/2");
+        assert_eq!(expand_template("+clientport ${PORT}", &ctx), "+clientport 27016");
+        assert_eq!(expand_template("${WORKDIR}/saves", &ctx), "/games/instance_1/saves");
+        assert_eq!(expand_template("hydra-${SESSION_ID}", &ctx), "hydra-beef");
+        assert_eq!(expand_template("-bind ${LOOPBACK_IP}:${PORT}", &ctx), "-bind 127.0.0.3:27016");
+        assert_eq!(expand_template("-ResX=${WIDTH} -ResY=${HEIGHT}", &ctx), "-ResX=960 -ResY=540");
+    }
+
+    #[test]
+    fn test_unknown_variables_stay_literal() {
+        let mut ctx = context();
+        assert_eq!(expand_template("${HOME}/x ${INSTANCE}", &ctx), "${HOME}/x 2");
+        ctx.port = None;
+        assert_eq!(expand_template("-port=${PORT}", &ctx), "-port=${PORT}");
+        ctx.resolution = None;
+        assert_eq!(expand_template("${WIDTH}", &ctx), "${WIDTH}");
+        // An unterminated reference isn't a variable at all
+        assert_eq!(expand_template("${INSTANCE", &ctx), "${INSTANCE");
+    }
+
+    #[test]
+    fn test_workdir_keeps_non_utf8_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let mut ctx = context();
+        ctx.working_dir = PathBuf::from(OsStr::from_bytes(b"/games/Caf\xe9 1"));
+        assert_eq!(expand_template_os("${WORKDIR}/saves", &ctx), OsStr::from_bytes(b"/games/Caf\xe9 1/saves"));
+        assert_eq!(expand_template("${WORKDIR}", &ctx), "/games/Caf\u{fffd} 1");
+    }
+
+    #[test]
+    fn test_escaping() {
+        let ctx = context();
+        assert_eq!(expand_template("$${INSTANCE}", &ctx), "${INSTANCE}");
+        assert_eq!(expand_template("cost: 5$ and $$$$", &ctx), "cost: 5$ and $$");
+        assert_eq!(expand_template("$$$$${PORT}", &ctx), "$$27016");
+    }
+
+    #[test]
+    fn test_legacy_placeholders() {
+        let mut ctx = context();
+        assert_eq!(expand_template("-player={instance}", &ctx), "-player=2");
+        assert_eq!(expand_template("+clientport {port} ${PORT}", &ctx), "+clientport 27016 27016");
+        assert_eq!(expand_template("{\"name\": {other}}", &ctx), "{\"name\": {other}}");
+        ctx.port = None;
+        assert_eq!(expand_template("{port}", &ctx), "{port}");
+    }
+}
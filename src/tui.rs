@@ -0,0 +1,634 @@
+//! Terminal User Interface
+//!
+//! A keyboard-driven alternative to the GTK GUI for terminals where it can't
+//! run, such as over SSH: pick the game, the number of players and their
+//! input devices (optionally by having each player press a button), the
+//! layout and the Windows runtime, then launch. While the session runs the
+//! screen shows how many instances are up, the network relay's counts, whether
+//! input is paused, which player each device controls, and the latest log
+//! lines. Devices can be moved to another player without restarting.
+//!
+//! The logger sends its lines here (see `logging::init_to_channel`) instead
+//! of printing them over the screen.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use log::{info, warn};
+
+use crate::config::{Config, LayoutConfig};
+use crate::errors::{HydraError, Result};
+use crate::input_mux::{CapabilityReport, DeviceIdentifier, InputAssignment, InputMux};
+use crate::net_emulator::RelayStats;
+use crate::universal_launcher::WindowsRuntime;
+use crate::{fill_network_ports, format_size, remember_game, run_core_logic, supervise_session};
+
+/// Log lines kept for the log pane
+const LOG_LINES: usize = 200;
+/// How long to wait for a key before redrawing
+const TICK: Duration = Duration::from_millis(250);
+
+/// Layouts offered besides the one in the config
+const PRESET_LAYOUTS: [LayoutConfig; 5] = [
+    LayoutConfig::Horizontal,
+    LayoutConfig::Vertical,
+    LayoutConfig::Grid2x2,
+    LayoutConfig::Grid3x1,
+    LayoutConfig::Tabbed,
+];
+const RUNTIMES: [WindowsRuntime; 3] = [WindowsRuntime::None, WindowsRuntime::Proton, WindowsRuntime::Wine];
+
+/// One line of the setup screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Game,
+    Players,
+    Input(usize),
+    AssignByButton,
+    Layout,
+    Runtime,
+    Launch,
+}
+
+/// A player's input device as chosen on the setup screen
+#[derive(Debug, Clone, PartialEq)]
+enum InputChoice {
+    AutoDetect,
+    Device(DeviceIdentifier),
+}
+
+impl InputChoice {
+    fn label(&self) -> &str {
+        match self {
+            InputChoice::AutoDetect => "Auto-detect",
+            InputChoice::Device(device) => &device.name,
+        }
+    }
+
+    fn assignment(&self) -> InputAssignment {
+        match self {
+            InputChoice::AutoDetect => InputAssignment::AutoDetect,
+            InputChoice::Device(device) => InputAssignment::Device(device.clone()),
+        }
+    }
+}
+
+/// Everything `run_core_logic` needs, checked on the setup screen
+struct LaunchPlan {
+    game_path: PathBuf,
+    num_instances: usize,
+    assignments: Vec<(usize, InputAssignment)>,
+    config: Config,
+}
+
+/// What the session thread reports back to the screen
+enum SessionEvent {
+    /// The instances are up; the flag pauses the players' input
+    Started(Arc<AtomicBool>),
+    Finished(Result<()>),
+}
+
+/// Counts the session thread refreshes for the status lines
+#[derive(Debug, Default, Clone)]
+struct SessionStatus {
+    running: usize,
+    relay: RelayStats,
+    /// A capture or relay thread died, see `InputMux::degraded_flag`
+    degraded: bool,
+    /// Input codes the virtual devices were created without, see
+    /// `InputMux::capability_reports`
+    skipped_input_codes: usize,
+    /// Which player each device is routed to, see `InputMux::current_assignments`
+    assignments: Vec<(DeviceIdentifier, usize)>,
+}
+
+/// Raw mode on the alternate screen while alive; the terminal is restored on
+/// drop, also when unwinding from a panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the text interface until the user quits. `controllers` are offered
+/// as the players' devices, `config` supplies the initial choices and every
+/// setting the screen doesn't edit, and `logs` receives the log lines.
+pub fn run_tui(controllers: Vec<Vec<DeviceIdentifier>>, config: Config, logs: Receiver<String>) -> Result<()> {
+    let mut tui = Tui::new(controllers, config, logs);
+    let _terminal = TerminalGuard::enter()?;
+    let mut out = io::stdout();
+    while let Some(plan) = tui.setup(&mut out)? {
+        if !tui.session(&mut out, plan)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Tui {
+    base_config: Config,
+    /// Primary interface of each controller, offered in the input rows
+    devices: Vec<DeviceIdentifier>,
+    game: String,
+    num_instances: usize,
+    /// One per possible player; only the first `num_instances` are used
+    inputs: Vec<InputChoice>,
+    layouts: Vec<LayoutConfig>,
+    layout: usize,
+    runtime: WindowsRuntime,
+    selected: usize,
+    /// Shown under the setup rows, e.g. why a launch was refused
+    message: Option<String>,
+    logs: Receiver<String>,
+    log_lines: VecDeque<String>,
+}
+
+impl Tui {
+    fn new(controllers: Vec<Vec<DeviceIdentifier>>, config: Config, logs: Receiver<String>) -> Self {
+        let devices: Vec<DeviceIdentifier> = controllers.into_iter().filter_map(|group| group.into_iter().next()).collect();
+        // Controllers sharing a name go to the players naming it in turn
+        let mut taken: Vec<&DeviceIdentifier> = Vec::new();
+        let inputs = (0..crate::defaults::MAX_INSTANCES)
+            .map(|player| {
+                let device = config
+                    .input_mappings
+                    .get(player)
+                    .and_then(|name| devices.iter().find(|device| device.name == *name && !taken.contains(device)));
+                match device {
+                    Some(device) => {
+                        taken.push(device);
+                        InputChoice::Device(device.clone())
+                    }
+                    None => InputChoice::AutoDetect,
+                }
+            })
+            .collect();
+        let mut layouts = vec![config.window_layout.clone()];
+        layouts.extend(PRESET_LAYOUTS.into_iter().filter(|preset| *preset != config.window_layout));
+
+        Tui {
+            game: config
+                .primary_game_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            num_instances: config.instances.clamp(1, crate::defaults::MAX_INSTANCES),
+            runtime: config.runtime(),
+            base_config: config,
+            devices,
+            inputs,
+            layouts,
+            layout: 0,
+            selected: 0,
+            message: None,
+            logs,
+            log_lines: VecDeque::new(),
+        }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![Row::Game, Row::Players];
+        rows.extend((0..self.num_instances).map(Row::Input));
+        rows.extend([Row::AssignByButton, Row::Layout, Row::Runtime, Row::Launch]);
+        rows
+    }
+
+    /// The setup screen; returns what to launch, or `None` when the user quits.
+    fn setup(&mut self, out: &mut impl Write) -> Result<Option<LaunchPlan>> {
+        loop {
+            self.draw_setup(out)?;
+            let Some(key) = next_key()? else { continue };
+            let rows = self.rows();
+            let row = rows[self.selected.min(rows.len() - 1)];
+            match key.code {
+                _ if is_interrupt(&key) => return Ok(None),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Up | KeyCode::BackTab => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Tab => self.selected = (self.selected + 1).min(rows.len() - 1),
+                KeyCode::Left => self.change(row, -1),
+                KeyCode::Right => self.change(row, 1),
+                KeyCode::Backspace if row == Row::Game => {
+                    self.game.pop();
+                }
+                KeyCode::Char(c) if row == Row::Game => self.game.push(c),
+                KeyCode::Enter if row == Row::AssignByButton => self.assign_by_button(out)?,
+                KeyCode::Enter if row == Row::Launch => match self.plan() {
+                    Ok(plan) => {
+                        self.message = None;
+                        return Ok(Some(plan));
+                    }
+                    Err(e) => self.message = Some(e.to_string()),
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Steps the value on `row` forwards or backwards.
+    fn change(&mut self, row: Row, step: isize) {
+        match row {
+            Row::Players => {
+                self.num_instances = self
+                    .num_instances
+                    .saturating_add_signed(step)
+                    .clamp(1, crate::defaults::MAX_INSTANCES);
+                self.selected = self.selected.min(self.rows().len() - 1);
+            }
+            Row::Input(player) => {
+                // Auto-detect, then every controller
+                let current = match &self.inputs[player] {
+                    InputChoice::AutoDetect => 0,
+                    InputChoice::Device(device) => {
+                        self.devices.iter().position(|d| d == device).map_or(0, |i| i + 1)
+                    }
+                };
+                let next = cycle(current, self.devices.len() + 1, step);
+                self.inputs[player] = match next {
+                    0 => InputChoice::AutoDetect,
+                    i => InputChoice::Device(self.devices[i - 1].clone()),
+                };
+            }
+            Row::Layout => self.layout = cycle(self.layout, self.layouts.len(), step),
+            Row::Runtime => {
+                let current = RUNTIMES.iter().position(|r| *r == self.runtime).unwrap_or(0);
+                self.runtime = RUNTIMES[cycle(current, RUNTIMES.len(), step)];
+            }
+            Row::Game | Row::AssignByButton | Row::Launch => {}
+        }
+    }
+
+    /// Asks each player in turn to press a button, like `--assign-by-button`.
+    /// Players who don't press one in time keep their previous device.
+    fn assign_by_button(&mut self, out: &mut impl Write) -> Result<()> {
+        let mut mux = InputMux::new();
+        mux.enumerate_devices()?;
+        let mut pressed: Vec<DeviceIdentifier> = Vec::new();
+        let mut player = 0;
+        self.message = None;
+        while player < self.num_instances {
+            if self.message.is_none() {
+                self.message = Some(format!("Player {}, press any button on your controller or keyboard…", player + 1));
+            }
+            self.draw_setup(out)?;
+            let deadline = Instant::now() + crate::defaults::BUTTON_ASSIGN_TIMEOUT;
+            let Some(device) = mux.detect_next_pressed(deadline.saturating_duration_since(Instant::now())) else {
+                self.message = Some(format!("No button pressed for player {}; stopped assigning.", player + 1));
+                break;
+            };
+            match pressed.iter().position(|taken| *taken == device) {
+                Some(owner) => {
+                    self.message = Some(format!(
+                        "'{}' is already player {}'s; player {}, press a button on another device.",
+                        device.name,
+                        owner + 1,
+                        player + 1
+                    ));
+                }
+                None => {
+                    self.inputs[player] = InputChoice::Device(device.clone());
+                    pressed.push(device);
+                    self.message = None;
+                    player += 1;
+                }
+            }
+        }
+        if player == self.num_instances {
+            self.message = Some("Every player has a device.".to_string());
+        }
+        // Keyboard presses reached the terminal too; don't act on them.
+        while event::poll(Duration::ZERO)? {
+            event::read()?;
+        }
+        Ok(())
+    }
+
+    /// The config and inputs for a launch with the current choices, checked
+    /// the way the CLI checks its arguments.
+    fn plan(&self) -> Result<LaunchPlan> {
+        let game = self.game.trim();
+        if game.is_empty() {
+            return Err(HydraError::validation("Enter the game's executable first"));
+        }
+        let game_path = crate::utils::expand_path(game);
+        let num_instances = self.num_instances;
+
+        let mut config = self.base_config.clone();
+        config.select_game_path(game_path.clone());
+        config.set_instances(num_instances);
+        for (mapping, input) in config.input_mappings.iter_mut().zip(&self.inputs) {
+            *mapping = input.label().to_string();
+        }
+        config.window_layout = self.layouts[self.layout].clone();
+        fill_network_ports(&mut config, num_instances);
+        config.set_runtime(self.runtime);
+        config.validate()?;
+        remember_game(&game_path);
+
+        Ok(LaunchPlan {
+            game_path,
+            num_instances,
+            assignments: self.inputs[..num_instances].iter().map(InputChoice::assignment).enumerate().collect(),
+            config,
+        })
+    }
+
+    /// The session screen. Returns `false` when the user asked to quit, and
+    /// `true` to go back to the setup screen once the session has ended.
+    fn session(&mut self, out: &mut impl Write, plan: LaunchPlan) -> Result<bool> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let status = Arc::new(Mutex::new(SessionStatus::default()));
+        let (events_tx, events) = mpsc::channel();
+        // Devices to route to another player, see `InputMux::reassign`
+        let (moves, move_requests) = mpsc::channel();
+        let total = plan.num_instances;
+        let game_name = plan
+            .game_path
+            .file_name()
+            .map_or_else(|| plan.game_path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        {
+            let stop = stop.clone();
+            let status = status.clone();
+            thread::spawn(move || {
+                let result = supervise(plan, &stop, &status, &events_tx, &move_requests);
+                let _ = events_tx.send(SessionEvent::Finished(result));
+            });
+        }
+
+        let mut input_paused: Option<Arc<AtomicBool>> = None;
+        let mut outcome: Option<Result<()>> = None;
+        let mut quit = false;
+        loop {
+            while let Ok(event) = events.try_recv() {
+                match event {
+                    SessionEvent::Started(flag) => input_paused = Some(flag),
+                    SessionEvent::Finished(result) => outcome = Some(result),
+                }
+            }
+
+            let current = status.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            let state = match (&outcome, &input_paused) {
+                (Some(Ok(())), _) => "Session ended".to_string(),
+                (Some(Err(e)), _) => format!("Session failed: {}", e),
+                (None, _) if stop.load(Ordering::SeqCst) => "Shutting down…".to_string(),
+                (None, Some(_)) if current.degraded => "Running, degraded (a background thread failed, see the log)".to_string(),
+                (None, Some(_)) => "Running".to_string(),
+                (None, None) => "Launching…".to_string(),
+            };
+            let paused = input_paused.as_ref().is_some_and(|flag| flag.load(Ordering::SeqCst));
+            let mut lines = vec![
+                format!("Hydra Co-op — {}", game_name),
+                String::new(),
+                format!("Status:    {}", state),
+                format!("Instances: {}/{} running", current.running, total),
+                format!(
+                    "Network:   received {}, forwarded {} ({}), dropped {}",
+                    current.relay.received,
+                    current.relay.forwarded,
+                    format_size(current.relay.bytes_forwarded),
+                    current.relay.dropped
+                ),
+                format!(
+                    "Input:     {}{}",
+                    if paused { "paused" } else { "active" },
+                    match current.skipped_input_codes {
+                        0 => String::new(),
+                        skipped => format!(" ({} input code(s) unavailable to games, see the log)", skipped),
+                    }
+                ),
+            ];
+            if outcome.is_none() && !current.assignments.is_empty() {
+                lines.push("Devices:".to_string());
+                for (i, (device, player)) in current.assignments.iter().enumerate() {
+                    lines.push(format!("  {}: {} → player {}", i + 1, device.name, player + 1));
+                }
+            }
+            lines.push(String::new());
+            lines.push(match outcome {
+                Some(_) => "Enter: back to setup   q: quit".to_string(),
+                None => "p: pause/resume input   1-9: move a device to the next player   q: shut down and quit".to_string(),
+            });
+            self.draw(out, &lines, None)?;
+
+            if outcome.is_some() && quit {
+                return Ok(false);
+            }
+            let Some(key) = next_key()? else { continue };
+            match key.code {
+                _ if is_interrupt(&key) || key.code == KeyCode::Char('q') => {
+                    if !stop.swap(true, Ordering::SeqCst) {
+                        info!("Shutting down the session.");
+                    }
+                    quit = true;
+                }
+                KeyCode::Char('p') => {
+                    if let Some(flag) = &input_paused {
+                        let paused = !flag.load(Ordering::SeqCst);
+                        flag.store(paused, Ordering::SeqCst);
+                        info!("Input {}.", if paused { "paused" } else { "resumed" });
+                    }
+                }
+                KeyCode::Char(digit @ '1'..='9') if outcome.is_none() => {
+                    let index = usize::from(digit as u8 - b'1');
+                    if let Some((device, player)) = current.assignments.get(index) {
+                        let _ = moves.send((device.clone(), cycle(*player, total, 1)));
+                    }
+                }
+                KeyCode::Enter | KeyCode::Esc if outcome.is_some() => return Ok(true),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_setup(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let rows = self.rows();
+        self.selected = self.selected.min(rows.len() - 1);
+        let mut lines = vec!["Hydra Co-op — set up a session".to_string(), String::new()];
+        for row in &rows {
+            lines.push(match *row {
+                Row::Game => format!("Game:            {}", self.game),
+                Row::Players => format!("Players:         < {} >", self.num_instances),
+                Row::Input(player) => format!("  Player {} input: < {} >", player + 1, self.inputs[player].label()),
+                Row::AssignByButton => "[ Assign inputs by pressing a button ]".to_string(),
+                Row::Layout => format!("Layout:          < {} >", self.layouts[self.layout].layout().name()),
+                Row::Runtime => format!("Windows runtime: < {} >", runtime_label(self.runtime)),
+                Row::Launch => "[ Launch ]".to_string(),
+            });
+        }
+        lines.push(String::new());
+        lines.push(self.message.clone().unwrap_or_default());
+        lines.push("Up/Down: move   Left/Right: change   Enter: select   type to edit the game   Esc: quit".to_string());
+        self.draw(out, &lines, Some(self.selected + 2))
+    }
+
+    /// Draws `lines` from the top, highlighting line `highlight`, and fills
+    /// the rest of the screen with the latest log lines.
+    fn draw(&mut self, out: &mut impl Write, lines: &[String], highlight: Option<usize>) -> io::Result<()> {
+        while let Ok(line) = self.logs.try_recv() {
+            if self.log_lines.len() == LOG_LINES {
+                self.log_lines.pop_front();
+            }
+            self.log_lines.push_back(line.trim_end().to_string());
+        }
+
+        let (width, height) = terminal::size()?;
+        let width = usize::from(width);
+        queue!(out, Clear(ClearType::All))?;
+        for (i, line) in lines.iter().enumerate().take(usize::from(height)) {
+            queue!(out, cursor::MoveTo(0, i as u16))?;
+            if highlight == Some(i) {
+                queue!(out, SetAttribute(Attribute::Reverse), Print(fit(line, width)), SetAttribute(Attribute::Reset))?;
+            } else {
+                queue!(out, Print(fit(line, width)))?;
+            }
+        }
+
+        let log_top = lines.len() + 1;
+        let log_rows = usize::from(height).saturating_sub(log_top + 1);
+        if log_rows > 0 {
+            queue!(out, cursor::MoveTo(0, log_top as u16), Print(fit("── Log ──", width)))?;
+            let skip = self.log_lines.len().saturating_sub(log_rows);
+            for (i, line) in self.log_lines.iter().skip(skip).enumerate() {
+                queue!(out, cursor::MoveTo(0, (log_top + 1 + i) as u16), Print(fit(line, width)))?;
+            }
+        }
+        out.flush()
+    }
+}
+
+/// Runs the session on its own thread, like the GUI does, so the screen keeps
+/// updating while the instances launch.
+fn supervise(
+    plan: LaunchPlan,
+    stop: &Arc<AtomicBool>,
+    status: &Mutex<SessionStatus>,
+    events: &Sender<SessionEvent>,
+    move_requests: &Receiver<(DeviceIdentifier, usize)>,
+) -> Result<()> {
+    let LaunchPlan { game_path, num_instances, assignments, config } = plan;
+    let layout = config.window_layout.layout();
+    let session = run_core_logic(&game_path, num_instances, &assignments, layout, config.runtime(), &config, None, stop)?;
+    let input_mux = &session.1;
+    let _ = events.send(SessionEvent::Started(input_mux.pause_flag()));
+    let degraded = input_mux.degraded_flag();
+    let skipped_input_codes = input_mux.capability_reports().iter().map(CapabilityReport::skipped).sum();
+
+    supervise_session(session, num_instances, layout, &config, stop, |input_mux, net_emulator, running| {
+        while let Ok((device, player)) = move_requests.try_recv() {
+            if let Err(e) = input_mux.reassign(&device, player) {
+                warn!("Could not move '{}' to player {}: {}", device.name, player + 1, e);
+            }
+        }
+        *status.lock().unwrap_or_else(|e| e.into_inner()) = SessionStatus {
+            running,
+            relay: net_emulator.stats(),
+            degraded: degraded.load(Ordering::SeqCst),
+            skipped_input_codes,
+            assignments: input_mux.current_assignments(),
+        };
+    });
+    status.lock().unwrap_or_else(|e| e.into_inner()).running = 0;
+    Ok(())
+}
+
+/// The next key press, or `None` when none came within a tick.
+fn next_key() -> io::Result<Option<KeyEvent>> {
+    if !event::poll(TICK)? {
+        return Ok(None);
+    }
+    Ok(match event::read()? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => Some(key),
+        _ => None,
+    })
+}
+
+/// Raw mode delivers Ctrl+C as a key press instead of a signal.
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// `current` moved by `step` within `0..len`, wrapping around.
+fn cycle(current: usize, len: usize, step: isize) -> usize {
+    (current as isize + step).rem_euclid(len as isize) as usize
+}
+
+fn runtime_label(runtime: WindowsRuntime) -> &'static str {
+    match runtime {
+        WindowsRuntime::None => "none (native)",
+        WindowsRuntime::Proton => "Proton",
+        WindowsRuntime::Wine => "Wine",
+    }
+}
+
+/// `line` cut to `width` characters so it never wraps.
+fn fit(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_wraps_both_ways() {
+        assert_eq!(cycle(0, 3, 1), 1);
+        assert_eq!(cycle(2, 3, 1), 0);
+        assert_eq!(cycle(0, 3, -1), 2);
+        assert_eq!(cycle(1, 3, -1), 0);
+        assert_eq!(cycle(0, 1, 1), 0);
+    }
+
+    #[test]
+    fn test_same_named_controllers_are_told_apart() {
+        let pad = |phys: &str| DeviceIdentifier {
+            name: "Wireless Controller".to_string(),
+            phys: Some(phys.to_string()),
+            bustype: 5,
+            vendor_id: 0x054c,
+            product_id: 0x09cc,
+            version: 1,
+            uniq: None,
+        };
+        let mut config = Config::default_config();
+        config.input_mappings = vec!["Wireless Controller".to_string(); 2];
+        let (_, logs) = mpsc::channel();
+        let mut tui = Tui::new(vec![vec![pad("bt-1")], vec![pad("bt-2")]], config, logs);
+        assert_eq!(tui.inputs[0], InputChoice::Device(pad("bt-1")));
+        assert_eq!(tui.inputs[1], InputChoice::Device(pad("bt-2")));
+
+        // Stepping on from the second pad goes past it, back to Auto-detect
+        tui.change(Row::Input(1), 1);
+        assert_eq!(tui.inputs[1], InputChoice::AutoDetect);
+        tui.change(Row::Input(1), -1);
+        assert_eq!(tui.inputs[1], InputChoice::Device(pad("bt-2")));
+        tui.change(Row::Input(1), -1);
+        assert_eq!(tui.inputs[1], InputChoice::Device(pad("bt-1")));
+    }
+
+    #[test]
+    fn test_fit_counts_characters() {
+        assert_eq!(fit("Player 1 input", 8), "Player 1");
+        assert_eq!(fit("short", 80), "short");
+        assert_eq!(fit("── Log ──", 4), "── L");
+        assert_eq!(fit("anything", 0), "");
+    }
+}
@@ -3,24 +3,400 @@
 //! This module provides a universal game launching system that works with any game
 //! without requiring game-specific handlers or configuration.
 
+use std::collections::{HashSet, VecDeque};
+use std::ffi::OsString;
+use std::net::Ipv4Addr;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Child};
+use std::process::{Command, Child, Stdio};
 use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use log::{info, warn, debug, error};
-use crate::errors::{HydraError, Result};
-use crate::game_detection::{GameConfiguration, GameDetector, InstanceSeparation, WorkingDirStrategy};
+use serde::{Deserialize, Serialize};
+use crate::adaptive_config::RecommendedConfig;
+use crate::config::FirejailConfig;
+use crate::flatpak::FlatpakApp;
+use crate::game_detection::{GameConfiguration, GameDetectionError, GameDetector, GameEngine, GameProfile, InstanceSeparation, MultiInstanceSupport, NetworkMode, WorkingDirStrategy};
+use crate::ports::ReservedPort;
+use crate::proton_integration::{analyze_binary, BinaryArch, BinaryFormat, ProtonError};
+use crate::template::{expand_template, expand_template_os, TemplateContext};
+use crate::wine_integration::WineError;
+
+/// Error type for game launching operations.
+#[derive(Debug)]
+pub enum LaunchError {
+    /// The game process for the given instance could not be spawned.
+    SpawnFailed { instance: usize, source: io::Error },
+    /// Creating or populating an instance's working directory failed.
+    WorkingDirSetup(io::Error),
+    /// Proton was requested but could not be located.
+    ProtonUnavailable(ProtonError),
+    /// The game executable could not be analysed.
+    GameDetection(GameDetectionError),
+    /// A Firejail sandbox was requested but `firejail` isn't installed.
+    FirejailUnavailable,
+    /// An instance can't be started as its configured user.
+    RunAsUser { user: String, reason: String },
+    /// Fewer users were configured than instances to run as them.
+    NotEnoughUsers { users: usize, instances: usize },
+    /// An instance failed under `LaunchPolicy::AllOrNothing`; the `launched`
+    /// instances started before it were stopped again.
+    PartialLaunch { launched: usize, source: Box<LaunchError> },
+    /// Wine was requested but could not be located.
+    WineUnavailable(WineError),
+    /// The pre-launch hook couldn't run or exited with an error.
+    HookFailed { hook: PathBuf, reason: String },
+    /// The game is already running outside Hydra and the config says not
+    /// to launch alongside it.
+    AlreadyRunning { pids: Vec<u32> },
+}
+
+impl std::fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LaunchError::SpawnFailed { instance, source } => {
+                write!(f, "Failed to spawn game instance {}: {}", instance, source)
+            }
+            LaunchError::WorkingDirSetup(e) => write!(f, "Failed to set up working directory: {}", e),
+            LaunchError::ProtonUnavailable(e) => write!(f, "Proton unavailable: {}", e),
+            LaunchError::WineUnavailable(e) => write!(f, "Wine unavailable: {}", e),
+            LaunchError::GameDetection(e) => write!(f, "Game detection failed: {}", e),
+            LaunchError::FirejailUnavailable => write!(
+                f,
+                "The config asks for a Firejail sandbox but firejail is not installed (not found on PATH). \
+                 Install firejail or remove the [firejail] section"
+            ),
+            LaunchError::RunAsUser { user, reason } => {
+                write!(f, "Cannot run game instance as user '{}': {}", user, reason)
+            }
+            LaunchError::NotEnoughUsers { users, instances } => {
+                write!(f, "{} instance(s) need a user each but only {} user(s) are configured", instances, users)
+            }
+            LaunchError::PartialLaunch { launched, source } => {
+                write!(f, "{} (stopped the {} instance(s) already started)", source, launched)
+            }
+            LaunchError::HookFailed { hook, reason } => {
+                write!(f, "Pre-launch hook {} failed: {}; not launching", hook.display(), reason)
+            }
+            LaunchError::AlreadyRunning { pids } => write!(
+                f,
+                "The game is already running (PID {}). Close it first, or set already_running = \"warn\" to launch anyway",
+                pids.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LaunchError::SpawnFailed { source, .. } => Some(source),
+            LaunchError::WorkingDirSetup(e) => Some(e),
+            LaunchError::ProtonUnavailable(e) => Some(e),
+            LaunchError::WineUnavailable(e) => Some(e),
+            LaunchError::GameDetection(e) => Some(e),
+            LaunchError::PartialLaunch { source, .. } => Some(source.as_ref()),
+            LaunchError::FirejailUnavailable
+            | LaunchError::RunAsUser { .. }
+            | LaunchError::NotEnoughUsers { .. }
+            | LaunchError::HookFailed { .. }
+            | LaunchError::AlreadyRunning { .. } => None,
+        }
+    }
+}
+
+impl From<ProtonError> for LaunchError {
+    fn from(err: ProtonError) -> Self {
+        LaunchError::ProtonUnavailable(err)
+    }
+}
+
+impl From<WineError> for LaunchError {
+    fn from(err: WineError) -> Self {
+        LaunchError::WineUnavailable(err)
+    }
+}
+
+impl From<GameDetectionError> for LaunchError {
+    fn from(err: GameDetectionError) -> Self {
+        LaunchError::GameDetection(err)
+    }
+}
+
+/// Result type alias for launcher operations
+pub type LaunchResult<T> = std::result::Result<T, LaunchError>;
+
+/// What `launch_game_instances` does when an instance fails to start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchPolicy {
+    /// Stop the instances already started and return the error
+    #[default]
+    AllOrNothing,
+    /// Keep going and report the failures alongside the started instances
+    BestEffort,
+}
+
+/// What `launch_game_instances` does when the game is already running,
+/// e.g. because Steam started it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlreadyRunningPolicy {
+    /// Log a warning and launch anyway
+    #[default]
+    Warn,
+    /// Return `LaunchError::AlreadyRunning`
+    Refuse,
+}
+
+/// What runs a Windows game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowsRuntime {
+    /// Run the executable directly (native games)
+    #[default]
+    None,
+    /// Steam's Proton, see `proton_integration`
+    Proton,
+    /// The system's Wine, see `wine_integration`
+    Wine,
+}
+
+impl WindowsRuntime {
+    /// `self`, or Proton when only the older `use_proton` switch is set.
+    pub fn or_proton(self, use_proton: bool) -> Self {
+        match self {
+            WindowsRuntime::None if use_proton => WindowsRuntime::Proton,
+            runtime => runtime,
+        }
+    }
+}
+
+/// The instances a `launch_game_instances` call started
+#[derive(Debug, Default)]
+pub struct LaunchReport {
+    /// PIDs of the started instances, in instance order
+    pub pids: Vec<u32>,
+    /// Instances that failed to start under `LaunchPolicy::BestEffort`
+    pub failures: Vec<(usize, LaunchError)>,
+    /// How long starting the instances took
+    pub elapsed: Duration,
+}
 
 /// Universal game launcher that can launch any game with multi-instance support
 pub struct UniversalLauncher {
     game_detector: GameDetector,
     active_instances: Vec<GameInstance>,
+    goldberg_path: Option<PathBuf>,
+    /// Everything needed to relaunch an instance, recorded by the last launch
+    last_launch: Option<LaunchSpec>,
+    /// Instances the watchdog has stopped restarting
+    abandoned: HashSet<usize>,
+    /// Learned settings to overlay on the generated configuration
+    recommendation: Option<RecommendedConfig>,
+    /// Configuration recorded by an earlier session whose arguments and
+    /// environment are launched again, see `set_replay`
+    replay: Option<GameConfiguration>,
+    /// Users to run the instances as, see `InstanceSeparation::SeparateUsers`
+    run_as_users: Vec<String>,
+    /// Extra arguments per instance, see `GameConfiguration::instance_args`
+    instance_args: Vec<Vec<String>>,
+    /// Port per instance, replacing the detected defaults when set
+    ports: Vec<u16>,
+    /// Ports still held for instances that haven't started, see `set_ports`
+    port_reservations: Mutex<Vec<Option<ReservedPort>>>,
+    /// Id for `${SESSION_ID}` in launch arguments and environment values
+    session_id: String,
+    /// Give each instance its own loopback address for `${LOOPBACK_IP}`
+    loopback_per_instance: bool,
+    /// Window size per instance for `${WIDTH}` and `${HEIGHT}`
+    instance_resolutions: Vec<(u32, u32)>,
+    /// Extra environment variables per instance, see `set_instance_env`
+    instance_env: Vec<Vec<(String, String)>>,
+    /// Input device node per instance, see `InstanceSeparation::InputIsolation`
+    input_isolation: Vec<Option<PathBuf>>,
+    /// Sandbox every instance with Firejail
+    firejail: Option<FirejailConfig>,
+    /// What to do when an instance fails to start
+    launch_policy: LaunchPolicy,
+    /// What to do when the game is already running outside Hydra
+    already_running: AlreadyRunningPolicy,
+    /// How long to wait for Steam to finish installing Proton
+    proton_wait: Duration,
+    /// The Proton found for the current launch, looked up (and waited for)
+    /// once before the instances start
+    proton_path: Option<PathBuf>,
+    /// How many instances may be starting at the same time
+    launch_concurrency: usize,
+    /// Minimum time between two instances starting
+    launch_delay: Duration,
+    /// Directory for Proton's logs, one subdirectory per instance; `None`
+    /// leaves Proton logging off
+    proton_log_dir: Option<PathBuf>,
+    /// Directory of the index that records the WINEPREFIXes instances use,
+    /// see `proton_integration::record_prefixes`
+    prefix_index_dir: Option<PathBuf>,
+    /// Program run before the instances are started; failing aborts the launch
+    pre_launch_hook: Option<PathBuf>,
+    /// Program run once the instances are started
+    post_launch_hook: Option<PathBuf>,
+    /// The post-launch hook while it runs alongside the instances
+    running_hook: Option<BackgroundHook>,
+    /// Globs selecting what separate instance directories get copies of
+    copy_include: Vec<String>,
+    /// Globs for what they never get copies of
+    copy_exclude: Vec<String>,
+    /// Set from another thread to stop starting further instances
+    cancel: Option<Arc<AtomicBool>>,
+    /// How long stopped instances get to exit after SIGTERM before they are killed
+    shutdown_grace: Duration,
+}
+
+/// Parameters of the most recent `launch_game_instances` call
+struct LaunchSpec {
+    executable_path: PathBuf,
+    config: GameConfiguration,
+    network_mode: NetworkMode,
+    runtime: WindowsRuntime,
+    num_instances: usize,
+}
+
+/// How long instances running as another user get to act on SIGTERM when
+/// stopped without a grace period
+const RUN_AS_STOP_WAIT: Duration = Duration::from_secs(3);
+
+/// How often a launch waiting between instance starts checks for being cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a wrapper gets to start the game before its own PID is used instead
+const GAME_PROCESS_WAIT: Duration = Duration::from_secs(10);
+
+/// Programs that start the game as a process of their own rather than
+/// becoming it, passed over when looking for the game below a wrapper
+const WRAPPER_COMMANDS: [&str; 8] = ["sudo", "firejail", "bwrap", "flatpak", "env", "sh", "bash", "dash"];
+
+/// Game directory entries a separate instance directory gets copies of,
+/// see [`UniversalLauncher::set_instance_copy_patterns`]
+pub const DEFAULT_INSTANCE_COPY_INCLUDE: [&str; 12] = [
+    "*.dll", "*.so", "*.dylib", "*.ini", "*.cfg", "*.config", "*.xml", "*.json",
+    "Data/", "Config/", "Plugins/", "Mods/",
+];
+/// Game directory entries never copied: the other instances' directories
+pub const DEFAULT_INSTANCE_COPY_EXCLUDE: [&str; 1] = ["instance_*"];
+
+/// Game file names are matched like Windows does, ignoring case, and `*`
+/// stays within one directory.
+const COPY_MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// A glob from `instance_copy_include` or `instance_copy_exclude`, matched
+/// against paths relative to the game directory. A trailing `/` makes it
+/// match directories only.
+#[derive(Debug, Clone)]
+pub struct CopyPattern {
+    pattern: glob::Pattern,
+    dirs_only: bool,
+}
+
+impl CopyPattern {
+    pub fn parse(pattern: &str) -> Result<Self, glob::PatternError> {
+        Ok(CopyPattern {
+            pattern: glob::Pattern::new(pattern.trim_end_matches('/'))?,
+            dirs_only: pattern.ends_with('/'),
+        })
+    }
+
+    fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        (is_dir || !self.dirs_only) && self.pattern.matches_path_with(relative, COPY_MATCH_OPTIONS)
+    }
+}
+
+/// What `setup_separate_instance_directory` copies
+struct CopyRules {
+    include: Vec<CopyPattern>,
+    exclude: Vec<CopyPattern>,
+    /// Whether an include reaches below the top of the game directory, so
+    /// unmatched directories have to be looked into
+    descend: bool,
+}
+
+impl CopyRules {
+    fn new(include: &[String], exclude: &[String]) -> Self {
+        let parse = |pattern: &str| match CopyPattern::parse(pattern) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                warn!("Ignoring instance copy pattern '{}': {}", pattern, e);
+                None
+            }
+        };
+        CopyRules {
+            descend: include.iter().any(|pattern| pattern.trim_end_matches('/').contains('/')),
+            include: include.iter().filter_map(|pattern| parse(pattern)).collect(),
+            exclude: exclude.iter().filter_map(|pattern| parse(pattern)).collect(),
+        }
+    }
+
+    fn included(&self, relative: &Path, is_dir: bool) -> bool {
+        self.include.iter().any(|pattern| pattern.matches(relative, is_dir))
+    }
+
+    fn excluded(&self, relative: &Path, is_dir: bool) -> bool {
+        self.exclude.iter().any(|pattern| pattern.matches(relative, is_dir))
+    }
 }
 
+/// Steam API libraries that Goldberg ships drop-in replacements for
+const STEAM_API_LIBRARIES: [&str; 3] = ["steam_api.dll", "steam_api64.dll", "libsteam_api.so"];
+
 /// Represents a running game instance
 #[derive(Debug)]
 pub struct GameInstance {
     pub id: usize,
     pub process: Child,
+    /// When this process was spawned
+    pub launched_at: Instant,
+    /// How many times the watchdog has relaunched this instance
+    pub restarts: u32,
+    /// Directory the process was started in
+    pub working_dir: PathBuf,
+    /// User the game runs as when launched through `sudo`; `process` is then
+    /// the sudo process, not the game itself
+    pub run_as: Option<String>,
+    /// The game started by a wrapper such as sudo, Firejail, bubblewrap or
+    /// `flatpak run`, once found
+    game_pid: Option<u32>,
+    /// WINEPREFIX the game was started with, under Proton or Wine
+    pub wineprefix: Option<PathBuf>,
+}
+
+impl GameInstance {
+    /// PID of the game itself, which its windows carry. For a wrapped launch
+    /// this is the process the wrapper started, or the wrapper's own PID if
+    /// that couldn't be found.
+    pub fn pid(&self) -> u32 {
+        self.game_pid.unwrap_or_else(|| self.process.id())
+    }
+
+    /// Whether the game is still running. A wrapper that exits after handing
+    /// the game off, as `flatpak run` can, doesn't end it.
+    pub fn is_running(&mut self) -> bool {
+        match self.process.try_wait() {
+            Ok(None) => true,
+            Ok(Some(_)) => self.game_pid.is_some_and(|pid| process_alive(Path::new("/proc"), pid)),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether the wrapper has exited while the game it started runs on
+    fn handed_off(&mut self) -> bool {
+        matches!(self.process.try_wait(), Ok(Some(_))) && self.is_running()
+    }
 }
 
 impl UniversalLauncher {
@@ -28,88 +404,554 @@ impl UniversalLauncher {
         Self {
             game_detector: GameDetector::new(),
             active_instances: Vec::new(),
+            goldberg_path: None,
+            last_launch: None,
+            abandoned: HashSet::new(),
+            recommendation: None,
+            replay: None,
+            run_as_users: Vec::new(),
+            instance_args: Vec::new(),
+            ports: Vec::new(),
+            port_reservations: Mutex::new(Vec::new()),
+            session_id: crate::input_mux::new_session_id(),
+            loopback_per_instance: false,
+            instance_resolutions: Vec::new(),
+            instance_env: Vec::new(),
+            input_isolation: Vec::new(),
+            firejail: None,
+            launch_policy: LaunchPolicy::default(),
+            already_running: AlreadyRunningPolicy::default(),
+            proton_wait: Duration::ZERO,
+            proton_path: None,
+            launch_concurrency: 1,
+            launch_delay: Duration::ZERO,
+            proton_log_dir: None,
+            prefix_index_dir: None,
+            pre_launch_hook: None,
+            post_launch_hook: None,
+            running_hook: None,
+            copy_include: DEFAULT_INSTANCE_COPY_INCLUDE.iter().map(|pattern| pattern.to_string()).collect(),
+            copy_exclude: DEFAULT_INSTANCE_COPY_EXCLUDE.iter().map(|pattern| pattern.to_string()).collect(),
+            cancel: None,
+            shutdown_grace: crate::defaults::SHUTDOWN_GRACE,
+        }
+    }
+
+    /// Enable the Goldberg Steam emulator for Steamworks games.
+    /// `path` is the directory holding the emulator's steam_api libraries;
+    /// `None` disables the integration.
+    pub fn set_goldberg_path(&mut self, path: Option<PathBuf>) {
+        self.goldberg_path = path;
+    }
+
+    /// Apply an adaptive recommendation on top of the generated configuration
+    /// for subsequent launches. `None` launches with detected defaults only.
+    pub fn set_recommendation(&mut self, recommendation: Option<RecommendedConfig>) {
+        self.recommendation = recommendation;
+    }
+
+    /// Launch subsequent sessions with the shared and per-instance arguments
+    /// and the environment of `recorded`, a configuration an earlier session
+    /// launched with, instead of the generated ones. See `session`.
+    pub fn set_replay(&mut self, recorded: Option<GameConfiguration>) {
+        self.replay = recorded;
+    }
+
+    /// Run instance `i` as `users[i]` via `sudo -u` in subsequent launches.
+    /// An empty list runs every instance as the current user.
+    pub fn set_run_as_users(&mut self, users: Vec<String>) {
+        self.run_as_users = users;
+    }
+
+    /// Pass `args[i]` to instance `i` on top of the shared launch arguments in
+    /// subsequent launches. `{instance}` and `{port}` are expanded per instance.
+    pub fn set_instance_args(&mut self, args: Vec<Vec<String>>) {
+        self.instance_args = args;
+    }
+
+    /// Give instance `i` port `ports[i]` (for `${PORT}` and `HYDRA_PORT`) in
+    /// subsequent launches instead of the game's detected default ports.
+    /// Ports still held are released just before their instance starts.
+    pub fn set_ports(&mut self, ports: Vec<ReservedPort>) {
+        self.ports = ports.iter().map(|reserved| reserved.port).collect();
+        self.port_reservations = Mutex::new(ports.into_iter().map(Some).collect());
+    }
+
+    /// Lets go of the port held for `instance_id`, so its game can bind it.
+    fn release_port(&self, instance_id: usize) {
+        let mut reservations = self.port_reservations.lock().unwrap();
+        if let Some(reserved) = reservations.get_mut(instance_id).and_then(Option::take) {
+            debug!("Releasing port {} for instance {}", reserved.release(), instance_id);
         }
     }
 
-    /// Launch multiple instances of any game using universal detection and configuration
+    /// Tell each instance to bind to its own loopback address (127.0.0.2,
+    /// 127.0.0.3, ...) through `${LOOPBACK_IP}` and `HYDRA_LOOPBACK_IP`,
+    /// matching `NetEmulator::set_loopback_per_instance`.
+    pub fn set_loopback_per_instance(&mut self, enabled: bool) {
+        self.loopback_per_instance = enabled;
+    }
+
+    /// Start instance `i` at `resolutions[i]` in subsequent launches, through
+    /// `${WIDTH}`/`${HEIGHT}` and the detected engine's resolution options.
+    /// An empty list leaves the resolution to the game.
+    pub fn set_instance_resolutions(&mut self, resolutions: Vec<(u32, u32)>) {
+        self.instance_resolutions = resolutions;
+    }
+
+    /// Set `env[i]` in instance `i`'s environment in subsequent launches, for
+    /// settings only known once the session is set up, such as which input
+    /// device belongs to which instance.
+    pub fn set_instance_env(&mut self, env: Vec<Vec<(String, String)>>) {
+        self.instance_env = env;
+    }
+
+    /// Show instance `i` only the input device `devices[i]` in subsequent
+    /// launches, see `InstanceSeparation::InputIsolation`. An empty list
+    /// lets every instance see every device.
+    pub fn set_input_isolation(&mut self, devices: Vec<Option<PathBuf>>) {
+        self.input_isolation = devices;
+    }
+
+    /// Run every instance inside a Firejail sandbox in subsequent launches.
+    /// `None` launches the games directly.
+    pub fn set_firejail(&mut self, firejail: Option<FirejailConfig>) {
+        self.firejail = firejail;
+    }
+
+    /// Choose what subsequent launches do when an instance fails to start.
+    pub fn set_launch_policy(&mut self, policy: LaunchPolicy) {
+        self.launch_policy = policy;
+    }
+
+    /// Choose what subsequent launches do when the game is already running.
+    pub fn set_already_running_policy(&mut self, policy: AlreadyRunningPolicy) {
+        self.already_running = policy;
+    }
+
+    /// PIDs of processes running `executable` that this launcher didn't
+    /// start, such as a copy Steam opened. Windows games are also matched
+    /// by the program Wine was asked to run.
+    pub fn find_running_instances(&self, executable: &Path) -> Vec<u32> {
+        let own: Vec<u32> = self.active_instances.iter().flat_map(|instance| [instance.process.id(), instance.pid()]).collect();
+        let mut pids = running_instances_in(Path::new("/proc"), executable);
+        pids.retain(|pid| *pid != std::process::id() && !own.contains(pid));
+        pids
+    }
+
+    /// Start up to `concurrency` instances at the same time in subsequent
+    /// launches, each preparing its working directory independently, and
+    /// no two within `delay` of each other. Games that may not allow a
+    /// second copy to start alongside the first are still started one by one.
+    pub fn set_launch_concurrency(&mut self, concurrency: usize, delay: Duration) {
+        self.launch_concurrency = concurrency.max(1);
+        self.launch_delay = delay;
+    }
+
+    /// Keep looking for Proton for up to `wait` when it isn't installed yet
+    /// but may be on its way, see `find_proton_path_waiting`.
+    pub fn set_proton_wait(&mut self, wait: Duration) {
+        self.proton_wait = wait;
+    }
+
+    /// Makes launches stop starting instances, and stop waiting for Proton,
+    /// as soon as `cancel` is set. Instances already started keep running;
+    /// the caller decides what to do with them.
+    pub fn set_cancel_flag(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Gives instances `grace` to exit after SIGTERM when they are stopped,
+    /// before they are killed. Zero kills them outright.
+    pub fn set_shutdown_grace(&mut self, grace: Duration) {
+        self.shutdown_grace = grace;
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+    }
+
+    /// Have Proton write its logs (`PROTON_LOG`) under `dir` in subsequent
+    /// launches, each instance into `dir/instance_N`. `None` turns logging off.
+    pub fn set_proton_log_dir(&mut self, dir: Option<PathBuf>) {
+        self.proton_log_dir = dir;
+    }
+
+    /// Record the WINEPREFIX of every Proton or Wine instance in the index
+    /// in `dir` in subsequent launches, so they can be found and cleaned up.
+    pub fn set_prefix_index_dir(&mut self, dir: Option<PathBuf>) {
+        self.prefix_index_dir = dir;
+    }
+
+    /// Run `pre` before and `post` after the instances start in subsequent
+    /// launches, see `run_hook` for what they are told about the session.
+    /// A pre-launch hook that fails aborts the launch.
+    pub fn set_hooks(&mut self, pre: Option<PathBuf>, post: Option<PathBuf>) {
+        self.pre_launch_hook = pre;
+        self.post_launch_hook = post;
+    }
+
+    /// Choose what instances running from separate directories get copies
+    /// of, as globs relative to the game directory (see [`CopyPattern`]).
+    /// A directory an include matches is copied whole, less what an exclude
+    /// matches.
+    pub fn set_instance_copy_patterns(&mut self, include: Vec<String>, exclude: Vec<String>) {
+        self.copy_include = include;
+        self.copy_exclude = exclude;
+    }
+
+    /// Short id of this session, substituted for `${SESSION_ID}`.
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Analyze a game executable without launching it.
+    /// The result is cached, so a following launch does not repeat the analysis.
+    pub fn detect_profile(&mut self, executable_path: &Path) -> LaunchResult<GameProfile> {
+        Ok(self.game_detector.detect_game(executable_path)?)
+    }
+
+    /// Launch multiple instances of any game using universal detection and configuration.
+    /// An instance that fails to start is handled according to the launch
+    /// policy, see `set_launch_policy`.
     pub fn launch_game_instances(
         &mut self,
         executable_path: &Path,
         num_instances: usize,
-        use_proton: bool,
-    ) -> Result<Vec<u32>> {
+        runtime: WindowsRuntime,
+    ) -> LaunchResult<LaunchReport> {
         info!("Launching {} instances of game: {}", num_instances, executable_path.display());
 
         // Detect and analyze the game
         let profile = self.game_detector.detect_game(executable_path)?;
-        let config = self.game_detector.get_recommended_config(&profile, num_instances);
+        let mut config = self.game_detector.get_recommended_config(&profile, num_instances);
+        if let Some(recommendation) = &self.recommendation {
+            recommendation.apply_to(&mut config);
+        }
+        if let Some(recorded) = &self.replay {
+            replay_args(&mut config, recorded);
+        }
+        if !self.instance_args.is_empty() {
+            config.instance_args = self.instance_args.clone();
+        }
+        if !self.ports.is_empty() {
+            if config.ports != self.ports {
+                warn!("Using the configured ports {:?} instead of the game's ports {:?}", self.ports, config.ports);
+            }
+            config.ports = self.ports.clone();
+        }
+        if !self.run_as_users.is_empty() {
+            config.instance_separation = InstanceSeparation::SeparateUsers { users: self.run_as_users.clone() };
+        }
+        if !self.input_isolation.is_empty() {
+            // The devices are applied on top of whatever separation the game needs
+            if matches!(config.instance_separation, InstanceSeparation::SeparateUsers { .. }) {
+                warn!("Input isolation can't be combined with run_as_users; every instance will see every input device.");
+            } else if FlatpakApp::from_path(executable_path).is_some() {
+                warn!("Inside a Flatpak sandbox input isolation only sets SDL_JOYSTICK_DEVICE; games reading /dev/input directly still see every device.");
+            } else if self.firejail.is_some() {
+                warn!("Input isolation inside a Firejail sandbox only sets SDL_JOYSTICK_DEVICE; games reading /dev/input directly still see every device.");
+            } else if !bubblewrap_available() {
+                warn!("bwrap (bubblewrap) is not installed, so input isolation only sets SDL_JOYSTICK_DEVICE; games reading /dev/input directly still see every device.");
+            }
+        }
+        if let InstanceSeparation::SeparateUsers { users } = &config.instance_separation {
+            check_run_as_users(users, num_instances)?;
+        }
+        if let Some(firejail) = &self.firejail {
+            check_firejail(firejail, executable_path)?;
+        }
+        if !self.instance_resolutions.is_empty() {
+            add_resolution_args(&mut config, profile.engine.as_ref());
+        }
 
         info!("Detected game profile: engine={:?}, support={:?}", 
                profile.engine, profile.multi_instance_support);
+        for blocker in &profile.instance_blockers {
+            warn!("{}", blocker);
+        }
+        let runtime = match FlatpakApp::from_path(executable_path) {
+            Some(app) => {
+                check_flatpak(&app, executable_path, runtime, &mut config, self.firejail.is_some());
+                WindowsRuntime::None
+            }
+            None => check_binary(executable_path, runtime),
+        };
 
-        let mut pids = Vec::new();
-
-        for instance_id in 0..num_instances {
-            info!("Launching instance {} of {}", instance_id + 1, num_instances);
+        let running = self.find_running_instances(executable_path);
+        if !running.is_empty() {
+            if self.already_running == AlreadyRunningPolicy::Refuse {
+                return Err(LaunchError::AlreadyRunning { pids: running });
+            }
+            warn!(
+                "{} is already running (PID {:?}); the new instances may conflict with it",
+                executable_path.display(),
+                running
+            );
+        }
 
-            let instance = self.launch_single_instance(
-                executable_path,
-                instance_id,
-                &config,
-                use_proton,
-            )?;
+        // Waiting for Steam to install Proton happens once, not per instance
+        self.proton_path = match runtime {
+            WindowsRuntime::Proton => Some(crate::proton_integration::find_proton_path_waiting(
+                self.proton_wait,
+                self.cancel.as_deref(),
+            )?),
+            _ => None,
+        };
 
-            pids.push(instance.process.id());
-            self.active_instances.push(instance);
+        let hook_env = HookEnv {
+            session_id: self.session_id.clone(),
+            executable_path: executable_path.to_path_buf(),
+            instance_count: num_instances,
+            ports: config.ports.iter().take(num_instances).copied().collect(),
+            pids: Vec::new(),
+        };
+        if let Some(hook) = &self.pre_launch_hook {
+            run_hook(hook, "pre_launch", &hook_env)
+                .map_err(|reason| LaunchError::HookFailed { hook: hook.clone(), reason })?;
         }
 
-        info!("Successfully launched {} game instances with PIDs: {:?}", num_instances, pids);
-        Ok(pids)
-    }
+        // Games that lock out a second copy while one is starting get one at a time
+        let concurrency = match profile.multi_instance_support {
+            MultiInstanceSupport::Native | MultiInstanceSupport::Configurable => self.launch_concurrency,
+            _ if self.launch_concurrency > 1 => {
+                info!("This game may not tolerate copies starting together; launching one instance at a time.");
+                1
+            }
+            _ => 1,
+        };
 
-    /// Check whether any game instance is still running.
-    pub fn any_running(&mut self) -> bool {
-        self.active_instances
-            .iter_mut()
-            .any(|inst| inst.process.try_wait().map(|s| s.is_none()).unwrap_or(false))
-    }
+        let started_at = Instant::now();
+        let results = self.start_instances(executable_path, num_instances, &config, profile.network_mode, runtime, concurrency);
+        // Ports of instances that never started are no use to anyone
+        self.port_reservations.lock().unwrap().clear();
+
+        let mut report = LaunchReport::default();
+        let first_new = self.active_instances.len();
+        let mut first_failure = None;
+        for (instance_id, result) in results {
+            match (result, self.launch_policy) {
+                (Ok(instance), _) => {
+                    report.pids.push(instance.pid());
+                    self.active_instances.push(instance);
+                }
+                (Err(e), LaunchPolicy::AllOrNothing) => {
+                    error!("Instance {} failed to start: {}", instance_id, e);
+                    first_failure.get_or_insert((instance_id, e));
+                }
+                (Err(e), LaunchPolicy::BestEffort) => {
+                    error!("Instance {} failed to start, continuing without it: {}", instance_id, e);
+                    report.failures.push((instance_id, e));
+                }
+            }
+        }
+        report.elapsed = started_at.elapsed();
 
-    /// Terminate all active game instances and wait for them to exit.
-    pub fn shutdown_instances(&mut self) {
-        use std::time::{Duration, Instant};
-        use std::thread;
+        if let Some((instance_id, e)) = first_failure {
+            let mut started = self.active_instances.split_off(first_new);
+            if !started.is_empty() {
+                warn!("Instance {} failed to start; stopping the {} already running.", instance_id, started.len());
+                stop_instances(&mut started, self.shutdown_grace);
+            }
+            return Err(LaunchError::PartialLaunch { launched: started.len(), source: Box::new(e) });
+        }
 
-        if self.active_instances.is_empty() {
-            return;
+        // Nothing to play with if every instance failed
+        if report.pids.is_empty() && !report.failures.is_empty() {
+            return Err(report.failures.swap_remove(0).1);
         }
 
-        info!("Stopping {} game instance(s)...", self.active_instances.len());
+        if let Some(dir) = self.prefix_index_dir.as_ref().filter(|_| runtime != WindowsRuntime::None) {
+            let game = executable_path.file_name().unwrap_or_default().to_string_lossy();
+            let prefixes: Vec<PathBuf> =
+                self.active_instances[first_new..].iter().filter_map(|instance| instance.wineprefix.clone()).collect();
+            if let Err(e) = crate::proton_integration::record_prefixes(dir, &game, &prefixes) {
+                warn!("Could not record the instances' WINEPREFIXes: {}", e);
+            }
+        }
 
-        // Signal every process to stop.
-        for inst in &mut self.active_instances {
-            let _ = inst.process.kill(); // SIGKILL on Unix, TerminateProcess on Windows
+        self.last_launch = Some(LaunchSpec {
+            executable_path: executable_path.to_path_buf(),
+            config,
+            network_mode: profile.network_mode,
+            runtime,
+            num_instances,
+        });
+
+        info!(
+            "Successfully launched {} game instances in {:.1?} with PIDs: {:?}",
+            report.pids.len(),
+            report.elapsed,
+            report.pids
+        );
+
+        // The games are already running, so the post-launch hook runs
+        // alongside them and failing only warns
+        if let Some(hook) = self.post_launch_hook.as_ref().filter(|_| !self.cancelled()) {
+            let hook_env = HookEnv { pids: report.pids.clone(), ..hook_env };
+            if let Some(previous) = self.running_hook.take() {
+                previous.stop();
+            }
+            match BackgroundHook::start(hook, &hook_env, self.shutdown_grace) {
+                Ok(running) => self.running_hook = Some(running),
+                Err(reason) => warn!("Post-launch hook {} failed: {}", hook.display(), reason),
+            }
         }
+        Ok(report)
+    }
 
-        // Wait up to 3 seconds for all processes to exit.
-        let deadline = Instant::now() + Duration::from_secs(3);
-        while Instant::now() < deadline {
-            let all_done = self.active_instances.iter_mut().all(|inst| {
-                inst.process.try_wait().map(|s| s.is_some()).unwrap_or(true)
-            });
-            if all_done {
+    /// Start instances `0..num_instances`, up to `concurrency` at a time and
+    /// `launch_delay` apart. Under `LaunchPolicy::AllOrNothing` no further
+    /// instances are started once one fails. Results are in instance order.
+    fn start_instances(
+        &self,
+        executable_path: &Path,
+        num_instances: usize,
+        config: &GameConfiguration,
+        network_mode: NetworkMode,
+        runtime: WindowsRuntime,
+        concurrency: usize,
+    ) -> Vec<(usize, LaunchResult<GameInstance>)> {
+        let next_instance = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let next_start = Mutex::new(Instant::now());
+        let results = Mutex::new(Vec::with_capacity(num_instances));
+
+        let worker = || loop {
+            if self.launch_policy == LaunchPolicy::AllOrNothing && failed.load(Ordering::SeqCst) {
                 break;
             }
-            thread::sleep(Duration::from_millis(100));
+            if self.cancelled() {
+                break;
+            }
+            let instance_id = next_instance.fetch_add(1, Ordering::SeqCst);
+            if instance_id >= num_instances {
+                break;
+            }
+            {
+                // Holding the lock while waiting spaces out the starts
+                let mut next_start = next_start.lock().unwrap();
+                while !self.cancelled() && Instant::now() < *next_start {
+                    thread::sleep(CANCEL_POLL_INTERVAL.min(next_start.saturating_duration_since(Instant::now())));
+                }
+                *next_start = Instant::now() + self.launch_delay;
+            }
+            if self.cancelled() {
+                break;
+            }
+
+            info!("Launching instance {} of {}", instance_id + 1, num_instances);
+            let result = self.launch_single_instance(executable_path, instance_id, num_instances, config, network_mode, runtime);
+            if result.is_err() {
+                failed.store(true, Ordering::SeqCst);
+            }
+            results.lock().unwrap().push((instance_id, result));
+        };
+
+        if concurrency <= 1 {
+            worker();
+        } else {
+            thread::scope(|scope| {
+                for _ in 0..concurrency.min(num_instances) {
+                    scope.spawn(worker);
+                }
+            });
+        }
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(instance_id, _)| *instance_id);
+        results
+    }
+
+    /// Relaunch instances that exited within `crash_window` of being spawned.
+    ///
+    /// Each instance is restarted at most `max_restarts` times; after that it is
+    /// left stopped. The relaunched process keeps its instance index, so it reuses
+    /// the same working directory, emulator port and virtual input device.
+    /// Returns `(instance_id, new_pid)` for every instance that was restarted.
+    pub fn restart_early_crashes(&mut self, crash_window: Duration, max_restarts: u32) -> Vec<(usize, u32)> {
+        let mut crashed = Vec::new();
+        for (index, inst) in self.active_instances.iter_mut().enumerate() {
+            if self.abandoned.contains(&inst.id) || inst.launched_at.elapsed() > crash_window {
+                continue;
+            }
+            if !inst.is_running() {
+                match inst.process.try_wait() {
+                    // A wrapper's status says nothing about how the game it handed off ended
+                    Ok(Some(status)) if inst.game_pid.is_none() => {
+                        warn!("Instance {} exited {:?} after launch with status: {}", inst.id, inst.launched_at.elapsed(), status);
+                    }
+                    _ => warn!("Instance {} exited {:?} after launch", inst.id, inst.launched_at.elapsed()),
+                }
+                crashed.push(index);
+            }
         }
 
-        // Reap child processes to avoid zombies.
-        for inst in &mut self.active_instances {
-            match inst.process.wait() {
-                Ok(status) => info!("Instance {} exited with status: {}", inst.id, status),
-                Err(e) => error!("Error waiting for instance {}: {}", inst.id, e),
+        let mut restarted = Vec::new();
+        for index in crashed {
+            let (instance_id, restarts) = {
+                let inst = &self.active_instances[index];
+                (inst.id, inst.restarts)
+            };
+            if restarts >= max_restarts {
+                error!("Instance {} crashed {} time(s); giving up on restarting it.", instance_id, restarts + 1);
+                self.abandoned.insert(instance_id);
+                continue;
+            }
+            let Some(spec) = &self.last_launch else {
+                continue;
+            };
+
+            info!("Restarting instance {} (attempt {} of {})", instance_id, restarts + 1, max_restarts);
+            match self.launch_single_instance(
+                &spec.executable_path,
+                instance_id,
+                spec.num_instances,
+                &spec.config,
+                spec.network_mode,
+                spec.runtime,
+            ) {
+                Ok(mut instance) => {
+                    instance.restarts = restarts + 1;
+                    restarted.push((instance_id, instance.pid()));
+                    self.active_instances[index] = instance;
+                }
+                Err(e) => {
+                    error!("Failed to restart instance {}: {}", instance_id, e);
+                    self.abandoned.insert(instance_id);
+                }
             }
         }
+        restarted
+    }
+
+    /// Instances launched so far, in launch order.
+    pub fn instances(&self) -> &[GameInstance] {
+        &self.active_instances
+    }
+
+    /// Configuration generated for the most recent launch.
+    pub fn launch_config(&self) -> Option<&GameConfiguration> {
+        self.last_launch.as_ref().map(|spec| &spec.config)
+    }
+
+    /// Number of game instances that are still running.
+    pub fn running_count(&mut self) -> usize {
+        self.active_instances.iter_mut().map(GameInstance::is_running).filter(|&running| running).count()
+    }
+
+    /// Terminate all active game instances and wait for them to exit. A
+    /// post-launch hook still running is stopped too.
+    pub fn shutdown_instances(&mut self) {
+        if let Some(hook) = self.running_hook.take() {
+            hook.stop();
+        }
+        if self.active_instances.is_empty() {
+            return;
+        }
 
+        info!("Stopping {} game instance(s)...", self.active_instances.len());
+        stop_instances(&mut self.active_instances, self.shutdown_grace);
         self.active_instances.clear();
         info!("All game instances have been shut down.");
     }
@@ -119,43 +961,120 @@ impl UniversalLauncher {
         &self,
         executable_path: &Path,
         instance_id: usize,
+        num_instances: usize,
         config: &GameConfiguration,
-        use_proton: bool,
-    ) -> Result<GameInstance> {
+        network_mode: NetworkMode,
+        runtime: WindowsRuntime,
+    ) -> LaunchResult<GameInstance> {
         // Prepare working directory
         let working_dir = self.prepare_working_directory(executable_path, instance_id, &config.working_dir_strategy)?;
 
+        if network_mode == NetworkMode::Steamworks {
+            if let Some(goldberg_dir) = &self.goldberg_path {
+                self.stage_goldberg(goldberg_dir, executable_path, &working_dir, instance_id)?;
+            }
+        }
+
         // Prepare the command
-        let mut command = if use_proton {
-            self.prepare_proton_command(executable_path, instance_id, &working_dir)?
-        } else {
-            Command::new(executable_path)
+        let mut command = match runtime {
+            WindowsRuntime::Proton => self.prepare_proton_command(executable_path, instance_id, &working_dir)?,
+            WindowsRuntime::Wine => prepare_wine_command(executable_path, &working_dir)?,
+            WindowsRuntime::None => Command::new(executable_path),
         };
 
         // Set working directory
         command.current_dir(&working_dir);
 
+        // What `${...}` in arguments and environment values refers to
+        let context = TemplateContext {
+            instance_id,
+            port: config.ports.get(instance_id).copied(),
+            loopback: if self.loopback_per_instance {
+                crate::net_emulator::instance_loopback(instance_id as u8)
+            } else {
+                Ipv4Addr::LOCALHOST
+            },
+            resolution: self.instance_resolutions.get(instance_id).copied(),
+            working_dir: working_dir.clone(),
+            instance_count: num_instances,
+            session_id: self.session_id.clone(),
+        };
+
         // Add universal launch arguments
-        self.add_launch_arguments(&mut command, instance_id, config);
+        self.add_launch_arguments(&mut command, &context, config);
 
         // Set environment variables
-        self.set_environment_variables(&mut command, instance_id, config);
+        self.set_environment_variables(&mut command, &context, config);
 
         // Apply instance separation strategies
         self.apply_instance_separation(&mut command, instance_id, config, &working_dir)?;
+        let input_devices = self.isolated_input_devices(config);
+        let input_device = input_devices.and_then(|devices| devices.get(instance_id)).and_then(Option::as_ref);
+        if let Some(node) = input_device {
+            command.env("SDL_JOYSTICK_DEVICE", node);
+        }
+
+        // Flatpak runs the game in its own sandbox, which takes the place of
+        // bubblewrap and Firejail
+        let flatpak = FlatpakApp::from_path(executable_path);
+        if let Some(app) = &flatpak {
+            command = wrap_with_flatpak(&command, app);
+        }
+
+        let mut wrapped = flatpak.is_some();
+        if input_devices.is_some() {
+            match input_device {
+                Some(node) if flatpak.is_none() && self.firejail.is_none() && bubblewrap_available() => {
+                    command = wrap_with_bubblewrap(&command, node);
+                    wrapped = true;
+                }
+                Some(_) => {}
+                None => warn!("Instance {} has no input device of its own; it sees every input device.", instance_id),
+            }
+        }
+
+        if let Some(firejail) = self.firejail.as_ref().filter(|_| flatpak.is_none()) {
+            command = wrap_with_firejail(&command, firejail);
+            wrapped = true;
+        }
+
+        let wineprefix = command
+            .get_envs()
+            .find(|(key, _)| *key == "WINEPREFIX")
+            .and_then(|(_, value)| value)
+            .map(PathBuf::from);
+
+        // Hand the prepared command to sudo when the instance runs as another user
+        let run_as = match &config.instance_separation {
+            InstanceSeparation::SeparateUsers { users } => {
+                let user = run_as_user_for(users, instance_id)?;
+                command = wrap_with_sudo(&command, user);
+                Some(user.to_string())
+            }
+            _ => None,
+        };
 
         info!("Spawning game instance {} with command: {:?}", instance_id, command);
 
         // Launch the process
-        let process = command.spawn()
-            .map_err(|e| HydraError::application(format!("Failed to spawn game instance {}: {}", instance_id, e)))?;
+        self.release_port(instance_id);
+        let mut process = command.spawn()
+            .map_err(|source| LaunchError::SpawnFailed { instance: instance_id, source })?;
 
+        wrapped |= run_as.is_some();
+        let game_pid = if wrapped { wait_for_game_process(&mut process, &self.session_id, instance_id) } else { None };
         let instance = GameInstance {
             id: instance_id,
             process,
+            launched_at: Instant::now(),
+            restarts: 0,
+            working_dir,
+            run_as,
+            game_pid,
+            wineprefix,
         };
 
-        info!("Game instance {} launched successfully with PID: {}", instance_id, instance.process.id());
+        info!("Game instance {} launched successfully with PID: {}", instance_id, instance.pid());
 
         Ok(instance)
     }
@@ -166,7 +1085,7 @@ impl UniversalLauncher {
         executable_path: &Path,
         instance_id: usize,
         strategy: &WorkingDirStrategy,
-    ) -> Result<PathBuf> {
+    ) -> LaunchResult<PathBuf> {
         let working_dir = match strategy {
             WorkingDirStrategy::GameDirectory => {
                 executable_path.parent()
@@ -183,14 +1102,14 @@ impl UniversalLauncher {
             },
             WorkingDirStrategy::Current => {
                 std::env::current_dir()
-                    .map_err(|e| HydraError::Io(e))?
+                    .map_err(LaunchError::WorkingDirSetup)?
             },
         };
 
         // Create the directory if it doesn't exist
         if !working_dir.exists() {
             fs::create_dir_all(&working_dir)
-                .map_err(|e| HydraError::Io(e))?;
+                .map_err(LaunchError::WorkingDirSetup)?;
             info!("Created working directory: {}", working_dir.display());
         }
 
@@ -202,79 +1121,137 @@ impl UniversalLauncher {
         Ok(working_dir)
     }
 
-    /// Setup a separate instance directory with necessary game files
-    fn setup_separate_instance_directory(&self, executable_path: &Path, instance_dir: &Path) -> Result<()> {
+    /// Setup a separate instance directory with the game files the copy
+    /// patterns select, see `set_instance_copy_patterns`. Files already in
+    /// the instance directory are kept.
+    fn setup_separate_instance_directory(&self, executable_path: &Path, instance_dir: &Path) -> LaunchResult<()> {
         let game_dir = executable_path.parent().unwrap_or(Path::new("."));
+        let rules = CopyRules::new(&self.copy_include, &self.copy_exclude);
+        // The instance directory usually sits in the game directory; never copy it into itself.
+        let instance_dir_real = fs::canonicalize(instance_dir).map_err(LaunchError::WorkingDirSetup)?;
+        self.copy_selected(game_dir, Path::new(""), instance_dir, &rules, &instance_dir_real);
+        Ok(())
+    }
 
-        // Copy essential files that games typically need
-        let essential_patterns = [
-            "*.dll",
-            "*.so",
-            "*.dylib",
-            "*.ini",
-            "*.cfg",
-            "*.config",
-            "*.xml",
-            "*.json",
-        ];
-
-        for pattern in &essential_patterns {
-            if let Ok(entries) = fs::read_dir(game_dir) {
-                for entry in entries.flatten() {
-                    if let Some(name) = entry.file_name().to_str() {
-                        let extension = format!("*.{}", 
-                            Path::new(name).extension()
-                                .and_then(|ext| ext.to_str())
-                                .unwrap_or("")
-                        );
-                        
-                        if pattern == &extension && entry.path().is_file() {
-                            let dest = instance_dir.join(name);
-                            if !dest.exists() {
-                                if let Err(e) = fs::copy(entry.path(), &dest) {
-                                    warn!("Failed to copy {} to instance directory: {}", name, e);
-                                } else {
-                                    debug!("Copied {} to instance directory", name);
-                                }
-                            }
-                        }
-                    }
+    /// Copies the entries of `src_dir`, at `relative` in the game directory,
+    /// that `rules` select into `dest_dir`, looking into unselected
+    /// directories when an include reaches into them. Entries that fail to
+    /// copy are skipped with a warning.
+    fn copy_selected(&self, src_dir: &Path, relative: &Path, dest_dir: &Path, rules: &CopyRules, instance_dir: &Path) {
+        let entries = match fs::read_dir(src_dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Could not read {} to copy game files: {}", src_dir.display(), e);
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let src = entry.path();
+            let entry_relative = relative.join(entry.file_name());
+            let dest = dest_dir.join(entry.file_name());
+            let is_dir = src.is_dir();
+            if rules.excluded(&entry_relative, is_dir)
+                || (is_dir && fs::canonicalize(&src).is_ok_and(|real| real == instance_dir))
+            {
+                continue;
+            }
+            if !rules.included(&entry_relative, is_dir) {
+                if is_dir && rules.descend {
+                    self.copy_selected(&src, &entry_relative, &dest, rules, instance_dir);
                 }
+                continue;
+            }
+            if dest.exists() {
+                continue;
+            }
+            let copied = if is_dir {
+                self.copy_dir_recursive(&src, &dest, &entry_relative, rules)
+            } else {
+                fs::create_dir_all(dest_dir)
+                    .and_then(|()| fs::copy(&src, &dest))
+                    .map(|_| ())
+                    .map_err(LaunchError::WorkingDirSetup)
+            };
+            match copied {
+                Ok(()) => debug!("Copied {} to instance directory", entry_relative.display()),
+                Err(e) => warn!("Failed to copy {} to instance directory: {}", entry_relative.display(), e),
             }
         }
+    }
 
-        // Copy essential directories
-        let essential_dirs = ["Data", "Config", "Plugins", "Mods"];
-        for dir_name in &essential_dirs {
-            let src_dir = game_dir.join(dir_name);
-            if src_dir.exists() && src_dir.is_dir() {
-                let dest_dir = instance_dir.join(dir_name);
-                if !dest_dir.exists() {
-                    if let Err(e) = self.copy_dir_recursive(&src_dir, &dest_dir) {
-                        warn!("Failed to copy directory {} to instance: {}", dir_name, e);
-                    } else {
-                        debug!("Copied directory {} to instance", dir_name);
-                    }
-                }
+    /// Replace the game's steam_api libraries in an instance directory with the
+    /// Goldberg emulator and give the instance its own Steam account name.
+    fn stage_goldberg(
+        &self,
+        goldberg_dir: &Path,
+        executable_path: &Path,
+        instance_dir: &Path,
+        instance_id: usize,
+    ) -> LaunchResult<()> {
+        let game_dir = executable_path.parent().unwrap_or(Path::new("."));
+
+        // Never overwrite the libraries inside the game installation itself.
+        if fs::canonicalize(game_dir).ok() == fs::canonicalize(instance_dir).ok() {
+            warn!("Instance {} runs from the game directory; not staging Goldberg over the original steam_api.", instance_id);
+            return Ok(());
+        }
+
+        for library in &STEAM_API_LIBRARIES {
+            if !game_dir.join(library).is_file() {
+                continue;
             }
+            let src = goldberg_dir.join(library);
+            if !src.is_file() {
+                warn!("Game ships {} but {} has no replacement for it.", library, goldberg_dir.display());
+                continue;
+            }
+            let dest = instance_dir.join(library);
+            if dest.exists() {
+                fs::remove_file(&dest).map_err(LaunchError::WorkingDirSetup)?;
+            }
+            self.link_or_copy(&src, &dest)?;
+            debug!("Staged Goldberg {} for instance {}", library, instance_id);
         }
 
+        let settings_dir = instance_dir.join("steam_settings");
+        fs::create_dir_all(&settings_dir).map_err(LaunchError::WorkingDirSetup)?;
+        fs::write(
+            settings_dir.join("force_account_name.txt"),
+            format!("Player{}", instance_id + 1),
+        )
+        .map_err(LaunchError::WorkingDirSetup)?;
+
+        info!("Goldberg Steam emulator staged for instance {}", instance_id);
         Ok(())
     }
 
-    /// Recursively copy a directory
-    fn copy_dir_recursive(&self, src: &Path, dest: &Path) -> Result<()> {
-        fs::create_dir_all(dest).map_err(HydraError::Io)?;
-
-        for entry in fs::read_dir(src).map_err(HydraError::Io)? {
-            let entry = entry.map_err(HydraError::Io)?;
+    /// Hard-link a file into place, falling back to a copy across filesystems
+    fn link_or_copy(&self, src: &Path, dest: &Path) -> LaunchResult<()> {
+        if fs::hard_link(src, dest).is_err() {
+            fs::copy(src, dest).map_err(LaunchError::WorkingDirSetup)?;
+        }
+        Ok(())
+    }
+
+    /// Recursively copy a directory, at `relative` in the game directory,
+    /// leaving out what `rules` exclude
+    fn copy_dir_recursive(&self, src: &Path, dest: &Path, relative: &Path, rules: &CopyRules) -> LaunchResult<()> {
+        fs::create_dir_all(dest).map_err(LaunchError::WorkingDirSetup)?;
+
+        for entry in fs::read_dir(src).map_err(LaunchError::WorkingDirSetup)? {
+            let entry = entry.map_err(LaunchError::WorkingDirSetup)?;
             let src_path = entry.path();
             let dest_path = dest.join(entry.file_name());
+            let entry_relative = relative.join(entry.file_name());
+            let is_dir = src_path.is_dir();
+            if rules.excluded(&entry_relative, is_dir) {
+                continue;
+            }
 
-            if src_path.is_dir() {
-                self.copy_dir_recursive(&src_path, &dest_path)?;
+            if is_dir {
+                self.copy_dir_recursive(&src_path, &dest_path, &entry_relative, rules)?;
             } else {
-                fs::copy(&src_path, &dest_path).map_err(HydraError::Io)?;
+                fs::copy(&src_path, &dest_path).map_err(LaunchError::WorkingDirSetup)?;
             }
         }
 
@@ -282,37 +1259,46 @@ impl UniversalLauncher {
     }
 
     /// Prepare Proton command for Windows games
-    fn prepare_proton_command(&self, executable_path: &Path, _instance_id: usize, working_dir: &Path) -> Result<Command> {
-        let proton_path = crate::proton_integration::find_proton_path()
-            .map_err(|e| HydraError::application(format!("Proton not found: {}", e)))?;
+    fn prepare_proton_command(&self, executable_path: &Path, instance_id: usize, working_dir: &Path) -> LaunchResult<Command> {
+        let proton_path = match &self.proton_path {
+            Some(path) => path.clone(),
+            None => crate::proton_integration::find_proton_path()?,
+        };
 
         let wineprefix = working_dir.join("wineprefix");
-        fs::create_dir_all(&wineprefix).map_err(HydraError::Io)?;
+        fs::create_dir_all(&wineprefix).map_err(LaunchError::WorkingDirSetup)?;
 
         let mut command = Command::new(proton_path);
         command.arg("run");
         command.arg(executable_path);
         command.env("WINEPREFIX", &wineprefix);
-        command.env("PROTON_LOG", "1");
+        // Proton logs are large, so they are only written when asked for
+        if let Some(dir) = &self.proton_log_dir {
+            let log_dir = dir.join(format!("instance_{}", instance_id));
+            fs::create_dir_all(&log_dir).map_err(LaunchError::WorkingDirSetup)?;
+            command.env("PROTON_LOG", "1");
+            command.env("PROTON_LOG_DIR", log_dir);
+        }
 
         Ok(command)
     }
 
     /// Add universal launch arguments
-    fn add_launch_arguments(&self, command: &mut Command, instance_id: usize, config: &GameConfiguration) {
-        // Add profile-specific arguments
-        for arg in &config.launch_args {
-            command.arg(arg);
+    fn add_launch_arguments(&self, command: &mut Command, context: &TemplateContext, config: &GameConfiguration) {
+        // Add profile-specific arguments, then this instance's own ones
+        let instance_args = config.instance_args.get(context.instance_id).into_iter().flatten();
+        for arg in config.launch_args.iter().chain(instance_args) {
+            command.arg(expand_template_os(arg, context));
         }
 
         // Add universal arguments for multi-instance support
-        command.arg(format!("-instance-id={}", instance_id));
-        command.arg(format!("-hydra-instance={}", instance_id));
-        
+        command.arg(expand_template("-instance-id=${INSTANCE_ID}", context));
+        command.arg(expand_template("-hydra-instance=${INSTANCE_ID}", context));
+
         // Add port-related arguments if the game might use them
-        if !config.ports.is_empty() {
-            command.arg(format!("-port={}", config.ports[0]));
-            command.arg(format!("-server-port={}", config.ports[0]));
+        if context.port.is_some() {
+            command.arg(expand_template("-port=${PORT}", context));
+            command.arg(expand_template("-server-port=${PORT}", context));
         }
 
         // Add windowed mode arguments (common for multi-instance)
@@ -321,21 +1307,27 @@ impl UniversalLauncher {
     }
 
     /// Set environment variables for the game instance
-    fn set_environment_variables(&self, command: &mut Command, instance_id: usize, config: &GameConfiguration) {
+    fn set_environment_variables(&self, command: &mut Command, context: &TemplateContext, config: &GameConfiguration) {
         // Set profile-specific environment variables
         for (key, value) in &config.environment_vars {
-            command.env(key, value);
+            command.env(key, expand_template_os(value, context));
         }
 
         // Set universal environment variables
-        command.env("HYDRA_INSTANCE_ID", instance_id.to_string());
-        command.env("HYDRA_INSTANCE_COUNT", "1"); // Will be updated by caller
-        
+        command.env("HYDRA_INSTANCE_ID", expand_template("${INSTANCE_ID}", context));
+        command.env("HYDRA_INSTANCE_COUNT", expand_template("${INSTANCE_COUNT}", context));
+        command.env("HYDRA_SESSION_ID", expand_template("${SESSION_ID}", context));
+        command.env("HYDRA_LOOPBACK_IP", expand_template("${LOOPBACK_IP}", context));
+
         // Set port-related environment variables
-        if !config.ports.is_empty() {
-            command.env("HYDRA_PORT", config.ports[0].to_string());
-            command.env("GAME_PORT", config.ports[0].to_string());
-            command.env("SERVER_PORT", config.ports[0].to_string());
+        if context.port.is_some() {
+            for key in ["HYDRA_PORT", "GAME_PORT", "SERVER_PORT"] {
+                command.env(key, expand_template("${PORT}", context));
+            }
+        }
+
+        for (key, value) in self.instance_env.get(context.instance_id).into_iter().flatten() {
+            command.env(key, value);
         }
 
         // Disable problematic features that might interfere with multi-instance
@@ -344,6 +1336,18 @@ impl UniversalLauncher {
         command.env("FORCE_WINDOWED", "1");
     }
 
+    /// Input device nodes instance `i` is limited to, by `devices[i]`: the
+    /// profile's own `InputIsolation` list, or the one from
+    /// `set_input_isolation`, which applies on top of any other separation
+    /// except running as other users. `None` when input isn't isolated.
+    fn isolated_input_devices<'a>(&'a self, config: &'a GameConfiguration) -> Option<&'a [Option<PathBuf>]> {
+        match &config.instance_separation {
+            InstanceSeparation::InputIsolation { devices } => Some(devices),
+            InstanceSeparation::SeparateUsers { .. } => None,
+            _ => (!self.input_isolation.is_empty()).then_some(self.input_isolation.as_slice()),
+        }
+    }
+
     /// Apply instance separation strategies
     fn apply_instance_separation(
         &self,
@@ -351,16 +1355,22 @@ impl UniversalLauncher {
         instance_id: usize,
         config: &GameConfiguration,
         working_dir: &Path,
-    ) -> Result<()> {
-        match config.instance_separation {
+    ) -> LaunchResult<()> {
+        match &config.instance_separation {
             InstanceSeparation::None => {
                 // No additional separation needed
             },
             InstanceSeparation::Environment => {
                 // Separate using environment variables
                 command.env("INSTANCE_ID", instance_id.to_string());
-                command.env("USER_DATA_DIR", working_dir.join("userdata").to_string_lossy().to_string());
-                command.env("SAVE_DIR", working_dir.join("saves").to_string_lossy().to_string());
+                command.env("USER_DATA_DIR", working_dir.join("userdata"));
+                command.env("SAVE_DIR", working_dir.join("saves"));
+            },
+            InstanceSeparation::InputIsolation { .. } => {
+                // The device itself is set up by `launch_single_instance`
+                command.env("INSTANCE_ID", instance_id.to_string());
+                command.env("USER_DATA_DIR", working_dir.join("userdata"));
+                command.env("SAVE_DIR", working_dir.join("saves"));
             },
             InstanceSeparation::Full => {
                 // Full separation with directories and configs
@@ -368,18 +1378,34 @@ impl UniversalLauncher {
                 let save_dir = working_dir.join("saves");
                 let cache_dir = working_dir.join("cache");
 
-                fs::create_dir_all(&config_dir).map_err(HydraError::Io)?;
-                fs::create_dir_all(&save_dir).map_err(HydraError::Io)?;
-                fs::create_dir_all(&cache_dir).map_err(HydraError::Io)?;
+                fs::create_dir_all(&config_dir).map_err(LaunchError::WorkingDirSetup)?;
+                fs::create_dir_all(&save_dir).map_err(LaunchError::WorkingDirSetup)?;
+                fs::create_dir_all(&cache_dir).map_err(LaunchError::WorkingDirSetup)?;
 
                 // Set various directory environment variables that games might use
-                command.env("APPDATA", config_dir.to_string_lossy().to_string());
-                command.env("LOCALAPPDATA", cache_dir.to_string_lossy().to_string());
-                command.env("USERPROFILE", working_dir.to_string_lossy().to_string());
-                command.env("HOME", working_dir.to_string_lossy().to_string());
-                command.env("XDG_CONFIG_HOME", config_dir.to_string_lossy().to_string());
-                command.env("XDG_DATA_HOME", save_dir.to_string_lossy().to_string());
-                command.env("XDG_CACHE_HOME", cache_dir.to_string_lossy().to_string());
+                command.env("APPDATA", &config_dir);
+                command.env("LOCALAPPDATA", &cache_dir);
+                command.env("USERPROFILE", working_dir);
+                command.env("HOME", working_dir);
+                command.env("XDG_CONFIG_HOME", &config_dir);
+                command.env("XDG_DATA_HOME", &save_dir);
+                command.env("XDG_CACHE_HOME", &cache_dir);
+            },
+            InstanceSeparation::SeparateUsers { users } => {
+                // The game gets the target user's own home and data directories;
+                // only the display is carried over from this session.
+                let user = run_as_user_for(users, instance_id)?;
+                let home = user_home(user)?;
+                command.env("HOME", &home);
+                command.env("USER", user);
+                command.env("LOGNAME", user);
+                if let Some(display) = std::env::var_os("DISPLAY") {
+                    command.env("DISPLAY", display);
+                }
+                // A prefix inside our working directory isn't writable by that user
+                if command.get_envs().any(|(key, _)| key == "WINEPREFIX") {
+                    command.env("WINEPREFIX", home.join(".local/share/hydra-coop").join(format!("wineprefix_{}", instance_id)));
+                }
             },
         }
 
@@ -388,6 +1414,683 @@ impl UniversalLauncher {
 
 }
 
+/// Takes the launch arguments, per-instance arguments and environment
+/// variables over from `recorded`, leaving the rest of `config` as generated.
+fn replay_args(config: &mut GameConfiguration, recorded: &GameConfiguration) {
+    config.launch_args = recorded.launch_args.clone();
+    config.instance_args = recorded.instance_args.clone();
+    config.environment_vars = recorded.environment_vars.clone();
+}
+
+/// Replaces any fixed resolution in the launch arguments with the engine's
+/// resolution options for `${WIDTH}` and `${HEIGHT}`.
+fn add_resolution_args(config: &mut GameConfiguration, engine: Option<&GameEngine>) {
+    let Some(engine) = engine.filter(|engine| !engine.resolution_args().is_empty()) else {
+        info!("No resolution options known for engine {:?}; use ${{WIDTH}} and ${{HEIGHT}} in per_instance_args to set one.", engine);
+        return;
+    };
+    let mut args = std::mem::take(&mut config.launch_args).into_iter().peekable();
+    while let Some(arg) = args.next() {
+        if engine.is_resolution_arg(&arg) {
+            // Drop the value too when it is a separate argument
+            args.next_if(|value| value.parse::<u32>().is_ok());
+        } else {
+            config.launch_args.push(arg);
+        }
+    }
+    config.launch_args.extend(engine.resolution_args().iter().map(|arg| arg.to_string()));
+}
+
+/// Signal `instances` to stop and wait for them to exit. They get SIGTERM and
+/// `grace` to exit before being killed; with no grace they are killed right
+/// away. sudo can't forward SIGKILL to the game it started, so instances
+/// running as another user always get SIGTERM, which sudo relays, and at
+/// least `RUN_AS_STOP_WAIT` to act on it.
+fn stop_instances(instances: &mut [GameInstance], grace: Duration) {
+    for inst in instances.iter_mut() {
+        if inst.handed_off() {
+            // The wrapper is gone; the game has to be told itself
+            let signal = if grace.is_zero() { "KILL" } else { "TERM" };
+            if let Err(e) = crate::orphans::signal(inst.pid(), signal) {
+                warn!("Could not stop instance {}: {}", inst.id, e);
+            }
+        } else if inst.run_as.is_some() || !grace.is_zero() {
+            if let Err(e) = crate::orphans::signal(inst.process.id(), "TERM") {
+                warn!("Could not stop instance {}: {}", inst.id, e);
+            }
+        } else {
+            let _ = inst.process.kill(); // SIGKILL on Unix, TerminateProcess on Windows
+        }
+    }
+
+    let any_run_as = instances.iter().any(|inst| inst.run_as.is_some());
+    let deadline = Instant::now() + if any_run_as { grace.max(RUN_AS_STOP_WAIT) } else { grace };
+    while Instant::now() < deadline {
+        let all_done = instances.iter_mut().all(|inst| !inst.is_running());
+        if all_done {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    // Reap child processes to avoid zombies.
+    for inst in instances.iter_mut() {
+        if let Ok(None) = inst.process.try_wait() {
+            match &inst.run_as {
+                Some(user) => warn!("Instance {} (user '{}') ignored SIGTERM; killing sudo, the game may keep running.", inst.id, user),
+                None if !grace.is_zero() => warn!("Instance {} did not exit within {:?}; killing it.", inst.id, grace),
+                None => {}
+            }
+            let _ = inst.process.kill();
+        } else if inst.handed_off() {
+            warn!("Instance {} did not exit within {:?}; killing it.", inst.id, grace);
+            let _ = crate::orphans::signal(inst.pid(), "KILL");
+        }
+        match inst.process.wait() {
+            Ok(status) => info!("Instance {} exited with status: {}", inst.id, status),
+            Err(e) => error!("Error waiting for instance {}: {}", inst.id, e),
+        }
+    }
+}
+
+/// The user instance `instance_id` runs as.
+fn run_as_user_for(users: &[String], instance_id: usize) -> LaunchResult<&str> {
+    users.get(instance_id).map(String::as_str).ok_or(LaunchError::NotEnoughUsers {
+        users: users.len(),
+        instances: instance_id + 1,
+    })
+}
+
+/// Build `wine <exe>` with a WINEPREFIX of the instance's own.
+fn prepare_wine_command(executable_path: &Path, working_dir: &Path) -> LaunchResult<Command> {
+    let wine_path = crate::wine_integration::find_wine_path()?;
+
+    let wineprefix = working_dir.join("wineprefix");
+    fs::create_dir_all(&wineprefix).map_err(LaunchError::WorkingDirSetup)?;
+
+    let mut command = Command::new(wine_path);
+    command.arg(executable_path);
+    command.env("WINEPREFIX", &wineprefix);
+    Ok(command)
+}
+
+/// What a launch hook is told about the session
+struct HookEnv {
+    session_id: String,
+    executable_path: PathBuf,
+    instance_count: usize,
+    /// Network port of each instance, in instance order
+    ports: Vec<u16>,
+    /// PIDs of the started instances; empty before launch
+    pids: Vec<u32>,
+}
+
+/// [`UniversalLauncher::find_running_instances`] against an arbitrary procfs
+/// root, without excluding any PIDs.
+fn running_instances_in(proc_root: &Path, executable: &Path) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+    let executable = fs::canonicalize(executable).unwrap_or_else(|_| executable.to_path_buf());
+    // Under Wine the exe link is the Wine loader; the game is in the arguments
+    let windows_name = crate::proton_integration::is_windows_binary(&executable)
+        .unwrap_or(false)
+        .then(|| executable.file_name().map(|name| name.to_string_lossy().to_lowercase()))
+        .flatten();
+
+    let mut pids: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let proc_dir = entry.path();
+            let runs_executable = fs::read_link(proc_dir.join("exe")).is_ok_and(|exe| {
+                // A replaced binary shows up as "<path> (deleted)"
+                exe == executable || exe.as_os_str() == OsString::from(format!("{} (deleted)", executable.display()))
+            });
+            let runs_under_wine = windows_name.as_ref().is_some_and(|name| {
+                fs::read(proc_dir.join("cmdline")).is_ok_and(|cmdline| {
+                    cmdline.split(|&b| b == 0).any(|arg| {
+                        let arg = String::from_utf8_lossy(arg).to_lowercase();
+                        arg.rsplit(['/', '\\']).next() == Some(name.as_str())
+                    })
+                })
+            });
+            (runs_executable || runs_under_wine).then_some(pid)
+        })
+        .collect();
+    pids.sort_unstable();
+    pids
+}
+
+/// Start a launch hook, logging what it prints as it prints it. The session
+/// is described in `HYDRA_HOOK` (`pre_launch` or `post_launch`),
+/// `HYDRA_SESSION_ID`, `HYDRA_GAME`, `HYDRA_INSTANCE_COUNT`, and the
+/// comma-separated `HYDRA_PORTS` and `HYDRA_PIDS`.
+fn spawn_hook(hook: &Path, stage: &'static str, env: &HookEnv) -> Result<Child, String> {
+    fn join<T: ToString>(values: &[T]) -> String {
+        values.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+    }
+
+    info!("Running {} hook {}", stage, hook.display());
+    let mut child = Command::new(hook)
+        .env("HYDRA_HOOK", stage)
+        .env("HYDRA_SESSION_ID", &env.session_id)
+        .env("HYDRA_GAME", &env.executable_path)
+        .env("HYDRA_INSTANCE_COUNT", env.instance_count.to_string())
+        .env("HYDRA_PORTS", join(&env.ports))
+        .env("HYDRA_PIDS", join(&env.pids))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run it: {}", e))?;
+    if let Some(stdout) = child.stdout.take() {
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                info!("[{} hook] {}", stage, line);
+            }
+        });
+    }
+    if let Some(stderr) = child.stderr.take() {
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                warn!("[{} hook] {}", stage, line);
+            }
+        });
+    }
+    Ok(child)
+}
+
+/// Run a launch hook and wait for it to exit.
+fn run_hook(hook: &Path, stage: &'static str, env: &HookEnv) -> Result<(), String> {
+    let status = spawn_hook(hook, stage, env)?
+        .wait()
+        .map_err(|e| format!("could not wait for it: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("it exited with {}", status))
+    }
+}
+
+/// How often a post-launch hook running in the background is checked on
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The post-launch hook, running alongside the instances. A thread watches
+/// it, warns if it fails and stops it when asked.
+struct BackgroundHook {
+    /// Set to have the watcher stop the hook
+    stop: Arc<AtomicBool>,
+    watcher: thread::JoinHandle<()>,
+}
+
+impl BackgroundHook {
+    /// Start `hook` without waiting for it. When stopped it gets SIGTERM
+    /// and `grace` to exit before being killed.
+    fn start(hook: &Path, env: &HookEnv, grace: Duration) -> Result<Self, String> {
+        let mut child = spawn_hook(hook, "post_launch", env)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let stop = stop.clone();
+            let hook = hook.to_path_buf();
+            thread::spawn(move || {
+                let mut deadline: Option<Instant> = None;
+                loop {
+                    match child.try_wait() {
+                        Ok(Some(status)) => {
+                            if !status.success() && deadline.is_none() {
+                                warn!("Post-launch hook {} failed: it exited with {}", hook.display(), status);
+                            }
+                            return;
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!("Could not wait for post-launch hook {}: {}", hook.display(), e);
+                            return;
+                        }
+                    }
+                    match deadline {
+                        None if stop.load(Ordering::SeqCst) => {
+                            info!("Stopping post-launch hook {}", hook.display());
+                            if grace.is_zero() || crate::orphans::signal(child.id(), "TERM").is_err() {
+                                let _ = child.kill();
+                            }
+                            deadline = Some(Instant::now() + grace);
+                        }
+                        Some(deadline) if Instant::now() >= deadline => {
+                            warn!("Post-launch hook {} did not exit within {:?}; killing it.", hook.display(), grace);
+                            let _ = child.kill();
+                            let _ = child.wait();
+                            return;
+                        }
+                        _ => {}
+                    }
+                    thread::sleep(HOOK_POLL_INTERVAL);
+                }
+            })
+        };
+        Ok(BackgroundHook { stop, watcher })
+    }
+
+    /// Stop the hook if it is still running and wait for it to exit.
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.watcher.join();
+    }
+}
+
+/// Check up front that every instance has a user that exists and that sudo
+/// can switch to without asking for a password, since a prompt would hang
+/// the launch.
+fn check_run_as_users(users: &[String], num_instances: usize) -> LaunchResult<()> {
+    if users.len() < num_instances {
+        return Err(LaunchError::NotEnoughUsers { users: users.len(), instances: num_instances });
+    }
+    for user in &users[..num_instances] {
+        user_home(user)?;
+        let output = Command::new("sudo")
+            .args(["-n", "-u", user, "true"])
+            .output()
+            .map_err(|e| LaunchError::RunAsUser {
+                user: user.clone(),
+                reason: format!("could not run sudo: {}", e),
+            })?;
+        if !output.status.success() {
+            let me = std::env::var("USER").unwrap_or_else(|_| "<you>".to_string());
+            return Err(LaunchError::RunAsUser {
+                user: user.clone(),
+                reason: format!(
+                    "sudo requires a password ({}). Allow passwordless switching with a rule like \
+                     `{} ALL=({}) NOPASSWD: ALL` added through `sudo visudo -f /etc/sudoers.d/hydra-coop`",
+                    String::from_utf8_lossy(&output.stderr).trim(),
+                    me,
+                    user
+                ),
+            });
+        }
+        debug!("Passwordless sudo to '{}' is available", user);
+    }
+    Ok(())
+}
+
+/// Check that firejail is installed, and warn when the sandbox's private home
+/// directory would hide the game.
+fn check_firejail(firejail: &FirejailConfig, executable_path: &Path) -> LaunchResult<()> {
+    let found = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|dir| dir.join("firejail").is_file()))
+        .unwrap_or(false);
+    if !found {
+        return Err(LaunchError::FirejailUnavailable);
+    }
+    if firejail.private_home {
+        if let Some(home) = dirs::home_dir().filter(|home| executable_path.starts_with(home)) {
+            warn!(
+                "{} is inside {}, which the Firejail sandbox replaces with a private home directory; \
+                 the game may not start. Set private_home = false under [firejail] if it doesn't.",
+                executable_path.display(),
+                home.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Warns about an executable that doesn't suit `runtime` or this machine,
+/// and returns the runtime to use: a native Linux program selected for
+/// Proton or Wine runs directly instead.
+fn check_binary(executable_path: &Path, runtime: WindowsRuntime) -> WindowsRuntime {
+    let info = match analyze_binary(executable_path) {
+        Ok(info) => info,
+        Err(e) => {
+            debug!("Could not read the headers of {}: {}", executable_path.display(), e);
+            return runtime;
+        }
+    };
+    let host_arch = std::env::consts::ARCH;
+    match info.format {
+        BinaryFormat::Pe if runtime == WindowsRuntime::None => {
+            warn!(
+                "{} is a Windows program but no Windows runtime is selected; use Proton or Wine if it doesn't start",
+                executable_path.display()
+            );
+        }
+        BinaryFormat::Pe if runtime == WindowsRuntime::Wine && info.bits == Some(32) => {
+            let wine64_only = crate::wine_integration::find_wine_path()
+                .is_ok_and(|wine| wine.file_name().is_some_and(|name| name == "wine64"));
+            if wine64_only {
+                warn!(
+                    "{} is a 32-bit Windows program but only wine64 was found; install 32-bit Wine if it doesn't start",
+                    executable_path.display()
+                );
+            }
+        }
+        BinaryFormat::Elf => {
+            if runtime != WindowsRuntime::None {
+                warn!(
+                    "{} is a native Linux program; running it directly instead of through {:?}",
+                    executable_path.display(),
+                    runtime
+                );
+            }
+            let runnable = match info.arch {
+                BinaryArch::Unknown => true,
+                BinaryArch::X86 => matches!(host_arch, "x86" | "x86_64"),
+                BinaryArch::Arm => matches!(host_arch, "arm" | "aarch64"),
+                arch => arch.name() == host_arch,
+            };
+            if !runnable {
+                warn!(
+                    "{} is built for {} but this machine is {}; it won't start without an emulator such as box64 or FEX",
+                    executable_path.display(),
+                    info.arch.name(),
+                    host_arch
+                );
+            } else if info.arch == BinaryArch::X86 && host_arch == "x86_64" && !has_32bit_loader() {
+                warn!(
+                    "{} is a 32-bit program but no 32-bit libraries were found; install your distribution's \
+                     multilib/i386 packages if it doesn't start",
+                    executable_path.display()
+                );
+            }
+            return WindowsRuntime::None;
+        }
+        _ => {}
+    }
+    runtime
+}
+
+/// Warns about settings that don't carry over into `app`'s Flatpak sandbox,
+/// and moves separate instance directories out of the read-only Flatpak
+/// installation.
+fn check_flatpak(
+    app: &FlatpakApp,
+    executable_path: &Path,
+    runtime: WindowsRuntime,
+    config: &mut GameConfiguration,
+    firejail: bool,
+) {
+    info!("{} belongs to the Flatpak app {}; starting it with flatpak run", executable_path.display(), app.app_id);
+    if runtime != WindowsRuntime::None {
+        warn!("Flatpak apps bring their own runtime; running {} without {:?}", app.app_id, runtime);
+    }
+    if firejail {
+        warn!("Firejail can't sandbox a Flatpak app, which has a sandbox of its own; ignoring the [firejail] section");
+    }
+    if config.working_dir_strategy == WorkingDirStrategy::SeparateDirectories {
+        info!("The Flatpak installation is read-only; giving each instance a temporary directory instead of one next to the game");
+        config.working_dir_strategy = WorkingDirStrategy::Temporary;
+    }
+    match &config.instance_separation {
+        InstanceSeparation::InputIsolation { .. } => {
+            warn!("Inside a Flatpak sandbox input isolation only sets SDL_JOYSTICK_DEVICE; games reading /dev/input directly still see every device.");
+        }
+        InstanceSeparation::SeparateUsers { .. } if dirs::home_dir().is_some_and(|home| executable_path.starts_with(home)) => {
+            warn!(
+                "{} is installed for your user only, so the other users can't run it; install it system-wide (flatpak install --system) if instances fail to start",
+                app.app_id
+            );
+        }
+        _ => {}
+    }
+}
+
+/// True if the 32-bit x86 dynamic loader is installed
+fn has_32bit_loader() -> bool {
+    ["/lib/ld-linux.so.2", "/lib32/ld-linux.so.2", "/usr/lib32/ld-linux.so.2", "/usr/lib/ld-linux.so.2"]
+        .iter()
+        .any(|path| Path::new(path).exists())
+}
+
+/// Rebuild `command` to run inside a Firejail sandbox. firejail passes the
+/// environment and working directory on to the game, so they stay on the
+/// outer command.
+fn wrap_with_firejail(command: &Command, firejail: &FirejailConfig) -> Command {
+    let mut wrapped = Command::new("firejail");
+    wrapped.arg("--quiet");
+    if let Some(profile) = &firejail.profile {
+        wrapped.arg(format!("--profile={}", profile));
+    }
+    if firejail.private_home {
+        if let Some(dir) = command.get_current_dir() {
+            let mut private = OsString::from("--private=");
+            private.push(dir);
+            wrapped.arg(private);
+        }
+    }
+    wrapped.args(&firejail.extra_args);
+    wrapped.arg("--");
+    wrapped.arg(command.get_program());
+    wrapped.args(command.get_args());
+    if let Some(dir) = command.get_current_dir() {
+        wrapped.current_dir(dir);
+    }
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => wrapped.env(key, value),
+            None => wrapped.env_remove(key),
+        };
+    }
+    wrapped
+}
+
+/// Rebuild `command` to run `app` through `flatpak run`. Variables set on
+/// `command` are passed into the sandbox with `--env`, and the working
+/// directory, which holds the instance's separate config and save
+/// directories, is shared with `--filesystem` and entered with `--cwd`.
+fn wrap_with_flatpak(command: &Command, app: &FlatpakApp) -> Command {
+    let mut wrapped = Command::new("flatpak");
+    wrapped.arg("run");
+    if let Some(branch) = &app.branch {
+        wrapped.arg(format!("--branch={}", branch));
+    }
+    if let Some(arch) = &app.arch {
+        wrapped.arg(format!("--arch={}", arch));
+    }
+    if let Some(program) = &app.command {
+        let mut option = OsString::from("--command=");
+        option.push(program);
+        wrapped.arg(option);
+    }
+    if let Some(dir) = command.get_current_dir() {
+        // A directory of the app itself is already in the sandbox, under /app
+        let inside = match FlatpakApp::from_path(dir).and_then(|dir_app| dir_app.command) {
+            Some(inside) => inside,
+            None => {
+                let mut filesystem = OsString::from("--filesystem=");
+                filesystem.push(dir);
+                wrapped.arg(filesystem);
+                dir.to_path_buf()
+            }
+        };
+        let mut cwd = OsString::from("--cwd=");
+        cwd.push(inside);
+        wrapped.arg(cwd);
+        wrapped.current_dir(dir);
+    }
+    for (key, value) in command.get_envs() {
+        if let Some(value) = value {
+            let mut assignment = OsString::from("--env=");
+            assignment.push(key);
+            assignment.push("=");
+            assignment.push(value);
+            wrapped.arg(assignment);
+        }
+    }
+    wrapped.arg(&app.app_id);
+    wrapped.args(command.get_args());
+    wrapped
+}
+
+/// Whether `bwrap` is on `PATH` to give instances a filtered `/dev/input`.
+fn bubblewrap_available() -> bool {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|dir| dir.join("bwrap").is_file()))
+        .unwrap_or(false)
+}
+
+/// Rebuild `command` to run in a bubblewrap mount namespace that sees the
+/// whole system except `/dev/input`, which holds only `node`. The game then
+/// can't open the other players' devices however it enumerates them.
+fn wrap_with_bubblewrap(command: &Command, node: &Path) -> Command {
+    let mut wrapped = Command::new("bwrap");
+    wrapped.args(["--dev-bind", "/", "/", "--tmpfs", "/dev/input", "--dev-bind"]);
+    wrapped.arg(node).arg(node);
+    wrapped.arg("--die-with-parent");
+    wrapped.arg("--");
+    wrapped.arg(command.get_program());
+    wrapped.args(command.get_args());
+    if let Some(dir) = command.get_current_dir() {
+        wrapped.current_dir(dir);
+    }
+    for (key, value) in command.get_envs() {
+        match value {
+            Some(value) => wrapped.env(key, value),
+            None => wrapped.env_remove(key),
+        };
+    }
+    wrapped
+}
+
+/// Home directory of `user`, looked up through `getent` so directory
+/// services are covered as well as /etc/passwd.
+fn user_home(user: &str) -> LaunchResult<PathBuf> {
+    let output = Command::new("getent").args(["passwd", user]).output().map_err(|e| LaunchError::RunAsUser {
+        user: user.to_string(),
+        reason: format!("could not look up user: {}", e),
+    })?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(home_from_passwd_entry)
+        .ok_or_else(|| LaunchError::RunAsUser {
+            user: user.to_string(),
+            reason: "no such user".to_string(),
+        })
+}
+
+/// The home directory field of a passwd(5) line.
+fn home_from_passwd_entry(line: &str) -> Option<PathBuf> {
+    line.split(':').nth(5).filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// Waits for the wrapper `process` to start the game and returns the game's
+/// PID, or `None` if it didn't within `GAME_PROCESS_WAIT` or failed first.
+/// The game is looked for below the wrapper, and then by the session and
+/// instance ids in its environment: `flatpak run` has the Flatpak session
+/// helper start the game and may exit once it has.
+fn wait_for_game_process(process: &mut Child, session_id: &str, instance_id: usize) -> Option<u32> {
+    let proc_root = Path::new("/proc");
+    let deadline = Instant::now() + GAME_PROCESS_WAIT;
+    loop {
+        let found = find_game_process_in(proc_root, process.id())
+            .or_else(|| find_marked_process_in(proc_root, session_id, instance_id));
+        if let Some(pid) = found {
+            debug!("Instance {}: wrapper {} started the game as PID {}", instance_id, process.id(), pid);
+            return Some(pid);
+        }
+        let failed = match process.try_wait() {
+            Ok(Some(status)) => !status.success(),
+            Ok(None) => false,
+            Err(_) => true,
+        };
+        if Instant::now() >= deadline || failed {
+            warn!(
+                "Could not find the game started by instance {}'s wrapper (PID {}); its windows can't be matched by PID, set window_title_marker to find them by title.",
+                instance_id,
+                process.id()
+            );
+            return None;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// A process other than a wrapper whose environment holds the
+/// `HYDRA_SESSION_ID` and `HYDRA_INSTANCE_ID` of the given instance.
+fn find_marked_process_in(proc_root: &Path, session_id: &str, instance_id: usize) -> Option<u32> {
+    let session = format!("HYDRA_SESSION_ID={}", session_id);
+    let instance = format!("HYDRA_INSTANCE_ID={}", instance_id);
+    let entries = fs::read_dir(proc_root).ok()?;
+    let mut pids: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let environ = fs::read(entry.path().join("environ")).ok()?;
+            let mut vars = environ.split(|&b| b == 0);
+            let marked = vars.clone().any(|var| var == session.as_bytes()) && vars.any(|var| var == instance.as_bytes());
+            let comm = fs::read_to_string(entry.path().join("comm")).ok()?;
+            (marked && !WRAPPER_COMMANDS.contains(&comm.trim())).then_some(pid)
+        })
+        .collect();
+    // The first one started, should the game have started helpers of its own
+    pids.sort_unstable();
+    pids.first().copied()
+}
+
+/// Whether `pid` exists under `proc_root` and isn't a zombie.
+fn process_alive(proc_root: &Path, pid: u32) -> bool {
+    let Ok(stat) = fs::read_to_string(proc_root.join(pid.to_string()).join("stat")) else {
+        return false;
+    };
+    // The state follows the command name, which is in parentheses and may hold spaces
+    stat.rsplit_once(')').and_then(|(_, rest)| rest.split_whitespace().next()).is_some_and(|state| state != "Z" && state != "X")
+}
+
+/// The first process below `wrapper_pid` that isn't a wrapper itself, going
+/// breadth-first through the `children` lists under `proc_root`.
+fn find_game_process_in(proc_root: &Path, wrapper_pid: u32) -> Option<u32> {
+    let mut queue: VecDeque<u32> = child_pids(proc_root, wrapper_pid).into();
+    let mut seen = HashSet::new();
+    while let Some(pid) = queue.pop_front() {
+        if !seen.insert(pid) {
+            continue;
+        }
+        let Ok(comm) = fs::read_to_string(proc_root.join(pid.to_string()).join("comm")) else {
+            continue;
+        };
+        if !WRAPPER_COMMANDS.contains(&comm.trim()) {
+            return Some(pid);
+        }
+        queue.extend(child_pids(proc_root, pid));
+    }
+    None
+}
+
+/// Children of every thread of `pid`.
+fn child_pids(proc_root: &Path, pid: u32) -> Vec<u32> {
+    let Ok(tasks) = fs::read_dir(proc_root.join(pid.to_string()).join("task")) else {
+        return Vec::new();
+    };
+    tasks
+        .flatten()
+        .filter_map(|task| fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| children.split_whitespace().filter_map(|pid| pid.parse().ok()).collect::<Vec<u32>>())
+        .collect()
+}
+
+/// Rebuild `command` to run as `user` through `sudo -n -u`. sudo resets the
+/// environment and won't change directory on its own, so the variables and
+/// working directory are passed to `env` instead.
+fn wrap_with_sudo(command: &Command, user: &str) -> Command {
+    let mut wrapped = Command::new("sudo");
+    wrapped.args(["-n", "-u", user, "--", "env"]);
+    if let Some(dir) = command.get_current_dir() {
+        let mut chdir = OsString::from("--chdir=");
+        chdir.push(dir);
+        wrapped.arg(chdir);
+    }
+    for (key, value) in command.get_envs() {
+        if let Some(value) = value {
+            let mut assignment = key.to_os_string();
+            assignment.push("=");
+            assignment.push(value);
+            wrapped.arg(assignment);
+        }
+    }
+    wrapped.arg(command.get_program());
+    wrapped.args(command.get_args());
+    wrapped
+}
+
 impl Default for UniversalLauncher {
     fn default() -> Self {
         Self::new()
@@ -400,6 +2103,19 @@ mod tests {
     use std::collections::HashMap;
     use tempfile::tempdir;
 
+    /// A configuration with nothing set, for tests to fill in what they need
+    fn test_config() -> GameConfiguration {
+        GameConfiguration {
+            ports: vec![],
+            layout: "horizontal".to_string(),
+            launch_args: vec![],
+            environment_vars: HashMap::new(),
+            working_dir_strategy: WorkingDirStrategy::Current,
+            instance_separation: InstanceSeparation::None,
+            instance_args: Vec::new(),
+        }
+    }
+
     #[test]
     fn test_working_directory_strategies() {
         let temp_dir = tempdir().unwrap();
@@ -419,22 +2135,605 @@ mod tests {
         assert!(working_dir.ends_with("instance_0"));
     }
 
+    #[test]
+    fn test_separate_directory_copy_patterns() {
+        let game_dir = tempdir().unwrap();
+        let exe_path = game_dir.path().join("Game.exe");
+        std::fs::File::create(&exe_path).unwrap();
+        for file in ["UnityPlayer.DLL", "readme.txt", "plugin.asi", "scripts/main.lua", "Game_Data/level0", "Game_Data/cache/big.bin"] {
+            let path = game_dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"data").unwrap();
+        }
+
+        let mut launcher = UniversalLauncher::new();
+        let mut include: Vec<String> = DEFAULT_INSTANCE_COPY_INCLUDE.iter().map(|p| p.to_string()).collect();
+        include.extend(["*.asi".to_string(), "scripts/".to_string(), "*_Data/".to_string()]);
+        launcher.set_instance_copy_patterns(include, vec!["instance_*".to_string(), "Game_Data/cache/".to_string()]);
+        let instance_dir = launcher.prepare_working_directory(&exe_path, 0, &WorkingDirStrategy::SeparateDirectories).unwrap();
+        // The first instance's directory must not end up in the second's.
+        let second_dir = launcher.prepare_working_directory(&exe_path, 1, &WorkingDirStrategy::SeparateDirectories).unwrap();
+
+        for copied in ["UnityPlayer.DLL", "plugin.asi", "scripts/main.lua", "Game_Data/level0"] {
+            assert!(instance_dir.join(copied).is_file(), "{} was not copied", copied);
+        }
+        for skipped in ["readme.txt", "Game_Data/cache"] {
+            assert!(!instance_dir.join(skipped).exists(), "{} was copied", skipped);
+        }
+        assert!(!second_dir.join("instance_0").exists());
+    }
+
+    fn context(instance_id: usize, port: Option<u16>) -> TemplateContext {
+        TemplateContext {
+            instance_id,
+            port,
+            loopback: crate::net_emulator::instance_loopback(instance_id as u8),
+            resolution: Some((960, 1080)),
+            working_dir: PathBuf::from("/games/instance"),
+            instance_count: 2,
+            session_id: "beef".to_string(),
+        }
+    }
+
     #[test]
     fn test_environment_variable_setup() {
         let mut command = Command::new("echo");
         let config = GameConfiguration {
             ports: vec![8080],
-            layout: "horizontal".to_string(),
-            launch_args: vec![],
-            environment_vars: HashMap::new(),
-            working_dir_strategy: WorkingDirStrategy::Current,
+            environment_vars: HashMap::from([("SAVE_PATH".to_string(), "${WORKDIR}/saves".to_string())]),
             instance_separation: InstanceSeparation::Environment,
+            ..test_config()
+        };
+
+        let mut launcher = UniversalLauncher::new();
+        launcher.set_instance_env(vec![vec![("SDL_JOYSTICK_DEVICE".to_string(), "/dev/input/event20".to_string())]]);
+        launcher.set_environment_variables(&mut command, &context(0, Some(8080)), &config);
+
+        let env: HashMap<_, _> = command.get_envs().filter_map(|(k, v)| Some((k.to_str()?, v?.to_str()?))).collect();
+        assert_eq!(env["HYDRA_INSTANCE_ID"], "0");
+        assert_eq!(env["HYDRA_INSTANCE_COUNT"], "2");
+        assert_eq!(env["HYDRA_PORT"], "8080");
+        assert_eq!(env["HYDRA_LOOPBACK_IP"], "127.0.0.2");
+        assert_eq!(env["SAVE_PATH"], "/games/instance/saves");
+        assert_eq!(env["SDL_JOYSTICK_DEVICE"], "/dev/input/event20");
+    }
+
+    #[test]
+    fn test_paths_with_spaces_and_non_utf8_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let temp_dir = tempdir().unwrap();
+        // "Café" spelled in Latin-1, which isn't valid UTF-8
+        let working_dir = temp_dir.path().join("Program Files (x86)/Jeu été").join(OsStr::from_bytes(b"Caf\xe9 1"));
+        let config = GameConfiguration {
+            launch_args: vec!["-savedir=${WORKDIR}/saves".to_string()],
+            environment_vars: HashMap::from([("GAME_DIR".to_string(), "${WORKDIR}".to_string())]),
+            instance_separation: InstanceSeparation::Full,
+            ..test_config()
+        };
+        let ctx = TemplateContext { working_dir: working_dir.clone(), ..context(0, None) };
+        let launcher = UniversalLauncher::new();
+
+        let mut command = Command::new("/bin/sh");
+        launcher.add_launch_arguments(&mut command, &ctx, &config);
+        launcher.set_environment_variables(&mut command, &ctx, &config);
+        launcher.apply_instance_separation(&mut command, 0, &config, &working_dir).unwrap();
+
+        let mut savedir = OsStr::new("-savedir=").to_owned();
+        savedir.push(working_dir.join("saves"));
+        assert_eq!(command.get_args().next(), Some(savedir.as_os_str()));
+        let env: HashMap<_, _> = command.get_envs().filter_map(|(k, v)| Some((k.to_str()?, v?))).collect();
+        assert_eq!(env["GAME_DIR"], working_dir.as_os_str());
+        assert_eq!(env["HOME"], working_dir.as_os_str());
+        assert_eq!(env["XDG_CONFIG_HOME"], working_dir.join("config").as_os_str());
+
+        // The game sees the directories that were created for it
+        let status = Command::new("/bin/sh")
+            .args(["-c", "test -d \"$XDG_CONFIG_HOME\" && test -d \"$XDG_DATA_HOME\""])
+            .envs(command.get_envs().filter_map(|(k, v)| Some((k, v?))))
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_instance_args_are_expanded_per_instance() {
+        let config = GameConfiguration {
+            ports: vec![27015, 27016],
+            launch_args: vec!["-nosplash".to_string()],
+            instance_args: vec![vec![], vec!["+clientport".to_string(), "${PORT}".to_string(), "-player=${INSTANCE}".to_string()]],
+            ..test_config()
+        };
+        let launcher = UniversalLauncher::new();
+
+        let mut first = Command::new("game");
+        launcher.add_launch_arguments(&mut first, &context(0, Some(27015)), &config);
+        assert!(!first.get_args().any(|arg| arg == "+clientport"));
+        assert!(first.get_args().any(|arg| arg == "-port=27015"));
+
+        let mut second = Command::new("game");
+        launcher.add_launch_arguments(&mut second, &context(1, Some(27016)), &config);
+        let args: Vec<_> = second.get_args().collect();
+        assert_eq!(&args[..6], ["-nosplash", "+clientport", "27016", "-player=2", "-instance-id=1", "-hydra-instance=1"]);
+        assert!(args.contains(&std::ffi::OsStr::new("-port=27016")));
+    }
+
+    #[test]
+    fn test_replay_args_take_over_recorded_args_and_env() {
+        let recorded = GameConfiguration {
+            ports: vec![40000, 40001],
+            launch_args: vec!["-nosplash".to_string()],
+            environment_vars: HashMap::from([("GAME_MODE".to_string(), "coop".to_string())]),
+            instance_args: vec![vec![], vec!["-player=${INSTANCE}".to_string()]],
+            ..test_config()
+        };
+        let mut config = GameConfiguration {
+            ports: vec![27015, 27016],
+            launch_args: vec!["-windowed".to_string()],
+            ..test_config()
         };
+        replay_args(&mut config, &recorded);
+        assert_eq!(config.launch_args, recorded.launch_args);
+        assert_eq!(config.instance_args, recorded.instance_args);
+        assert_eq!(config.environment_vars, recorded.environment_vars);
+        assert_eq!(config.ports, [27015, 27016]);
+    }
+
+    #[test]
+    fn test_resolution_args_replace_engine_defaults() {
+        let mut config = GameConfiguration {
+            launch_args: vec!["-windowed".to_string(), "-ResX=800".to_string(), "-ResY=600".to_string()],
+            ..test_config()
+        };
+        add_resolution_args(&mut config, Some(&GameEngine::UnrealEngine));
+        assert_eq!(config.launch_args, ["-windowed", "-ResX=${WIDTH}", "-ResY=${HEIGHT}"]);
+
+        let mut command = Command::new("game");
+        UniversalLauncher::new().add_launch_arguments(&mut command, &context(0, None), &config);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(&args[..3], ["-windowed", "-ResX=960", "-ResY=1080"]);
+
+        let mut unity = GameConfiguration {
+            launch_args: ["-screen-width", "1280", "-popupwindow", "-screen-height", "720"].map(String::from).to_vec(),
+            ..test_config()
+        };
+        add_resolution_args(&mut unity, Some(&GameEngine::Unity));
+        assert_eq!(unity.launch_args, ["-popupwindow", "-screen-width", "${WIDTH}", "-screen-height", "${HEIGHT}"]);
+
+        // Engines without known options are left alone
+        let before = config.launch_args.clone();
+        add_resolution_args(&mut config, Some(&GameEngine::Godot));
+        add_resolution_args(&mut config, None);
+        assert_eq!(config.launch_args, before);
+    }
+
+    #[test]
+    fn test_stage_goldberg() {
+        let game_dir = tempdir().unwrap();
+        let goldberg_dir = tempdir().unwrap();
+        let exe_path = game_dir.path().join("game.exe");
+        std::fs::File::create(&exe_path).unwrap();
+        std::fs::write(game_dir.path().join("steam_api64.dll"), b"original").unwrap();
+        std::fs::write(goldberg_dir.path().join("steam_api64.dll"), b"goldberg").unwrap();
 
         let launcher = UniversalLauncher::new();
-        launcher.set_environment_variables(&mut command, 0, &config);
+        let instance_dir = launcher.prepare_working_directory(
+            &exe_path,
+            1,
+            &WorkingDirStrategy::SeparateDirectories,
+        ).unwrap();
+        launcher.stage_goldberg(goldberg_dir.path(), &exe_path, &instance_dir, 1).unwrap();
+
+        assert_eq!(std::fs::read(instance_dir.join("steam_api64.dll")).unwrap(), b"goldberg");
+        assert_eq!(std::fs::read(game_dir.path().join("steam_api64.dll")).unwrap(), b"original");
+        assert_eq!(
+            std::fs::read_to_string(instance_dir.join("steam_settings/force_account_name.txt")).unwrap(),
+            "Player2"
+        );
+    }
+
+    #[test]
+    fn test_restart_early_crashes() {
+        let temp_dir = tempdir().unwrap();
+        let mut launcher = UniversalLauncher::new();
+        launcher.last_launch = Some(LaunchSpec {
+            executable_path: PathBuf::from("/bin/false"),
+            config: GameConfiguration { working_dir_strategy: WorkingDirStrategy::Temporary, ..test_config() },
+            network_mode: NetworkMode::None,
+            runtime: WindowsRuntime::None,
+            num_instances: 1,
+        });
+        let mut process = Command::new("/bin/false").current_dir(temp_dir.path()).spawn().unwrap();
+        process.wait().unwrap();
+        launcher.active_instances.push(GameInstance {
+            id: 0,
+            process,
+            launched_at: Instant::now(),
+            restarts: 0,
+            working_dir: temp_dir.path().to_path_buf(),
+            run_as: None,
+            game_pid: None,
+            wineprefix: None,
+        });
+
+        let restarted = launcher.restart_early_crashes(Duration::from_secs(60), 1);
+        assert_eq!(restarted.len(), 1);
+        assert_eq!(launcher.active_instances[0].restarts, 1);
+
+        launcher.active_instances[0].process.wait().unwrap();
+        assert!(launcher.restart_early_crashes(Duration::from_secs(60), 1).is_empty());
+        assert!(launcher.abandoned.contains(&0));
+    }
+
+    #[test]
+    fn test_start_instances_in_parallel() {
+        let mut launcher = UniversalLauncher::new();
+        launcher.set_launch_concurrency(3, Duration::from_millis(20));
+        let config = GameConfiguration { working_dir_strategy: WorkingDirStrategy::Temporary, ..test_config() };
+
+        let started_at = Instant::now();
+        let results = launcher.start_instances(Path::new("/bin/true"), 4, &config, NetworkMode::None, WindowsRuntime::None, 3);
+        assert!(started_at.elapsed() >= Duration::from_millis(60));
+        let ids: Vec<usize> = results.iter().map(|(instance_id, _)| *instance_id).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+        for (_, result) in results {
+            let mut instance = result.unwrap();
+            instance.process.wait().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cancel_stops_starting_instances() {
+        let mut launcher = UniversalLauncher::new();
+        launcher.set_launch_concurrency(1, Duration::from_secs(30));
+        let cancel = Arc::new(AtomicBool::new(false));
+        launcher.set_cancel_flag(cancel.clone());
+        let config = GameConfiguration { working_dir_strategy: WorkingDirStrategy::Temporary, ..test_config() };
+
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            cancel.store(true, Ordering::SeqCst);
+        });
+        let started_at = Instant::now();
+        let results = launcher.start_instances(Path::new("/bin/true"), 3, &config, NetworkMode::None, WindowsRuntime::None, 1);
+        canceller.join().unwrap();
+
+        // The first instance started; the wait for the second ended with the cancel
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+        assert_eq!(results.len(), 1);
+        for (_, result) in results {
+            result.unwrap().process.wait().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_wrap_with_sudo() {
+        let mut command = Command::new("/games/game");
+        command.arg("-windowed").current_dir("/games/instance_1").env("HOME", "/home/player2");
+
+        let wrapped = wrap_with_sudo(&command, "player2");
+        assert_eq!(wrapped.get_program(), "sudo");
+        let args: Vec<_> = wrapped.get_args().collect();
+        assert_eq!(
+            args,
+            ["-n", "-u", "player2", "--", "env", "--chdir=/games/instance_1", "HOME=/home/player2", "/games/game", "-windowed"]
+        );
+
+        let users = vec!["player1".to_string(), "player2".to_string()];
+        assert_eq!(run_as_user_for(&users, 1).unwrap(), "player2");
+        assert!(matches!(run_as_user_for(&users, 2), Err(LaunchError::NotEnoughUsers { users: 2, instances: 3 })));
+        assert_eq!(
+            home_from_passwd_entry("player2:x:1001:1001:Player Two:/home/player2:/bin/bash"),
+            Some(PathBuf::from("/home/player2"))
+        );
+    }
+
+    #[test]
+    fn test_wrap_with_firejail() {
+        let mut command = Command::new("/opt/game/game");
+        command.arg("-windowed").current_dir("/opt/game/instance_1").env("HYDRA_INSTANCE_ID", "1");
+
+        let firejail = FirejailConfig {
+            profile: Some("steam".to_string()),
+            private_home: true,
+            extra_args: vec!["--nosound".to_string()],
+        };
+        let wrapped = wrap_with_firejail(&command, &firejail);
+        assert_eq!(wrapped.get_program(), "firejail");
+        let args: Vec<_> = wrapped.get_args().collect();
+        assert_eq!(
+            args,
+            ["--quiet", "--profile=steam", "--private=/opt/game/instance_1", "--nosound", "--", "/opt/game/game", "-windowed"]
+        );
+        assert_eq!(wrapped.get_current_dir(), Some(Path::new("/opt/game/instance_1")));
+        assert!(wrapped.get_envs().any(|(key, value)| key == "HYDRA_INSTANCE_ID" && value == Some("1".as_ref())));
+
+        // Wrapped for a user after the sandbox, sudo runs firejail
+        let as_user = wrap_with_sudo(&wrapped, "player2");
+        assert!(as_user.get_args().any(|arg| arg == "firejail"));
+    }
+
+    #[test]
+    fn test_wrap_with_flatpak() {
+        let executable = Path::new("/var/lib/flatpak/app/org.example.Game/x86_64/stable/0a1b2c/files/bin/game");
+        let app = FlatpakApp::from_path(executable).unwrap();
+        let mut command = Command::new(executable);
+        command.arg("-windowed").current_dir("/tmp/hydra_game_instance_1").env("HYDRA_INSTANCE_ID", "1");
+
+        let wrapped = wrap_with_flatpak(&command, &app);
+        assert_eq!(wrapped.get_program(), "flatpak");
+        let args: Vec<_> = wrapped.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--branch=stable",
+                "--arch=x86_64",
+                "--command=/app/bin/game",
+                "--filesystem=/tmp/hydra_game_instance_1",
+                "--cwd=/tmp/hydra_game_instance_1",
+                "--env=HYDRA_INSTANCE_ID=1",
+                "org.example.Game",
+                "-windowed",
+            ]
+        );
+        assert_eq!(wrapped.get_envs().count(), 0);
+
+        // The game's own directory is reached through /app
+        command.current_dir(executable.parent().unwrap());
+        let args: Vec<_> = wrap_with_flatpak(&command, &app).get_args().map(|arg| arg.to_os_string()).collect();
+        assert!(args.contains(&"--cwd=/app/bin".into()));
+        assert!(!args.iter().any(|arg| arg.to_string_lossy().starts_with("--filesystem=")));
+    }
+
+    #[test]
+    fn test_input_isolation() {
+        let mut command = Command::new("/opt/game/game");
+        command.arg("-windowed").current_dir("/opt/game/instance_1");
+        let config = GameConfiguration {
+            instance_separation: InstanceSeparation::InputIsolation {
+                devices: vec![Some(PathBuf::from("/dev/input/event20")), Some(PathBuf::from("/dev/input/event21"))],
+            },
+            ..test_config()
+        };
+        let mut launcher = UniversalLauncher::new();
+        launcher.apply_instance_separation(&mut command, 1, &config, Path::new("/opt/game/instance_1")).unwrap();
+        let env: HashMap<_, _> = command.get_envs().filter_map(|(k, v)| Some((k.to_str()?, v?.to_str()?))).collect();
+        assert_eq!(env["INSTANCE_ID"], "1");
+        let devices = launcher.isolated_input_devices(&config).unwrap();
+        assert_eq!(devices[1].as_deref(), Some(Path::new("/dev/input/event21")));
+
+        // Devices from set_input_isolation go on top of the game's own separation
+        let full = GameConfiguration { instance_separation: InstanceSeparation::Full, ..config.clone() };
+        assert!(launcher.isolated_input_devices(&full).is_none());
+        launcher.set_input_isolation(vec![None, Some(PathBuf::from("/dev/input/event30"))]);
+        let devices = launcher.isolated_input_devices(&full).unwrap();
+        assert_eq!(devices[1].as_deref(), Some(Path::new("/dev/input/event30")));
+        let separate_users = GameConfiguration {
+            instance_separation: InstanceSeparation::SeparateUsers { users: vec!["a".into(), "b".into()] },
+            ..config.clone()
+        };
+        assert!(launcher.isolated_input_devices(&separate_users).is_none());
+
+        command.env("SDL_JOYSTICK_DEVICE", "/dev/input/event21");
+        let wrapped = wrap_with_bubblewrap(&command, Path::new("/dev/input/event21"));
+        assert_eq!(wrapped.get_program(), "bwrap");
+        let args: Vec<_> = wrapped.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "--dev-bind", "/", "/", "--tmpfs", "/dev/input", "--dev-bind", "/dev/input/event21", "/dev/input/event21",
+                "--die-with-parent", "--", "/opt/game/game", "-windowed"
+            ]
+        );
+        assert_eq!(wrapped.get_current_dir(), Some(Path::new("/opt/game/instance_1")));
+        assert!(wrapped.get_envs().any(|(key, value)| key == "SDL_JOYSTICK_DEVICE" && value == Some("/dev/input/event21".as_ref())));
+    }
+
+    #[test]
+    fn test_run_hook() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp_dir = tempdir().unwrap();
+        let seen = temp_dir.path().join("seen");
+        let hook = temp_dir.path().join("hook.sh");
+        std::fs::write(
+            &hook,
+            format!(
+                "#!/bin/sh\necho \"$HYDRA_HOOK $HYDRA_INSTANCE_COUNT $HYDRA_PORTS $HYDRA_PIDS\" > {}\n",
+                seen.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let env = hook_env();
+        run_hook(&hook, "post_launch", &env).unwrap();
+        assert_eq!(std::fs::read_to_string(&seen).unwrap(), "post_launch 2 7777,7778 100,101\n");
+
+        let failing = temp_dir.path().join("failing.sh");
+        std::fs::write(&failing, "#!/bin/sh\nexit 3\n").unwrap();
+        std::fs::set_permissions(&failing, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(run_hook(&failing, "pre_launch", &env).unwrap_err().contains("exit status: 3"));
+        assert!(run_hook(&temp_dir.path().join("missing"), "pre_launch", &env).is_err());
+    }
 
-        // Verify environment variables are set (this is a simplified test)
-        // In a real test, you'd need to check the command's environment
+    /// A game directory whose game starts fine except as instance 1, whose
+    /// working directory is in the way as a file
+    fn game_failing_second_instance() -> (tempfile::TempDir, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+        let game_dir = tempdir().unwrap();
+        let game = game_dir.path().join("game.sh");
+        std::fs::write(&game, "#!/bin/sh\nexec sleep 30\n").unwrap();
+        std::fs::set_permissions(&game, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(game_dir.path().join("instance_1"), b"").unwrap();
+        (game_dir, game)
+    }
+
+    #[test]
+    fn test_all_or_nothing_stops_started_instances() {
+        let (game_dir, game) = game_failing_second_instance();
+        let mut launcher = UniversalLauncher::new();
+        launcher.set_launch_policy(LaunchPolicy::AllOrNothing);
+
+        match launcher.launch_game_instances(&game, 3, WindowsRuntime::None) {
+            Err(LaunchError::PartialLaunch { launched, .. }) => assert_eq!(launched, 1),
+            other => panic!("expected a partial launch, got {:?}", other.map(|report| report.pids)),
+        }
+        assert!(launcher.instances().is_empty());
+        // Nothing is started after the failure
+        assert!(!game_dir.path().join("instance_2").exists());
+    }
+
+    #[test]
+    fn test_best_effort_continues_without_failed_instances() {
+        let (_game_dir, game) = game_failing_second_instance();
+        let mut launcher = UniversalLauncher::new();
+        launcher.set_launch_policy(LaunchPolicy::BestEffort);
+
+        let report = launcher.launch_game_instances(&game, 3, WindowsRuntime::None).unwrap();
+        assert_eq!(report.pids.len(), 2);
+        assert_eq!(report.failures.iter().map(|(instance_id, _)| *instance_id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(launcher.instances().iter().map(|instance| instance.id).collect::<Vec<_>>(), vec![0, 2]);
+        launcher.shutdown_instances();
+        assert!(launcher.instances().is_empty());
+    }
+
+    fn hook_env() -> HookEnv {
+        HookEnv {
+            session_id: "beef".to_string(),
+            executable_path: PathBuf::from("/games/game"),
+            instance_count: 2,
+            ports: vec![7777, 7778],
+            pids: vec![100, 101],
+        }
+    }
+
+    #[test]
+    fn test_post_launch_hook_runs_in_background() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp_dir = tempdir().unwrap();
+        let hook = temp_dir.path().join("hook.sh");
+        let pid_file = temp_dir.path().join("pid");
+        std::fs::write(&hook, format!("#!/bin/sh\necho $$ > {}\nexec sleep 30\n", pid_file.display())).unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let started = Instant::now();
+        let running = BackgroundHook::start(&hook, &hook_env(), Duration::from_secs(5)).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5), "the launch waited for the hook");
+        while !pid_file.exists() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let pid = std::fs::read_to_string(&pid_file).unwrap().trim().to_string();
+        assert!(Path::new("/proc").join(&pid).exists());
+
+        running.stop();
+        assert!(started.elapsed() < Duration::from_secs(5), "the hook was not stopped on SIGTERM");
+        assert!(!Path::new("/proc").join(&pid).exists(), "the hook was not reaped");
+    }
+
+    #[test]
+    fn test_running_instances_in() {
+        let proc_root = tempdir().unwrap();
+        let game_dir = tempdir().unwrap();
+        let game = game_dir.path().join("Game.exe");
+        std::fs::write(&game, b"MZ").unwrap();
+        let add_process = |pid: &str, exe: &Path, cmdline: &[u8]| {
+            let dir = proc_root.path().join(pid);
+            std::fs::create_dir(&dir).unwrap();
+            std::os::unix::fs::symlink(exe, dir.join("exe")).unwrap();
+            std::fs::write(dir.join("cmdline"), cmdline).unwrap();
+        };
+        add_process("120", &game, b"");
+        add_process("45", Path::new("/usr/bin/wine64-preloader"), b"C:\\Games\\game.EXE\0-windowed\0");
+        add_process("300", Path::new("/usr/bin/bash"), b"bash\0");
+        add_process("self", &game, b"");
+
+        assert_eq!(running_instances_in(proc_root.path(), &game), vec![45, 120]);
+        assert!(running_instances_in(proc_root.path(), Path::new("/usr/bin/true")).is_empty());
+    }
+
+    #[test]
+    fn test_find_game_process_below_wrapper() {
+        let proc_root = tempdir().unwrap();
+        let add_process = |pid: u32, comm: &str, threads: &[(u32, &str)]| {
+            let dir = proc_root.path().join(pid.to_string());
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("comm"), format!("{}\n", comm)).unwrap();
+            for (tid, children) in threads {
+                let task = dir.join("task").join(tid.to_string());
+                std::fs::create_dir_all(&task).unwrap();
+                std::fs::write(task.join("children"), children).unwrap();
+            }
+        };
+        // sudo -> sudo monitor -> launch script, whose second thread started the game
+        add_process(100, "sudo", &[(100, "101 ")]);
+        add_process(101, "sudo", &[(101, "102 ")]);
+        add_process(102, "bash", &[(102, ""), (103, "104 ")]);
+        add_process(104, "game", &[(104, "")]);
+        assert_eq!(find_game_process_in(proc_root.path(), 100), Some(104));
+
+        // Nothing but wrappers yet
+        add_process(200, "sudo", &[(200, "201")]);
+        add_process(201, "env", &[(201, "")]);
+        assert_eq!(find_game_process_in(proc_root.path(), 200), None);
+        assert_eq!(find_game_process_in(proc_root.path(), 999), None);
+    }
+
+    #[test]
+    fn test_find_flatpak_game_by_environment() {
+        let proc_root = tempdir().unwrap();
+        let add_process = |pid: u32, comm: &str, environ: &[u8], stat: &str| {
+            let dir = proc_root.path().join(pid.to_string());
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("comm"), format!("{}\n", comm)).unwrap();
+            std::fs::write(dir.join("environ"), environ).unwrap();
+            std::fs::write(dir.join("stat"), stat).unwrap();
+        };
+        // The sandbox's bwrap carries the markers too, but isn't the game
+        add_process(300, "bwrap", b"HYDRA_SESSION_ID=beef\0HYDRA_INSTANCE_ID=1\0", "300 (bwrap) S 1");
+        add_process(310, "game", b"HOME=/home/p\0HYDRA_INSTANCE_ID=1\0HYDRA_SESSION_ID=beef\0", "310 (my game) S 300");
+        add_process(320, "game", b"HYDRA_SESSION_ID=beef\0HYDRA_INSTANCE_ID=0\0", "320 (game) Z 300");
+        add_process(330, "game", b"HYDRA_SESSION_ID=cafe\0HYDRA_INSTANCE_ID=1\0", "330 (game) S 1");
+
+        assert_eq!(find_marked_process_in(proc_root.path(), "beef", 1), Some(310));
+        assert_eq!(find_marked_process_in(proc_root.path(), "beef", 0), Some(320));
+        assert_eq!(find_marked_process_in(proc_root.path(), "beef", 2), None);
+
+        assert!(process_alive(proc_root.path(), 310));
+        assert!(!process_alive(proc_root.path(), 320));
+        assert!(!process_alive(proc_root.path(), 999));
+    }
+
+    #[test]
+    fn test_partial_launch_error_keeps_cause() {
+        use std::error::Error;
+        let err = LaunchError::PartialLaunch {
+            launched: 1,
+            source: Box::new(LaunchError::SpawnFailed {
+                instance: 1,
+                source: io::Error::new(io::ErrorKind::PermissionDenied, "denied"),
+            }),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Failed to spawn game instance 1: denied (stopped the 1 instance(s) already started)"
+        );
+        assert!(matches!(
+            err.source().and_then(|e| e.downcast_ref::<LaunchError>()),
+            Some(LaunchError::SpawnFailed { instance: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_launch_error_converts_to_hydra_error() {
+        let err = LaunchError::SpawnFailed {
+            instance: 1,
+            source: io::Error::new(io::ErrorKind::NotFound, "missing"),
+        };
+        let hydra: crate::errors::HydraError = err.into();
+        assert!(matches!(
+            hydra,
+            crate::errors::HydraError::Launch(LaunchError::SpawnFailed { instance: 1, .. })
+        ));
     }
 }
\ No newline at end of file
@@ -1,13 +1,31 @@
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{self, AtomEnum, ConfigureWindowAux, ConnectionExt, PropMode};
+use x11rb::protocol::xproto::{self, AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt, EventMask, GrabMode, ModMask, PropMode, StackMode};
+use x11rb::protocol::{res, ErrorKind, Event};
 use x11rb::rust_connection::RustConnection;
 use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
 use std::error::Error;
 use log::{info, error, warn, debug};
-use std::sync::Arc;
 use std::time::{Duration, Instant};
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+
+/// How long the window manager gets to take a window's frame away after
+/// being asked to, before the frame it reports is trusted
+const DECORATION_SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Most windows read from `_NET_CLIENT_LIST`, in 32-bit units
+const CLIENT_LIST_MAX_WINDOWS: u32 = 4096;
+
+/// Levels below the top-level windows `WindowMatchStrategy::Tree` searches
+const TREE_SEARCH_DEPTH: usize = 3;
+
+/// How long a search looks by PID and title before also walking the window
+/// tree, at most; a quarter of the search timeout when that is shorter
+const TREE_FALLBACK_DELAY: Duration = Duration::from_secs(2);
 
 // Custom error type for window management operations
 #[derive(Debug)]
@@ -18,6 +36,12 @@ pub enum WindowManagerError {
     InvalidPropertyData(xproto::Window, xproto::Atom),
     MonitorDetectionError(String),
     WindowNotFound(Vec<u32>),
+    /// The window search was stopped through the cancel flag
+    Cancelled,
+    /// A hotkey that isn't a known key name with optional modifiers
+    InvalidHotkey(String),
+    /// No key on the keyboard makes the hotkey, or another program has grabbed it
+    HotkeyUnavailable(String),
 }
 
 impl std::fmt::Display for WindowManagerError {
@@ -33,10 +57,32 @@ impl std::fmt::Display for WindowManagerError {
             WindowManagerError::WindowNotFound(pids) => {
                 write!(f, "Window not found for PIDs: {:?}", pids)
             },
+            WindowManagerError::Cancelled => write!(f, "Window search cancelled"),
+            WindowManagerError::InvalidHotkey(hotkey) => write!(
+                f,
+                "Invalid hotkey '{}': use a key such as F12 or Scroll_Lock, optionally after Ctrl+, Shift+, Alt+ or Super+",
+                hotkey
+            ),
+            WindowManagerError::HotkeyUnavailable(hotkey) => {
+                write!(f, "Hotkey '{}' is not on the keyboard or is already taken by another program", hotkey)
+            }
         }
     }
 }
 
+impl WindowManagerError {
+    /// True for an X11 error about a single window that no longer exists,
+    /// which is routine while scanning windows other programs own, as
+    /// opposed to trouble with the connection itself.
+    pub fn is_window_gone(&self) -> bool {
+        matches!(
+            self,
+            WindowManagerError::X11rbReplyError(ReplyError::X11Error(e))
+                if matches!(e.error_kind, ErrorKind::Window | ErrorKind::Drawable)
+        )
+    }
+}
+
 impl Error for WindowManagerError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
@@ -66,69 +112,648 @@ impl From<ReplyError> for WindowManagerError {
     }
 }
 
+/// The X11 requests the window manager relies on. `RustConnection` is the real
+/// implementation; tests substitute `mock::MockX11`.
+pub trait X11Ops: Send + Sync {
+    /// Root window of the default screen
+    fn root_window(&self) -> xproto::Window;
+    fn intern_atom(&self, name: &[u8]) -> Result<xproto::Atom, WindowManagerError>;
+    /// Children of `window`, in stacking order
+    fn query_tree(&self, window: xproto::Window) -> Result<Vec<xproto::Window>, WindowManagerError>;
+    /// Raw value of a window property, up to `long_length` 32-bit units
+    fn get_property(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: AtomEnum,
+        long_length: u32,
+    ) -> Result<Vec<u8>, WindowManagerError>;
+    fn configure_window(&self, window: xproto::Window, aux: &ConfigureWindowAux) -> Result<(), WindowManagerError>;
+    fn change_property(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: xproto::Atom,
+        format: u8,
+        data_len: u32,
+        data: &[u8],
+    ) -> Result<(), WindowManagerError>;
+    /// Sends a 32-bit client message about `window` to the root window, the
+    /// way EWMH asks clients to request state changes from the window manager
+    fn send_client_message(&self, window: xproto::Window, message_type: xproto::Atom, data: [u32; 5]) -> Result<(), WindowManagerError>;
+    /// Window that owns `selection`, or 0 when nobody does
+    fn selection_owner(&self, selection: xproto::Atom) -> Result<xproto::Window, WindowManagerError>;
+    /// Position (in root window coordinates) and size of `window`
+    fn window_geometry(&self, window: xproto::Window) -> Result<WindowRect, WindowManagerError>;
+    /// PID of the client that created `window` as the X server sees it, from
+    /// the X-Resource extension; `None` when the server can't tell
+    fn client_pid(&self, window: xproto::Window) -> Result<Option<u32>, WindowManagerError>;
+    fn flush(&self) -> Result<(), WindowManagerError>;
+}
+
+impl X11Ops for RustConnection {
+    fn root_window(&self) -> xproto::Window {
+        self.setup().roots[0].root
+    }
+
+    fn intern_atom(&self, name: &[u8]) -> Result<xproto::Atom, WindowManagerError> {
+        Ok(ConnectionExt::intern_atom(self, false, name)?.reply()?.atom)
+    }
+
+    fn query_tree(&self, window: xproto::Window) -> Result<Vec<xproto::Window>, WindowManagerError> {
+        Ok(ConnectionExt::query_tree(self, window)?.reply()?.children)
+    }
+
+    fn get_property(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: AtomEnum,
+        long_length: u32,
+    ) -> Result<Vec<u8>, WindowManagerError> {
+        Ok(ConnectionExt::get_property(self, false, window, property, type_, 0, long_length)?.reply()?.value)
+    }
+
+    fn configure_window(&self, window: xproto::Window, aux: &ConfigureWindowAux) -> Result<(), WindowManagerError> {
+        ConnectionExt::configure_window(self, window, aux)?.check()?;
+        Ok(())
+    }
+
+    fn change_property(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: xproto::Atom,
+        format: u8,
+        data_len: u32,
+        data: &[u8],
+    ) -> Result<(), WindowManagerError> {
+        ConnectionExt::change_property(self, PropMode::REPLACE, window, property, type_, format, data_len, data)?.check()?;
+        Ok(())
+    }
+
+    fn send_client_message(&self, window: xproto::Window, message_type: xproto::Atom, data: [u32; 5]) -> Result<(), WindowManagerError> {
+        let event = ClientMessageEvent::new(32, window, message_type, data);
+        let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+        ConnectionExt::send_event(self, false, self.root_window(), mask, event)?.check()?;
+        Ok(())
+    }
+
+    fn selection_owner(&self, selection: xproto::Atom) -> Result<xproto::Window, WindowManagerError> {
+        Ok(ConnectionExt::get_selection_owner(self, selection)?.reply()?.owner)
+    }
+
+    fn window_geometry(&self, window: xproto::Window) -> Result<WindowRect, WindowManagerError> {
+        let geometry = ConnectionExt::get_geometry(self, window)?.reply()?;
+        // The geometry is relative to the parent, which is the window
+        // manager's frame for reparented windows.
+        let origin = ConnectionExt::translate_coordinates(self, window, self.root_window(), 0, 0)?.reply()?;
+        Ok(WindowRect {
+            x: origin.dst_x.into(),
+            y: origin.dst_y.into(),
+            width: geometry.width.into(),
+            height: geometry.height.into(),
+        })
+    }
+
+    fn client_pid(&self, window: xproto::Window) -> Result<Option<u32>, WindowManagerError> {
+        let spec = res::ClientIdSpec { client: window, mask: res::ClientIdMask::LOCAL_CLIENT_PID };
+        // Servers without the extension, and remote clients, have no answer
+        let Ok(reply) = res::query_client_ids(self, &[spec]).map_err(ReplyError::from).and_then(|cookie| cookie.reply()) else {
+            return Ok(None);
+        };
+        Ok(reply
+            .ids
+            .iter()
+            .find(|id| id.spec.mask == res::ClientIdMask::LOCAL_CLIENT_PID)
+            .and_then(|id| id.value.first().copied()))
+    }
+
+    fn flush(&self) -> Result<(), WindowManagerError> {
+        Connection::flush(self)?;
+        Ok(())
+    }
+}
+
+/// What `set_layout` does when some windows haven't appeared by the timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutFailurePolicy {
+    /// Fail with `WindowNotFound` without moving any window
+    Abort,
+    /// Place the windows that were found in their slots and leave the
+    /// others' slots empty
+    #[default]
+    LayoutFound,
+    /// Leave every window where the game put it
+    Ignore,
+}
+
+/// How a game window was told apart from the others. A window search tries
+/// each in turn for every instance, see `WindowManager::set_preferred_match_strategy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMatchStrategy {
+    /// The instance's PID in `_NET_WM_PID` of a top-level window
+    Pid,
+    /// The instance's title marker in the window title
+    Title,
+    /// The instance's PID on a window below the top-level ones, for window
+    /// managers that reparent windows without listing them in
+    /// `_NET_CLIENT_LIST` and games that set `_NET_WM_PID` on a child window
+    Tree,
+}
+
+/// Order a search tries the strategies in unless told otherwise
+const DEFAULT_MATCH_ORDER: [WindowMatchStrategy; 3] =
+    [WindowMatchStrategy::Pid, WindowMatchStrategy::Title, WindowMatchStrategy::Tree];
+
+/// How long and how often window lookups poll for windows to appear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowSearchTiming {
+    /// Give up with `WindowNotFound` after this long
+    pub timeout: Duration,
+    /// Delay before the first retry; doubled after each miss
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between retries
+    pub max_backoff: Duration,
+}
+
+impl Default for WindowSearchTiming {
+    fn default() -> Self {
+        WindowSearchTiming {
+            timeout: crate::defaults::WINDOW_SEARCH_TIMEOUT,
+            initial_backoff: crate::defaults::WINDOW_SEARCH_INITIAL_BACKOFF,
+            max_backoff: crate::defaults::WINDOW_SEARCH_MAX_BACKOFF,
+        }
+    }
+}
 
 pub struct WindowManager {
-    conn: Arc<RustConnection>,
+    conn: Box<dyn X11Ops>,
+    search_timing: WindowSearchTiming,
+    /// Keep windows placed by `set_layout` above other windows
+    keep_above: bool,
+    /// Windows made always-on-top, for `release_always_on_top`
+    above_windows: Mutex<Vec<xproto::Window>>,
+    /// Per-slot aspect ratio to letterbox windows to; `None` fills the cell
+    aspect_ratios: Option<Vec<f64>>,
+    /// Set from another thread to abandon a window search early
+    cancel: Option<Arc<AtomicBool>>,
+    /// Leave window decorations alone and fit the frame into the slot instead
+    keep_decorations: bool,
+    /// Window positions for `Layout::Custom`, one per slot
+    custom_rects: Vec<WindowRect>,
+    /// Windows placed by the last `set_layout`, in slot order
+    laid_out: Mutex<Vec<xproto::Window>>,
+    /// Switches between the windows of a tabbed layout
+    tab_cycler: Option<TabCycler>,
+    /// What `set_layout` does when not every window turns up
+    on_layout_failure: LayoutFailurePolicy,
+    /// Add each window's player number to its title when placing it
+    label_windows: bool,
+    /// (window, label) of every window given a player label
+    labels: Mutex<Vec<(xproto::Window, String)>>,
+    /// Stack the windows `set_layout` places in player order
+    restack_windows: bool,
+    /// Opacity of the window in each slot; slots past the end stay opaque
+    window_opacity: Vec<f64>,
+    /// Opacity of the windows behind the front one in a tabbed layout
+    dim_unfocused: Option<f64>,
+    /// Strategy window searches try first, see `set_preferred_match_strategy`
+    preferred_match: Option<WindowMatchStrategy>,
+    /// Strategy that matched most windows in the last search that found them all
+    last_match: Mutex<Option<WindowMatchStrategy>>,
 }
 
 impl WindowManager {
     pub fn new() -> Result<Self, WindowManagerError> {
         let (conn, _) = RustConnection::connect(None)?;
-        Ok(WindowManager { conn: Arc::new(conn) })
+        Ok(WindowManager {
+            conn: Box::new(conn),
+            search_timing: WindowSearchTiming::default(),
+            keep_above: false,
+            above_windows: Mutex::new(Vec::new()),
+            aspect_ratios: None,
+            cancel: None,
+            keep_decorations: false,
+            custom_rects: Vec::new(),
+            laid_out: Mutex::new(Vec::new()),
+            tab_cycler: None,
+            on_layout_failure: LayoutFailurePolicy::default(),
+            label_windows: false,
+            labels: Mutex::new(Vec::new()),
+            restack_windows: false,
+            window_opacity: Vec::new(),
+            dim_unfocused: None,
+            preferred_match: None,
+            last_match: Mutex::new(None),
+        })
     }
 
-    /// Finds a window by its _NET_WM_PID property.
+    /// Creates a window manager over any `X11Ops` implementation.
+    #[cfg(any(test, feature = "mock-x11"))]
+    pub fn with_backend(conn: Box<dyn X11Ops>, search_timing: WindowSearchTiming) -> Self {
+        WindowManager {
+            conn,
+            search_timing,
+            keep_above: false,
+            above_windows: Mutex::new(Vec::new()),
+            aspect_ratios: None,
+            cancel: None,
+            keep_decorations: false,
+            custom_rects: Vec::new(),
+            laid_out: Mutex::new(Vec::new()),
+            tab_cycler: None,
+            on_layout_failure: LayoutFailurePolicy::default(),
+            label_windows: false,
+            labels: Mutex::new(Vec::new()),
+            restack_windows: false,
+            window_opacity: Vec::new(),
+            dim_unfocused: None,
+            preferred_match: None,
+            last_match: Mutex::new(None),
+        }
+    }
+
+    /// Changes how long and how often lookups wait for windows to appear.
+    pub fn set_search_timing(&mut self, search_timing: WindowSearchTiming) {
+        self.search_timing = search_timing;
+    }
+
+    /// How long and how often lookups wait for windows to appear.
+    pub fn search_timing(&self) -> WindowSearchTiming {
+        self.search_timing
+    }
+
+    /// Makes window searches give up with `Cancelled` as soon as `cancel` is set.
+    pub fn set_cancel_flag(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Letterboxes windows instead of stretching them over their layout cell:
+    /// slot `i` gets the largest centred rectangle with width/height ratio
+    /// `ratios[i]`, or the default 16:9 past the end of `ratios`. `None`
+    /// restores filling the cell.
+    pub fn set_aspect_ratios(&mut self, ratios: Option<Vec<f64>>) {
+        self.aspect_ratios = ratios;
+    }
+
+    /// Makes `set_layout` keep every window it arranges above other windows,
+    /// so clicking the desktop or a notification can't bury a game.
+    pub fn set_keep_above(&mut self, keep_above: bool) {
+        self.keep_above = keep_above;
+    }
+
+    /// Makes window searches try `strategy` before the others, e.g. the one
+    /// that found a game's windows last time; the others still follow when it
+    /// finds nothing. `None` restores the default order: PID, title, tree.
+    pub fn set_preferred_match_strategy(&mut self, strategy: Option<WindowMatchStrategy>) {
+        self.preferred_match = strategy;
+    }
+
+    /// The strategy that matched most windows in the last search that found
+    /// every window, for [`set_preferred_match_strategy`](Self::set_preferred_match_strategy)
+    /// next time.
+    pub fn match_strategy(&self) -> Option<WindowMatchStrategy> {
+        *self.last_match.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The strategies in the order searches try them
+    fn match_order(&self) -> Vec<WindowMatchStrategy> {
+        let mut order: Vec<WindowMatchStrategy> =
+            DEFAULT_MATCH_ORDER.into_iter().filter(|&strategy| Some(strategy) != self.preferred_match).collect();
+        if let Some(preferred) = self.preferred_match {
+            order.insert(0, preferred);
+        }
+        order
+    }
+
+    /// Sets what `set_layout` does when some windows can't be found in time.
+    pub fn set_layout_failure_policy(&mut self, policy: LayoutFailurePolicy) {
+        self.on_layout_failure = policy;
+    }
+
+    /// Makes `set_layout` and `place_window` append `[Player N]` to the title
+    /// of the window in slot N, so players can tell whose window is whose.
+    /// Games that reset their title get the label back from
+    /// [`reapply_window_labels`](Self::reapply_window_labels).
+    pub fn set_label_windows(&mut self, label_windows: bool) {
+        self.label_windows = label_windows;
+    }
+
+    /// Makes `set_layout` stack the windows it places in player order, with
+    /// Player 1 on top, so cycling windows with the keyboard goes 1, 2, 3, ...
+    pub fn set_restack_windows(&mut self, restack_windows: bool) {
+        self.restack_windows = restack_windows;
+    }
+
+    /// Makes `set_layout` and `place_window` give the window in slot `i`
+    /// opacity `opacity[i]`, from 0.0 (invisible) to 1.0. Slots past the end
+    /// are left alone. Only has a visible effect with a compositor running.
+    pub fn set_window_opacities(&mut self, opacity: Vec<f64>) {
+        self.window_opacity = opacity;
+    }
+
+    /// Makes the tabbed layout dim every window but the one in front to
+    /// `opacity`; the front window is fully opaque. `None` leaves them alone.
+    pub fn set_dim_unfocused(&mut self, opacity: Option<f64>) {
+        self.dim_unfocused = opacity;
+    }
+
+    /// Stops layouts from removing window decorations, for compositors that
+    /// ignore the Motif hint or leave undecorated windows unusable. Windows
+    /// are then shrunk by their frame size so the frames tile without overlap.
+    pub fn set_keep_decorations(&mut self, keep_decorations: bool) {
+        self.keep_decorations = keep_decorations;
+    }
+
+    /// Sets where each window goes in `Layout::Custom`: slot `i` gets
+    /// `rects[i]`. Slots without a rectangle fall back to the horizontal layout.
+    pub fn set_custom_rects(&mut self, rects: Vec<WindowRect>) {
+        self.custom_rects = rects;
+    }
+
+    /// Frame size (left, right, top, bottom) the window manager adds around
+    /// `window`, from `_NET_FRAME_EXTENTS`. Window managers that don't set it
+    /// are assumed to add a plain titlebar.
+    fn frame_extents(&self, window: xproto::Window) -> Result<(u32, u32, u32, u32), WindowManagerError> {
+        match self.reported_frame_extents(window)? {
+            Some(extents) => Ok(extents),
+            None => {
+                debug!("Window {} has no _NET_FRAME_EXTENTS; assuming a {}px titlebar", window, crate::defaults::TITLEBAR_HEIGHT);
+                Ok((0, 0, crate::defaults::TITLEBAR_HEIGHT, 0))
+            }
+        }
+    }
+
+    /// `window`'s `_NET_FRAME_EXTENTS` (left, right, top, bottom), or `None`
+    /// when the window manager doesn't set it.
+    fn reported_frame_extents(&self, window: xproto::Window) -> Result<Option<(u32, u32, u32, u32)>, WindowManagerError> {
+        let atom = self.conn.intern_atom(b"_NET_FRAME_EXTENTS")?;
+        let value = self.conn.get_property(window, atom, AtomEnum::CARDINAL, 4)?;
+        if value.len() < 16 {
+            return Ok(None);
+        }
+        let extent = |i: usize| u32::from_ne_bytes([value[i * 4], value[i * 4 + 1], value[i * 4 + 2], value[i * 4 + 3]]);
+        Ok(Some((extent(0), extent(1), extent(2), extent(3))))
+    }
+
+    /// Asks the window manager to drop the decorations of `windows`. Windows
+    /// that had a frame get `DECORATION_SETTLE_TIME` for it to go away, so
+    /// the frame reported afterwards is the one the window manager keeps.
+    fn undecorate(&self, windows: &[xproto::Window]) -> Result<(), WindowManagerError> {
+        let mut framed = false;
+        for &window in windows {
+            framed |= self.reported_frame_extents(window)?.is_some_and(|(left, right, top, bottom)| left + right + top + bottom > 0);
+            self.remove_decorations(window)?;
+        }
+        if framed {
+            self.conn.flush()?;
+            thread::sleep(DECORATION_SETTLE_TIME);
+        }
+        Ok(())
+    }
+
+    /// Finds windows by their _NET_WM_PID property.
     /// This is generally more reliable than finding by title.
-    /// Returns Ok(Some(window)) if found, Ok(None) if not found, and Err on X11 error.
-    pub fn find_window_by_pid(&self, pid: u32) -> Result<Option<xproto::Window>, WindowManagerError> {
+    /// Returns all top-level windows owned by `pid`, in the order of
+    /// [`top_level_windows`](Self::top_level_windows); Wine can map several
+    /// game windows to one PID, so there may be more than one.
+    pub fn find_windows_by_pid(&self, pid: u32) -> Result<Vec<xproto::Window>, WindowManagerError> {
         debug!("Attempting to find window with PID: {}", pid);
-        let setup = self.conn.setup();
-        let screen = &setup.roots[0];
+        let matches = self.windows_by_pid(self.top_level_windows()?, &mut HashMap::new())?.remove(&pid).unwrap_or_default();
+        if matches.is_empty() {
+            debug!("No window found with PID: {}", pid);
+        } else {
+            info!("Matched windows {:?} with target PID {}", matches, pid);
+        }
+        Ok(matches)
+    }
 
-        let pid_atom_request = self.conn.intern_atom(false, b"_NET_WM_PID");
-        let windows_request = self.conn.query_tree(screen.root);
+    /// `windows` grouped by their _NET_WM_PID. `known_pids` caches the PID
+    /// of every window already probed, or `None` for windows without one, so
+    /// repeated calls during one search only ask the X server about windows
+    /// that are new since. Windows without a PID may still get one; a
+    /// search forgets them before each pass.
+    ///
+    /// A game in its own PID namespace (under Firejail, say) puts its PID in
+    /// that namespace in _NET_WM_PID, so the PID the X server knows the
+    /// window's client by is used instead where there is one.
+    fn windows_by_pid(
+        &self,
+        windows: Vec<xproto::Window>,
+        known_pids: &mut HashMap<xproto::Window, Option<u32>>,
+    ) -> Result<HashMap<u32, Vec<xproto::Window>>, WindowManagerError> {
+        let pid_atom = self.conn.intern_atom(b"_NET_WM_PID")?;
+        let mut by_pid: HashMap<u32, Vec<xproto::Window>> = HashMap::new();
+        for window in windows {
+            let window_pid = match known_pids.get(&window) {
+                Some(&Some(pid)) => pid,
+                Some(None) => continue,
+                None => {
+                    let Some(pid_prop_value) = self.scanned_property(window, pid_atom, AtomEnum::CARDINAL, 1)? else {
+                        known_pids.insert(window, None);
+                        continue;
+                    };
+                    let Ok(bytes) = <[u8; 4]>::try_from(pid_prop_value.as_slice()) else {
+                        if !pid_prop_value.is_empty() {
+                            debug!("Window {} has _NET_WM_PID property with unexpected size: {}", window, pid_prop_value.len());
+                        }
+                        known_pids.insert(window, None);
+                        continue;
+                    };
+                    let pid = match self.conn.client_pid(window)? {
+                        Some(client_pid) => client_pid,
+                        None => u32::from_ne_bytes(bytes),
+                    };
+                    debug!("Found window {} with PID {}", window, pid);
+                    known_pids.insert(window, Some(pid));
+                    pid
+                }
+            };
+            by_pid.entry(window_pid).or_default().push(window);
+        }
+        Ok(by_pid)
+    }
 
-        let pid_atom = pid_atom_request?.reply()?.atom;
-        let windows = windows_request?.reply()?.children;
+    /// The windows to search for game windows: the window manager's managed
+    /// clients from `_NET_CLIENT_LIST`, or every child of the root window when
+    /// no EWMH window manager publishes that list.
+    fn top_level_windows(&self) -> Result<Vec<xproto::Window>, WindowManagerError> {
+        let client_list = self.conn.intern_atom(b"_NET_CLIENT_LIST")?;
+        let value = self.conn.get_property(self.conn.root_window(), client_list, AtomEnum::WINDOW, CLIENT_LIST_MAX_WINDOWS)?;
+        if value.is_empty() || value.len() % 4 != 0 {
+            return self.conn.query_tree(self.conn.root_window());
+        }
+        Ok(value.chunks_exact(4).map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])).collect())
+    }
 
-        for window in windows {
-            let pid_prop_reply = self.conn.get_property(false, window, pid_atom, AtomEnum::CARDINAL, 0, 1)?.reply()?;
-            let pid_prop_value = &pid_prop_reply.value;
-            if !pid_prop_value.is_empty() {
-                if pid_prop_value.len() == 4 {
-                    let window_pid = u32::from_ne_bytes([
-                        pid_prop_value[0],
-                        pid_prop_value[1],
-                        pid_prop_value[2],
-                        pid_prop_value[3],
-                    ]);
-                    debug!("Found window {} with PID {}", window, window_pid);
-                    if window_pid == pid {
-                        info!("Matched window {} with target PID {}", window, pid);
-                        return Ok(Some(window));
-                    }
-                } else {
-                    debug!("Window {} has _NET_WM_PID property with unexpected size: {}", window, pid_prop_value.len());
+    /// The windows up to `TREE_SEARCH_DEPTH` levels below `top_level`,
+    /// parents before their children. Windows destroyed meanwhile are skipped.
+    fn descendant_windows(&self, top_level: &[xproto::Window]) -> Result<Vec<xproto::Window>, WindowManagerError> {
+        let mut descendants = Vec::new();
+        let mut level = top_level.to_vec();
+        for _ in 0..TREE_SEARCH_DEPTH {
+            let mut children = Vec::new();
+            for window in level {
+                match self.conn.query_tree(window) {
+                    Ok(found) => children.extend(found),
+                    Err(e) if e.is_window_gone() => continue,
+                    Err(e) => return Err(e),
                 }
             }
+            if children.is_empty() {
+                break;
+            }
+            descendants.extend_from_slice(&children);
+            level = children;
         }
+        Ok(descendants)
+    }
+
+    /// Finds a top-level window whose title contains `marker`, skipping `claimed` windows.
+    /// Reads _NET_WM_NAME, falling back to the legacy WM_NAME.
+    fn find_window_by_title(&self, marker: &str, claimed: &HashSet<xproto::Window>) -> Result<Option<xproto::Window>, WindowManagerError> {
+        debug!("Attempting to find window with title containing: {}", marker);
+        let net_wm_name = self.conn.intern_atom(b"_NET_WM_NAME")?;
+        let windows = self.top_level_windows()?;
 
-        debug!("No window found with PID: {}", pid);
+        for window in windows {
+            if claimed.contains(&window) {
+                continue;
+            }
+            let Some(title) = self.window_title(window, net_wm_name)? else {
+                continue;
+            };
+            if title.contains(marker) {
+                info!("Matched window {} by title marker '{}'", window, marker);
+                return Ok(Some(window));
+            }
+        }
         Ok(None)
     }
 
+    /// `window`'s title from `net_wm_name` (the interned _NET_WM_NAME),
+    /// falling back to the legacy WM_NAME. `None` if the window is gone, see
+    /// [`scanned_property`](Self::scanned_property).
+    fn window_title(&self, window: xproto::Window, net_wm_name: xproto::Atom) -> Result<Option<String>, WindowManagerError> {
+        let Some(mut title) = self.scanned_property(window, net_wm_name, AtomEnum::ANY, 256)? else {
+            return Ok(None);
+        };
+        if title.is_empty() {
+            let Some(wm_name) = self.scanned_property(window, AtomEnum::WM_NAME.into(), AtomEnum::ANY, 256)? else {
+                return Ok(None);
+            };
+            title = wm_name;
+        }
+        Ok(Some(String::from_utf8_lossy(&title).into_owned()))
+    }
+
+    /// Sets `window`'s title: the UTF-8 _NET_WM_NAME most window managers
+    /// show, and the legacy WM_NAME for the rest. WM_NAME is a Latin-1
+    /// STRING, so characters outside Latin-1 show up as `?` there.
+    pub fn set_window_name(&self, window: xproto::Window, name: &str) -> Result<(), WindowManagerError> {
+        let net_wm_name = self.conn.intern_atom(b"_NET_WM_NAME")?;
+        let utf8_string = self.conn.intern_atom(b"UTF8_STRING")?;
+        let data = name.as_bytes();
+        self.conn.change_property(window, net_wm_name, utf8_string, 8, data.len() as u32, data)?;
+        let legacy = latin1_lossy(name);
+        self.conn.change_property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into(), 8, legacy.len() as u32, &legacy)
+    }
+
+    /// Sets `_NET_WM_WINDOW_OPACITY` on `window`: 0.0 is invisible, 1.0
+    /// opaque. Compositors read the property; without one it does nothing.
+    pub fn set_window_opacity(&self, window: xproto::Window, opacity: f64) -> Result<(), WindowManagerError> {
+        let value = (opacity.clamp(0.0, 1.0) * f64::from(u32::MAX)).round() as u32;
+        debug!("Setting opacity of window {} to {:.2}", window, opacity);
+        let net_wm_window_opacity = self.conn.intern_atom(b"_NET_WM_WINDOW_OPACITY")?;
+        self.conn.change_property(window, net_wm_window_opacity, AtomEnum::CARDINAL.into(), 32, 1, &value.to_ne_bytes())
+    }
+
+    /// Whether a compositing manager is running, i.e. whether window opacity
+    /// will show. Compositors own the `_NET_WM_CM_S0` selection.
+    pub fn compositor_running(&self) -> Result<bool, WindowManagerError> {
+        let selection = self.conn.intern_atom(b"_NET_WM_CM_S0")?;
+        Ok(self.conn.selection_owner(selection)? != x11rb::NONE)
+    }
+
+    /// Gives the window in slot `window_index` its player label and remembers
+    /// it for [`reapply_window_labels`](Self::reapply_window_labels).
+    fn label_window(&self, window: xproto::Window, window_index: usize) -> Result<(), WindowManagerError> {
+        let label = format!("[Player {}]", window_index + 1);
+        let net_wm_name = self.conn.intern_atom(b"_NET_WM_NAME")?;
+        let title = self.window_title(window, net_wm_name)?.unwrap_or_default();
+        if !title.ends_with(&label) {
+            self.set_window_name(window, &labelled(&title, &label))?;
+        }
+        let mut labels = self.labels.lock().unwrap();
+        labels.retain(|&(labelled_window, _)| labelled_window != window);
+        labels.push((window, label));
+        Ok(())
+    }
+
+    /// Puts the player labels back on windows whose game has changed its
+    /// title since, and forgets windows that were closed. Meant to be called
+    /// periodically while the session runs.
+    pub fn reapply_window_labels(&self) -> Result<(), WindowManagerError> {
+        let labels = self.labels.lock().unwrap().clone();
+        if labels.is_empty() {
+            return Ok(());
+        }
+        let net_wm_name = self.conn.intern_atom(b"_NET_WM_NAME")?;
+        let mut gone = Vec::new();
+        for (window, label) in labels {
+            let Some(title) = self.window_title(window, net_wm_name)? else {
+                gone.push(window);
+                continue;
+            };
+            if title.ends_with(&label) {
+                continue;
+            }
+            debug!("Window {} was retitled '{}'; adding '{}' back", window, title, label);
+            match self.set_window_name(window, &labelled(&title, &label)) {
+                Ok(()) => {}
+                Err(e) if e.is_window_gone() => gone.push(window),
+                Err(e) => return Err(e),
+            }
+        }
+        if !gone.is_empty() {
+            self.labels.lock().unwrap().retain(|(window, _)| !gone.contains(window));
+        }
+        self.conn.flush()
+    }
+
+    /// Reads a property of a window found by `query_tree`, or `None` if the
+    /// window was destroyed since; windows come and go during a scan, so that
+    /// only skips the window. Connection errors still fail.
+    fn scanned_property(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: AtomEnum,
+        long_length: u32,
+    ) -> Result<Option<Vec<u8>>, WindowManagerError> {
+        match self.conn.get_property(window, property, type_, long_length) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.is_window_gone() => {
+                debug!("Window {} disappeared while scanning windows ({}); skipping it", window, e);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn resize_window(&self, window: xproto::Window, width: u32, height: u32) -> Result<(), WindowManagerError> {
         info!("Resizing window {} to {}x{}", window, width, height);
         let aux = ConfigureWindowAux::new().width(width).height(height);
-        self.conn.configure_window(window, &aux)?.check()?;
-        Ok(())
+        self.conn.configure_window(window, &aux)
     }
 
     pub fn move_window(&self, window: xproto::Window, x: i32, y: i32) -> Result<(), WindowManagerError> {
         info!("Moving window {} to ({}, {})", window, x, y);
         let aux = ConfigureWindowAux::new().x(x).y(y);
-        self.conn.configure_window(window, &aux)?.check()?;
-        Ok(())
+        self.conn.configure_window(window, &aux)
     }
 
     /// Attempts to remove window decorations using _MOTIF_WM_HINTS.
@@ -137,7 +762,7 @@ impl WindowManager {
     /// or influencing the window type, or potentially sending client messages.
     pub fn remove_decorations(&self, window: xproto::Window) -> Result<(), WindowManagerError> {
         info!("Attempting to remove decorations from window {}", window);
-        let atom = self.conn.intern_atom(false, b"_MOTIF_WM_HINTS")?.reply()?.atom;
+        let atom = self.conn.intern_atom(b"_MOTIF_WM_HINTS")?;
 
         // _MOTIF_WM_HINTS layout: flags, functions, decorations, input_mode, status (5 x u32).
         const MWM_HINTS_DECORATIONS: u32 = 1 << 1;
@@ -145,19 +770,117 @@ impl WindowManager {
         let data_bytes: Vec<u8> = data.iter().flat_map(|&v| v.to_ne_bytes()).collect();
 
         self.conn.change_property(
-            PropMode::REPLACE,
             window,
             atom,
-            AtomEnum::CARDINAL,
+            AtomEnum::CARDINAL.into(),
             32,
             data.len() as u32,
             &data_bytes,
-        )?.check()?;
+        )?;
         info!("Sent request to remove decorations for window {}", window);
         Ok(())
     }
 
 
+    /// Asks the window manager to add or remove `_NET_WM_STATE_ABOVE` on `window`.
+    pub fn set_always_on_top(&self, window: xproto::Window, above: bool) -> Result<(), WindowManagerError> {
+        // _NET_WM_STATE client message: action, first property, second property, source indication
+        const NET_WM_STATE_REMOVE: u32 = 0;
+        const NET_WM_STATE_ADD: u32 = 1;
+        const SOURCE_APPLICATION: u32 = 1;
+
+        info!("{} always-on-top for window {}", if above { "Enabling" } else { "Disabling" }, window);
+        let state = self.conn.intern_atom(b"_NET_WM_STATE")?;
+        let state_above = self.conn.intern_atom(b"_NET_WM_STATE_ABOVE")?;
+        let action = if above { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+        self.conn.send_client_message(window, state, [action, state_above, 0, SOURCE_APPLICATION, 0])?;
+
+        let mut above_windows = self.above_windows.lock().unwrap();
+        above_windows.retain(|&w| w != window);
+        if above {
+            above_windows.push(window);
+        }
+        Ok(())
+    }
+
+    /// Raises `window`, keeps it above the others and gives it the focus, for
+    /// switching windows in a tabbed layout. `previous`, the window that was in
+    /// front before, stops being kept above.
+    pub fn bring_to_front(&self, window: xproto::Window, previous: Option<xproto::Window>) -> Result<(), WindowManagerError> {
+        // _NET_ACTIVE_WINDOW client message: source indication, timestamp, requestor's active window
+        const SOURCE_APPLICATION: u32 = 1;
+        const CURRENT_TIME: u32 = 0;
+
+        if let Some(previous) = previous.filter(|&previous| previous != window) {
+            if let Err(e) = self.set_always_on_top(previous, false) {
+                // The previous game may have exited since.
+                debug!("Could not lower window {}: {}", previous, e);
+            }
+            if let Some(dim) = self.dim_unfocused {
+                if let Err(e) = self.set_window_opacity(previous, dim) {
+                    debug!("Could not dim window {}: {}", previous, e);
+                }
+            }
+        }
+        if self.dim_unfocused.is_some() {
+            self.set_window_opacity(window, 1.0)?;
+        }
+        self.set_always_on_top(window, true)?;
+        self.conn.configure_window(window, &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE))?;
+        let active = self.conn.intern_atom(b"_NET_ACTIVE_WINDOW")?;
+        self.conn.send_client_message(window, active, [SOURCE_APPLICATION, CURRENT_TIME, 0, 0, 0])?;
+        self.conn.flush()
+    }
+
+    /// Stacks the windows of `pids_in_order` so the first PID's window ends up
+    /// on top and each following one right below the one before. PIDs without
+    /// a window are skipped; a PID listed twice gets its next window.
+    pub fn restack(&self, pids_in_order: &[u32]) -> Result<(), WindowManagerError> {
+        let mut windows: Vec<xproto::Window> = Vec::with_capacity(pids_in_order.len());
+        for &pid in pids_in_order {
+            let window = self.find_windows_by_pid(pid)?.into_iter().find(|window| !windows.contains(window));
+            match window {
+                Some(window) => windows.push(window),
+                None => debug!("No window to restack for PID {}", pid),
+            }
+        }
+        self.stack_in_order(&windows)?;
+        self.conn.flush()
+    }
+
+    /// Raises `windows` from last to first, leaving the first one on top.
+    fn stack_in_order(&self, windows: &[xproto::Window]) -> Result<(), WindowManagerError> {
+        for &window in windows.iter().rev() {
+            self.conn.configure_window(window, &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE))?;
+        }
+        Ok(())
+    }
+
+    /// Windows placed by the last [`set_layout`](Self::set_layout), in slot order.
+    pub fn laid_out_windows(&self) -> Vec<xproto::Window> {
+        self.laid_out.lock().unwrap().clone()
+    }
+
+    /// Starts switching between the windows of the last layout whenever
+    /// `hotkey` is pressed. Switching stops when this manager is dropped.
+    pub fn start_tab_cycle(&mut self, hotkey: &Hotkey) -> Result<(), WindowManagerError> {
+        self.tab_cycler = Some(TabCycler::start(self.laid_out_windows(), hotkey, self.dim_unfocused)?);
+        Ok(())
+    }
+
+    /// Removes always-on-top from every window this manager set it on. Call at
+    /// shutdown so windows that outlive the session don't stay stuck on top.
+    pub fn release_always_on_top(&self) -> Result<(), WindowManagerError> {
+        let windows: Vec<xproto::Window> = self.above_windows.lock().unwrap().clone();
+        for window in windows {
+            if let Err(e) = self.set_always_on_top(window, false) {
+                // The window may already be gone along with its game.
+                debug!("Could not release always-on-top for window {}: {}", window, e);
+            }
+        }
+        self.conn.flush()
+    }
+
      /// Sets the layout of the given windows on the screen(s).
      /// This function attempts to find the windows by their PIDs with retries
      /// and exponential backoff. Once found, it applies the specified layout.
@@ -175,143 +898,397 @@ impl WindowManager {
      ///
      /// # Returns
      ///
-     /// * `Result<(), WindowManagerError>` - Ok(()) on success, Err on failure to find
-     ///                                      windows or apply layout.
-     pub fn set_layout(&self, window_pids: &[u32], layout: Layout) -> Result<(), WindowManagerError> {
+     /// * `Result<Vec<u32>, WindowManagerError>` - the PIDs whose windows weren't found,
+     ///   which the layout failure policy let the layout go ahead without; Err on
+     ///   failure to find windows or apply layout.
+     pub fn set_layout(&self, window_pids: &[u32], layout: Layout) -> Result<Vec<u32>, WindowManagerError> {
+         self.set_layout_with_titles(window_pids, &[], layout)
+     }
+
+     /// Like [`set_layout`](Self::set_layout), but falls back to window titles for
+     /// instances whose PID can't identify their window: Wine/Proton games may
+     /// share one PID across instances or report none. `titles[i]`, if present,
+     /// is a marker that only instance `i`'s window title contains.
+     pub fn set_layout_with_titles(&self, window_pids: &[u32], titles: &[String], layout: Layout) -> Result<Vec<u32>, WindowManagerError> {
          info!("Starting to set layout {:?} for windows with PIDs: {:?}", layout, window_pids);
 
          if window_pids.is_empty() {
              warn!("No window PIDs provided for layout.");
-             return Ok(()); // Nothing to do if no PIDs are given
+             return Ok(Vec::new()); // Nothing to do if no PIDs are given
          }
 
-         let monitors = self.get_monitors()?;
+         let monitors = self.monitors()?;
 
          if monitors.is_empty() {
              error!("No monitors detected. Cannot set window layout.");
               return Err(WindowManagerError::MonitorDetectionError("No monitors found".to_string()));
          }
 
-         let mut found_windows: HashMap<u32, xproto::Window> = HashMap::new();
-         let mut unfound_pids: HashSet<u32> = window_pids.iter().cloned().collect();
-
-         let start_time = Instant::now();
-         let max_wait_duration = Duration::from_secs(30); // Maximum time to wait for windows (e.g., 30 seconds)
-         let mut current_delay = Duration::from_millis(50); // Initial delay for exponential backoff
-         let max_delay = Duration::from_millis(500); // Maximum delay between retries
+         let slots = self.search_windows(window_pids, titles)?;
+         let unplaced: Vec<u32> = window_pids
+             .iter()
+             .zip(&slots)
+             .filter(|(_, window)| window.is_none())
+             .map(|(&pid, _)| pid)
+             .collect();
+         if !unplaced.is_empty() {
+             match self.on_layout_failure {
+                 LayoutFailurePolicy::Abort => return Err(WindowManagerError::WindowNotFound(unplaced)),
+                 LayoutFailurePolicy::LayoutFound => {
+                     warn!("No window found for PIDs {:?}; laying out the other windows without them.", unplaced);
+                 }
+                 LayoutFailurePolicy::Ignore => {
+                     warn!("No window found for PIDs {:?}; leaving every window where it is.", unplaced);
+                     return Ok(unplaced);
+                 }
+             }
+         } else {
+             info!("All required windows found. Proceeding with layout application.");
+         }
 
-         info!("Attempting to find {} windows with a maximum wait of {:?}.", window_pids.len(), max_wait_duration);
+         if !self.keep_decorations {
+             self.undecorate(&slots.iter().flatten().copied().collect::<Vec<_>>())?;
+         }
 
-         // Main loop to find windows with exponential backoff
-         while !unfound_pids.is_empty() && start_time.elapsed() < max_wait_duration {
-             debug!("Searching for {} unfound windows...", unfound_pids.len());
-             let mut found_in_this_pass = Vec::new(); // PIDs found in the current iteration
-
-             // Iterate over a drained list to avoid modifying the set while iterating
-             for pid in unfound_pids.drain().collect::<Vec<_>>() {
-                 match self.find_window_by_pid(pid) {
-                     Ok(Some(window_id)) => {
-                         info!("Successfully found window {} for PID {}", window_id, pid);
-                         found_windows.insert(pid, window_id);
-                         found_in_this_pass.push(pid);
-                     }
-                     Ok(None) => {
-                         debug!("Window for PID {} not found in this pass.", pid);
-                         // Re-insert into unfound_pids for the next iteration
-                         unfound_pids.insert(pid);
-                     }
-                     Err(e) => {
-                         error!("Error while searching for window for PID {}: {}", pid, e);
-                         // Decide how to handle this error during the search.
-                         // For now, let's propagate it.
-                         return Err(e);
-                     }
+         // Windows keep their own slot, so a missing window leaves a gap
+         // rather than shifting the players after it.
+         let num_windows = slots.len();
+         for (window_index, window_id) in slots.iter().enumerate() {
+             let Some(window_id) = *window_id else { continue };
+             self.apply_slot(window_id, window_pids[window_index], &monitors, &layout, window_index, num_windows)?;
+             // Tabbed windows overlap, so only the one in front is kept above
+             if self.keep_above && layout != Layout::Tabbed {
+                 self.set_always_on_top(window_id, true)?;
+             }
+         }
+         let found_windows: Vec<xproto::Window> = slots.into_iter().flatten().collect();
+         if self.restack_windows {
+             self.stack_in_order(&found_windows)?;
+         }
+         if layout == Layout::Tabbed {
+             if let Some(dim) = self.dim_unfocused {
+                 for &window in found_windows.iter().skip(1) {
+                     self.set_window_opacity(window, dim)?;
                  }
              }
+             if let Some(&front) = found_windows.first() {
+                 self.bring_to_front(front, None)?;
+             }
+         }
+         *self.laid_out.lock().unwrap() = found_windows;
 
-             if !unfound_pids.is_empty() {
-                 info!("{} windows still unfound. Waiting {:?} before retrying...", unfound_pids.len(), current_delay);
-                 thread::sleep(current_delay);
-                 current_delay = std::cmp::min(current_delay * 2, max_delay); // Exponential backoff
-             } else {
-                 info!("All windows found.");
+         self.conn.flush()?; // Ensure all requests are sent after all operations
+         info!("Window layout set successfully.");
+         Ok(unplaced)
+     }
+
+     /// Places a single window into slot `window_index` of a layout holding
+     /// `num_windows` windows, keeping it above the others like
+     /// [`set_layout`](Self::set_layout) does. Used to put a relaunched
+     /// instance back where it was.
+     pub fn place_window(&self, pid: u32, window_index: usize, num_windows: usize, layout: Layout) -> Result<(), WindowManagerError> {
+         info!("Placing window for PID {} into slot {} of {} ({:?})", pid, window_index, num_windows, layout);
+
+         let monitors = self.monitors()?;
+         if monitors.is_empty() {
+             error!("No monitors detected. Cannot place window.");
+             return Err(WindowManagerError::MonitorDetectionError("No monitors found".to_string()));
+         }
+
+         let found_windows = self.wait_for_windows(&[pid], &[])?;
+         if !self.keep_decorations {
+             self.undecorate(&found_windows[..1])?;
+         }
+         self.apply_slot(found_windows[0], pid, &monitors, &layout, window_index, num_windows)?;
+         if self.keep_above && layout != Layout::Tabbed {
+             self.set_always_on_top(found_windows[0], true)?;
+         }
+
+         self.conn.flush()?;
+         Ok(())
+     }
+
+     /// Size (width, height) each of `num_windows` windows will get in
+     /// `layout`, so games can be started at that resolution instead of being
+     /// resized after they open. Frames of decorated windows are assumed to
+     /// be a plain titlebar, since the windows don't exist yet.
+     pub fn slot_sizes(&self, layout: Layout, num_windows: usize) -> Result<Vec<(u32, u32)>, WindowManagerError> {
+         let monitors = self.monitors()?;
+         if monitors.is_empty() {
+             return Err(WindowManagerError::MonitorDetectionError("No monitors found".to_string()));
+         }
+         Ok(self
+             .layout_slots(&monitors, layout, num_windows)
+             .into_iter()
+             .map(|(_, rect)| {
+                 let height = if self.keep_decorations {
+                     rect.height.saturating_sub(crate::defaults::TITLEBAR_HEIGHT).max(1)
+                 } else {
+                     rect.height
+                 };
+                 (rect.width, height)
+             })
+             .collect())
+     }
+
+     /// Where each of `num_windows` windows goes in `layout` on `monitors`,
+     /// as (monitor index, rectangle) with aspect ratios applied; frames
+     /// aren't taken off. Empty without monitors. For previewing a layout
+     /// without any windows.
+     pub fn layout_slots(&self, monitors: &[Monitor], layout: Layout, num_windows: usize) -> Vec<(usize, WindowRect)> {
+         if monitors.is_empty() {
+             return Vec::new();
+         }
+         (0..num_windows)
+             .map(|window_index| {
+                 let (monitor_index, mut x, mut y, mut width, mut height) =
+                     slot_geometry(monitors, &layout, &self.custom_rects, window_index, num_windows);
+                 if let Some(ratios) = &self.aspect_ratios {
+                     let ratio = ratios.get(window_index).copied().unwrap_or(crate::defaults::ASPECT_RATIO);
+                     (x, y, width, height) = letterbox(x, y, width, height, ratio);
+                 }
+                 (monitor_index, WindowRect { x, y, width, height })
+             })
+             .collect()
+     }
+
+     /// Moves and resizes one window according to its layout slot. With
+     /// decorations kept, or when the window manager keeps a frame around an
+     /// undecorated window, the frame's corner goes at the slot's corner and
+     /// the window is made smaller by the frame size.
+     fn apply_slot(
+         &self,
+         window_id: xproto::Window,
+         pid: u32,
+         monitors: &[Monitor],
+         layout: &Layout,
+         window_index: usize,
+         num_windows: usize,
+     ) -> Result<(), WindowManagerError> {
+         let (monitor_index, mut x, mut y, mut width, mut height) = slot_geometry(monitors, layout, &self.custom_rects, window_index, num_windows);
+         if let Some(ratios) = &self.aspect_ratios {
+             let ratio = ratios.get(window_index).copied().unwrap_or(crate::defaults::ASPECT_RATIO);
+             (x, y, width, height) = letterbox(x, y, width, height, ratio);
+             info!("Letterboxed slot {} to aspect ratio {:.3}: {}x{} at ({}, {})", window_index, ratio, width, height, x, y);
+         }
+
+         let frame = if self.keep_decorations {
+             Some(self.frame_extents(window_id)?)
+         } else {
+             // Window managers that ignore the Motif hint still report their frame
+             let kept = self.reported_frame_extents(window_id)?.filter(|&(left, right, top, bottom)| left + right + top + bottom > 0);
+             if let Some((left, right, top, bottom)) = kept {
+                 info!("Window {} kept its frame ({}, {}, {}, {}); fitting the frame into the slot", window_id, left, right, top, bottom);
              }
+             kept
+         };
+         if let Some((left, right, top, bottom)) = frame {
+             width = width.saturating_sub(left + right).max(1);
+             height = height.saturating_sub(top + bottom).max(1);
+         }
+
+         info!("Applying layout for window {} (PID {}): monitor index {}, x={}, y={}, width={}, height={}", window_id, pid, monitor_index, x, y, width, height);
+
+         self.move_window(window_id, x, y)?;
+         self.resize_window(window_id, width, height)?;
+         if self.label_windows {
+             self.label_window(window_id, window_index)?;
+         }
+         if let Some(&opacity) = self.window_opacity.get(window_index) {
+             self.set_window_opacity(window_id, opacity)?;
          }
+         Ok(())
+     }
 
-         // After the waiting loop, check if all windows were found
+     /// Like [`search_windows`](Self::search_windows), but fails with
+     /// `WindowNotFound` unless every instance has a window.
+     fn wait_for_windows(&self, window_pids: &[u32], titles: &[String]) -> Result<Vec<xproto::Window>, WindowManagerError> {
+         let found = self.search_windows(window_pids, titles)?;
+         let unfound_pids: Vec<u32> = window_pids
+             .iter()
+             .zip(&found)
+             .filter(|(_, window)| window.is_none())
+             .map(|(&pid, _)| pid)
+             .collect();
          if !unfound_pids.is_empty() {
-             error!("Failed to find all windows after waiting {:?}. Unfound PIDs: {:?}", start_time.elapsed(), unfound_pids);
-             return Err(WindowManagerError::WindowNotFound(unfound_pids.into_iter().collect()));
+             return Err(WindowManagerError::WindowNotFound(unfound_pids));
          }
+         Ok(found.into_iter().flatten().collect())
+     }
 
-         info!("All required windows found. Proceeding with layout application.");
+     /// Polls with exponential backoff until every instance has a window or
+     /// the timeout passes, and returns the windows in the order of
+     /// `window_pids`, with `None` for instances still without one.
+     ///
+     /// Each instance's window is looked for with the strategies of
+     /// [`WindowMatchStrategy`] in turn, the preferred one first, until one
+     /// finds it. A PID that is 0 or shared with another instance isn't used
+     /// when a title marker was given for the instance. The window tree is
+     /// only walked once PID and title have had `TREE_FALLBACK_DELAY`, unless
+     /// `Tree` is preferred. A window is never given to two instances. Fails
+     /// with `Cancelled` when the cancel flag is set.
+     fn search_windows(&self, window_pids: &[u32], titles: &[String]) -> Result<Vec<Option<xproto::Window>>, WindowManagerError> {
+         let mut found: Vec<Option<xproto::Window>> = vec![None; window_pids.len()];
+         let mut matched_by: Vec<WindowMatchStrategy> = Vec::new();
+         let mut claimed: HashSet<xproto::Window> = HashSet::new();
+         let mut known_pids: HashMap<xproto::Window, Option<u32>> = HashMap::new();
+         let order = self.match_order();
 
-         // Now apply the layout using the found window IDs.
-         // Ensure the order matches the original window_pids slice.
-         let ordered_windows: Vec<(u32, xproto::Window)> = window_pids.iter()
-             .filter_map(|&pid| found_windows.get(&pid).map(|&window| (pid, window)))
-             .collect();
+         let ambiguous = |index: usize| {
+             let pid = window_pids[index];
+             pid == 0 || window_pids.iter().filter(|&&p| p == pid).count() > 1
+         };
 
-         let num_windows = ordered_windows.len();
-         let num_monitors = monitors.len();
-
-         // Round-robin windows across monitors, then tile within each monitor.
-         for (window_index, (pid, window_id)) in ordered_windows.iter().enumerate() {
-             let monitor_index = window_index % num_monitors;
-             let monitor = &monitors[monitor_index];
-             // index_on_monitor: 0-based slot for this window within its assigned monitor.
-             let index_on_monitor = window_index / num_monitors;
-             // Total windows assigned to this monitor under round-robin distribution.
-             let windows_on_monitor = (num_windows + num_monitors - 1 - monitor_index) / num_monitors;
-
-             let (x, y, width, height): (i32, i32, u32, u32) = match &layout {
-                 Layout::Horizontal => {
-                     let single_width = monitor.width / windows_on_monitor.max(1) as i32;
-                     let x_offset = index_on_monitor as i32 * single_width;
-                     (monitor.x + x_offset, monitor.y, single_width as u32, monitor.height as u32)
-                 }
-                 Layout::Vertical => {
-                     let single_height = monitor.height / windows_on_monitor.max(1) as i32;
-                     let y_offset = index_on_monitor as i32 * single_height;
-                     (monitor.x, monitor.y + y_offset, monitor.width as u32, single_height as u32)
+         let start_time = Instant::now();
+         let max_wait_duration = self.search_timing.timeout;
+         // Walking the tree is costly, so it waits until PID and title have
+         // had a chance, unless it is known to be what finds this game's windows
+         let tree_delay = if order.first() == Some(&WindowMatchStrategy::Tree) {
+             Duration::ZERO
+         } else {
+             TREE_FALLBACK_DELAY.min(max_wait_duration / 4)
+         };
+         let mut current_delay = self.search_timing.initial_backoff;
+         let max_delay = self.search_timing.max_backoff;
+
+         info!("Attempting to find {} windows with a maximum wait of {:?}.", window_pids.len(), max_wait_duration);
+
+         // Main loop to find windows with exponential backoff
+         loop {
+             // One scan of the windows per pass serves every PID
+             let mut top_level: Option<Vec<xproto::Window>> = None;
+             let mut by_pid: Option<HashMap<u32, Vec<xproto::Window>>> = None;
+             let mut tree_by_pid: Option<HashMap<u32, Vec<xproto::Window>>> = None;
+             let tree_ready = start_time.elapsed() >= tree_delay;
+             known_pids.retain(|_, pid| pid.is_some());
+             for index in 0..window_pids.len() {
+                 if found[index].is_some() {
+                     continue;
                  }
-                 Layout::Grid2x2 => {
-                     let grid_x = window_index % 2;
-                     let grid_y = (window_index / 2) % 2;
-                     let cell_width = monitor.width / 2;
-                     let cell_height = monitor.height / 2;
-                     let x = monitor.x + (grid_x as i32 * cell_width);
-                     let y = monitor.y + (grid_y as i32 * cell_height);
-                     (x, y, cell_width as u32, cell_height as u32)
+                 let pid = window_pids[index];
+                 let marker = titles.get(index).filter(|marker| !marker.is_empty());
+                 let pid_usable = marker.is_none() || !ambiguous(index);
+
+                 let mut window = None;
+                 for &strategy in &order {
+                     let candidates = match strategy {
+                         WindowMatchStrategy::Title => {
+                             window = match marker {
+                                 Some(marker) => self.find_window_by_title(marker, &claimed)?,
+                                 None => None,
+                             };
+                             None
+                         }
+                         _ if !pid_usable => None,
+                         WindowMatchStrategy::Tree if !tree_ready => None,
+                         WindowMatchStrategy::Pid => Some(match &mut by_pid {
+                             Some(by_pid) => by_pid,
+                             None => {
+                                 let windows = match &top_level {
+                                     Some(windows) => windows.clone(),
+                                     None => top_level.insert(self.top_level_windows()?).clone(),
+                                 };
+                                 by_pid.insert(self.windows_by_pid(windows, &mut known_pids)?)
+                             }
+                         }),
+                         WindowMatchStrategy::Tree => Some(match &mut tree_by_pid {
+                             Some(tree_by_pid) => tree_by_pid,
+                             None => {
+                                 let windows = match &top_level {
+                                     Some(windows) => windows,
+                                     None => top_level.insert(self.top_level_windows()?),
+                                 };
+                                 let descendants = self.descendant_windows(windows)?;
+                                 tree_by_pid.insert(self.windows_by_pid(descendants, &mut known_pids)?)
+                             }
+                         }),
+                     };
+                     if let Some(candidates) = candidates {
+                         window = candidates.get(&pid).into_iter().flatten().copied().find(|window| !claimed.contains(window));
+                     }
+                     if window.is_some() {
+                         matched_by.push(strategy);
+                         break;
+                     }
                  }
-                 Layout::Grid3x1 => {
-                     let cell_width = monitor.width / 3;
-                     let x = monitor.x + ((window_index % 3) as i32 * cell_width);
-                     (x, monitor.y, cell_width as u32, monitor.height as u32)
+
+                 if let Some(window_id) = window {
+                     info!(
+                         "Successfully found window {} for instance {} (PID {}) by {:?}",
+                         window_id,
+                         index,
+                         pid,
+                         matched_by.last().copied().unwrap_or(WindowMatchStrategy::Pid)
+                     );
+                     claimed.insert(window_id);
+                     found[index] = Some(window_id);
+                 } else {
+                     debug!("Window for instance {} (PID {}) not found in this pass.", index, pid);
                  }
-             };
+             }
+
+             let unfound_pids: Vec<u32> = window_pids
+                 .iter()
+                 .zip(&found)
+                 .filter(|(_, window)| window.is_none())
+                 .map(|(&pid, _)| pid)
+                 .collect();
+             if unfound_pids.is_empty() {
+                 info!("All windows found.");
+                 // The strategy that found the most windows, the earlier one on a tie
+                 let best = order.iter().copied().max_by_key(|&strategy| {
+                     let count = matched_by.iter().filter(|&&matched| matched == strategy).count();
+                     (count, std::cmp::Reverse(order.iter().position(|&s| s == strategy)))
+                 });
+                 *self.last_match.lock().unwrap_or_else(|e| e.into_inner()) = best.filter(|_| !matched_by.is_empty());
+                 break;
+             }
+             if start_time.elapsed() >= max_wait_duration {
+                 error!("Failed to find all windows after waiting {:?}. Unfound PIDs: {:?}", start_time.elapsed(), unfound_pids);
+                 break;
+             }
 
-             info!("Applying layout for window {} (PID {}): monitor index {}, x={}, y={}, width={}, height={}", window_id, pid, monitor_index, x, y, width, height);
+             if self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::SeqCst)) {
+                 info!("Window search cancelled with {} window(s) still unfound.", unfound_pids.len());
+                 return Err(WindowManagerError::Cancelled);
+             }
 
-             self.move_window(*window_id, x, y)?;
-             self.resize_window(*window_id, width, height)?;
-             self.remove_decorations(*window_id)?;
+             info!("{} windows still unfound. Waiting {:?} before retrying...", unfound_pids.len(), current_delay);
+             thread::sleep(current_delay);
+             current_delay = std::cmp::min(current_delay * 2, max_delay); // Exponential backoff
          }
 
-         self.conn.flush()?; // Ensure all requests are sent after all operations
-         info!("Window layout set successfully.");
-         Ok(())
+         Ok(found)
+     }
+
+     /// Where `pid`'s window is and how big, or `None` if it has no window.
+     /// A PID with several windows reports the first.
+     pub fn window_geometry(&self, pid: u32) -> Result<Option<WindowRect>, WindowManagerError> {
+         match self.find_windows_by_pid(pid)?.first() {
+             Some(&window) => Ok(Some(self.conn.window_geometry(window)?)),
+             None => Ok(None),
+         }
+     }
+
+     /// Index into [`monitors`](Self::monitors) of the monitor holding the
+     /// centre of `pid`'s window, or `None` if it has no window or the centre
+     /// is off every monitor.
+     pub fn window_monitor(&self, pid: u32) -> Result<Option<usize>, WindowManagerError> {
+         let Some(rect) = self.window_geometry(pid)? else {
+             return Ok(None);
+         };
+         let (center_x, center_y) = (rect.x + (rect.width / 2) as i32, rect.y + (rect.height / 2) as i32);
+         Ok(self.monitors()?.iter().position(|monitor| monitor.contains(center_x, center_y)))
      }
 
      /// Retrieves monitor information using the _NET_WORKAREA EWMH property.
      /// Returns a list of usable desktop areas.
      /// This is generally more reliable than SCREEN information as it respects panels/docks.
-     fn get_monitors(&self) -> Result<Vec<Monitor>, WindowManagerError> {
+     pub fn monitors(&self) -> Result<Vec<Monitor>, WindowManagerError> {
          info!("Attempting to get monitor information using _NET_WORKAREA");
-         let root = self.conn.setup().roots[0].root;
-         let atom = self.conn.intern_atom(false, b"_NET_WORKAREA")?.reply()?.atom;
-         let reply = self.conn.get_property(false, root, atom, AtomEnum::CARDINAL, 0, u32::MAX)?.reply()?;
-         let value = reply.value;
+         let root = self.conn.root_window();
+         let atom = self.conn.intern_atom(b"_NET_WORKAREA")?;
+         let value = self.conn.get_property(root, atom, AtomEnum::CARDINAL, u32::MAX)?;
 
          if value.is_empty() {
              error!("_NET_WORKAREA property not found or is empty.");
@@ -328,7 +1305,7 @@ impl WindowManager {
              let y = u32::from_ne_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as i32;
              let width = u32::from_ne_bytes([chunk[8], chunk[9], chunk[10], chunk[11]]) as i32;
              let height = u32::from_ne_bytes([chunk[12], chunk[13], chunk[14], chunk[15]]) as i32;
-             monitors.push(Monitor { x, y, width, height });
+             monitors.push(Monitor { x, y, width, height, name: None });
              info!("Detected monitor {}: x={}, y={}, width={}, height={}", i, x, y, width, height);
          }
          info!("Detected {} monitors based on _NET_WORKAREA.", monitors.len());
@@ -336,12 +1313,153 @@ impl WindowManager {
      }
 }
 
-#[derive(Debug)]
+/// Computes the monitor index and geometry (x, y, width, height) for slot
+/// `window_index` of a layout holding `num_windows` windows.
+/// Windows are distributed round-robin across monitors, then tiled within each.
+/// Custom layouts place slot `i` at `custom_rects[i]` instead, and tabbed
+/// layouts give every slot the whole first monitor.
+fn slot_geometry(
+    monitors: &[Monitor],
+    layout: &Layout,
+    custom_rects: &[WindowRect],
+    window_index: usize,
+    num_windows: usize,
+) -> (usize, i32, i32, u32, u32) {
+    if *layout == Layout::Custom {
+        match custom_rects.get(window_index) {
+            Some(rect) => {
+                let monitor_index = monitors.iter().position(|m| m.contains(rect.x, rect.y)).unwrap_or(0);
+                return (monitor_index, rect.x, rect.y, rect.width, rect.height);
+            }
+            None => {
+                warn!("Custom layout has no rectangle for window {}; placing it as in the horizontal layout.", window_index);
+                return slot_geometry(monitors, &Layout::Horizontal, custom_rects, window_index, num_windows);
+            }
+        }
+    }
+
+    if *layout == Layout::Tabbed {
+        let monitor = &monitors[0];
+        return (0, monitor.x, monitor.y, monitor.width as u32, monitor.height as u32);
+    }
+
+    let num_monitors = monitors.len();
+    let monitor_index = window_index % num_monitors;
+    let monitor = &monitors[monitor_index];
+    // index_on_monitor: 0-based slot for this window within its assigned monitor.
+    let index_on_monitor = window_index / num_monitors;
+    // Total windows assigned to this monitor under round-robin distribution.
+    let windows_on_monitor = (num_windows + num_monitors - 1 - monitor_index) / num_monitors;
+
+    let (x, y, width, height) = match layout {
+        Layout::Horizontal => {
+            let single_width = monitor.width / windows_on_monitor.max(1) as i32;
+            let x_offset = index_on_monitor as i32 * single_width;
+            (monitor.x + x_offset, monitor.y, single_width as u32, monitor.height as u32)
+        }
+        Layout::Vertical => {
+            let single_height = monitor.height / windows_on_monitor.max(1) as i32;
+            let y_offset = index_on_monitor as i32 * single_height;
+            (monitor.x, monitor.y + y_offset, monitor.width as u32, single_height as u32)
+        }
+        Layout::Grid2x2 => {
+            let grid_x = window_index % 2;
+            let grid_y = (window_index / 2) % 2;
+            let cell_width = monitor.width / 2;
+            let cell_height = monitor.height / 2;
+            let x = monitor.x + (grid_x as i32 * cell_width);
+            let y = monitor.y + (grid_y as i32 * cell_height);
+            (x, y, cell_width as u32, cell_height as u32)
+        }
+        Layout::Grid3x1 => {
+            let cell_width = monitor.width / 3;
+            let x = monitor.x + ((window_index % 3) as i32 * cell_width);
+            (x, monitor.y, cell_width as u32, monitor.height as u32)
+        }
+        Layout::Grid { cols, rows } => {
+            let (cols, rows) = ((*cols).max(1) as usize, (*rows).max(1) as usize);
+            let grid_x = window_index % cols;
+            let grid_y = (window_index / cols) % rows;
+            let cell_width = monitor.width / cols as i32;
+            let cell_height = monitor.height / rows as i32;
+            let x = monitor.x + (grid_x as i32 * cell_width);
+            let y = monitor.y + (grid_y as i32 * cell_height);
+            (x, y, cell_width as u32, cell_height as u32)
+        }
+        Layout::Custom | Layout::Tabbed => unreachable!("custom and tabbed layouts are placed above"),
+    };
+    (monitor_index, x, y, width, height)
+}
+
+/// Largest rectangle with width/height `ratio` that fits the cell, centred in it.
+fn letterbox(x: i32, y: i32, width: u32, height: u32, ratio: f64) -> (i32, i32, u32, u32) {
+    let (fit_width, fit_height) = if width as f64 / height.max(1) as f64 > ratio {
+        ((height as f64 * ratio).round() as u32, height)
+    } else {
+        (width, (width as f64 / ratio).round() as u32)
+    };
+    let x = x + ((width - fit_width) / 2) as i32;
+    let y = y + ((height - fit_height) / 2) as i32;
+    (x, y, fit_width, fit_height)
+}
+
+/// `text` encoded as Latin-1 for a STRING property, with `?` standing in
+/// for the characters Latin-1 lacks.
+fn latin1_lossy(text: &str) -> Vec<u8> {
+    text.chars().map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?')).collect()
+}
+
+/// `title` with a player `label` after it, or the label alone for an untitled window.
+fn labelled(title: &str, label: &str) -> String {
+    if title.trim().is_empty() {
+        label.to_string()
+    } else {
+        format!("{} {}", title.trim_end(), label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Layout {
     Horizontal,
     Vertical,
     Grid2x2,
     Grid3x1,
+    /// `cols` by `rows` cells per monitor, filled row by row
+    Grid { cols: u8, rows: u8 },
+    /// Windows go where `WindowManager::set_custom_rects` says
+    Custom,
+    /// Every window fills the first monitor and one is in front at a time;
+    /// `WindowManager::start_tab_cycle` switches between them
+    Tabbed,
+}
+
+impl Layout {
+    /// Every name `name` returns
+    pub const NAMES: [&'static str; 7] = ["horizontal", "vertical", "grid2x2", "grid3x1", "grid", "custom", "tabbed"];
+
+    /// The name used in the config file and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Horizontal => "horizontal",
+            Layout::Vertical => "vertical",
+            Layout::Grid2x2 => "grid2x2",
+            Layout::Grid3x1 => "grid3x1",
+            Layout::Grid { .. } => "grid",
+            Layout::Custom => "custom",
+            Layout::Tabbed => "tabbed",
+        }
+    }
+}
+
+/// Where one window goes in a custom layout, in pixels from the top-left
+/// corner of the desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
 }
 
 impl From<&str> for Layout {
@@ -351,6 +1469,7 @@ impl From<&str> for Layout {
             "horizontal" => Layout::Horizontal,
             "grid2x2" => Layout::Grid2x2,
             "grid3x1" => Layout::Grid3x1,
+            "tabbed" => Layout::Tabbed,
             _ => {
                 log::warn!("Unknown layout '{}', defaulting to Horizontal.", s);
                 Layout::Horizontal // Default layout
@@ -359,44 +1478,1065 @@ impl From<&str> for Layout {
     }
 }
 
-#[derive(Debug)] // Derive Debug for Monitor struct
-struct Monitor {
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
+/// A usable desktop area that windows are laid out on, in root window
+/// coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// Output name (e.g. "DP-1") when the source reports one; `_NET_WORKAREA`
+    /// doesn't, so this is `None` for monitors found that way
+    pub name: Option<String>,
 }
 
-// Add tests similar to instance_manager.rs if possible,
-// but X11 interaction makes these harder without a virtual display.
-// You might need integration tests that run in an X11 environment.
+impl Monitor {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// How often the tab cycler checks for hotkey presses and for being stopped
+const TAB_CYCLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Keys with names besides letters, digits and F1–F24, with their X keysyms
+const NAMED_KEYS: [(&str, u32); 10] = [
+    ("tab", 0xff09),
+    ("pause", 0xff13),
+    ("scroll_lock", 0xff14),
+    ("home", 0xff50),
+    ("page_up", 0xff55),
+    ("page_down", 0xff56),
+    ("end", 0xff57),
+    ("insert", 0xff63),
+    ("space", 0x20),
+    ("grave", 0x60),
+];
+
+/// A key combination such as `Ctrl+Alt+Tab`, grabbed for the whole desktop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    /// The combination as written, for messages
+    name: String,
+    modifiers: ModMask,
+    keysym: u32,
+}
+
+impl FromStr for Hotkey {
+    type Err = WindowManagerError;
+
+    /// Parses a key name after any number of `Ctrl+`, `Shift+`, `Alt+` and
+    /// `Super+` modifiers, ignoring case. Keys are letters, digits, F1–F24
+    /// and the names in `NAMED_KEYS`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || WindowManagerError::InvalidHotkey(s.to_string());
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().ok_or_else(invalid)?.to_ascii_lowercase();
+        let mut modifiers = ModMask::from(0u16);
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => ModMask::CONTROL,
+                "shift" => ModMask::SHIFT,
+                "alt" => ModMask::M1,
+                "super" => ModMask::M4,
+                _ => return Err(invalid()),
+            };
+        }
+        let function_key = key
+            .strip_prefix('f')
+            .and_then(|number| number.parse::<u32>().ok())
+            .filter(|number| (1..=24).contains(number));
+        let keysym = match key.as_bytes() {
+            // Latin-1 keysyms are the characters themselves
+            [c] if c.is_ascii_alphanumeric() => u32::from(*c),
+            _ => match function_key {
+                Some(number) => 0xffbe + number - 1,
+                None => NAMED_KEYS.iter().find(|&&(name, _)| name == key).map(|&(_, keysym)| keysym).ok_or_else(invalid)?,
+            },
+        };
+        Ok(Hotkey { name: s.to_string(), modifiers, keysym })
+    }
+}
+
+/// Brings the windows of a tabbed layout to the front in turn each time a
+/// hotkey is pressed. Stops and lets the windows stack normally again when
+/// dropped.
+pub struct TabCycler {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TabCycler {
+    /// Grabs `hotkey` and starts cycling through `windows`, the first of
+    /// which should already be in front. With `dim_unfocused`, the windows
+    /// behind are dimmed to that opacity.
+    pub fn start(windows: Vec<xproto::Window>, hotkey: &Hotkey, dim_unfocused: Option<f64>) -> Result<Self, WindowManagerError> {
+        let (conn, _) = RustConnection::connect(None)?;
+        let root = X11Ops::root_window(&conn);
+        let (min_keycode, max_keycode) = (conn.setup().min_keycode, conn.setup().max_keycode);
+        let mapping = conn.get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)?.reply()?;
+        let keycode = mapping
+            .keysyms
+            .chunks(usize::from(mapping.keysyms_per_keycode.max(1)))
+            .position(|keysyms| keysyms.contains(&hotkey.keysym))
+            .map(|index| min_keycode + index as u8)
+            .ok_or_else(|| WindowManagerError::HotkeyUnavailable(hotkey.name.clone()))?;
+
+        // Caps Lock and Num Lock change the modifier state, so grab the key
+        // with every combination of them too
+        let grabs: Vec<ModMask> = [ModMask::from(0u16), ModMask::LOCK, ModMask::M2, ModMask::LOCK | ModMask::M2]
+            .into_iter()
+            .map(|locks| hotkey.modifiers | locks)
+            .collect();
+        for &modifiers in &grabs {
+            conn.grab_key(false, root, modifiers, keycode, GrabMode::ASYNC, GrabMode::ASYNC)?
+                .check()
+                .map_err(|_| WindowManagerError::HotkeyUnavailable(hotkey.name.clone()))?;
+        }
+        let mut manager = WindowManager::new()?;
+        manager.set_dim_unfocused(dim_unfocused);
+        info!("Press {} to switch between the {} game windows", hotkey.name, windows.len());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let handle = thread::spawn(move || {
+            let mut front = 0;
+            while !stop_flag.load(Ordering::Relaxed) && !windows.is_empty() {
+                match conn.poll_for_event() {
+                    Ok(Some(Event::KeyPress(event))) if event.detail == keycode => {
+                        let next = (front + 1) % windows.len();
+                        debug!("Bringing window {} (slot {}) to the front", windows[next], next);
+                        if let Err(e) = manager.bring_to_front(windows[next], Some(windows[front])) {
+                            warn!("Could not bring game window {} to the front: {}", windows[next], e);
+                        }
+                        // Move on even after an error, so a closed game's
+                        // window doesn't block the others
+                        front = next;
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => thread::sleep(TAB_CYCLE_POLL_INTERVAL),
+                    Err(e) => {
+                        error!("Lost the X connection for the window switching hotkey: {}", e);
+                        break;
+                    }
+                }
+            }
+            for modifiers in grabs {
+                let _ = conn.ungrab_key(keycode, root, modifiers);
+            }
+            let _ = Connection::flush(&conn);
+            if let Err(e) = manager.release_always_on_top() {
+                debug!("Could not release the front game window: {}", e);
+            }
+        });
+        Ok(TabCycler { stop, handle: Some(handle) })
+    }
+}
+
+impl Drop for TabCycler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Scripted stand-in for an X server, for testing layouts without a display.
+#[cfg(any(test, feature = "mock-x11"))]
+pub mod mock {
+    use super::{WindowManagerError, WindowRect, X11Ops};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+    use x11rb::errors::ReplyError;
+    use x11rb::protocol::xproto::{self, AtomEnum, ConfigureWindowAux};
+    use x11rb::protocol::ErrorKind;
+    use x11rb::x11_utils::X11Error;
+
+    const ROOT: xproto::Window = 1;
+
+    /// A client message as recorded by `MockX11`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ClientMessageCall {
+        pub window: xproto::Window,
+        pub message_type: xproto::Atom,
+        pub data: [u32; 5],
+    }
+
+    /// A configure request as recorded by `MockX11`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ConfigureCall {
+        pub window: xproto::Window,
+        pub x: Option<i32>,
+        pub y: Option<i32>,
+        pub width: Option<u32>,
+        pub height: Option<u32>,
+        pub stack_mode: Option<xproto::StackMode>,
+    }
+
+    /// Answers `query_tree` from a script of window trees and records every
+    /// configure request. Each `query_tree` call consumes one tree; the last
+    /// one keeps being returned once the script runs out.
+    ///
+    /// `X11Ops` is implemented for `Arc<MockX11>` so a test can keep a handle
+    /// for inspecting the recorded calls.
+    pub struct MockX11 {
+        /// (x, y, width, height) of each monitor's work area
+        workareas: Vec<(u32, u32, u32, u32)>,
+        /// Successive query_tree answers: (window, _NET_WM_PID) pairs
+        trees: Mutex<Vec<Vec<(xproto::Window, u32)>>>,
+        /// The tree most recently returned by query_tree
+        visible: Mutex<Vec<(xproto::Window, u32)>>,
+        /// _NET_WM_NAME of each window that has one
+        titles: Mutex<HashMap<xproto::Window, String>>,
+        /// _NET_FRAME_EXTENTS (left, right, top, bottom) of framed windows
+        frame_extents: HashMap<xproto::Window, [u32; 4]>,
+        /// Windows query_tree lists but that are destroyed before anything
+        /// else can be asked about them
+        vanished: HashSet<xproto::Window>,
+        /// (window, property) of every change_property call
+        property_changes: Mutex<Vec<(xproto::Window, xproto::Atom)>>,
+        /// Data most recently written to each (window, property)
+        property_values: Mutex<HashMap<(xproto::Window, xproto::Atom), Vec<u8>>>,
+        /// Whether a compositor owns _NET_WM_CM_S0
+        compositor: bool,
+        /// Whether the root window has a _NET_CLIENT_LIST, which then lists
+        /// the windows of the scripted trees
+        client_list: bool,
+        /// (window, _NET_WM_PID) children of windows below the root
+        children: HashMap<xproto::Window, Vec<(xproto::Window, u32)>>,
+        /// PID the X server knows each window's client by, where it differs
+        /// from _NET_WM_PID
+        client_pids: HashMap<xproto::Window, u32>,
+        query_tree_calls: Mutex<usize>,
+        pid_reads: Mutex<usize>,
+        atoms: Mutex<HashMap<Vec<u8>, xproto::Atom>>,
+        configure_calls: Mutex<Vec<ConfigureCall>>,
+        client_messages: Mutex<Vec<ClientMessageCall>>,
+    }
+
+    impl MockX11 {
+        pub fn new(workareas: Vec<(u32, u32, u32, u32)>, trees: Vec<Vec<(xproto::Window, u32)>>) -> Self {
+            MockX11 {
+                workareas,
+                trees: Mutex::new(trees),
+                visible: Mutex::new(Vec::new()),
+                titles: Mutex::new(HashMap::new()),
+                frame_extents: HashMap::new(),
+                vanished: HashSet::new(),
+                property_changes: Mutex::new(Vec::new()),
+                property_values: Mutex::new(HashMap::new()),
+                compositor: false,
+                client_list: false,
+                children: HashMap::new(),
+                client_pids: HashMap::new(),
+                query_tree_calls: Mutex::new(0),
+                pid_reads: Mutex::new(0),
+                atoms: Mutex::new(HashMap::new()),
+                configure_calls: Mutex::new(Vec::new()),
+                client_messages: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Gives windows a _NET_WM_NAME
+        pub fn with_titles(mut self, titles: &[(xproto::Window, &str)]) -> Self {
+            self.titles = Mutex::new(titles.iter().map(|&(window, title)| (window, title.to_string())).collect());
+            self
+        }
+
+        /// Changes a window's _NET_WM_NAME the way its game would
+        pub fn set_title(&self, window: xproto::Window, title: &str) {
+            self.titles.lock().unwrap().insert(window, title.to_string());
+        }
+
+        /// A window's current _NET_WM_NAME
+        pub fn title(&self, window: xproto::Window) -> Option<String> {
+            self.titles.lock().unwrap().get(&window).cloned()
+        }
+
+        /// Gives windows a _NET_FRAME_EXTENTS
+        pub fn with_frame_extents(mut self, extents: &[(xproto::Window, [u32; 4])]) -> Self {
+            self.frame_extents = extents.iter().copied().collect();
+            self
+        }
+
+        /// Makes property reads on `windows` fail with BadWindow
+        pub fn with_vanished_windows(mut self, windows: &[xproto::Window]) -> Self {
+            self.vanished = windows.iter().copied().collect();
+            self
+        }
+
+        /// Makes a compositor own _NET_WM_CM_S0
+        pub fn with_compositor(mut self) -> Self {
+            self.compositor = true;
+            self
+        }
+
+        /// Publishes the scripted trees as _NET_CLIENT_LIST, the way an EWMH
+        /// window manager would
+        pub fn with_client_list(mut self) -> Self {
+            self.client_list = true;
+            self
+        }
+
+        /// Gives windows below the root children, the way a reparenting
+        /// window manager's frames hold the game windows
+        pub fn with_children(mut self, children: &[(xproto::Window, Vec<(xproto::Window, u32)>)]) -> Self {
+            self.children = children.iter().cloned().collect();
+            self
+        }
+
+        /// Makes the X server know windows' clients by PIDs other than their
+        /// _NET_WM_PID, the way it does for games in a PID namespace
+        pub fn with_client_pids(mut self, client_pids: &[(xproto::Window, u32)]) -> Self {
+            self.client_pids = client_pids.iter().copied().collect();
+            self
+        }
+
+        /// How many times the whole window tree has been queried
+        pub fn query_tree_calls(&self) -> usize {
+            *self.query_tree_calls.lock().unwrap()
+        }
+
+        /// How many times a window's _NET_WM_PID has been read
+        pub fn pid_reads(&self) -> usize {
+            *self.pid_reads.lock().unwrap()
+        }
+
+        /// Moves on to the next scripted tree and returns its windows
+        fn next_tree(&self) -> Vec<xproto::Window> {
+            let mut trees = self.trees.lock().unwrap();
+            let tree = if trees.len() > 1 { trees.remove(0) } else { trees.first().cloned().unwrap_or_default() };
+            let windows = tree.iter().map(|&(window, _)| window).collect();
+            *self.visible.lock().unwrap() = tree;
+            windows
+        }
+
+        /// Data last written to `property` of `window`
+        pub fn property_value(&self, window: xproto::Window, property: xproto::Atom) -> Option<Vec<u8>> {
+            self.property_values.lock().unwrap().get(&(window, property)).cloned()
+        }
+
+        /// (window, property) of every property changed so far, in order
+        pub fn property_changes(&self) -> Vec<(xproto::Window, xproto::Atom)> {
+            self.property_changes.lock().unwrap().clone()
+        }
+
+        /// Configure requests received so far, in order
+        pub fn configure_calls(&self) -> Vec<ConfigureCall> {
+            self.configure_calls.lock().unwrap().clone()
+        }
+
+        /// Client messages received so far, in order
+        pub fn client_messages(&self) -> Vec<ClientMessageCall> {
+            self.client_messages.lock().unwrap().clone()
+        }
+
+        /// The atom the mock interns `name` as
+        pub fn atom(&self, name: &[u8]) -> xproto::Atom {
+            let mut atoms = self.atoms.lock().unwrap();
+            let next = 100 + atoms.len() as xproto::Atom;
+            *atoms.entry(name.to_vec()).or_insert(next)
+        }
+    }
+
+    impl X11Ops for std::sync::Arc<MockX11> {
+        fn root_window(&self) -> xproto::Window {
+            ROOT
+        }
+
+        fn intern_atom(&self, name: &[u8]) -> Result<xproto::Atom, WindowManagerError> {
+            Ok(self.atom(name))
+        }
+
+        fn query_tree(&self, window: xproto::Window) -> Result<Vec<xproto::Window>, WindowManagerError> {
+            if window != ROOT {
+                return Ok(self.children.get(&window).into_iter().flatten().map(|&(child, _)| child).collect());
+            }
+            *self.query_tree_calls.lock().unwrap() += 1;
+            Ok(self.next_tree())
+        }
+
+        fn get_property(
+            &self,
+            window: xproto::Window,
+            property: xproto::Atom,
+            _type: AtomEnum,
+            _long_length: u32,
+        ) -> Result<Vec<u8>, WindowManagerError> {
+            if self.vanished.contains(&window) {
+                return Err(ReplyError::X11Error(X11Error {
+                    error_kind: ErrorKind::Window,
+                    error_code: 3,
+                    sequence: 0,
+                    bad_value: window,
+                    minor_opcode: 0,
+                    major_opcode: 20,
+                    extension_name: None,
+                    request_name: Some("GetProperty"),
+                })
+                .into());
+            }
+            if window == ROOT && property == self.atom(b"_NET_WORKAREA") {
+                return Ok(self
+                    .workareas
+                    .iter()
+                    .flat_map(|&(x, y, w, h)| [x, y, w, h])
+                    .flat_map(u32::to_ne_bytes)
+                    .collect());
+            }
+            if window == ROOT && property == self.atom(b"_NET_CLIENT_LIST") {
+                if !self.client_list {
+                    return Ok(Vec::new());
+                }
+                return Ok(self.next_tree().into_iter().flat_map(u32::to_ne_bytes).collect());
+            }
+            if property == self.atom(b"_NET_WM_NAME") {
+                return Ok(self.titles.lock().unwrap().get(&window).map(|title| title.as_bytes().to_vec()).unwrap_or_default());
+            }
+            if property == self.atom(b"_NET_FRAME_EXTENTS") {
+                return Ok(self.frame_extents.get(&window).map(|e| e.iter().flat_map(|v| v.to_ne_bytes()).collect()).unwrap_or_default());
+            }
+            if property == self.atom(b"_NET_WM_PID") {
+                *self.pid_reads.lock().unwrap() += 1;
+                let visible = self.visible.lock().unwrap();
+                let pid = visible
+                    .iter()
+                    .chain(self.children.values().flatten())
+                    .find(|&&(w, _)| w == window)
+                    .map(|&(_, pid)| pid);
+                return Ok(pid.map(|pid| pid.to_ne_bytes().to_vec()).unwrap_or_default());
+            }
+            Ok(Vec::new())
+        }
+
+        fn configure_window(&self, window: xproto::Window, aux: &ConfigureWindowAux) -> Result<(), WindowManagerError> {
+            self.configure_calls.lock().unwrap().push(ConfigureCall {
+                window,
+                x: aux.x,
+                y: aux.y,
+                width: aux.width,
+                height: aux.height,
+                stack_mode: aux.stack_mode,
+            });
+            Ok(())
+        }
+
+        fn change_property(
+            &self,
+            window: xproto::Window,
+            property: xproto::Atom,
+            _type: xproto::Atom,
+            _format: u8,
+            _data_len: u32,
+            data: &[u8],
+        ) -> Result<(), WindowManagerError> {
+            if property == self.atom(b"_NET_WM_NAME") {
+                self.titles.lock().unwrap().insert(window, String::from_utf8_lossy(data).into_owned());
+            }
+            self.property_changes.lock().unwrap().push((window, property));
+            self.property_values.lock().unwrap().insert((window, property), data.to_vec());
+            Ok(())
+        }
+
+        fn send_client_message(&self, window: xproto::Window, message_type: xproto::Atom, data: [u32; 5]) -> Result<(), WindowManagerError> {
+            self.client_messages.lock().unwrap().push(ClientMessageCall { window, message_type, data });
+            Ok(())
+        }
+
+        fn selection_owner(&self, selection: xproto::Atom) -> Result<xproto::Window, WindowManagerError> {
+            let owned = self.compositor && selection == self.atom(b"_NET_WM_CM_S0");
+            Ok(if owned { ROOT + 1 } else { x11rb::NONE })
+        }
+
+        /// Where the configure requests so far have put `window`, starting
+        /// from an empty rectangle at the origin
+        fn window_geometry(&self, window: xproto::Window) -> Result<WindowRect, WindowManagerError> {
+            let mut rect = WindowRect { x: 0, y: 0, width: 0, height: 0 };
+            for call in self.configure_calls.lock().unwrap().iter().filter(|call| call.window == window) {
+                rect.x = call.x.unwrap_or(rect.x);
+                rect.y = call.y.unwrap_or(rect.y);
+                rect.width = call.width.unwrap_or(rect.width);
+                rect.height = call.height.unwrap_or(rect.height);
+            }
+            Ok(rect)
+        }
+
+        fn client_pid(&self, window: xproto::Window) -> Result<Option<u32>, WindowManagerError> {
+            Ok(self.client_pids.get(&window).copied())
+        }
+
+        fn flush(&self) -> Result<(), WindowManagerError> {
+            Ok(())
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    // Mock X11 server interaction is complex.
-    // These tests would primarily verify the logic *given* successful X11 calls.
-    // Real-world testing requires an X server.
-
-    // Example test structure (would require mocking x11rb responses)
-    // #[test]
-    // fn test_set_layout_finds_windows_with_retry() {
-    //     // Mock a WindowManager that initially doesn't find a PID, then finds it on retry
-    // }
-
-    // #[test]
-    // fn test_set_layout_fails_if_windows_not_found() {
-    //     // Mock a WindowManager that never finds a specific PID
-    // }
-
-    // #[test]
-    // fn test_set_layout_applies_correct_positions_horizontal() {
-    //     // Mock get_monitors to return specific monitor sizes
-    //     // Mock find_window_by_pid to return window IDs
-    //     // Verify move_window and resize_window are called with expected arguments
-    // }
-
-     // #[test]
-    // fn test_set_layout_applies_correct_positions_vertical() {
-    //     // Similar to horizontal test, but verify vertical tiling
-    // }
+    use super::mock::{ConfigureCall, MockX11};
+    use super::*;
+    use std::sync::Arc;
+
+    fn manager(mock: &Arc<MockX11>, timeout: Duration) -> WindowManager {
+        let timing = WindowSearchTiming {
+            timeout,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        };
+        WindowManager::with_backend(Box::new(mock.clone()), timing)
+    }
+
+    /// (x, y, width, height) per window from the recorded move/resize pairs
+    fn geometries(calls: &[ConfigureCall]) -> Vec<(xproto::Window, i32, i32, u32, u32)> {
+        calls
+            .chunks(2)
+            .map(|pair| (pair[0].window, pair[0].x.unwrap(), pair[0].y.unwrap(), pair[1].width.unwrap(), pair[1].height.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_set_layout_applies_correct_positions_horizontal() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101)]]));
+        manager(&mock, Duration::from_secs(1)).set_layout(&[100, 101], Layout::Horizontal).unwrap();
+
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(10, 0, 0, 960, 1080), (11, 960, 0, 960, 1080)]
+        );
+    }
+
+    #[test]
+    fn test_window_monitor_after_layout() {
+        let mock = Arc::new(MockX11::new(
+            vec![(0, 0, 1920, 1080), (1920, 0, 1920, 1080)],
+            vec![vec![(10, 100), (11, 101)]],
+        ));
+        let manager = manager(&mock, Duration::from_secs(1));
+        manager.set_layout(&[100, 101], Layout::Horizontal).unwrap();
+
+        assert_eq!(
+            manager.window_geometry(101).unwrap(),
+            Some(WindowRect { x: 1920, y: 0, width: 1920, height: 1080 })
+        );
+        assert_eq!(manager.window_monitor(100).unwrap(), Some(0));
+        assert_eq!(manager.window_monitor(101).unwrap(), Some(1));
+        assert_eq!(manager.window_geometry(999).unwrap(), None);
+        assert_eq!(manager.window_monitor(999).unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_layout_applies_correct_positions_vertical() {
+        let mock = Arc::new(MockX11::new(vec![(0, 32, 1920, 1048)], vec![vec![(11, 101), (10, 100)]]));
+        manager(&mock, Duration::from_secs(1)).set_layout(&[100, 101], Layout::Vertical).unwrap();
+
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(10, 0, 32, 1920, 524), (11, 0, 556, 1920, 524)]
+        );
+    }
+
+    #[test]
+    fn test_monitors_from_workarea() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080), (1920, 0, 2560, 1440)], vec![]));
+        let monitors = manager(&mock, Duration::from_secs(1)).monitors().unwrap();
+
+        assert_eq!(
+            monitors,
+            vec![
+                Monitor { x: 0, y: 0, width: 1920, height: 1080, name: None },
+                Monitor { x: 1920, y: 0, width: 2560, height: 1440, name: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slot_sizes() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        assert_eq!(manager.slot_sizes(Layout::Grid2x2, 4).unwrap(), vec![(960, 540); 4]);
+        assert_eq!(manager.slot_sizes(Layout::Horizontal, 2).unwrap(), vec![(960, 1080); 2]);
+
+        manager.set_keep_decorations(true);
+        assert_eq!(manager.slot_sizes(Layout::Vertical, 2).unwrap(), vec![(1920, 510); 2]);
+
+        manager.set_keep_decorations(false);
+        manager.set_aspect_ratios(Some(vec![4.0 / 3.0]));
+        assert_eq!(manager.slot_sizes(Layout::Horizontal, 2).unwrap(), vec![(960, 720), (960, 540)]);
+        assert!(mock.configure_calls().is_empty());
+    }
+
+    #[test]
+    fn test_layout_slots_across_monitors() {
+        let mock = Arc::new(MockX11::new(vec![], vec![]));
+        let manager = manager(&mock, Duration::from_secs(1));
+        let monitors = [
+            Monitor { x: 0, y: 0, width: 1920, height: 1080, name: None },
+            Monitor { x: 1920, y: 0, width: 1280, height: 1024, name: None },
+        ];
+        assert_eq!(
+            manager.layout_slots(&monitors, Layout::Vertical, 3),
+            vec![
+                (0, WindowRect { x: 0, y: 0, width: 1920, height: 540 }),
+                (1, WindowRect { x: 1920, y: 0, width: 1280, height: 1024 }),
+                (0, WindowRect { x: 0, y: 540, width: 1920, height: 540 }),
+            ]
+        );
+        assert!(manager.layout_slots(&[], Layout::Horizontal, 2).is_empty());
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![]));
+        let sizes = manager(&mock, Duration::from_secs(1))
+            .slot_sizes(Layout::Grid { cols: 3, rows: 2 }, 6)
+            .unwrap();
+        assert_eq!(sizes, vec![(640, 540); 6]);
+
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101), (12, 102)]]));
+        manager(&mock, Duration::from_secs(1))
+            .set_layout(&[100, 101, 102], Layout::Grid { cols: 2, rows: 2 })
+            .unwrap();
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(10, 0, 0, 960, 540), (11, 960, 0, 960, 540), (12, 0, 540, 960, 540)]
+        );
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101)]]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        manager.set_custom_rects(vec![WindowRect { x: 100, y: 50, width: 1280, height: 720 }]);
+        manager.set_layout(&[100, 101], Layout::Custom).unwrap();
+
+        // The second window has no rectangle and is placed as in the horizontal layout
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(10, 100, 50, 1280, 720), (11, 960, 0, 960, 1080)]
+        );
+    }
+
+    #[test]
+    fn test_set_layout_finds_windows_with_retry() {
+        let mock = Arc::new(MockX11::new(
+            vec![(0, 0, 1920, 1080)],
+            vec![vec![], vec![(10, 100)], vec![(10, 100), (11, 101)]],
+        ));
+        manager(&mock, Duration::from_secs(5)).set_layout(&[100, 101], Layout::Horizontal).unwrap();
+
+        assert_eq!(geometries(&mock.configure_calls()).len(), 2);
+    }
+
+    #[test]
+    fn test_set_layout_reads_client_list() {
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100)], vec![(10, 100), (11, 101), (12, 102)]])
+                .with_client_list(),
+        );
+        manager(&mock, Duration::from_secs(5)).set_layout(&[100, 101, 102], Layout::Vertical).unwrap();
+
+        assert_eq!(geometries(&mock.configure_calls()).len(), 3);
+        // The window manager's list is enough, and each window's PID is read
+        // once however many passes the search takes
+        assert_eq!(mock.query_tree_calls(), 0);
+        assert_eq!(mock.pid_reads(), 3);
+    }
+
+    #[test]
+    fn test_set_layout_searches_below_frames() {
+        // A reparenting window manager without _NET_CLIENT_LIST: the root's
+        // children are frames carrying the window manager's own PID
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 999), (11, 999)]])
+                .with_children(&[(10, vec![(20, 100)]), (11, vec![(21, 101)])]),
+        );
+        let window_manager = manager(&mock, Duration::from_secs(5));
+        assert_eq!(window_manager.match_strategy(), None);
+        window_manager.set_layout(&[100, 101], Layout::Horizontal).unwrap();
+
+        let placed: Vec<xproto::Window> = geometries(&mock.configure_calls()).iter().map(|g| g.0).collect();
+        assert_eq!(placed, vec![20, 21]);
+        assert_eq!(window_manager.match_strategy(), Some(WindowMatchStrategy::Tree));
+    }
+
+    #[test]
+    fn test_tree_search_waits_for_pid_and_title() {
+        let mock = || {
+            Arc::new(
+                MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 999), (11, 999)]])
+                    .with_children(&[(10, vec![(20, 100)]), (11, vec![(21, 101)])]),
+            )
+        };
+        let fallback = mock();
+        let started = Instant::now();
+        manager(&fallback, Duration::from_secs(1)).set_layout(&[100, 101], Layout::Horizontal).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(250));
+        assert!(fallback.query_tree_calls() > 1);
+        // The frames' PID is read once, not on every pass
+        assert_eq!(fallback.pid_reads(), 4);
+
+        // A game whose windows the tree found before gets it right away
+        let preferred = mock();
+        let mut window_manager = manager(&preferred, Duration::from_secs(1));
+        window_manager.set_preferred_match_strategy(Some(WindowMatchStrategy::Tree));
+        window_manager.set_layout(&[100, 101], Layout::Horizontal).unwrap();
+        assert_eq!(preferred.query_tree_calls(), 1);
+    }
+
+    #[test]
+    fn test_preferred_match_strategy_is_tried_first() {
+        // The game's PID also owns its launcher window, listed first
+        let mock = || {
+            Arc::new(
+                MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 100), (12, 101)]])
+                    .with_titles(&[(10, "Launcher"), (11, "Game [P1]"), (12, "Game [P2]")]),
+            )
+        };
+        let titles = vec!["[P1]".to_string(), "[P2]".to_string()];
+        let placed = |mock: &Arc<MockX11>| -> Vec<xproto::Window> {
+            geometries(&mock.configure_calls()).iter().map(|g| g.0).collect()
+        };
+
+        let by_pid = mock();
+        let mut window_manager = manager(&by_pid, Duration::from_secs(5));
+        window_manager.set_layout_with_titles(&[100, 101], &titles, Layout::Horizontal).unwrap();
+        assert_eq!(placed(&by_pid), vec![10, 12]);
+        assert_eq!(window_manager.match_strategy(), Some(WindowMatchStrategy::Pid));
+
+        let by_title = mock();
+        window_manager = manager(&by_title, Duration::from_secs(5));
+        window_manager.set_preferred_match_strategy(Some(WindowMatchStrategy::Title));
+        window_manager.set_layout_with_titles(&[100, 101], &titles, Layout::Horizontal).unwrap();
+        assert_eq!(placed(&by_title), vec![11, 12]);
+        assert_eq!(window_manager.match_strategy(), Some(WindowMatchStrategy::Title));
+    }
+
+    #[test]
+    fn test_set_layout_matches_namespaced_windows_by_client_pid() {
+        // Two sandboxed instances both see themselves as PID 2.
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 2), (11, 2)]]).with_client_pids(&[(10, 4200), (11, 4100)]),
+        );
+        manager(&mock, Duration::from_secs(1)).set_layout(&[4100, 4200], Layout::Horizontal).unwrap();
+
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(11, 0, 0, 960, 1080), (10, 960, 0, 960, 1080)]
+        );
+    }
+
+    #[test]
+    fn test_set_layout_with_titles_disambiguates_shared_pid() {
+        // Both Proton instances report the same PID; only the titles tell them apart.
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 500), (11, 500)]])
+                .with_titles(&[(10, "Game [P2]"), (11, "Game [P1]")]),
+        );
+        let titles = vec!["[P1]".to_string(), "[P2]".to_string()];
+        manager(&mock, Duration::from_secs(1))
+            .set_layout_with_titles(&[500, 500], &titles, Layout::Horizontal)
+            .unwrap();
+
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(11, 0, 0, 960, 1080), (10, 960, 0, 960, 1080)]
+        );
+    }
+
+    #[test]
+    fn test_set_layout_with_titles_falls_back_for_missing_pid() {
+        // The second window has no _NET_WM_PID at all.
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 0)]])
+                .with_titles(&[(10, "Game [P1]"), (11, "Game [P2]")]),
+        );
+        let titles = vec!["[P1]".to_string(), "[P2]".to_string()];
+        manager(&mock, Duration::from_secs(1))
+            .set_layout_with_titles(&[100, 101], &titles, Layout::Horizontal)
+            .unwrap();
+
+        let windows: Vec<_> = geometries(&mock.configure_calls()).into_iter().map(|g| g.0).collect();
+        assert_eq!(windows, vec![10, 11]);
+    }
+
+    #[test]
+    fn test_set_layout_skips_windows_destroyed_mid_scan() {
+        // Window 5 is listed by query_tree but gone by the time it is read.
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(5, 0), (10, 100), (11, 0)]])
+                .with_titles(&[(11, "Game [P2]")])
+                .with_vanished_windows(&[5]),
+        );
+        let titles = vec![String::new(), "[P2]".to_string()];
+        manager(&mock, Duration::from_secs(1))
+            .set_layout_with_titles(&[100, 101], &titles, Layout::Horizontal)
+            .unwrap();
+
+        let windows: Vec<_> = geometries(&mock.configure_calls()).into_iter().map(|g| g.0).collect();
+        assert_eq!(windows, vec![10, 11]);
+    }
+
+    #[test]
+    fn test_label_windows_with_player_numbers() {
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101)]]).with_titles(&[(10, "Game")]),
+        );
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        manager.set_label_windows(true);
+        manager.set_layout(&[100, 101], Layout::Horizontal).unwrap();
+        assert_eq!(mock.title(10).as_deref(), Some("Game [Player 1]"));
+        assert_eq!(mock.title(11).as_deref(), Some("[Player 2]"));
+        let wm_name = xproto::Atom::from(AtomEnum::WM_NAME);
+        assert!(mock.property_changes().contains(&(10, wm_name)));
+
+        // The game resets its title; the label comes back, once.
+        mock.set_title(10, "Game - Loading");
+        manager.reapply_window_labels().unwrap();
+        manager.reapply_window_labels().unwrap();
+        assert_eq!(mock.title(10).as_deref(), Some("Game - Loading [Player 1]"));
+        let renames = mock.property_changes().iter().filter(|&&(window, atom)| window == 10 && atom == wm_name).count();
+        assert_eq!(renames, 2);
+    }
+
+    #[test]
+    fn test_latin1_lossy() {
+        assert_eq!(latin1_lossy("Game [Player 1]"), b"Game [Player 1]");
+        assert_eq!(latin1_lossy("Café"), b"Caf\xe9");
+        assert_eq!(latin1_lossy("ゲーム 1"), b"??? 1");
+    }
+
+    #[test]
+    fn test_set_layout_fails_if_windows_not_found() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100)]]));
+        let mut manager = manager(&mock, Duration::from_millis(200));
+        manager.set_layout_failure_policy(LayoutFailurePolicy::Abort);
+        let result = manager.set_layout(&[100, 101], Layout::Horizontal);
+
+        match result {
+            Err(WindowManagerError::WindowNotFound(pids)) => assert_eq!(pids, vec![101]),
+            other => panic!("Expected WindowNotFound, got {:?}", other),
+        }
+        assert!(mock.configure_calls().is_empty());
+    }
+
+    #[test]
+    fn test_layout_failure_policy_places_found_windows() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(11, 101)]]));
+        let unplaced = manager(&mock, Duration::from_millis(200)).set_layout(&[100, 101], Layout::Horizontal).unwrap();
+
+        // Player 2 keeps the right half; player 1's slot stays empty
+        assert_eq!(unplaced, vec![100]);
+        assert_eq!(geometries(&mock.configure_calls()), vec![(11, 960, 0, 960, 1080)]);
+
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(11, 101)]]));
+        let mut manager = manager(&mock, Duration::from_millis(200));
+        manager.set_layout_failure_policy(LayoutFailurePolicy::Ignore);
+        assert_eq!(manager.set_layout(&[100, 101], Layout::Horizontal).unwrap(), vec![100]);
+        assert!(mock.configure_calls().is_empty());
+    }
+
+    #[test]
+    fn test_preserve_aspect_ratio_letterboxes_slots() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101)]]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        // Player 2 overrides the 16:9 default with 4:3
+        manager.set_aspect_ratios(Some(vec![16.0 / 9.0, 4.0 / 3.0]));
+        manager.set_layout(&[100, 101], Layout::Vertical).unwrap();
+
+        // Each 1920x540 cell is wider than either ratio, so windows are pillarboxed.
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(10, 480, 0, 960, 540), (11, 600, 540, 720, 540)]
+        );
+    }
+
+    #[test]
+    fn test_keep_above_is_set_and_released() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101)]]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        manager.set_keep_above(true);
+        manager.set_layout(&[100, 101], Layout::Horizontal).unwrap();
+        manager.release_always_on_top().unwrap();
+
+        let state = mock.atom(b"_NET_WM_STATE");
+        let above = mock.atom(b"_NET_WM_STATE_ABOVE");
+        let changes: Vec<(u32, u32)> = mock
+            .client_messages()
+            .iter()
+            .inspect(|call| {
+                assert_eq!(call.message_type, state);
+                assert_eq!(call.data[1], above);
+            })
+            .map(|call| (call.window, call.data[0]))
+            .collect();
+        assert_eq!(changes, vec![(10, 1), (11, 1), (10, 0), (11, 0)]);
+    }
+
+    #[test]
+    fn test_place_window_keeps_restarted_window_above() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (12, 102)]]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        manager.set_keep_above(true);
+        manager.place_window(102, 1, 2, Layout::Horizontal).unwrap();
+
+        assert_eq!(geometries(&mock.configure_calls()), vec![(12, 960, 0, 960, 1080)]);
+        let above: Vec<(u32, u32)> = mock.client_messages().iter().map(|call| (call.window, call.data[0])).collect();
+        assert_eq!(above, vec![(12, 1)]);
+    }
+
+    #[test]
+    fn test_restack_windows_in_player_order() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101), (12, 102)]]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        manager.set_restack_windows(true);
+        manager.set_layout(&[100, 101, 102], Layout::Vertical).unwrap();
+
+        // Raised last to first, so Player 1's window ends up on top
+        let raised = |calls: &[ConfigureCall]| -> Vec<xproto::Window> {
+            calls.iter().filter(|call| call.stack_mode == Some(StackMode::ABOVE)).map(|call| call.window).collect()
+        };
+        assert_eq!(raised(&mock.configure_calls()), vec![12, 11, 10]);
+
+        let before = mock.configure_calls().len();
+        manager.restack(&[101, 999, 100]).unwrap();
+        assert_eq!(raised(&mock.configure_calls()[before..]), vec![10, 11]);
+    }
+
+    #[test]
+    fn test_window_opacity() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101), (12, 102)]]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        assert!(!manager.compositor_running().unwrap());
+        let opacity = |window| mock.property_value(window, mock.atom(b"_NET_WM_WINDOW_OPACITY")).map(|data| u32::from_ne_bytes(data.try_into().unwrap()));
+
+        manager.set_window_opacities(vec![1.0, 0.5]);
+        manager.set_layout(&[100, 101, 102], Layout::Vertical).unwrap();
+        assert_eq!(opacity(10), Some(u32::MAX));
+        assert_eq!(opacity(11), Some(0x8000_0000));
+        assert_eq!(opacity(12), None);
+
+        // Tabbed: everything behind the front window is dimmed, and switching
+        // swaps which one is
+        manager.set_window_opacities(Vec::new());
+        manager.set_dim_unfocused(Some(0.25));
+        manager.set_layout(&[100, 101, 102], Layout::Tabbed).unwrap();
+        assert_eq!([opacity(10), opacity(11), opacity(12)], [Some(u32::MAX), Some(0x4000_0000), Some(0x4000_0000)]);
+        manager.bring_to_front(11, Some(10)).unwrap();
+        assert_eq!([opacity(10), opacity(11)], [Some(0x4000_0000), Some(u32::MAX)]);
+
+        let composited = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![]]).with_compositor());
+        assert!(WindowManager::with_backend(Box::new(composited), WindowSearchTiming::default()).compositor_running().unwrap());
+    }
+
+    #[test]
+    fn test_tabbed_layout_stacks_windows_on_first_monitor() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1280, 800), (1280, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101), (12, 102)]]));
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        manager.set_keep_above(true);
+        manager.set_layout(&[100, 101, 102], Layout::Tabbed).unwrap();
+
+        let calls = mock.configure_calls();
+        assert_eq!(
+            geometries(&calls[..6]),
+            vec![(10, 0, 0, 1280, 800), (11, 0, 0, 1280, 800), (12, 0, 0, 1280, 800)]
+        );
+        assert_eq!(calls[6].window, 10);
+        assert_eq!(calls[6].stack_mode, Some(StackMode::ABOVE));
+        assert_eq!(manager.laid_out_windows(), vec![10, 11, 12]);
+
+        // Only the window in front is kept above, and it gets the focus
+        let (state, active) = (mock.atom(b"_NET_WM_STATE"), mock.atom(b"_NET_ACTIVE_WINDOW"));
+        let messages: Vec<(u32, u32, u32)> = mock.client_messages().iter().map(|call| (call.window, call.message_type, call.data[0])).collect();
+        assert_eq!(messages, vec![(10, state, 1), (10, active, 1)]);
+
+        manager.bring_to_front(11, Some(10)).unwrap();
+        let messages: Vec<(u32, u32, u32)> = mock.client_messages()[2..].iter().map(|call| (call.window, call.message_type, call.data[0])).collect();
+        assert_eq!(messages, vec![(10, state, 0), (11, state, 1), (11, active, 1)]);
+    }
+
+    #[test]
+    fn test_parse_hotkey() {
+        let hotkey: Hotkey = "Ctrl+Alt+Tab".parse().unwrap();
+        assert_eq!(hotkey.modifiers, ModMask::CONTROL | ModMask::M1);
+        assert_eq!(hotkey.keysym, 0xff09);
+        assert_eq!("scroll_lock".parse::<Hotkey>().unwrap().keysym, 0xff14);
+        assert_eq!("Super + F12".parse::<Hotkey>().unwrap().keysym, 0xffc9);
+        assert_eq!("shift+N".parse::<Hotkey>().unwrap().keysym, u32::from(b'n'));
+        assert_eq!("F".parse::<Hotkey>().unwrap().keysym, u32::from(b'f'));
+
+        for invalid in ["", "Ctrl+", "Hyper+Tab", "F25", "Escape"] {
+            assert!(matches!(invalid.parse::<Hotkey>(), Err(WindowManagerError::InvalidHotkey(_))), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_keep_decorations_fits_frames_into_slots() {
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101)]])
+                .with_frame_extents(&[(10, [2, 2, 28, 2])]),
+        );
+        let mut manager = manager(&mock, Duration::from_secs(1));
+        manager.set_keep_decorations(true);
+        manager.set_layout(&[100, 101], Layout::Horizontal).unwrap();
+
+        // Window 11 reports no extents and gets the default titlebar height
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(10, 0, 0, 956, 1050), (11, 960, 0, 960, 1050)]
+        );
+        let motif = mock.atom(b"_MOTIF_WM_HINTS");
+        assert!(!mock.property_changes().iter().any(|&(_, property)| property == motif));
+    }
+
+    #[test]
+    fn test_kept_frames_fit_into_slots() {
+        // Window 10's window manager ignores the request to drop decorations
+        let mock = Arc::new(
+            MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![(10, 100), (11, 101), (12, 102)]])
+                .with_frame_extents(&[(10, [2, 2, 28, 2]), (11, [0, 0, 0, 0])]),
+        );
+        manager(&mock, Duration::from_secs(1)).set_layout(&[100, 101, 102], Layout::Horizontal).unwrap();
+
+        // Windows without a frame, or without _NET_FRAME_EXTENTS, fill their slot
+        assert_eq!(
+            geometries(&mock.configure_calls()),
+            vec![(10, 0, 0, 636, 1050), (11, 640, 0, 640, 1080), (12, 1280, 0, 640, 1080)]
+        );
+        let motif = mock.atom(b"_MOTIF_WM_HINTS");
+        assert_eq!(mock.property_changes().iter().filter(|&&(_, property)| property == motif).count(), 3);
+    }
+
+    #[test]
+    fn test_search_timing_bounds_wait() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![]]));
+        let timing = WindowSearchTiming {
+            timeout: Duration::from_millis(300),
+            initial_backoff: Duration::from_millis(20),
+            max_backoff: Duration::from_millis(40),
+        };
+        let mut manager = WindowManager::with_backend(Box::new(mock.clone()), timing);
+        manager.set_layout_failure_policy(LayoutFailurePolicy::Abort);
+
+        let started = std::time::Instant::now();
+        assert!(manager.set_layout(&[100], Layout::Horizontal).is_err());
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(300), "gave up after {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "waited {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_cancel_flag_stops_search() {
+        let mock = Arc::new(MockX11::new(vec![(0, 0, 1920, 1080)], vec![vec![]]));
+        let mut manager = manager(&mock, Duration::from_secs(30));
+        manager.set_cancel_flag(Arc::new(AtomicBool::new(true)));
+
+        let started = std::time::Instant::now();
+        assert!(matches!(
+            manager.set_layout(&[100], Layout::Horizontal),
+            Err(WindowManagerError::Cancelled)
+        ));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
 }
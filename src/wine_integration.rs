@@ -0,0 +1,80 @@
+//! Wine Integration
+//!
+//! Runs Windows games with the system's Wine, for users without Steam and
+//! Proton. Each instance gets its own `WINEPREFIX`, like under Proton.
+
+use std::env;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use log::{debug, info};
+
+/// Error type for Wine integration operations
+#[derive(Debug)]
+pub enum WineError {
+    /// Neither `WINE_PATH` nor `wine`/`wine64` on `PATH` lead to Wine
+    WineNotFound,
+}
+
+impl std::fmt::Display for WineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WineError::WineNotFound => write!(
+                f,
+                "Wine not found. Install it with your package manager (e.g. `sudo apt install wine`) \
+                 or set the WINE_PATH environment variable to the wine binary"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WineError {}
+
+/// Attempts to find the Wine binary.
+///
+/// Search order:
+/// 1. `WINE_PATH` environment variable.
+/// 2. `wine`, then `wine64`, on `PATH`.
+pub fn find_wine_path() -> Result<PathBuf, WineError> {
+    if let Some(path) = env::var_os("WINE_PATH").map(PathBuf::from) {
+        if path.is_file() {
+            info!("Using Wine from WINE_PATH: {}", path.display());
+            return Ok(path);
+        }
+        debug!("WINE_PATH='{}' is not a file — continuing search.", path.display());
+    }
+    let path = find_wine_in(env::var_os("PATH").as_deref().unwrap_or_default()).ok_or(WineError::WineNotFound)?;
+    info!("Found Wine at: {}", path.display());
+    Ok(path)
+}
+
+/// First `wine` or `wine64` in the directories of `path_var`; `wine`
+/// anywhere wins over `wine64`, which some distributions ship alone.
+fn find_wine_in(path_var: &OsStr) -> Option<PathBuf> {
+    ["wine", "wine64"].iter().find_map(|name| {
+        env::split_paths(path_var)
+            .map(|dir| dir.join(name))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_wine_in_prefers_wine_over_wine64() {
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+        fs::write(first.path().join("wine64"), b"").unwrap();
+        let path_var = env::join_paths([first.path(), second.path()]).unwrap();
+        assert_eq!(find_wine_in(&path_var), Some(first.path().join("wine64")));
+
+        fs::write(second.path().join("wine"), b"").unwrap();
+        assert_eq!(find_wine_in(&path_var), Some(second.path().join("wine")));
+
+        let empty = tempdir().unwrap();
+        assert_eq!(find_wine_in(empty.path().as_os_str()), None);
+    }
+}
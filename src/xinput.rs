@@ -0,0 +1,302 @@
+//! XInput2 Input Routing
+//!
+//! Instead of copying events to virtual devices, the X server can keep each
+//! player's keyboard and mouse apart itself: every instance gets its own
+//! master pointer/keyboard pair (a second cursor) and the player's physical
+//! devices are attached to it. Focus is set per master, so each keyboard
+//! types into its own game window. Controllers aren't X devices and keep
+//! going through uinput.
+
+use std::collections::HashSet;
+use log::{debug, info, warn};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
+use x11rb::protocol::xinput::{
+    self, ChangeMode, ConnectionExt as _, DeviceId, DeviceType, HierarchyChange, HierarchyChangeData,
+    HierarchyChangeDataAddMaster, HierarchyChangeDataAttachSlave, HierarchyChangeDataRemoveMaster, XIDeviceInfo,
+};
+use x11rb::protocol::xproto::Window;
+use x11rb::rust_connection::RustConnection;
+use crate::input_mux::DeviceIdentifier;
+
+/// Virtual core pointer and keyboard, which removed masters hand their
+/// devices back to
+const CORE_POINTER: DeviceId = 2;
+const CORE_KEYBOARD: DeviceId = 3;
+
+/// Error type for XInput2 routing
+#[derive(Debug)]
+pub enum XInputError {
+    Connect(ConnectError),
+    Connection(ConnectionError),
+    Reply(ReplyError),
+    /// The X server doesn't support XInput 2
+    Unsupported(String),
+}
+
+impl std::fmt::Display for XInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XInputError::Connect(e) => write!(f, "Could not connect to the X server: {}", e),
+            XInputError::Connection(e) => write!(f, "X connection error: {}", e),
+            XInputError::Reply(e) => write!(f, "X request failed: {}", e),
+            XInputError::Unsupported(reason) => write!(f, "XInput 2 is not available: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for XInputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XInputError::Connect(e) => Some(e),
+            XInputError::Connection(e) => Some(e),
+            XInputError::Reply(e) => Some(e),
+            XInputError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<ConnectError> for XInputError {
+    fn from(e: ConnectError) -> Self {
+        XInputError::Connect(e)
+    }
+}
+
+impl From<ConnectionError> for XInputError {
+    fn from(e: ConnectionError) -> Self {
+        XInputError::Connection(e)
+    }
+}
+
+impl From<ReplyError> for XInputError {
+    fn from(e: ReplyError) -> Self {
+        XInputError::Reply(e)
+    }
+}
+
+/// Master pointer/keyboard pair created for one instance
+#[derive(Debug, Clone, Copy)]
+struct MasterPair {
+    instance: usize,
+    pointer: DeviceId,
+    keyboard: DeviceId,
+}
+
+/// Per-instance master devices, removed again when dropped. Their devices
+/// then return to the desktop's own pointer and keyboard.
+pub struct XInputMasters {
+    conn: RustConnection,
+    masters: Vec<MasterPair>,
+    /// Physical devices now routed by the X server
+    attached: HashSet<DeviceIdentifier>,
+}
+
+impl XInputMasters {
+    /// Creates a master pair for each instance in `assignments` and attaches
+    /// the X devices named like the instance's physical device to it.
+    pub fn create(assignments: &[(usize, DeviceIdentifier)]) -> Result<Self, XInputError> {
+        let (conn, _) = RustConnection::connect(None)?;
+        if conn.extension_information(xinput::X11_EXTENSION_NAME)?.is_none() {
+            return Err(XInputError::Unsupported("the X server has no XInputExtension".to_string()));
+        }
+        let version = conn.xinput_xi_query_version(2, 2)?.reply()?;
+        if version.major_version < 2 {
+            return Err(XInputError::Unsupported(format!(
+                "the X server only has XInput {}.{}",
+                version.major_version, version.minor_version
+            )));
+        }
+
+        let mut instances: Vec<usize> = assignments.iter().map(|(instance, _)| *instance).collect();
+        instances.sort_unstable();
+        instances.dedup();
+        let mut masters = XInputMasters { conn, masters: Vec::new(), attached: HashSet::new() };
+        if instances.is_empty() {
+            return Ok(masters);
+        }
+
+        let added: Vec<HierarchyChange> = instances.iter().map(|&instance| add_master(&master_name(instance))).collect();
+        masters.conn.xinput_xi_change_hierarchy(&added)?.check()?;
+        let devices = masters.conn.xinput_xi_query_device(xinput::Device::ALL)?.reply()?.infos;
+        for &instance in &instances {
+            let name = master_name(instance);
+            let pointer = newest_device(&devices, &format!("{} pointer", name), DeviceType::MASTER_POINTER);
+            let keyboard = newest_device(&devices, &format!("{} keyboard", name), DeviceType::MASTER_KEYBOARD);
+            match (pointer, keyboard) {
+                (Some(pointer), Some(keyboard)) => {
+                    debug!("Player {} has X pointer {} and keyboard {}", instance + 1, pointer, keyboard);
+                    masters.masters.push(MasterPair { instance, pointer, keyboard });
+                }
+                _ => warn!("The X server did not create a pointer and keyboard for player {}", instance + 1),
+            }
+        }
+
+        let mut attach = Vec::new();
+        for (instance, device) in assignments {
+            let Some(pair) = masters.masters.iter().find(|pair| pair.instance == *instance) else {
+                continue;
+            };
+            let slaves = slaves_named(&devices, &device.name);
+            if slaves.is_empty() {
+                debug!("'{}' is not an X input device; it stays on uinput", device.name);
+                continue;
+            }
+            for slave in slaves {
+                let master = if slave.type_ == DeviceType::SLAVE_POINTER { pair.pointer } else { pair.keyboard };
+                attach.push(attach_slave(slave.deviceid, master));
+            }
+            info!("Routing '{}' to player {} through its own X pointer and keyboard", device.name, instance + 1);
+            masters.attached.insert(device.clone());
+        }
+        if !attach.is_empty() {
+            masters.conn.xinput_xi_change_hierarchy(&attach)?.check()?;
+        }
+        Ok(masters)
+    }
+
+    /// True if the X server routes `device` now, so it must not also be
+    /// forwarded through uinput.
+    pub fn is_attached(&self, device: &DeviceIdentifier) -> bool {
+        self.attached.contains(device)
+    }
+
+    /// Gives each instance's keyboard focus to its window, and makes its
+    /// pointer the one the window's queries refer to.
+    pub fn focus_windows(&self, windows: &[(usize, Window)]) -> Result<(), XInputError> {
+        for pair in &self.masters {
+            if let Some(&(_, window)) = windows.iter().find(|(instance, _)| *instance == pair.instance) {
+                self.conn.xinput_xi_set_focus(window, x11rb::CURRENT_TIME, pair.keyboard)?;
+                self.conn.xinput_xi_set_client_pointer(window, pair.pointer)?;
+            }
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for XInputMasters {
+    fn drop(&mut self) {
+        if self.masters.is_empty() {
+            return;
+        }
+        let removed: Vec<HierarchyChange> = self.masters.iter().map(|pair| remove_master(pair.pointer)).collect();
+        let result = self
+            .conn
+            .xinput_xi_change_hierarchy(&removed)
+            .map_err(XInputError::from)
+            .and_then(|cookie| cookie.check().map_err(XInputError::from));
+        match result {
+            Ok(()) => info!("Removed {} per-player X pointer(s)", self.masters.len()),
+            Err(e) => warn!(
+                "Could not remove the per-player X pointers ({}); run `xinput list` and `xinput remove-master` to clean up",
+                e
+            ),
+        }
+    }
+}
+
+/// Name of the master devices for `instance`; X appends " pointer" and
+/// " keyboard".
+fn master_name(instance: usize) -> String {
+    format!("Hydra P{}", instance + 1)
+}
+
+/// Id of the device called `name` of the given type. A crashed session may
+/// have left a master of the same name behind, so the newest one wins.
+fn newest_device(devices: &[XIDeviceInfo], name: &str, type_: DeviceType) -> Option<DeviceId> {
+    devices
+        .iter()
+        .filter(|device| device.type_ == type_ && device.name == name.as_bytes())
+        .map(|device| device.deviceid)
+        .max()
+}
+
+/// Attached pointers and keyboards called `name`. One physical device can
+/// show up as several, e.g. a keyboard with media keys.
+fn slaves_named<'a>(devices: &'a [XIDeviceInfo], name: &str) -> Vec<&'a XIDeviceInfo> {
+    devices
+        .iter()
+        .filter(|device| matches!(device.type_, DeviceType::SLAVE_POINTER | DeviceType::SLAVE_KEYBOARD))
+        .filter(|device| device.name == name.as_bytes())
+        .collect()
+}
+
+/// `len` of a hierarchy change counts 4-byte units, including its header
+fn add_master(name: &str) -> HierarchyChange {
+    let padded = name.len().div_ceil(4) * 4;
+    HierarchyChange {
+        len: ((8 + padded) / 4) as u16,
+        data: HierarchyChangeData::AddMaster(HierarchyChangeDataAddMaster {
+            send_core: true,
+            enable: true,
+            name: name.as_bytes().to_vec(),
+        }),
+    }
+}
+
+fn remove_master(pointer: DeviceId) -> HierarchyChange {
+    HierarchyChange {
+        len: 3,
+        data: HierarchyChangeData::RemoveMaster(HierarchyChangeDataRemoveMaster {
+            deviceid: pointer,
+            return_mode: ChangeMode::ATTACH,
+            return_pointer: CORE_POINTER,
+            return_keyboard: CORE_KEYBOARD,
+        }),
+    }
+}
+
+fn attach_slave(slave: DeviceId, master: DeviceId) -> HierarchyChange {
+    HierarchyChange {
+        len: 2,
+        data: HierarchyChangeData::AttachSlave(HierarchyChangeDataAttachSlave { deviceid: slave, master }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::x11_utils::Serialize;
+
+    fn device(deviceid: DeviceId, type_: DeviceType, name: &str) -> XIDeviceInfo {
+        XIDeviceInfo {
+            deviceid,
+            type_,
+            attachment: 0,
+            enabled: true,
+            name: name.as_bytes().to_vec(),
+            classes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_hierarchy_change_lengths_match_encoding() {
+        for change in [
+            add_master("Hydra P1"),
+            add_master("Hydra P10"),
+            remove_master(12),
+            attach_slave(9, 12),
+        ] {
+            assert_eq!(change.serialize().len(), usize::from(change.len) * 4);
+        }
+    }
+
+    #[test]
+    fn test_device_lookup() {
+        let devices = vec![
+            device(2, DeviceType::MASTER_POINTER, "Virtual core pointer"),
+            device(9, DeviceType::SLAVE_KEYBOARD, "Logitech K120"),
+            device(10, DeviceType::SLAVE_POINTER, "Logitech K120"),
+            device(11, DeviceType::FLOATING_SLAVE, "Logitech K120"),
+            device(12, DeviceType::MASTER_POINTER, "Hydra P2 pointer"),
+            device(14, DeviceType::MASTER_POINTER, "Hydra P2 pointer"),
+            device(15, DeviceType::MASTER_KEYBOARD, "Hydra P2 keyboard"),
+        ];
+        assert_eq!(newest_device(&devices, "Hydra P2 pointer", DeviceType::MASTER_POINTER), Some(14));
+        assert_eq!(newest_device(&devices, "Hydra P2 keyboard", DeviceType::MASTER_POINTER), None);
+        let slaves: Vec<DeviceId> = slaves_named(&devices, "Logitech K120").iter().map(|d| d.deviceid).collect();
+        assert_eq!(slaves, vec![9, 10]);
+        assert!(slaves_named(&devices, "Xbox Wireless Controller").is_empty());
+        assert_eq!(master_name(1), "Hydra P2");
+    }
+}